@@ -0,0 +1,229 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Configuration for the search result cache.
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// Maximum number of cached result sets (0 disables caching)
+    pub capacity: usize,
+    /// How long a cached result set stays valid
+    pub ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 64,
+            ttl: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Modification times of a set of database files (including their WAL files).
+///
+/// Used to detect that a database changed after a result set was cached.
+pub type DbFingerprint = Vec<Option<SystemTime>>;
+
+/// Computes the fingerprint of the given database files.
+///
+/// The `-wal` file is included because writes in WAL mode do not touch the
+/// main database file until a checkpoint happens.
+pub fn db_fingerprint(db_paths: &[PathBuf]) -> DbFingerprint {
+    db_paths
+        .iter()
+        .flat_map(|path| {
+            let mut wal = path.as_os_str().to_owned();
+            wal.push("-wal");
+            [modified_time(path), modified_time(Path::new(&wal))]
+        })
+        .collect()
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+struct CacheEntry<V> {
+    value: V,
+    inserted_at: Instant,
+    fingerprint: DbFingerprint,
+}
+
+/// A small LRU cache with TTL expiry for search result sets.
+///
+/// Entries are keyed by a normalized request string and are discarded when
+/// they expire, when the database fingerprint changes, or when evicted as
+/// least recently used.
+pub struct ResultCache<V> {
+    config: CacheConfig,
+    entries: HashMap<String, CacheEntry<V>>,
+    order: VecDeque<String>,
+}
+
+impl<V: Clone> ResultCache<V> {
+    /// Creates an empty cache.
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Returns the cached value for `key` if it is still valid.
+    pub fn get(&mut self, key: &str, fingerprint: &DbFingerprint) -> Option<V> {
+        let valid = match self.entries.get(key) {
+            Some(entry) => {
+                entry.inserted_at.elapsed() < self.config.ttl && &entry.fingerprint == fingerprint
+            }
+            None => return None,
+        };
+
+        if !valid {
+            self.remove(key);
+            return None;
+        }
+
+        self.touch(key);
+        self.entries.get(key).map(|entry| entry.value.clone())
+    }
+
+    /// Stores a value, evicting the least recently used entries if needed.
+    pub fn insert(&mut self, key: String, value: V, fingerprint: DbFingerprint) {
+        if self.config.capacity == 0 {
+            return;
+        }
+
+        if self.entries.contains_key(&key) {
+            self.touch(&key);
+        } else {
+            while self.entries.len() >= self.config.capacity {
+                let Some(oldest) = self.order.pop_front() else {
+                    break;
+                };
+                self.entries.remove(&oldest);
+            }
+            self.order.push_back(key.clone());
+        }
+
+        self.entries.insert(
+            key,
+            CacheEntry {
+                value,
+                inserted_at: Instant::now(),
+                fingerprint,
+            },
+        );
+    }
+
+    /// Removes all cached entries.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    /// Number of cached entries.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache holds no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn touch(&mut self, key: &str) {
+        if let Some(pos) = self.order.iter().position(|k| k == key) {
+            let key = self.order.remove(pos).unwrap();
+            self.order.push_back(key);
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.entries.remove(key);
+        self.order.retain(|k| k != key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(capacity: usize, ttl_ms: u64) -> CacheConfig {
+        CacheConfig {
+            capacity,
+            ttl: Duration::from_millis(ttl_ms),
+        }
+    }
+
+    #[test]
+    fn test_cache_hit_and_miss() {
+        let mut cache = ResultCache::new(config(4, 60_000));
+        let fp = vec![None];
+
+        assert_eq!(cache.get("a", &fp), None);
+        cache.insert("a".to_string(), 1, fp.clone());
+        assert_eq!(cache.get("a", &fp), Some(1));
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let mut cache = ResultCache::new(config(2, 60_000));
+        let fp = vec![None];
+
+        cache.insert("a".to_string(), 1, fp.clone());
+        cache.insert("b".to_string(), 2, fp.clone());
+        // Touch "a" so that "b" becomes the least recently used entry
+        assert_eq!(cache.get("a", &fp), Some(1));
+        cache.insert("c".to_string(), 3, fp.clone());
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("b", &fp), None);
+        assert_eq!(cache.get("a", &fp), Some(1));
+        assert_eq!(cache.get("c", &fp), Some(3));
+    }
+
+    #[test]
+    fn test_cache_expires_after_ttl() {
+        let mut cache = ResultCache::new(config(4, 10));
+        let fp = vec![None];
+
+        cache.insert("a".to_string(), 1, fp.clone());
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(cache.get("a", &fp), None);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_cache_invalidated_by_fingerprint_change() {
+        let mut cache = ResultCache::new(config(4, 60_000));
+
+        cache.insert("a".to_string(), 1, vec![None]);
+        assert_eq!(cache.get("a", &vec![Some(SystemTime::now())]), None);
+    }
+
+    #[test]
+    fn test_cache_disabled_with_zero_capacity() {
+        let mut cache = ResultCache::new(config(0, 60_000));
+        let fp = vec![None];
+
+        cache.insert("a".to_string(), 1, fp.clone());
+        assert_eq!(cache.get("a", &fp), None);
+    }
+
+    #[test]
+    fn test_db_fingerprint_tracks_modification() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("test.reminex.db");
+
+        let missing = db_fingerprint(std::slice::from_ref(&db_path));
+        assert_eq!(missing, vec![None, None]);
+
+        fs::write(&db_path, b"data").unwrap();
+        let present = db_fingerprint(std::slice::from_ref(&db_path));
+        assert!(present[0].is_some());
+        assert_ne!(missing, present);
+    }
+}
@@ -0,0 +1,223 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::searcher::SearchResult;
+
+/// Category used for extensions that are not in the map.
+pub const OTHER_CATEGORY: &str = "Other";
+
+/// Built-in extension table, in display order.
+const DEFAULT_CATEGORIES: &[(&str, &[&str])] = &[
+    (
+        "Images",
+        &[
+            "jpg", "jpeg", "png", "gif", "bmp", "webp", "tif", "tiff", "svg", "ico", "heic",
+            "heif", "raw", "cr2", "cr3", "nef", "arw", "dng", "psd",
+        ],
+    ),
+    (
+        "Videos",
+        &[
+            "mp4", "mkv", "avi", "mov", "wmv", "flv", "webm", "m4v", "mpg", "mpeg", "m2ts", "3gp",
+        ],
+    ),
+    (
+        "Audio",
+        &[
+            "mp3", "flac", "wav", "aac", "ogg", "m4a", "wma", "opus", "ape", "aiff",
+        ],
+    ),
+    (
+        "Documents",
+        &[
+            "pdf", "doc", "docx", "xls", "xlsx", "ppt", "pptx", "odt", "ods", "odp", "txt", "md",
+            "rtf", "csv", "epub", "mobi",
+        ],
+    ),
+    (
+        "Archives",
+        &[
+            "zip", "rar", "7z", "tar", "gz", "bz2", "xz", "zst", "tgz", "iso", "dmg",
+        ],
+    ),
+    (
+        "Code",
+        &[
+            "rs", "py", "js", "ts", "jsx", "tsx", "c", "h", "cpp", "hpp", "cs", "java", "go", "rb",
+            "php", "sh", "ps1", "html", "css", "json", "toml", "yaml", "yml", "xml", "sql",
+        ],
+    ),
+];
+
+/// User overrides loaded from a TOML file.
+///
+/// ```toml
+/// [categories]
+/// "Raw Photos" = ["cr2", "nef", "arw"]
+/// Images = ["avif"]
+/// ```
+#[derive(Debug, Deserialize)]
+struct CategoryOverrides {
+    #[serde(default)]
+    categories: BTreeMap<String, Vec<String>>,
+}
+
+/// Maps file extensions to content categories such as "Images" or "Videos".
+#[derive(Debug, Clone)]
+pub struct CategoryMap {
+    by_extension: HashMap<String, String>,
+    order: Vec<String>,
+}
+
+impl Default for CategoryMap {
+    fn default() -> Self {
+        let mut map = Self {
+            by_extension: HashMap::new(),
+            order: Vec::new(),
+        };
+        for (category, extensions) in DEFAULT_CATEGORIES {
+            for ext in *extensions {
+                map.insert(category, ext);
+            }
+        }
+        map
+    }
+}
+
+impl CategoryMap {
+    /// Assigns an extension to a category, replacing any previous assignment.
+    pub fn insert(&mut self, category: &str, extension: &str) {
+        let ext = extension.trim().trim_start_matches('.').to_lowercase();
+        if ext.is_empty() {
+            return;
+        }
+
+        if !self.order.iter().any(|c| c == category) {
+            self.order.push(category.to_string());
+        }
+        self.by_extension.insert(ext, category.to_string());
+    }
+
+    /// Loads the built-in table and applies overrides from a TOML file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read category map: {}", path.display()))?;
+        let overrides: CategoryOverrides = toml::from_str(&content)
+            .with_context(|| format!("Invalid category map: {}", path.display()))?;
+
+        let mut map = Self::default();
+        for (category, extensions) in &overrides.categories {
+            for ext in extensions {
+                map.insert(category, ext);
+            }
+        }
+        Ok(map)
+    }
+
+    /// Returns the category of a filename, or [`OTHER_CATEGORY`] if unknown.
+    pub fn category_of(&self, name: &str) -> &str {
+        extension_of(name)
+            .and_then(|ext| self.by_extension.get(&ext))
+            .map(|c| c.as_str())
+            .unwrap_or(OTHER_CATEGORY)
+    }
+
+    /// Buckets search results by category.
+    ///
+    /// Groups follow the table order, with [`OTHER_CATEGORY`] last.
+    /// Empty categories are omitted.
+    pub fn group(&self, results: &[SearchResult]) -> Vec<(String, Vec<SearchResult>)> {
+        let mut buckets: HashMap<&str, Vec<SearchResult>> = HashMap::new();
+        for result in results {
+            buckets
+                .entry(self.category_of(&result.name))
+                .or_default()
+                .push(result.clone());
+        }
+
+        self.order
+            .iter()
+            .map(|c| c.as_str())
+            .chain(std::iter::once(OTHER_CATEGORY))
+            .filter_map(|category| {
+                buckets
+                    .remove(category)
+                    .map(|items| (category.to_string(), items))
+            })
+            .collect()
+    }
+}
+
+/// Returns the lowercased extension of a filename without the dot.
+pub(crate) fn extension_of(name: &str) -> Option<String> {
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !ext.is_empty() => Some(ext.to_lowercase()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(name: &str) -> SearchResult {
+        SearchResult {
+            path: format!("/data/{}", name),
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_category_of_default_table() {
+        let map = CategoryMap::default();
+        assert_eq!(map.category_of("summer.JPG"), "Images");
+        assert_eq!(map.category_of("movie.mkv"), "Videos");
+        assert_eq!(map.category_of("report.pdf"), "Documents");
+        assert_eq!(map.category_of("backup.tar.gz"), "Archives");
+        assert_eq!(map.category_of("unknown.xyz"), OTHER_CATEGORY);
+        assert_eq!(map.category_of("Makefile"), OTHER_CATEGORY);
+        assert_eq!(map.category_of(".bashrc"), OTHER_CATEGORY);
+    }
+
+    #[test]
+    fn test_group_orders_categories() {
+        let map = CategoryMap::default();
+        let results = vec![
+            result("notes.xyz"),
+            result("report.pdf"),
+            result("summer.jpg"),
+            result("winter.png"),
+        ];
+
+        let groups = map.group(&results);
+        let names: Vec<_> = groups
+            .iter()
+            .map(|(c, items)| (c.as_str(), items.len()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![("Images", 2), ("Documents", 1), (OTHER_CATEGORY, 1)]
+        );
+    }
+
+    #[test]
+    fn test_overrides_from_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("categories.toml");
+        fs::write(
+            &path,
+            "[categories]\n\"Raw Photos\" = [\"cr2\", \".NEF\"]\nImages = [\"avif\"]\n",
+        )
+        .unwrap();
+
+        let map = CategoryMap::from_file(&path).unwrap();
+        assert_eq!(map.category_of("IMG_1.cr2"), "Raw Photos");
+        assert_eq!(map.category_of("IMG_2.nef"), "Raw Photos");
+        assert_eq!(map.category_of("pic.avif"), "Images");
+        assert_eq!(map.category_of("pic.jpg"), "Images");
+    }
+}
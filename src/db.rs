@@ -1,7 +1,16 @@
-use anyhow::{Context, Result};
-use rusqlite::Connection;
+use anyhow::{Context, Result, bail};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+
+use crate::category::extension_of;
+use crate::indexer::{ARCHIVE_SEPARATOR, split_archive_path};
+use crate::maintenance::is_under;
+use crate::searcher::escape_like;
 
 /// Represents a file index entry in the database.
 #[derive(Debug, Clone, PartialEq)]
@@ -10,6 +19,10 @@ pub struct Index {
     pub name: String,
     pub mtime: Option<f64>,
     pub size: Option<i64>,
+    /// Owner user id (Unix only)
+    pub owner: Option<i64>,
+    /// Mode bits on Unix, file attributes on Windows
+    pub mode: Option<i64>,
 }
 
 impl Index {
@@ -20,6 +33,8 @@ impl Index {
             name,
             mtime: None,
             size: None,
+            owner: None,
+            mode: None,
         }
     }
 
@@ -30,7 +45,313 @@ impl Index {
             name,
             mtime: Some(mtime),
             size: Some(size),
+            owner: None,
+            mode: None,
+        }
+    }
+
+    /// Creates an index entry from a directory entry found while scanning.
+    ///
+    /// Paths and names that are not valid UTF-8 are stored lossily, with
+    /// invalid sequences replaced by U+FFFD, since the database stores text.
+    /// Metadata follows symlinks, so a linked file records its target's size.
+    ///
+    /// # Returns
+    /// The entry, or an error if `with_metadata` is set and reading it failed
+    pub fn from_dir_entry(entry: &fs::DirEntry, with_metadata: bool) -> Result<Self> {
+        let path = entry.path().to_string_lossy().into_owned();
+        let name = entry.file_name().to_string_lossy().into_owned();
+
+        if with_metadata {
+            let (mtime, size) = file_metadata(&entry.path())?;
+            Ok(Self::with_metadata(path, name, mtime, size))
+        } else {
+            Ok(Self::new(path, name))
+        }
+    }
+}
+
+/// Reads a file's modification time (unix seconds) and size in bytes.
+pub(crate) fn file_metadata(path: &Path) -> Result<(f64, i64)> {
+    let metadata = fs::metadata(path).context("Failed to read file metadata")?;
+
+    let mtime = metadata
+        .modified()
+        .context("Failed to get modification time")?
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .context("Invalid modification time")?
+        .as_secs_f64();
+
+    let size = metadata.len() as i64;
+
+    Ok((mtime, size))
+}
+
+/// Nullable columns added after the initial schema, as (name, type).
+///
+/// Databases created by older versions get them via `ALTER TABLE` when
+/// opened with [`Database::init`].
+const ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("owner", "INTEGER"),
+    ("mode", "INTEGER"),
+    ("indexed_at", "REAL"),
+    ("root_id", "INTEGER REFERENCES roots (id)"),
+    ("ext", "TEXT"),
+];
+
+/// Inserts an entry, or updates its metadata if the path is already indexed.
+///
+/// `indexed_at` is only set on insert, so it records when a path was first
+/// added rather than when it was last rescanned. A row written without a
+/// root keeps the one it already had.
+const UPSERT_SQL: &str =
+    "INSERT INTO files (path, name, mtime, size, owner, mode, indexed_at, root_id, ext)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+     ON CONFLICT(path) DO UPDATE SET
+         name = excluded.name,
+         ext = excluded.ext,
+         mtime = excluded.mtime,
+         size = excluded.size,
+         owner = excluded.owner,
+         mode = excluded.mode,
+         root_id = COALESCE(excluded.root_id, files.root_id)";
+
+/// Current time as unix seconds, used to stamp `indexed_at`.
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default()
+}
+
+/// `meta` key holding the friendly label of a database
+const LABEL_KEY: &str = "label";
+
+/// Page cache and temporary storage settings for read-write connections,
+/// which indexing opens per batch and closes again
+const WRITE_PRAGMAS: &str = "
+    PRAGMA cache_size = -2000000;
+    PRAGMA temp_store = MEMORY;
+";
+
+/// Page cache and temporary storage settings for read-only connections.
+///
+/// Pooled readers live as long as the process, so their page cache is
+/// capped at 16 MiB each, at most [`MAX_IDLE_READERS`] times that per
+/// database once full scans have filled them.
+const READ_PRAGMAS: &str = "
+    PRAGMA cache_size = -16384;
+    PRAGMA temp_store = MEMORY;
+";
+
+/// Idle read-only connections kept per database, see
+/// [`Database::batch_operation_readonly`]
+const MAX_IDLE_READERS: usize = 4;
+
+/// A read-only connection waiting to be reused, with the version of the
+/// file it was opened on
+struct IdleReader {
+    version: FileVersion,
+    conn: Connection,
+}
+
+/// Idle read-only connections by database path.
+///
+/// Searches take one out for the duration of a query and put it back, so
+/// opening a connection and reading the schema is paid once per database
+/// rather than once per keyword.
+static IDLE_READERS: LazyLock<Mutex<HashMap<PathBuf, Vec<IdleReader>>>> =
+    LazyLock::new(Default::default);
+
+/// Identifies one version of a database file, so that connections to a
+/// database since deleted and rebuilt at the same path are not reused.
+///
+/// Writes to the main file, such as checkpoints, also change it, which
+/// merely costs a reopen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileVersion {
+    modified: Option<SystemTime>,
+    len: u64,
+    /// Inode number on Unix, which a rebuilt file gets anew even within the
+    /// timestamp resolution
+    inode: u64,
+}
+
+impl FileVersion {
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(&metadata);
+        #[cfg(not(unix))]
+        let inode = 0;
+        Some(Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            inode,
+        })
+    }
+}
+
+/// `meta` key holding the separator stored paths are rewritten to
+const PATH_SEPARATOR_KEY: &str = "path_separator";
+
+/// `meta` key holding the root of the latest scan
+const INDEXED_ROOT_KEY: &str = "indexed_root";
+
+/// `meta` key holding when the latest scan finished, as unix seconds
+const INDEXED_AT_KEY: &str = "indexed_at";
+
+/// `meta` key holding the version of reminex that last opened the database
+/// for writing
+const VERSION_KEY: &str = "reminex_version";
+
+/// FTS5 table mirroring `name` and `path` of `files`, kept in sync by
+/// triggers once created with [`Database::enable_fts`]
+const FTS_SCHEMA: &str = "
+    CREATE VIRTUAL TABLE IF NOT EXISTS files_fts
+        USING fts5 (name, path, content = 'files', content_rowid = 'rowid');
+
+    CREATE TRIGGER IF NOT EXISTS files_fts_insert AFTER INSERT ON files BEGIN
+        INSERT INTO files_fts (rowid, name, path) VALUES (new.rowid, new.name, new.path);
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS files_fts_delete AFTER DELETE ON files BEGIN
+        INSERT INTO files_fts (files_fts, rowid, name, path)
+            VALUES ('delete', old.rowid, old.name, old.path);
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS files_fts_update AFTER UPDATE ON files
+        WHEN old.name IS NOT new.name OR old.path IS NOT new.path
+    BEGIN
+        INSERT INTO files_fts (files_fts, rowid, name, path)
+            VALUES ('delete', old.rowid, old.name, old.path);
+        INSERT INTO files_fts (rowid, name, path) VALUES (new.rowid, new.name, new.path);
+    END;
+";
+
+/// Reads the configured path separator on an open read-write connection,
+/// which only databases set up by [`Database::init`] get, so `meta` exists.
+fn separator_of(conn: &Connection) -> Result<Option<char>> {
+    let value = conn
+        .query_row(
+            "SELECT value FROM meta WHERE key = ?1",
+            [PATH_SEPARATOR_KEY],
+            |row| row.get(0),
+        )
+        .optional()
+        .context("Failed to read path separator")?;
+    Ok(parse_separator(value))
+}
+
+fn parse_separator(value: Option<String>) -> Option<char> {
+    value
+        .and_then(|value| value.chars().next())
+        .filter(|&c| is_separator(c))
+}
+
+/// Reads the registered scan roots as (id, path).
+pub(crate) fn read_roots(conn: &Connection) -> Result<Vec<(i64, String)>> {
+    conn.prepare("SELECT id, path FROM roots")
+        .context("Failed to prepare roots query")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()
+        .context("Failed to read roots")
+}
+
+/// Returns the id of the deepest of `roots` containing `path`.
+pub(crate) fn deepest_root(roots: &[(i64, String)], path: &str) -> Option<i64> {
+    roots
+        .iter()
+        .filter(|(_, root)| is_under(path, root))
+        .max_by_key(|(_, root)| root.len())
+        .map(|(id, _)| *id)
+}
+
+/// Returns true for the separators recognized in stored paths.
+fn is_separator(c: char) -> bool {
+    c == '/' || c == '\\'
+}
+
+/// Collapses runs of `/` and `\` in a path into a single `separator`.
+///
+/// Without a separator the path is returned unchanged, since `\` is an
+/// ordinary file name character on Unix. A leading pair is kept, so UNC
+/// paths such as `\\server\share` stay intact. The part of an archive
+/// entry's path after [`ARCHIVE_SEPARATOR`] always uses `/`, as inside the
+/// archive.
+pub fn normalize_path(path: &str, separator: Option<char>) -> String {
+    let Some(separator) = separator else {
+        return path.to_string();
+    };
+    if let Some((archive, inner)) = split_archive_path(path) {
+        return format!(
+            "{}{}{}",
+            normalize_path(archive, Some(separator)),
+            ARCHIVE_SEPARATOR,
+            normalize_path(inner.trim_start_matches(is_separator), Some('/'))
+        );
+    }
+
+    let mut normalized = String::with_capacity(path.len());
+    let mut chars = path.chars().peekable();
+    let mut at_start = true;
+    while let Some(c) = chars.next() {
+        if !is_separator(c) {
+            normalized.push(c);
+            at_start = false;
+            continue;
+        }
+
+        normalized.push(separator);
+        if at_start && chars.next_if(|&next| is_separator(next)).is_some() {
+            normalized.push(separator);
         }
+        while chars.next_if(|&next| is_separator(next)).is_some() {}
+        at_start = false;
+    }
+    normalized
+}
+
+/// Stored modification time and size of a row, as read by
+/// [`Database::metadata_under`].
+pub type StoredMetadata = (Option<f64>, Option<i64>);
+
+/// A scan root registered in the `roots` table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RootInfo {
+    pub id: i64,
+    /// Root directory as given when indexing, normalized
+    pub path: String,
+    /// Number of rows indexed under this root
+    pub files: usize,
+}
+
+/// How a database was indexed, as recorded in its `meta` table.
+///
+/// Databases written by older versions lack some or all of the values.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct IndexInfo {
+    /// Root directory of the latest scan, normalized like stored paths
+    pub indexed_root: Option<String>,
+    /// When the latest scan finished, as unix seconds
+    pub indexed_at: Option<f64>,
+    /// Version of reminex that last opened the database for writing
+    pub reminex_version: Option<String>,
+}
+
+/// Space on disk before and after [`Database::optimize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OptimizeReport {
+    /// Bytes of the database file and its write-ahead log before
+    pub size_before: u64,
+    /// Bytes of the database file and its write-ahead log after
+    pub size_after: u64,
+}
+
+impl OptimizeReport {
+    /// Bytes freed, zero if the files grew.
+    pub fn reclaimed(&self) -> u64 {
+        self.size_before.saturating_sub(self.size_after)
     }
 }
 
@@ -51,7 +372,8 @@ impl Database {
     /// Initializes a new SQLite database at the instance's path.
     ///
     /// Creates the database file with optimized settings for fast indexing.
-    /// Sets up the `files` table for storing file metadata.
+    /// Sets up the `files` table for storing file metadata, and upgrades the
+    /// schema of databases created by older versions.
     ///
     /// # Returns
     /// Returns `Ok(Database)` on success
@@ -71,10 +393,9 @@ impl Database {
             "
             PRAGMA journal_mode = WAL;
             PRAGMA synchronous = OFF;
-            PRAGMA cache_size = -2000000;
-            PRAGMA temp_store = MEMORY;
             ",
         )
+        .and_then(|_| conn.execute_batch(WRITE_PRAGMAS))
         .context("Failed to set database pragmas")?;
 
         // Create files table
@@ -84,14 +405,46 @@ impl Database {
                 path  TEXT    PRIMARY KEY,
                 name  TEXT    NOT NULL,
                 mtime REAL,
-                size  INTEGER
+                size  INTEGER,
+                owner INTEGER,
+                mode  INTEGER,
+                indexed_at REAL,
+                root_id INTEGER REFERENCES roots (id),
+                ext   TEXT
             );
             
             CREATE INDEX IF NOT EXISTS idx_name ON files (name);
+
+            CREATE TABLE IF NOT EXISTS meta (
+                key   TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS roots (
+                id   INTEGER PRIMARY KEY,
+                path TEXT    NOT NULL UNIQUE
+            );
             ",
         )
         .context("Failed to create database schema")?;
 
+        migrate(&conn)?;
+
+        conn.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+            [VERSION_KEY, env!("CARGO_PKG_VERSION")],
+        )
+        .context("Failed to record reminex version")?;
+
+        // Created after migrating, since older databases lack the columns
+        conn.execute_batch(
+            "
+            CREATE INDEX IF NOT EXISTS idx_root_id ON files (root_id);
+            CREATE INDEX IF NOT EXISTS idx_ext ON files (ext);
+            ",
+        )
+        .context("Failed to create column indexes")?;
+
         Ok(Self {
             path: path.to_path_buf(),
         })
@@ -99,7 +452,64 @@ impl Database {
 
     /// Opens a connection to this database.
     fn connect(&self) -> Result<Connection> {
-        Connection::open(&self.path).context("Failed to open database connection")
+        let conn = Connection::open(&self.path).context("Failed to open database connection")?;
+        conn.execute_batch(WRITE_PRAGMAS)
+            .context("Failed to set database pragmas")?;
+        Ok(conn)
+    }
+
+    /// Opens a read-only connection to this database.
+    ///
+    /// Unlike [`Database::connect`], this never creates the file and cannot
+    /// write to it, so it works on read-only mounts. A database left in WAL
+    /// mode still needs its `-shm` file, which readers create next to it if
+    /// the directory allows.
+    fn connect_readonly(&self) -> Result<Connection> {
+        let conn = Connection::open_with_flags(
+            &self.path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .with_context(|| format!("Failed to open database read-only: {}", self.path.display()))?;
+        conn.execute_batch(READ_PRAGMAS)
+            .context("Failed to set database pragmas")?;
+        Ok(conn)
+    }
+
+    /// Takes an idle read-only connection to this database, or opens one.
+    fn take_reader(&self) -> Result<(Option<FileVersion>, Connection)> {
+        let version = FileVersion::of(&self.path);
+        let idle = IDLE_READERS
+            .lock()
+            .ok()
+            .and_then(|mut pool| pool.get_mut(&self.path)?.pop());
+        match idle {
+            Some(reader) if Some(reader.version) == version => Ok((version, reader.conn)),
+            _ => Ok((version, self.connect_readonly()?)),
+        }
+    }
+
+    /// Returns a read-only connection to the idle pool, unless the pool for
+    /// this database is full.
+    fn put_reader(&self, version: Option<FileVersion>, conn: Connection) {
+        // Connections to a vanished file or left inside a transaction are dropped
+        let Some(version) = version else { return };
+        if !conn.is_autocommit() {
+            return;
+        }
+        if let Ok(mut pool) = IDLE_READERS.lock() {
+            let idle = pool.entry(self.path.clone()).or_default();
+            if idle.len() < MAX_IDLE_READERS {
+                idle.push(IdleReader { version, conn });
+            }
+        }
+    }
+
+    /// Closes the idle read-only connections to this database, such as
+    /// before deleting its file, which Windows refuses while it is open.
+    pub fn close_readers(&self) {
+        if let Ok(mut pool) = IDLE_READERS.lock() {
+            pool.remove(&self.path);
+        }
     }
 
     /// Adds a single index entry to the database.
@@ -111,10 +521,21 @@ impl Database {
     /// Returns `Ok(())` on success
     pub fn add_idx(&self, idx: &Index) -> Result<()> {
         let conn = self.connect()?;
+        let path = normalize_path(&idx.path, separator_of(&conn)?);
 
         conn.execute(
-            "INSERT OR REPLACE INTO files (path, name, mtime, size) VALUES (?1, ?2, ?3, ?4)",
-            rusqlite::params![&idx.path, &idx.name, &idx.mtime, &idx.size],
+            UPSERT_SQL,
+            rusqlite::params![
+                &path,
+                &idx.name,
+                &idx.mtime,
+                &idx.size,
+                &idx.owner,
+                &idx.mode,
+                unix_now(),
+                None::<i64>,
+                extension_of(&idx.name)
+            ],
         )
         .context("Failed to insert index entry")?;
 
@@ -123,25 +544,65 @@ impl Database {
 
     /// Adds multiple index entries to the database in a single transaction.
     ///
+    /// New entries share one `indexed_at` timestamp taken when the
+    /// transaction starts. Paths are stored normalized, see
+    /// [`normalize_path`] and [`Database::path_separator`].
+    ///
     /// # Arguments
     /// * `idxs` - Slice of index entries to add
     ///
     /// # Returns
     /// Returns `Ok(())` on success, rolls back on error
     pub fn add_idxs(&self, idxs: &[Index]) -> Result<()> {
+        self.add_idxs_in_root(idxs, None)
+    }
+
+    /// Adds multiple index entries found under a scan root, see
+    /// [`Database::register_root`].
+    ///
+    /// Rows below a registered root nested inside that root are assigned to
+    /// the deepest such root instead, so root-scoped queries of the nested
+    /// root keep finding them. With `root_id` set to `None`, rows keep
+    /// whatever root they already had.
+    pub fn add_idxs_in_root(&self, idxs: &[Index], root_id: Option<i64>) -> Result<()> {
         let mut conn = self.connect()?;
+        let separator = separator_of(&conn)?;
 
         let tx = conn.transaction().context("Failed to start transaction")?;
+        let indexed_at = unix_now();
+        let nested = match root_id {
+            Some(root_id) => {
+                let roots = read_roots(&tx)?;
+                match roots.iter().find(|(id, _)| *id == root_id) {
+                    Some((_, root)) => roots
+                        .iter()
+                        .filter(|(id, path)| *id != root_id && is_under(path, root))
+                        .cloned()
+                        .collect(),
+                    None => Vec::new(),
+                }
+            }
+            None => Vec::new(),
+        };
 
         {
-            let mut stmt = tx.prepare(
-                "INSERT OR REPLACE INTO files (path, name, mtime, size) VALUES (?1, ?2, ?3, ?4)"
-            )
-            .context("Failed to prepare statement")?;
+            let mut stmt = tx
+                .prepare(UPSERT_SQL)
+                .context("Failed to prepare statement")?;
 
             for idx in idxs {
+                let path = normalize_path(&idx.path, separator);
+                let root_id = deepest_root(&nested, &path).or(root_id);
                 stmt.execute(rusqlite::params![
-                    &idx.path, &idx.name, &idx.mtime, &idx.size
+                    &path,
+                    &idx.name,
+                    &idx.mtime,
+                    &idx.size,
+                    &idx.owner,
+                    &idx.mode,
+                    indexed_at,
+                    root_id,
+                    extension_of(&idx.name)
                 ])
                 .context("Failed to insert index entry")?;
             }
@@ -179,6 +640,654 @@ impl Database {
         let mut conn = self.connect()?;
         f(&mut conn)
     }
+
+    /// Like [`Database::batch_operation`], on a read-only connection.
+    ///
+    /// Searches and other reads use this, so they work on read-only mounts,
+    /// never create a missing database, and cannot change it.
+    ///
+    /// Connections are pooled per database path and reused while the file
+    /// is unchanged, so settings an operation makes on `conn` may carry
+    /// over to later ones.
+    pub fn batch_operation_readonly<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&Connection) -> Result<R>,
+    {
+        let (version, conn) = self.take_reader()?;
+        let result = f(&conn);
+        self.put_reader(version, conn);
+        result
+    }
+
+    /// Reads a value from the `meta` table.
+    ///
+    /// Databases created before the table existed have no values, so a
+    /// missing table reads as `None` rather than an error.
+    pub fn get_meta(&self, key: &str) -> Result<Option<String>> {
+        self.batch_operation_readonly(|conn| {
+            let has_meta: bool = conn
+                .query_row(
+                    "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'meta')",
+                    [],
+                    |row| row.get(0),
+                )
+                .context("Failed to read database schema")?;
+            if !has_meta {
+                return Ok(None);
+            }
+
+            conn.query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()
+            .context("Failed to read database metadata")
+        })
+    }
+
+    /// Stores a value in the `meta` table, replacing any previous value.
+    ///
+    /// The database must have been opened with [`Database::init`].
+    pub fn set_meta(&self, key: &str, value: &str) -> Result<()> {
+        self.connect()?
+            .execute(
+                "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+                [key, value],
+            )
+            .context("Failed to write database metadata")?;
+        Ok(())
+    }
+
+    /// Returns the friendly label set for this database, if any.
+    pub fn label(&self) -> Result<Option<String>> {
+        self.get_meta(LABEL_KEY)
+    }
+
+    /// Sets a friendly label shown instead of the file name in listings.
+    pub fn set_label(&self, label: &str) -> Result<()> {
+        self.set_meta(LABEL_KEY, label.trim())
+    }
+
+    /// Returns the recorded root and time of the latest scan, and the
+    /// version of reminex that last wrote the database.
+    pub fn index_info(&self) -> Result<IndexInfo> {
+        Ok(IndexInfo {
+            indexed_root: self.get_meta(INDEXED_ROOT_KEY)?,
+            indexed_at: self
+                .get_meta(INDEXED_AT_KEY)?
+                .and_then(|value| value.parse().ok()),
+            reminex_version: self.get_meta(VERSION_KEY)?,
+        })
+    }
+
+    /// Records a finished scan of `root`, see [`Database::index_info`].
+    pub fn record_scan(&self, root: &str) -> Result<()> {
+        self.set_meta(INDEXED_ROOT_KEY, &self.normalize_root(root)?)?;
+        self.set_meta(INDEXED_AT_KEY, &unix_now().to_string())
+    }
+
+    /// Returns the separator that new paths are rewritten to, if one is set.
+    ///
+    /// Without one, paths are stored as given.
+    pub fn path_separator(&self) -> Result<Option<char>> {
+        Ok(parse_separator(self.get_meta(PATH_SEPARATOR_KEY)?))
+    }
+
+    /// Sets the separator that new paths are rewritten to, or clears it.
+    ///
+    /// Rows stored earlier keep their paths, so this is best set before the
+    /// first scan or followed by a full rebuild.
+    pub fn set_path_separator(&self, separator: Option<char>) -> Result<()> {
+        if let Some(c) = separator
+            && !is_separator(c)
+        {
+            bail!("Unsupported path separator: {:?}", c);
+        }
+        self.set_meta(
+            PATH_SEPARATOR_KEY,
+            &separator.map(String::from).unwrap_or_default(),
+        )
+    }
+
+    /// Creates the full-text index over file names and paths, if missing.
+    ///
+    /// Existing rows are indexed right away; afterwards triggers keep the
+    /// index in sync with every insert, update and delete. The index takes
+    /// roughly as much space as the `files` table.
+    pub fn enable_fts(&self) -> Result<()> {
+        self.batch_operation(|conn| {
+            if has_fts_table(conn)? {
+                return Ok(());
+            }
+            let tx = conn.transaction().context("Failed to start transaction")?;
+            tx.execute_batch(FTS_SCHEMA)
+                .context("Failed to create full-text index")?;
+            tx.execute("INSERT INTO files_fts (files_fts) VALUES ('rebuild')", [])
+                .context("Failed to build full-text index")?;
+            tx.commit().context("Failed to commit transaction")?;
+            Ok(())
+        })
+    }
+
+    /// Returns true if [`Database::enable_fts`] was run on this database.
+    pub fn has_fts(&self) -> Result<bool> {
+        self.batch_operation_readonly(has_fts_table)
+    }
+
+    /// Compacts the database file and refreshes the query planner's
+    /// statistics.
+    ///
+    /// Runs `VACUUM` to drop the free pages left by deletes and updates,
+    /// `PRAGMA optimize`, and finally `PRAGMA wal_checkpoint(TRUNCATE)` to
+    /// move the write-ahead log into the main file and empty it. `VACUUM`
+    /// rewrites the whole file, so it needs about as much free disk space
+    /// as the database takes, and fails while another connection is
+    /// writing.
+    pub fn optimize(&self) -> Result<OptimizeReport> {
+        let size_before = self.size_on_disk();
+        self.batch_operation(|conn| {
+            conn.execute_batch("VACUUM")
+                .context("Failed to vacuum database")?;
+            conn.execute_batch("PRAGMA optimize")
+                .context("Failed to optimize database")?;
+            // Returns (busy, log frames, checkpointed frames)
+            let busy: i64 = conn
+                .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))
+                .context("Failed to checkpoint write-ahead log")?;
+            if busy != 0 {
+                bail!("Write-ahead log is in use by another connection");
+            }
+            Ok(())
+        })?;
+
+        Ok(OptimizeReport {
+            size_before,
+            size_after: self.size_on_disk(),
+        })
+    }
+
+    /// Bytes taken by the database file and its write-ahead log.
+    fn size_on_disk(&self) -> u64 {
+        let mut wal = self.path.clone().into_os_string();
+        wal.push("-wal");
+        [self.path.as_os_str(), wal.as_os_str()]
+            .into_iter()
+            .filter_map(|path| fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+
+    /// Normalizes a root path the way row paths are stored, without a
+    /// trailing separator.
+    pub(crate) fn normalize_root(&self, root: &str) -> Result<String> {
+        let mut root = normalize_path(root, self.path_separator()?);
+        while root.len() > 1 && root.ends_with(is_separator) {
+            root.pop();
+        }
+        Ok(root)
+    }
+
+    /// Records a scan root and returns its id.
+    ///
+    /// Rows below the root that have no root yet, such as those indexed
+    /// before roots were tracked, are assigned to it. This is the one
+    /// prefix scan per root; later root-scoped queries use `root_id`.
+    pub fn register_root(&self, root: &str) -> Result<i64> {
+        let root = self.normalize_root(root)?;
+        self.batch_operation(|conn| {
+            let tx = conn.transaction().context("Failed to start transaction")?;
+            tx.execute("INSERT OR IGNORE INTO roots (path) VALUES (?1)", [&root])
+                .context("Failed to register root")?;
+            let id: i64 = tx
+                .query_row("SELECT id FROM roots WHERE path = ?1", [&root], |row| {
+                    row.get(0)
+                })
+                .context("Failed to read root id")?;
+
+            // Rows of enclosing roots below this one now belong to it, as
+            // the deepest root containing them
+            let enclosing: Vec<String> = read_roots(&tx)?
+                .into_iter()
+                .filter(|(other, path)| *other != id && is_under(&root, path))
+                .map(|(other, _)| other.to_string())
+                .collect();
+
+            // Rows equal to the root, or below it with either separator
+            let prefix = root.trim_end_matches(is_separator);
+            tx.execute(
+                &format!(
+                    "UPDATE files SET root_id = ?1
+                     WHERE (root_id IS NULL OR root_id IN ({}))
+                       AND (path = ?2
+                            OR substr(path, 1, ?3) IN (?4 || '/', ?4 || '\\'))",
+                    enclosing.join(", ")
+                ),
+                rusqlite::params![id, root, prefix.chars().count() + 1, prefix],
+            )
+            .context("Failed to assign rows to root")?;
+            tx.commit().context("Failed to commit transaction")?;
+            Ok(id)
+        })
+    }
+
+    /// Lists the registered scan roots with their row counts, by path.
+    pub fn roots(&self) -> Result<Vec<RootInfo>> {
+        self.batch_operation(|conn| {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT r.id, r.path, COUNT(f.root_id)
+                     FROM roots r LEFT JOIN files f ON f.root_id = r.id
+                     GROUP BY r.id ORDER BY r.path",
+                )
+                .context("Failed to prepare roots query")?;
+            let roots = stmt
+                .query_map([], |row| {
+                    Ok(RootInfo {
+                        id: row.get(0)?,
+                        path: row.get(1)?,
+                        files: row.get::<_, i64>(2)? as usize,
+                    })
+                })?
+                .collect::<rusqlite::Result<_>>()
+                .context("Failed to read roots")?;
+            Ok(roots)
+        })
+    }
+
+    /// Lists the paths of the registered scan roots, without counting their
+    /// rows like [`Database::roots`].
+    pub fn root_paths(&self) -> Result<Vec<String>> {
+        self.batch_operation_readonly(|conn| {
+            let mut stmt = conn
+                .prepare("SELECT path FROM roots ORDER BY path")
+                .context("Failed to prepare roots query")?;
+            let paths = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()
+                .context("Failed to read roots")?;
+            Ok(paths)
+        })
+    }
+
+    /// Returns the id of a registered root, matched by its normalized path.
+    pub fn root_id(&self, root: &str) -> Result<Option<i64>> {
+        let root = self.normalize_root(root)?;
+        self.connect()?
+            .query_row("SELECT id FROM roots WHERE path = ?1", [&root], |row| {
+                row.get(0)
+            })
+            .optional()
+            .context("Failed to read root id")
+    }
+
+    /// Loads the size and modification time of every row at or below
+    /// `root`, keyed by path, for comparing against a rescan.
+    ///
+    /// Rows are found by path rather than by root id, so rescanning a
+    /// subdirectory of an indexed root also sees the rows already stored
+    /// for it. The ranges below stay on the primary key index.
+    pub fn metadata_under(&self, root: &Path) -> Result<HashMap<String, StoredMetadata>> {
+        let root = self.normalize_root(&root.to_string_lossy())?;
+        let prefix = root.trim_end_matches(is_separator);
+        self.batch_operation(|conn| {
+            // '0' and ']' sort right after '/' and '\'
+            let mut stmt = conn
+                .prepare(
+                    "SELECT path, mtime, size FROM files
+                     WHERE path = ?1
+                        OR (path > ?2 || '/' AND path < ?2 || '0')
+                        OR (path > ?2 || '\\' AND path < ?2 || ']')",
+                )
+                .context("Failed to prepare metadata query")?;
+            let rows = stmt
+                .query_map([&root, prefix], |row| {
+                    Ok((row.get(0)?, (row.get(1)?, row.get(2)?)))
+                })?
+                .collect::<rusqlite::Result<_>>()
+                .context("Failed to read stored metadata")?;
+            Ok(rows)
+        })
+    }
+
+    /// Lists the paths of the rows at or below `prefix`, in path order.
+    ///
+    /// When `prefix` is a registered root, rows are found by `root_id`,
+    /// together with those of the roots nested below it; only rows not yet
+    /// assigned to a root are matched by path. Other prefixes are matched
+    /// by path, with ranges on the primary key index.
+    pub fn paths_under(&self, prefix: &str) -> Result<Vec<String>> {
+        let root = self.normalize_root(prefix)?;
+        let trimmed = root.trim_end_matches(is_separator);
+        let root_ids = match self.root_id(&root)? {
+            Some(_) => self
+                .batch_operation_readonly(read_roots)?
+                .into_iter()
+                .filter(|(_, path)| is_under(path, &root))
+                .map(|(id, _)| id.to_string())
+                .collect::<Vec<_>>(),
+            None => Vec::new(),
+        };
+
+        // '0' and ']' sort right after '/' and '\'
+        let in_range = "(path = ?1
+                         OR (path > ?2 || '/' AND path < ?2 || '0')
+                         OR (path > ?2 || '\\' AND path < ?2 || ']'))";
+        let query = if root_ids.is_empty() {
+            format!("SELECT path FROM files WHERE {in_range} ORDER BY path")
+        } else {
+            format!(
+                "SELECT path FROM files
+                 WHERE root_id IN ({}) OR (root_id IS NULL AND {in_range})
+                 ORDER BY path",
+                root_ids.join(", ")
+            )
+        };
+        self.batch_operation_readonly(|conn| {
+            let mut stmt = conn
+                .prepare(&query)
+                .context("Failed to prepare path query")?;
+            let paths = stmt
+                .query_map([&root, trimmed], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()
+                .context("Failed to read paths")?;
+            Ok(paths)
+        })
+    }
+
+    /// Counts the rows indexed under a root, without those of registered
+    /// roots nested inside it.
+    pub fn count_in_root(&self, root_id: i64) -> Result<usize> {
+        let count: i64 = self
+            .connect()?
+            .query_row(
+                "SELECT COUNT(*) FROM files WHERE root_id = ?1",
+                [root_id],
+                |row| row.get(0),
+            )
+            .context("Failed to count rows in root")?;
+        Ok(count as usize)
+    }
+
+    /// Deletes every row indexed under a root, and the root itself.
+    ///
+    /// # Returns
+    /// Number of rows deleted
+    pub fn delete_root(&self, root_id: i64) -> Result<usize> {
+        self.batch_operation(|conn| {
+            let tx = conn.transaction().context("Failed to start transaction")?;
+            let deleted = tx
+                .execute("DELETE FROM files WHERE root_id = ?1", [root_id])
+                .context("Failed to delete rows in root")?;
+            tx.execute("DELETE FROM roots WHERE id = ?1", [root_id])
+                .context("Failed to delete root")?;
+            tx.commit().context("Failed to commit transaction")?;
+            Ok(deleted)
+        })
+    }
+
+    /// Deletes rows at or below `root` whose paths are not in `seen_paths`.
+    ///
+    /// Meant to run after a scan of `root`, with `seen_paths` holding the
+    /// normalized paths it found, so files deleted from disk do not linger.
+    /// Rows outside `root`, such as those of sibling directories, are never
+    /// touched.
+    ///
+    /// # Returns
+    /// Number of rows deleted
+    pub fn remove_missing(&self, root: &Path, seen_paths: &HashSet<String>) -> Result<usize> {
+        self.remove_missing_except(root, seen_paths, &[])
+    }
+
+    /// Like [`Database::remove_missing`], but also keeps every row at or
+    /// below one of `keep`, such as directories the scan could not read.
+    pub(crate) fn remove_missing_except(
+        &self,
+        root: &Path,
+        seen_paths: &HashSet<String>,
+        keep: &[String],
+    ) -> Result<usize> {
+        let root = self.normalize_root(&root.to_string_lossy())?;
+        let keep = keep
+            .iter()
+            .map(|path| self.normalize_root(path))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.batch_operation(|conn| {
+            let tx = conn.transaction().context("Failed to start transaction")?;
+            let stale: Vec<String> = {
+                // Rows equal to the root, or below it with either separator
+                let prefix = root.trim_end_matches(is_separator);
+                let mut stmt = tx
+                    .prepare(
+                        "SELECT path FROM files
+                         WHERE path = ?1 OR substr(path, 1, ?2) IN (?3 || '/', ?3 || '\\')",
+                    )
+                    .context("Failed to prepare root query")?;
+                let paths = stmt
+                    .query_map(
+                        rusqlite::params![root, prefix.chars().count() + 1, prefix],
+                        |row| row.get::<_, String>(0),
+                    )?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+                    .context("Failed to read rows under root")?;
+                paths
+                    .into_iter()
+                    .filter(|path| {
+                        !seen_paths.contains(path) && !keep.iter().any(|kept| is_under(path, kept))
+                    })
+                    .collect()
+            };
+
+            let mut deleted = 0;
+            {
+                let mut stmt = tx
+                    .prepare("DELETE FROM files WHERE path = ?1")
+                    .context("Failed to prepare delete statement")?;
+                for path in &stale {
+                    deleted += stmt.execute([path]).context("Failed to delete row")?;
+                }
+            }
+            tx.commit().context("Failed to commit transaction")?;
+            Ok(deleted)
+        })
+    }
+
+    /// Deletes the row of a single path.
+    ///
+    /// # Returns
+    /// True if the path was indexed
+    pub fn remove_path(&self, path: &str) -> Result<bool> {
+        let path = normalize_path(path, self.path_separator()?);
+        let deleted = self
+            .connect()?
+            .execute("DELETE FROM files WHERE path = ?1", [&path])
+            .context("Failed to delete row")?;
+        Ok(deleted > 0)
+    }
+
+    /// Deletes every row whose path begins with `prefix`.
+    ///
+    /// This is a plain string prefix: `/data/a` also matches
+    /// `/data/abc.txt`, so pass a trailing separator to stay inside a
+    /// directory, or see [`Database::remove_under`]. `%` and `_` in the
+    /// prefix match themselves, and letters match case-sensitively.
+    ///
+    /// # Returns
+    /// Number of rows deleted
+    pub fn remove_prefix(&self, prefix: &str) -> Result<usize> {
+        if prefix.is_empty() {
+            bail!("The path prefix must not be empty");
+        }
+        let prefix = normalize_path(prefix, self.path_separator()?);
+        let conn = self.connect()?;
+        // Case-sensitive LIKE is also what lets SQLite use the primary key
+        conn.pragma_update(None, "case_sensitive_like", true)
+            .context("Failed to set LIKE case sensitivity")?;
+        conn.execute(
+            "DELETE FROM files WHERE path LIKE ?1 ESCAPE '\\'",
+            [format!("{}%", escape_like(&prefix))],
+        )
+        .context("Failed to delete rows by prefix")
+    }
+
+    /// Deletes the rows below a directory, such as one that was removed.
+    ///
+    /// Rows for the directory path itself are kept; see
+    /// [`Database::remove_path`].
+    ///
+    /// # Returns
+    /// Number of rows deleted
+    pub fn remove_under(&self, dir: &str) -> Result<usize> {
+        let dir = self.normalize_root(dir)?;
+        let prefix = dir.trim_end_matches(is_separator);
+        self.connect()?
+            .execute(
+                "DELETE FROM files WHERE substr(path, 1, ?1) IN (?2 || '/', ?2 || '\\')",
+                rusqlite::params![prefix.chars().count() + 1, prefix],
+            )
+            .context("Failed to delete rows under directory")
+    }
+
+    /// Returns the label, or the file name when no label is set.
+    ///
+    /// Unreadable databases also fall back to the file name, since this is
+    /// only used for display.
+    pub fn display_name(&self) -> String {
+        match self.label() {
+            Ok(Some(label)) if !label.is_empty() => label,
+            _ => self
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| self.path.display().to_string()),
+        }
+    }
+
+    /// Streams every index entry, ordered by path, to a callback.
+    ///
+    /// Rows are read one at a time on a read-only connection, so whole
+    /// databases can be processed without loading them into memory.
+    /// Databases created before the `owner`/`mode` columns existed are read
+    /// without modifying them.
+    ///
+    /// # Returns
+    /// Number of entries visited, or the first error from the callback
+    pub fn iter_all<F>(&self, f: F) -> Result<usize>
+    where
+        F: FnMut(Index) -> Result<()>,
+    {
+        self.batch_operation_readonly(|conn| iter_rows(conn, f))
+    }
+}
+
+/// Streams every row of `files`, ordered by path, see [`Database::iter_all`].
+fn iter_rows<F>(conn: &Connection, mut f: F) -> Result<usize>
+where
+    F: FnMut(Index) -> Result<()>,
+{
+    let existing = table_columns(conn)?;
+    let column = |name: &str| {
+        if existing.iter().any(|c| c == name) {
+            name.to_string()
+        } else {
+            format!("NULL AS {}", name)
+        }
+    };
+
+    let query = format!(
+        "SELECT path, name, mtime, size, {}, {} FROM files ORDER BY path",
+        column("owner"),
+        column("mode")
+    );
+    let mut stmt = conn
+        .prepare(&query)
+        .context("Failed to prepare row query")?;
+    let mut rows = stmt.query([]).context("Failed to query rows")?;
+
+    let mut count = 0;
+    while let Some(row) = rows.next().context("Failed to read row")? {
+        f(Index {
+            path: row.get(0)?,
+            name: row.get(1)?,
+            mtime: row.get(2)?,
+            size: row.get(3)?,
+            owner: row.get(4)?,
+            mode: row.get(5)?,
+        })?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Lists the column names of the `files` table.
+fn table_columns(conn: &Connection) -> Result<Vec<String>> {
+    conn.prepare("SELECT name FROM pragma_table_info('files')")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()
+        .context("Failed to read database schema")
+}
+
+/// Returns true if the `files` table has the column.
+pub(crate) fn has_column(conn: &Connection, column: &str) -> Result<bool> {
+    Ok(table_columns(conn)?.iter().any(|c| c == column))
+}
+
+/// Returns true if the full-text table exists.
+fn has_fts_table(conn: &Connection) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'files_fts')",
+        [],
+        |row| row.get(0),
+    )
+    .context("Failed to check for full-text index")
+}
+
+/// Adds columns missing from databases created by older versions.
+///
+/// A newly added `ext` column is filled in from the names already stored.
+fn migrate(conn: &Connection) -> Result<()> {
+    let existing = table_columns(conn)?;
+
+    for (column, column_type) in ADDED_COLUMNS {
+        if !existing.iter().any(|c| c == column) {
+            conn.execute_batch(&format!(
+                "ALTER TABLE files ADD COLUMN {} {}",
+                column, column_type
+            ))
+            .with_context(|| format!("Failed to add column '{}'", column))?;
+
+            if *column == "ext" {
+                backfill_extensions(conn)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sets `ext` on every row from its name, in one transaction.
+fn backfill_extensions(conn: &Connection) -> Result<()> {
+    let tx = conn
+        .unchecked_transaction()
+        .context("Failed to start transaction")?;
+    {
+        let mut select = tx
+            .prepare("SELECT rowid, name FROM files")
+            .context("Failed to prepare name query")?;
+        let mut update = tx
+            .prepare("UPDATE files SET ext = ?2 WHERE rowid = ?1")
+            .context("Failed to prepare extension update")?;
+        let mut rows = select.query([]).context("Failed to query names")?;
+        while let Some(row) = rows.next().context("Failed to read row")? {
+            let name: String = row.get(1)?;
+            if let Some(ext) = extension_of(&name) {
+                update
+                    .execute(rusqlite::params![row.get::<_, i64>(0)?, ext])
+                    .context("Failed to store extension")?;
+            }
+        }
+    }
+    tx.commit().context("Failed to commit extensions")
 }
 
 /// Collects all `.reminex.db` files from the given paths.
@@ -818,4 +1927,542 @@ mod tests {
 
         let _ = fs::remove_dir_all(&temp_dir);
     }
+
+    #[test]
+    fn test_init_migrates_old_schema() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("old.reminex.db");
+
+        // Schema written by earlier versions, without owner/mode
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE files (path TEXT PRIMARY KEY, name TEXT NOT NULL, mtime REAL, size INTEGER);
+             INSERT INTO files (path, name) VALUES ('/a.txt', 'a.txt');",
+        )
+        .unwrap();
+        drop(conn);
+
+        let db = Database::init(&db_path).unwrap();
+        let mut idx = Index::new("/b.txt".to_string(), "b.txt".to_string());
+        idx.owner = Some(1000);
+        idx.mode = Some(0o100644);
+        db.add_idx(&idx).unwrap();
+
+        let rows: Vec<(String, Option<i64>, Option<i64>)> = db
+            .batch_operation(|conn| {
+                let mut stmt = conn.prepare("SELECT path, owner, mode FROM files ORDER BY path")?;
+                let rows = stmt
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(rows)
+            })
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("/a.txt".to_string(), None, None),
+                ("/b.txt".to_string(), Some(1000), Some(0o100644)),
+            ]
+        );
+
+        // Extensions of rows written before the column existed are filled in
+        let extensions: Vec<Option<String>> = db
+            .batch_operation(|conn| {
+                let mut stmt = conn.prepare("SELECT ext FROM files ORDER BY path")?;
+                let rows = stmt
+                    .query_map([], |row| row.get(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(rows)
+            })
+            .unwrap();
+        assert_eq!(extensions, vec![Some("txt".into()), Some("txt".into())]);
+    }
+
+    #[test]
+    fn test_optimize_reclaims_deleted_rows() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("bloated.reminex.db")).unwrap();
+        let idxs: Vec<Index> = (0..5000)
+            .map(|i| {
+                Index::new(
+                    format!("/data/{:05}_{}.txt", i, "x".repeat(100)),
+                    "x.txt".into(),
+                )
+            })
+            .collect();
+        db.add_idxs(&idxs).unwrap();
+        db.batch_operation(|conn| Ok(conn.execute("DELETE FROM files", [])?))
+            .unwrap();
+
+        let report = db.optimize().unwrap();
+        assert!(report.size_after < report.size_before, "{:?}", report);
+        assert_eq!(report.reclaimed(), report.size_before - report.size_after);
+
+        // The log was checkpointed and emptied
+        let wal = temp_dir.path().join("bloated.reminex.db-wal");
+        assert_eq!(fs::metadata(wal).map(|m| m.len()).unwrap_or(0), 0);
+    }
+
+    #[test]
+    fn test_roots_count_and_delete_by_root_id() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("flat.reminex.db");
+
+        // Flat schema from before roots were tracked
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE files (path TEXT PRIMARY KEY, name TEXT NOT NULL, mtime REAL, size INTEGER);
+                 INSERT INTO files (path, name) VALUES ('/a/x.txt', 'x.txt'), ('/ab/y.txt', 'y.txt');",
+            )
+            .unwrap();
+
+        let db = Database::init(&db_path).unwrap();
+        let a = db.register_root("/a/").unwrap();
+        assert_eq!(db.register_root("/a").unwrap(), a);
+        assert_eq!(db.root_id("/a//").unwrap(), Some(a));
+        assert_eq!(
+            db.roots().unwrap(),
+            vec![RootInfo {
+                id: a,
+                path: "/a".into(),
+                files: 1
+            }]
+        );
+
+        let b = db.register_root("/ab").unwrap();
+        db.add_idxs_in_root(&[Index::new("/a/z.txt".into(), "z.txt".into())], Some(a))
+            .unwrap();
+        // Rewriting without a root keeps the one already assigned
+        db.add_idxs(&[Index::new("/a/z.txt".into(), "z.txt".into())])
+            .unwrap();
+        assert_eq!(db.count_in_root(a).unwrap(), 2);
+        assert_eq!(db.count_in_root(b).unwrap(), 1);
+
+        assert_eq!(db.delete_root(a).unwrap(), 2);
+        let mut paths = Vec::new();
+        db.iter_all(|idx| {
+            paths.push(idx.path);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(paths, vec!["/ab/y.txt"]);
+        assert_eq!(db.root_id("/a").unwrap(), None);
+        assert_eq!(db.roots().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_nested_roots_claim_their_rows() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("nested.reminex.db")).unwrap();
+        let top = db.register_root("/top").unwrap();
+        db.add_idxs_in_root(
+            &[
+                Index::new("/top/a.txt".into(), "a.txt".into()),
+                Index::new("/top/sub/b.txt".into(), "b.txt".into()),
+            ],
+            Some(top),
+        )
+        .unwrap();
+
+        // Registering a root inside another moves its rows over
+        let sub = db.register_root("/top/sub").unwrap();
+        assert_eq!(db.count_in_root(sub).unwrap(), 1);
+
+        // Writes under the outer root keep them with the deepest root
+        db.add_idxs_in_root(
+            &[
+                Index::new("/top/sub/b.txt".into(), "b.txt".into()),
+                Index::new("/top/sub/c.txt".into(), "c.txt".into()),
+            ],
+            Some(top),
+        )
+        .unwrap();
+        assert_eq!(db.count_in_root(sub).unwrap(), 2);
+        assert_eq!(db.count_in_root(top).unwrap(), 1);
+        assert_eq!(
+            db.paths_under("/top/sub").unwrap(),
+            vec!["/top/sub/b.txt", "/top/sub/c.txt"]
+        );
+        assert_eq!(db.paths_under("/top").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_paths_under_uses_root_ids() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("under.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/a/x.txt".into(), "x.txt".into()),
+            Index::new("/a/sub/y.txt".into(), "y.txt".into()),
+            Index::new("/ab/z.txt".into(), "z.txt".into()),
+        ])
+        .unwrap();
+        db.register_root("/a/sub").unwrap();
+        db.register_root("/a").unwrap();
+        // Indexed without a root, so only found by path
+        db.add_idxs(&[Index::new("/a/late.txt".into(), "late.txt".into())])
+            .unwrap();
+
+        assert_eq!(
+            db.paths_under("/a/").unwrap(),
+            vec!["/a/late.txt", "/a/sub/y.txt", "/a/x.txt"]
+        );
+        assert_eq!(db.paths_under("/a/sub").unwrap(), vec!["/a/sub/y.txt"]);
+        assert_eq!(db.paths_under("/ab").unwrap(), vec!["/ab/z.txt"]);
+        assert!(db.paths_under("/b").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fts_index_stays_in_sync() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("fts.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/a/x.txt".into(), "x.txt".into()),
+            Index::new("/b/x.txt".into(), "x.txt".into()),
+        ])
+        .unwrap();
+        db.enable_fts().unwrap();
+
+        // Upserts, deletes and replacing renames all go through triggers
+        db.add_idxs(&[
+            Index::with_metadata("/a/x.txt".into(), "x.txt".into(), 1.0, 2),
+            Index::new("/c/y.txt".into(), "y.txt".into()),
+        ])
+        .unwrap();
+        let report = crate::maintenance::relocate(&db, "/a", "/b", false).unwrap();
+        assert_eq!(report.conflicts, 1);
+        db.remove_missing(Path::new("/c"), &HashSet::new()).unwrap();
+
+        let conn = db.connect().unwrap();
+        conn.execute(
+            "INSERT INTO files_fts (files_fts, rank) VALUES ('integrity-check', 1)",
+            [],
+        )
+        .unwrap();
+        let matched: Vec<String> = conn
+            .prepare(
+                "SELECT path FROM files WHERE rowid IN
+                 (SELECT rowid FROM files_fts WHERE files_fts MATCH 'x')",
+            )
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(matched, vec!["/b/x.txt"]);
+    }
+
+    #[test]
+    fn test_remove_missing_stays_under_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("missing.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/a/kept.txt".into(), "kept.txt".into()),
+            Index::new("/a/gone.txt".into(), "gone.txt".into()),
+            Index::new("/a/locked/secret.txt".into(), "secret.txt".into()),
+            Index::new("/ab/sibling.txt".into(), "sibling.txt".into()),
+            Index::new("/b/other.txt".into(), "other.txt".into()),
+        ])
+        .unwrap();
+
+        let seen: HashSet<String> = ["/a/kept.txt".to_string()].into();
+        assert_eq!(
+            db.remove_missing_except(Path::new("/a/"), &seen, &["/a/locked".into()])
+                .unwrap(),
+            1
+        );
+        assert_eq!(db.remove_missing(Path::new("/a"), &seen).unwrap(), 1);
+
+        let mut paths = Vec::new();
+        db.iter_all(|idx| {
+            paths.push(idx.path);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            paths,
+            vec!["/a/kept.txt", "/ab/sibling.txt", "/b/other.txt"]
+        );
+    }
+
+    #[test]
+    fn test_remove_path_and_rows_under_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("remove.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/a/x.txt".into(), "x.txt".into()),
+            Index::new("/a/sub/y.txt".into(), "y.txt".into()),
+            Index::new("D:\\a\\z.txt".into(), "z.txt".into()),
+            Index::new("/ab/sibling.txt".into(), "sibling.txt".into()),
+            Index::new("/a".into(), "a".into()),
+        ])
+        .unwrap();
+
+        assert!(!db.remove_path("/a//x.txt").unwrap());
+        assert!(db.remove_path("/a/x.txt").unwrap());
+        assert!(!db.remove_path("/a/x.txt").unwrap());
+        assert_eq!(db.remove_under("/a/").unwrap(), 1);
+        assert_eq!(db.remove_under("D:\\a").unwrap(), 1);
+
+        let mut paths = Vec::new();
+        db.iter_all(|idx| {
+            paths.push(idx.path);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(paths, vec!["/a", "/ab/sibling.txt"]);
+    }
+
+    #[test]
+    fn test_remove_prefix_matches_wildcards_literally() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("prefix.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/data/100%_done/a.txt".into(), "a.txt".into()),
+            Index::new("/data/100%_done/b.txt".into(), "b.txt".into()),
+            Index::new("/data/100x_done/c.txt".into(), "c.txt".into()),
+            Index::new("/data/100%Xdone/d.txt".into(), "d.txt".into()),
+            Index::new("/data/a\\b/e.txt".into(), "e.txt".into()),
+            Index::new("/data/aXb/f.txt".into(), "f.txt".into()),
+            Index::new("/DATA/g.txt".into(), "g.txt".into()),
+            Index::new("/data/g.txt".into(), "g.txt".into()),
+        ])
+        .unwrap();
+
+        assert_eq!(db.remove_prefix("/data/100%_done/").unwrap(), 2);
+        assert_eq!(db.remove_prefix("/data/a\\b").unwrap(), 1);
+        assert_eq!(db.remove_prefix("/data/nothing").unwrap(), 0);
+        assert_eq!(db.remove_prefix("/DATA/").unwrap(), 1);
+        assert!(db.remove_prefix("").is_err());
+
+        let mut paths = Vec::new();
+        db.iter_all(|idx| {
+            paths.push(idx.path);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            paths,
+            vec![
+                "/data/100%Xdone/d.txt",
+                "/data/100x_done/c.txt",
+                "/data/aXb/f.txt",
+                "/data/g.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_label_round_trips_through_meta() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("a1b2.reminex.db");
+
+        // Databases without a meta table read as unlabeled
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch("CREATE TABLE files (path TEXT PRIMARY KEY, name TEXT NOT NULL)")
+            .unwrap();
+        let db = Database::new(&db_path);
+        assert_eq!(db.label().unwrap(), None);
+        assert_eq!(db.display_name(), "a1b2.reminex.db");
+
+        let db = Database::init(&db_path).unwrap();
+        db.set_label("  Photos Archive ").unwrap();
+
+        let reopened = Database::new(&db_path);
+        assert_eq!(reopened.label().unwrap().as_deref(), Some("Photos Archive"));
+        assert_eq!(reopened.display_name(), "Photos Archive");
+
+        reopened.set_label("Photos").unwrap();
+        assert_eq!(db.get_meta("label").unwrap().as_deref(), Some("Photos"));
+        assert_eq!(db.get_meta("missing").unwrap(), None);
+    }
+
+    #[test]
+    fn test_readonly_operations_never_write() {
+        let temp_dir = tempfile::tempdir().unwrap();
+
+        // A missing database is an error rather than a new empty file
+        let missing = temp_dir.path().join("missing.reminex.db");
+        let searched =
+            crate::searcher::search_by_keyword(&Database::new(&missing), "a", &Default::default());
+        assert!(searched.is_err());
+        assert!(!missing.exists());
+
+        let db = Database::init(temp_dir.path().join("ro.reminex.db")).unwrap();
+        db.add_idx(&Index::new("/a.txt".into(), "a.txt".into()))
+            .unwrap();
+        let deleted =
+            db.batch_operation_readonly(|conn| Ok(conn.execute("DELETE FROM files", [])?));
+        assert!(format!("{:#}", deleted.unwrap_err()).contains("readonly"));
+        let count: i64 = db
+            .batch_operation_readonly(|conn| {
+                Ok(conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))?)
+            })
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // Pooled readers outlive the search, so they keep a small page cache
+        let cache_size = |conn: &Connection| -> Result<i64> {
+            Ok(conn.query_row("PRAGMA cache_size", [], |row| row.get(0))?)
+        };
+        assert_eq!(db.batch_operation_readonly(cache_size).unwrap(), -16384);
+        assert_eq!(
+            db.batch_operation(|conn| cache_size(conn)).unwrap(),
+            -2000000
+        );
+    }
+
+    #[test]
+    fn test_readonly_connections_reused_until_file_replaced() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("pool.reminex.db");
+        let db = Database::init(&db_path).unwrap();
+        db.add_idx(&Index::new("/a.txt".into(), "a.txt".into()))
+            .unwrap();
+
+        // A temp table only lives as long as the connection it was made on
+        let marked = |db: &Database| {
+            db.batch_operation_readonly(|conn| {
+                Ok(conn
+                    .prepare("SELECT 1 FROM temp.sqlite_master WHERE name = 'marker'")?
+                    .exists([])?)
+            })
+            .unwrap()
+        };
+        let count = |db: &Database| -> i64 {
+            db.batch_operation_readonly(|conn| {
+                Ok(conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))?)
+            })
+            .unwrap()
+        };
+        db.batch_operation_readonly(|conn| Ok(conn.execute_batch("CREATE TEMP TABLE marker (x)")?))
+            .unwrap();
+        assert!(marked(&db));
+        assert!(marked(&db.clone()));
+
+        // Rows written since are visible on the reused connection
+        db.add_idx(&Index::new("/b.txt".into(), "b.txt".into()))
+            .unwrap();
+        assert_eq!(count(&db), 2);
+
+        // Closed connections are gone, and a database rebuilt at the same
+        // path is reopened rather than read through a pooled connection
+        db.close_readers();
+        assert!(!marked(&db));
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = fs::remove_file(format!("{}{suffix}", db_path.display()));
+        }
+        let rebuilt = Database::init(&db_path).unwrap();
+        assert_eq!(count(&rebuilt), 0);
+    }
+
+    #[test]
+    fn test_index_info_records_version_and_scans() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("info.reminex.db");
+        Connection::open(&db_path)
+            .unwrap()
+            .execute_batch("CREATE TABLE files (path TEXT PRIMARY KEY, name TEXT NOT NULL)")
+            .unwrap();
+        assert_eq!(
+            Database::new(&db_path).index_info().unwrap(),
+            IndexInfo::default()
+        );
+
+        let db = Database::init(&db_path).unwrap();
+        let info = db.index_info().unwrap();
+        assert_eq!(
+            info.reminex_version.as_deref(),
+            Some(env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!((info.indexed_root, info.indexed_at), (None, None));
+
+        db.record_scan("/data/photos/").unwrap();
+        let info = db.index_info().unwrap();
+        assert_eq!(info.indexed_root.as_deref(), Some("/data/photos"));
+        assert!(info.indexed_at.unwrap() > 1_600_000_000.0);
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/a//b///c.txt", Some('/')), "/a/b/c.txt");
+        assert_eq!(normalize_path("/a\\\\b/c.txt", None), "/a\\\\b/c.txt");
+        assert_eq!(normalize_path("/a//b.txt", None), "/a//b.txt");
+        assert_eq!(
+            normalize_path("D:\\a//b/\\c.txt", Some('\\')),
+            "D:\\a\\b\\c.txt"
+        );
+        assert_eq!(
+            normalize_path("D:\\a//b/\\c.txt", Some('/')),
+            "D:/a/b/c.txt"
+        );
+        assert_eq!(
+            normalize_path("\\\\server\\\\share\\x.txt", Some('\\')),
+            "\\\\server\\share\\x.txt"
+        );
+        assert_eq!(
+            normalize_path("D:\\\\p.zip!/trip//beach.jpg", Some('\\')),
+            "D:\\p.zip!/trip/beach.jpg"
+        );
+        assert_eq!(normalize_path("plain.txt", Some('/')), "plain.txt");
+    }
+
+    #[test]
+    fn test_add_idxs_stores_normalized_paths() {
+        let temp_dir = setup_test_dir();
+        let db = Database::init(temp_dir.path().join("messy.reminex.db")).unwrap();
+        assert_eq!(db.path_separator().unwrap(), None);
+
+        // Without a separator, a backslash may be part of a Unix file name
+        db.add_idx(&Index::new("/a/x\\y.txt".into(), "x\\y.txt".into()))
+            .unwrap();
+        db.set_path_separator(Some('/')).unwrap();
+        db.add_idxs(&[
+            Index::new("/a//b.txt".into(), "b.txt".into()),
+            Index::new("/a/b.txt".into(), "b.txt".into()),
+        ])
+        .unwrap();
+        db.add_idx(&Index::new("/a\\\\c.txt".into(), "c.txt".into()))
+            .unwrap();
+        assert!(db.set_path_separator(Some(':')).is_err());
+
+        let mut paths = Vec::new();
+        db.iter_all(|idx| {
+            paths.push(idx.path);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(paths, vec!["/a/b.txt", "/a/c.txt", "/a/x\\y.txt"]);
+        assert_eq!(db.path_separator().unwrap(), Some('/'));
+
+        db.set_path_separator(None).unwrap();
+        assert_eq!(db.path_separator().unwrap(), None);
+    }
+
+    #[test]
+    fn test_index_from_dir_entry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join("entry.txt"), b"hello").unwrap();
+        let entry = fs::read_dir(temp_dir.path())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap();
+        let expected_path = temp_dir.path().join("entry.txt");
+
+        let idx = Index::from_dir_entry(&entry, false).unwrap();
+        assert_eq!(idx.path, expected_path.to_string_lossy());
+        assert_eq!(idx.name, "entry.txt");
+        assert_eq!((idx.mtime, idx.size), (None, None));
+
+        let idx = Index::from_dir_entry(&entry, true).unwrap();
+        assert_eq!(idx.size, Some(5));
+        assert!(idx.mtime.unwrap() > 0.0);
+
+        // Metadata errors are reported, e.g. when the file vanished mid-scan
+        fs::remove_file(&expected_path).unwrap();
+        assert!(Index::from_dir_entry(&entry, true).is_err());
+        assert!(Index::from_dir_entry(&entry, false).is_ok());
+    }
 }
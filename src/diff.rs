@@ -0,0 +1,208 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::db::{Database, Index};
+use crate::indexer::{IndexConfig, scan_idxs_with_config};
+use crate::maintenance::is_under;
+
+/// Modification times closer than this are treated as equal, since some
+/// filesystems and importers round them
+const MTIME_TOLERANCE: f64 = 1e-3;
+
+/// Differences between an old and a new set of indexed paths.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DiffReport {
+    /// Paths only on the new side, in path order
+    pub added: Vec<String>,
+    /// Paths only on the old side, in path order
+    pub removed: Vec<String>,
+    /// Paths on both sides whose size or modification time differ
+    pub changed: Vec<String>,
+}
+
+impl DiffReport {
+    /// Returns true if both sides hold the same paths and metadata.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Size and modification time of a row, as compared by the diff.
+type Metadata = (Option<i64>, Option<f64>);
+
+/// Returns true if two rows' metadata differ.
+///
+/// A field only counts when both sides recorded it, so a database indexed
+/// in fast mode does not report every file as changed.
+fn metadata_changed((old_size, old_mtime): Metadata, (new_size, new_mtime): Metadata) -> bool {
+    let size_changed = matches!((old_size, new_size), (Some(a), Some(b)) if a != b);
+    let mtime_changed =
+        matches!((old_mtime, new_mtime), (Some(a), Some(b)) if (a - b).abs() > MTIME_TOLERANCE);
+    size_changed || mtime_changed
+}
+
+/// Compares two databases by path.
+///
+/// Both sides are read with [`Database::iter_all`]; only the old side's
+/// paths and metadata are held in memory.
+///
+/// # Arguments
+/// * `old` - Earlier snapshot
+/// * `new` - Later snapshot
+/// * `prefix` - Only compare rows at or below this path
+pub fn diff_databases(old: &Database, new: &Database, prefix: Option<&str>) -> Result<DiffReport> {
+    let in_scope = |path: &str| prefix.is_none_or(|prefix| is_under(path, prefix));
+
+    let mut remaining: HashMap<String, Metadata> = HashMap::new();
+    old.iter_all(|idx| {
+        if in_scope(&idx.path) {
+            remaining.insert(idx.path, (idx.size, idx.mtime));
+        }
+        Ok(())
+    })?;
+
+    let mut report = DiffReport::default();
+    new.iter_all(|idx: Index| {
+        if !in_scope(&idx.path) {
+            return Ok(());
+        }
+        match remaining.remove(&idx.path) {
+            None => report.added.push(idx.path),
+            Some(old) if metadata_changed(old, (idx.size, idx.mtime)) => {
+                report.changed.push(idx.path)
+            }
+            Some(_) => {}
+        }
+        Ok(())
+    })?;
+
+    report.removed = remaining.into_keys().collect();
+    report.removed.sort();
+    Ok(report)
+}
+
+/// Compares a database with the files currently under `root`.
+///
+/// The tree is walked with the regular indexer into a temporary database,
+/// which is deleted afterwards; `db` is only read. Rows of `db` outside
+/// `root` are ignored.
+pub fn diff_against_disk(db: &Database, root: &Path) -> Result<DiffReport> {
+    let scratch = ScratchDatabase::create()?;
+    scratch.db.set_path_separator(db.path_separator()?)?;
+
+    let config = IndexConfig {
+        with_metadata: true,
+        exclude_paths: vec![db.path.clone(), scratch.db.path.clone()],
+        ..Default::default()
+    };
+    scan_idxs_with_config(root, &scratch.db, 5000, &config).context("Failed to scan directory")?;
+
+    let root = root.to_string_lossy();
+    diff_databases(db, &scratch.db, Some(root.trim_end_matches(['/', '\\'])))
+}
+
+/// A database in the temp directory, removed with its side files on drop.
+struct ScratchDatabase {
+    db: Database,
+}
+
+impl ScratchDatabase {
+    fn create() -> Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let path = std::env::temp_dir().join(format!(
+            "reminex-diff-{}-{}.reminex.db",
+            std::process::id(),
+            nanos
+        ));
+        let db = Database::init(&path).context("Failed to create temporary database")?;
+        Ok(Self { db })
+    }
+}
+
+impl Drop for ScratchDatabase {
+    fn drop(&mut self) {
+        self.db.close_readers();
+        let path = self.db.path.as_os_str();
+        for suffix in ["", "-wal", "-shm", "-journal"] {
+            let mut side = path.to_os_string();
+            side.push(suffix);
+            let _ = fs::remove_file(PathBuf::from(side));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_databases_reports_added_removed_changed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let old = Database::init(temp_dir.path().join("old.reminex.db")).unwrap();
+        old.add_idxs(&[
+            Index::with_metadata("/d/kept.txt".into(), "kept.txt".into(), 100.0, 1),
+            Index::with_metadata("/d/grown.txt".into(), "grown.txt".into(), 100.0, 1),
+            Index::with_metadata("/d/gone.txt".into(), "gone.txt".into(), 100.0, 1),
+            Index::new("/d/fast.txt".into(), "fast.txt".into()),
+            Index::new("/other/x.txt".into(), "x.txt".into()),
+        ])
+        .unwrap();
+
+        let new = Database::init(temp_dir.path().join("new.reminex.db")).unwrap();
+        new.add_idxs(&[
+            Index::with_metadata("/d/kept.txt".into(), "kept.txt".into(), 100.0001, 1),
+            Index::with_metadata("/d/grown.txt".into(), "grown.txt".into(), 100.0, 2),
+            Index::with_metadata("/d/fast.txt".into(), "fast.txt".into(), 200.0, 3),
+            Index::new("/d/fresh.txt".into(), "fresh.txt".into()),
+        ])
+        .unwrap();
+
+        let report = diff_databases(&old, &new, None).unwrap();
+        assert_eq!(report.added, vec!["/d/fresh.txt"]);
+        assert_eq!(report.removed, vec!["/d/gone.txt", "/other/x.txt"]);
+        assert_eq!(report.changed, vec!["/d/grown.txt"]);
+
+        let scoped = diff_databases(&old, &new, Some("/d")).unwrap();
+        assert_eq!(scoped.removed, vec!["/d/gone.txt"]);
+
+        assert!(diff_databases(&new, &new, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_diff_against_disk_leaves_database_untouched() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("files");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("kept.txt"), b"kept").unwrap();
+        fs::write(root.join("edited.txt"), b"before").unwrap();
+
+        let db = Database::init(temp_dir.path().join("disk.reminex.db")).unwrap();
+        crate::indexer::scan_idxs_with_metadata(&root, &db, 100).unwrap();
+        db.add_idx(&Index::new("/elsewhere/a.txt".into(), "a.txt".into()))
+            .unwrap();
+
+        fs::write(root.join("edited.txt"), b"after, and longer").unwrap();
+        fs::write(root.join("new.txt"), b"new").unwrap();
+        fs::remove_file(root.join("kept.txt")).unwrap();
+
+        let path_of = |name: &str| root.join(name).to_string_lossy().into_owned();
+        let report = diff_against_disk(&db, &root).unwrap();
+        assert_eq!(report.added, vec![path_of("new.txt")]);
+        assert_eq!(report.removed, vec![path_of("kept.txt")]);
+        assert_eq!(report.changed, vec![path_of("edited.txt")]);
+
+        let mut count = 0;
+        db.iter_all(|_| {
+            count += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(count, 3);
+    }
+}
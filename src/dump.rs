@@ -0,0 +1,240 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::db::{Database, Index};
+
+/// Version of the dump layout, bumped when columns or structure change
+pub const DUMP_FORMAT_VERSION: u32 = 1;
+
+/// Columns written for every row, in order
+pub const DUMP_COLUMNS: [&str; 6] = ["path", "name", "size", "mtime", "owner", "mode"];
+
+/// First line of a CSV dump, which the CSV importer uses to recognize its header block
+pub const CSV_DUMP_TITLE: &str = "# reminex database dump";
+
+/// Output format of a database dump.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// A single JSON document with a header and a `files` array
+    Json,
+    /// CSV with `#` comment lines for the header, readable by the CSV importer
+    Csv,
+}
+
+/// Schema and provenance written at the start of a dump.
+#[derive(Debug, Serialize)]
+pub struct DumpHeader {
+    /// Layout version, see [`DUMP_FORMAT_VERSION`]
+    pub format_version: u32,
+    /// Reminex version that wrote the dump
+    pub reminex_version: String,
+    /// When the dump was written
+    pub exported_at: DateTime<Utc>,
+    /// Database the rows were read from
+    pub source: String,
+    /// Column names of each row
+    pub columns: Vec<String>,
+}
+
+impl DumpHeader {
+    fn new(db: &Database) -> Self {
+        Self {
+            format_version: DUMP_FORMAT_VERSION,
+            reminex_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported_at: Utc::now(),
+            source: db.path.display().to_string(),
+            columns: DUMP_COLUMNS.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+}
+
+/// One row of a JSON dump.
+#[derive(Serialize)]
+struct DumpRow<'a> {
+    path: &'a str,
+    name: &'a str,
+    size: Option<i64>,
+    mtime: Option<f64>,
+    owner: Option<i64>,
+    mode: Option<i64>,
+}
+
+impl<'a> From<&'a Index> for DumpRow<'a> {
+    fn from(idx: &'a Index) -> Self {
+        Self {
+            path: &idx.path,
+            name: &idx.name,
+            size: idx.size,
+            mtime: idx.mtime,
+            owner: idx.owner,
+            mode: idx.mode,
+        }
+    }
+}
+
+/// Dumps every row of a database to a writer.
+///
+/// Rows are streamed with [`Database::iter_all`], so memory use does not
+/// grow with the database. CSV dumps can be loaded back with
+/// [`crate::import::import_csv`] using columns 1–4 for path, name, size
+/// and mtime.
+///
+/// # Returns
+/// Number of rows written
+pub fn dump_database<W: Write>(db: &Database, format: DumpFormat, out: W) -> Result<usize> {
+    let header = DumpHeader::new(db);
+    match format {
+        DumpFormat::Json => dump_json(db, &header, out),
+        DumpFormat::Csv => dump_csv(db, &header, out),
+    }
+}
+
+/// Writes `{"header": {...}, "files": [...]}` one row at a time.
+fn dump_json<W: Write>(db: &Database, header: &DumpHeader, mut out: W) -> Result<usize> {
+    out.write_all(b"{\"header\":")?;
+    serde_json::to_writer(&mut out, header)?;
+    out.write_all(b",\"files\":[\n")?;
+
+    let mut first = true;
+    let count = db.iter_all(|idx| {
+        if !first {
+            out.write_all(b",\n")?;
+        }
+        first = false;
+        serde_json::to_writer(&mut out, &DumpRow::from(&idx))?;
+        Ok(())
+    })?;
+
+    out.write_all(b"\n]}\n")?;
+    out.flush()?;
+    Ok(count)
+}
+
+/// Writes the header as `#` comment lines followed by a CSV table.
+fn dump_csv<W: Write>(db: &Database, header: &DumpHeader, mut out: W) -> Result<usize> {
+    writeln!(out, "{}", CSV_DUMP_TITLE)?;
+    writeln!(out, "# format_version: {}", header.format_version)?;
+    writeln!(out, "# reminex_version: {}", header.reminex_version)?;
+    writeln!(out, "# exported_at: {}", header.exported_at.to_rfc3339())?;
+    writeln!(out, "# source: {}", header.source)?;
+
+    let mut writer = csv::Writer::from_writer(out);
+    writer.write_record(DUMP_COLUMNS)?;
+
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    let count = db.iter_all(|idx| {
+        writer
+            .write_record([
+                idx.path,
+                idx.name,
+                optional(idx.size.map(|v| v.to_string())),
+                optional(idx.mtime.map(|v| v.to_string())),
+                optional(idx.owner.map(|v| v.to_string())),
+                optional(idx.mode.map(|v| v.to_string())),
+            ])
+            .context("Failed to write CSV row")
+    })?;
+
+    writer.flush()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::{CsvImportOptions, import_csv};
+
+    fn sample_db(dir: &std::path::Path) -> Database {
+        let db = Database::init(dir.join("source.reminex.db")).unwrap();
+        let mut with_perms = Index::with_metadata(
+            "/d/b, \"quoted\".txt".into(),
+            "b, \"quoted\".txt".into(),
+            1.5,
+            42,
+        );
+        with_perms.owner = Some(1000);
+        with_perms.mode = Some(0o644);
+        db.add_idxs(&[
+            Index::new("/d/a.txt".into(), "a.txt".into()),
+            with_perms,
+            Index::with_metadata("/d/c.jpg".into(), "c.jpg".into(), 1700000000.25, 0),
+        ])
+        .unwrap();
+        db
+    }
+
+    fn all_rows(db: &Database) -> Vec<Index> {
+        let mut rows = Vec::new();
+        db.iter_all(|idx| {
+            rows.push(idx);
+            Ok(())
+        })
+        .unwrap();
+        rows
+    }
+
+    #[test]
+    fn test_csv_dump_round_trips_through_import() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = sample_db(temp_dir.path());
+
+        let dump_path = temp_dir.path().join("dump.csv");
+        let file = std::fs::File::create(&dump_path).unwrap();
+        assert_eq!(dump_database(&source, DumpFormat::Csv, file).unwrap(), 3);
+
+        let target = Database::init(temp_dir.path().join("target.reminex.db")).unwrap();
+        let options = CsvImportOptions {
+            name_col: Some(2),
+            size_col: Some(3),
+            mtime_col: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(import_csv(&dump_path, &target, &options).unwrap(), 3);
+
+        let strip_perms = |rows: Vec<Index>| -> Vec<_> {
+            rows.into_iter()
+                .map(|i| (i.path, i.name, i.size, i.mtime))
+                .collect()
+        };
+        assert_eq!(
+            strip_perms(all_rows(&target)),
+            strip_perms(all_rows(&source))
+        );
+    }
+
+    #[test]
+    fn test_json_dump_has_header_and_rows() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source = sample_db(temp_dir.path());
+
+        let mut out = Vec::new();
+        assert_eq!(
+            dump_database(&source, DumpFormat::Json, &mut out).unwrap(),
+            3
+        );
+
+        let dump: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(dump["header"]["format_version"], DUMP_FORMAT_VERSION);
+        assert_eq!(dump["header"]["columns"][5], "mode");
+        let files = dump["files"].as_array().unwrap();
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0]["path"], "/d/a.txt");
+        assert!(files[0]["size"].is_null());
+        assert_eq!(files[1]["owner"], 1000);
+        assert_eq!(files[1]["mode"], 0o644);
+    }
+
+    #[test]
+    fn test_json_dump_of_empty_database() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("empty.reminex.db")).unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(dump_database(&db, DumpFormat::Json, &mut out).unwrap(), 0);
+
+        let dump: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert!(dump["files"].as_array().unwrap().is_empty());
+    }
+}
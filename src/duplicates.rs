@@ -0,0 +1,254 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::db::Database;
+
+/// What files must share to count as copies of each other.
+///
+/// No content hashes are indexed, so either key is a heuristic: files with
+/// the same name and size are likely, not certainly, identical.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicateKey {
+    /// Same file name and size; files indexed without metadata are left out
+    #[default]
+    NameAndSize,
+    /// Same file name, whatever the size
+    Name,
+}
+
+/// One file of a [`DuplicateGroup`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateFile {
+    pub path: String,
+    /// Size in bytes, if recorded
+    pub size: Option<u64>,
+}
+
+/// Files that are probably copies of each other.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateGroup {
+    /// The shared file name
+    pub name: String,
+    /// Two or more files, in path order
+    pub files: Vec<DuplicateFile>,
+    /// Bytes taken up by all but the largest file, so `size * (count - 1)`
+    /// for files of the same size; unknown sizes count as zero
+    pub wasted_bytes: u64,
+}
+
+/// Name and, for [`DuplicateKey::NameAndSize`], size shared by a group
+type GroupKey = (String, Option<i64>);
+
+/// Finds files that are probably copies of each other.
+///
+/// Files are grouped by name, and by size too with
+/// [`DuplicateKey::NameAndSize`]. This is a heuristic, not a byte-for-byte
+/// comparison: no file content is read. Names are compared exactly,
+/// including case.
+///
+/// # Returns
+/// Groups of two or more files, ordered by name, then size
+pub fn find_duplicates(db: &Database, by: DuplicateKey) -> Result<Vec<DuplicateGroup>> {
+    find_duplicates_across(std::slice::from_ref(db), by)
+}
+
+/// Like [`find_duplicates`], grouping the files of several databases
+/// together, e.g. one database per drive.
+pub fn find_duplicates_across(dbs: &[Database], by: DuplicateKey) -> Result<Vec<DuplicateGroup>> {
+    // Count names first, through the name index, so that only the rows of
+    // repeated names are read and held; sizes are told apart afterwards
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for db in dbs {
+        db.batch_operation_readonly(|conn| {
+            let mut stmt = conn
+                .prepare("SELECT name, COUNT(*) FROM files GROUP BY name")
+                .context("Failed to prepare duplicate count query")?;
+            let mut rows = stmt.query([]).context("Failed to count file names")?;
+            while let Some(row) = rows.next()? {
+                let count: i64 = row.get(1)?;
+                *counts.entry(row.get(0)?).or_default() += count as usize;
+            }
+            Ok(())
+        })?;
+    }
+    counts.retain(|_, count| *count > 1);
+
+    let mut groups: HashMap<GroupKey, Vec<DuplicateFile>> = HashMap::new();
+    for db in dbs {
+        if counts.is_empty() {
+            break;
+        }
+        db.batch_operation_readonly(|conn| {
+            let mut stmt = conn
+                .prepare("SELECT name, size, path FROM files")
+                .context("Failed to prepare duplicate query")?;
+            let mut rows = stmt.query([]).context("Failed to query files")?;
+            while let Some(row) = rows.next()? {
+                let name = row.get_ref(0)?.as_str()?;
+                if !counts.contains_key(name) {
+                    continue;
+                }
+                let size: Option<i64> = row.get(1)?;
+                let key = match by {
+                    DuplicateKey::NameAndSize if size.is_none() => continue,
+                    DuplicateKey::NameAndSize => (name.to_string(), size),
+                    DuplicateKey::Name => (name.to_string(), None),
+                };
+                groups.entry(key).or_default().push(DuplicateFile {
+                    path: row.get(2)?,
+                    size: size.map(|size| size.max(0) as u64),
+                });
+            }
+            Ok(())
+        })?;
+    }
+
+    let mut groups: Vec<_> = groups
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .collect();
+    groups.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(groups
+        .into_iter()
+        .map(|((name, _), mut files)| {
+            files.sort_by(|a, b| a.path.cmp(&b.path));
+            let sizes = files.iter().map(|file| file.size.unwrap_or(0));
+            let wasted_bytes = sizes.clone().sum::<u64>() - sizes.max().unwrap_or(0);
+            DuplicateGroup {
+                name,
+                files,
+                wasted_bytes,
+            }
+        })
+        .collect())
+}
+
+/// Sorts groups by wasted bytes, most first, then by name.
+pub fn sort_by_wasted_bytes(groups: &mut [DuplicateGroup]) {
+    groups.sort_by(|a, b| {
+        b.wasted_bytes
+            .cmp(&a.wasted_bytes)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Index;
+
+    fn create_test_db(
+        dir: &std::path::Path,
+        name: &str,
+        files: &[(&str, Option<i64>)],
+    ) -> Database {
+        let db = Database::init(dir.join(name)).unwrap();
+        let idxs: Vec<Index> = files
+            .iter()
+            .map(|&(path, size)| {
+                let name = path.rsplit('/').next().unwrap().to_string();
+                match size {
+                    Some(size) => Index::with_metadata(path.into(), name, 0.0, size),
+                    None => Index::new(path.into(), name),
+                }
+            })
+            .collect();
+        db.add_idxs(&idxs).unwrap();
+        db
+    }
+
+    fn summary(groups: &[DuplicateGroup]) -> Vec<(&str, Vec<&str>, u64)> {
+        groups
+            .iter()
+            .map(|group| {
+                let paths = group.files.iter().map(|f| f.path.as_str()).collect();
+                (group.name.as_str(), paths, group.wasted_bytes)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_find_duplicates_by_name_and_size() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = create_test_db(
+            temp_dir.path(),
+            "dupes.reminex.db",
+            &[
+                ("/b/photo.jpg", Some(100)),
+                ("/a/photo.jpg", Some(100)),
+                ("/c/photo.jpg", Some(100)),
+                ("/d/photo.jpg", Some(7)),
+                ("/a/Photo.jpg", Some(100)),
+                ("/a/notes.txt", None),
+                ("/b/notes.txt", None),
+                ("/a/report.pdf", Some(50)),
+                ("/b/report.pdf", Some(60)),
+                ("/a/unique.txt", Some(1)),
+            ],
+        );
+
+        let groups = find_duplicates(&db, DuplicateKey::NameAndSize).unwrap();
+        assert_eq!(
+            summary(&groups),
+            vec![(
+                "photo.jpg",
+                vec!["/a/photo.jpg", "/b/photo.jpg", "/c/photo.jpg"],
+                200
+            )]
+        );
+
+        let mut groups = find_duplicates(&db, DuplicateKey::Name).unwrap();
+        assert_eq!(
+            summary(&groups),
+            vec![
+                ("notes.txt", vec!["/a/notes.txt", "/b/notes.txt"], 0),
+                (
+                    "photo.jpg",
+                    vec![
+                        "/a/photo.jpg",
+                        "/b/photo.jpg",
+                        "/c/photo.jpg",
+                        "/d/photo.jpg"
+                    ],
+                    207
+                ),
+                ("report.pdf", vec!["/a/report.pdf", "/b/report.pdf"], 50),
+            ]
+        );
+
+        sort_by_wasted_bytes(&mut groups);
+        let names: Vec<_> = groups.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["photo.jpg", "report.pdf", "notes.txt"]);
+    }
+
+    #[test]
+    fn test_find_duplicates_across_databases() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let first = create_test_db(
+            temp_dir.path(),
+            "first.reminex.db",
+            &[("/d/movie.mkv", Some(900)), ("/d/a.txt", Some(1))],
+        );
+        let second = create_test_db(
+            temp_dir.path(),
+            "second.reminex.db",
+            &[("/e/backup/movie.mkv", Some(900)), ("/e/b.txt", Some(1))],
+        );
+
+        assert!(
+            find_duplicates(&first, DuplicateKey::NameAndSize)
+                .unwrap()
+                .is_empty()
+        );
+        let groups = find_duplicates_across(&[first, second], DuplicateKey::NameAndSize).unwrap();
+        assert_eq!(
+            summary(&groups),
+            vec![(
+                "movie.mkv",
+                vec!["/d/movie.mkv", "/e/backup/movie.mkv"],
+                900
+            )]
+        );
+    }
+}
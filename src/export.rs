@@ -1,9 +1,74 @@
 use anyhow::Result;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::io::Write;
 use std::path::Path;
 
+use crate::searcher::{DatabaseResults, SearchConfig, SearchMode, SortBy, merge_by_keyword};
+
+/// UTF-8 字节顺序标记
+const UTF8_BOM: char = '\u{feff}';
+
+/// CSV 导出的表头
+const CSV_COLUMNS: [&str; 4] = ["keyword", "path", "size", "modified"];
+
+/// 导出文件格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// 带搜索参数的 TOML，可重新导入
+    #[default]
+    Toml,
+    /// 每个文件一行的 CSV
+    Csv,
+    /// 与 TOML 结构相同的 JSON
+    Json,
+    /// 每行一个 JSON 对象（JSON Lines），便于流式处理
+    Ndjson,
+}
+
+impl ExportFormat {
+    /// 根据文件扩展名（.toml、.csv、.json、.ndjson 或 .jsonl，不区分大小写）判断格式
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "toml" => Some(ExportFormat::Toml),
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            "ndjson" | "jsonl" => Some(ExportFormat::Ndjson),
+            _ => None,
+        }
+    }
+
+    /// 该格式的文件扩展名（不含点）
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Toml => "toml",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+/// JSON Lines 导出中的一行，以 `_type` 区分
+#[derive(Serialize)]
+#[serde(tag = "_type", rename_all = "lowercase")]
+enum NdjsonRecord<'a> {
+    /// 首行：导出元数据和搜索参数
+    Metadata {
+        metadata: &'a ExportMetadata,
+        search_params: &'a SearchParams,
+    },
+    /// 其余每行一个文件
+    File {
+        keyword: &'a str,
+        #[serde(flatten)]
+        file: &'a FileEntry,
+    },
+}
+
 /// 导出的搜索结果（TOML格式）
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ExportedSearchResults {
@@ -48,6 +113,30 @@ pub struct SearchParams {
     /// 排除过滤器
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub exclude_filters: Vec<String>,
+    /// 匹配方式（glob、regex；默认子串匹配时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<SearchMode>,
+    /// 是否按文件名主干精确匹配
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stem: bool,
+    /// 扩展名过滤
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extensions: Vec<String>,
+    /// 最小文件大小（字节）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_size: Option<i64>,
+    /// 最大文件大小（字节）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size: Option<i64>,
+    /// 修改时间下限（RFC 3339，UTC）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_after: Option<String>,
+    /// 排序方式（默认按路径时省略）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sort: Option<SortBy>,
+    /// 是否倒序
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub descending: bool,
 }
 
 /// 关键词结果组
@@ -69,7 +158,7 @@ pub struct FileEntry {
     /// 文件大小（字节）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub size: Option<i64>,
-    /// 修改时间
+    /// 修改时间（RFC 3339，UTC）
     #[serde(skip_serializing_if = "Option::is_none")]
     pub modified: Option<String>,
 }
@@ -99,6 +188,14 @@ impl ExportedSearchResults {
                 limit,
                 include_filters,
                 exclude_filters,
+                mode: None,
+                stem: false,
+                extensions: vec![],
+                min_size: None,
+                max_size: None,
+                modified_after: None,
+                sort: None,
+                descending: false,
             },
             results: vec![],
         }
@@ -121,17 +218,107 @@ impl ExportedSearchResults {
     }
 
     /// 从 TOML 字符串导入
+    ///
+    /// 会忽略开头的 UTF-8 BOM（Windows 记事本等编辑器保存时可能会添加）
     pub fn from_toml(toml_str: &str) -> Result<Self> {
+        let toml_str = toml_str.strip_prefix(UTF8_BOM).unwrap_or(toml_str);
         Ok(toml::from_str(toml_str)?)
     }
 
-    /// 导出到文件
+    /// 导出为 JSON 字符串
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// 导出为 JSON Lines 字符串，见 [`Self::write_ndjson`]
+    pub fn to_ndjson(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        self.write_ndjson(&mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+
+    /// 以 JSON Lines 格式逐行写出
+    ///
+    /// 首行为 `"_type": "metadata"` 的元数据和搜索参数，之后每个文件一行
+    /// （`"_type": "file"`，含 keyword、path、size、modified），每行都可单独解析
+    pub fn write_ndjson<W: Write>(&self, mut writer: W) -> Result<()> {
+        let mut write_line = |record: &NdjsonRecord| -> Result<()> {
+            serde_json::to_writer(&mut writer, record)?;
+            writer.write_all(b"\n")?;
+            Ok(())
+        };
+        write_line(&NdjsonRecord::Metadata {
+            metadata: &self.metadata,
+            search_params: &self.search_params,
+        })?;
+        for group in &self.results {
+            for file in &group.files {
+                write_line(&NdjsonRecord::File {
+                    keyword: &group.keyword,
+                    file,
+                })?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// 按指定格式导出为字符串
+    pub fn render(&self, format: ExportFormat) -> Result<String> {
+        match format {
+            ExportFormat::Toml => self.to_toml(),
+            ExportFormat::Csv => self.to_csv(),
+            ExportFormat::Json => self.to_json(),
+            ExportFormat::Ndjson => self.to_ndjson(),
+        }
+    }
+
+    /// 导出到文件（UTF-8 编码，除 CSV 外不带 BOM）
+    ///
+    /// 格式由扩展名决定：`.csv` 为 CSV，`.json` 为 JSON，`.ndjson`、`.jsonl`
+    /// 为 JSON Lines，其余均为 TOML
     pub fn export_to_file(&self, path: &Path) -> Result<()> {
-        let toml_content = self.to_toml()?;
-        fs::write(path, toml_content)?;
+        let format = ExportFormat::from_path(path).unwrap_or(ExportFormat::Toml);
+        self.export_to_file_as(path, format)
+    }
+
+    /// 按指定格式导出到文件（UTF-8 编码，除 CSV 外不带 BOM）
+    pub fn export_to_file_as(&self, path: &Path, format: ExportFormat) -> Result<()> {
+        if format == ExportFormat::Ndjson {
+            // 逐行写出，不在内存中拼接整个文件
+            let file = fs::File::create(path)?;
+            return self.write_ndjson(std::io::BufWriter::new(file));
+        }
+        fs::write(path, self.render(format)?)?;
         Ok(())
     }
 
+    /// 导出为 CSV 字符串
+    ///
+    /// 每个文件一行，列为 keyword、path、size、modified，带表头；
+    /// 缺失的大小和修改时间为空单元格，没有结果的关键词不产生行。
+    /// 开头带 UTF-8 BOM，否则 Excel 会按本地代码页打开，中文路径显示为乱码
+    pub fn to_csv(&self) -> Result<String> {
+        let mut writer = csv::Writer::from_writer(UTF8_BOM.to_string().into_bytes());
+        writer.write_record(CSV_COLUMNS)?;
+        for group in &self.results {
+            for file in &group.files {
+                writer.write_record([
+                    group.keyword.as_str(),
+                    file.path.as_str(),
+                    &file.size.map(|size| size.to_string()).unwrap_or_default(),
+                    file.modified.as_deref().unwrap_or_default(),
+                ])?;
+            }
+        }
+        Ok(String::from_utf8(writer.into_inner()?)?)
+    }
+
+    /// 导出到 CSV 文件（UTF-8 编码，带 BOM），不论扩展名
+    pub fn export_to_csv_file(&self, path: &Path) -> Result<()> {
+        self.export_to_file_as(path, ExportFormat::Csv)
+    }
+
     /// 从文件导入
     pub fn import_from_file(path: &Path) -> Result<Self> {
         let content = fs::read_to_string(path)?;
@@ -150,6 +337,8 @@ pub struct ConvertParams {
     pub include_filters: Vec<String>,
     pub exclude_filters: Vec<String>,
     pub keyword_results: Vec<crate::web::KeywordResults>,
+    /// 是否保留没有结果的关键词（以 `count = 0` 的空组导出）
+    pub include_empty: bool,
 }
 
 /// 从 Web API 的搜索结果转换为导出格式
@@ -165,6 +354,13 @@ pub fn convert_from_web_results(params: ConvertParams) -> ExportedSearchResults
     );
 
     for kr in params.keyword_results {
+        // 无结果的关键词只有一个占位节点，不能当作文件导出
+        if kr.count == 0 {
+            if params.include_empty {
+                export.add_keyword_group(kr.keyword, vec![]);
+            }
+            continue;
+        }
         let files = flatten_tree_to_files(&kr.tree);
         export.add_keyword_group(kr.keyword, files);
     }
@@ -172,6 +368,62 @@ pub fn convert_from_web_results(params: ConvertParams) -> ExportedSearchResults
     export
 }
 
+/// 命令行搜索结果转换参数
+#[derive(Debug)]
+pub struct SearchConvertParams<'a> {
+    pub query: String,
+    pub selected_db: String,
+    /// 搜索时使用的配置，写入导出文件的搜索参数
+    pub config: &'a SearchConfig,
+    pub results: DatabaseResults,
+    /// 是否保留没有结果的关键词（以 `count = 0` 的空组导出）
+    pub include_empty: bool,
+}
+
+/// 从命令行搜索结果转换为导出格式
+///
+/// 多个数据库中同一关键词的结果合并为一组，并按配置的排序方式排列
+pub fn convert_from_search_results(params: SearchConvertParams) -> ExportedSearchResults {
+    let config = params.config;
+    let mut export = ExportedSearchResults::new(
+        params.query,
+        params.selected_db,
+        !config.search_in_path,
+        config.case_sensitive,
+        Some(config.max_results),
+        config.include_filters.clone(),
+        config.exclude_filters.clone(),
+    );
+    let search_params = &mut export.search_params;
+    search_params.mode =
+        (config.search_mode != SearchMode::Substring).then_some(config.search_mode);
+    search_params.stem = config.match_stem;
+    search_params.extensions = config.extensions.clone();
+    search_params.min_size = config.min_size;
+    search_params.max_size = config.max_size;
+    search_params.modified_after = config.modified_after.and_then(format_mtime);
+    search_params.sort = (config.sort_by != SortBy::Path).then_some(config.sort_by);
+    search_params.descending = config.descending;
+
+    let merged = merge_by_keyword(params.results, config.sort_by, config.descending);
+    for (keyword, items) in merged {
+        if items.is_empty() && !params.include_empty {
+            continue;
+        }
+        let files = items
+            .into_iter()
+            .map(|item| FileEntry {
+                modified: item.mtime.and_then(format_mtime),
+                size: item.size,
+                path: item.path,
+            })
+            .collect();
+        export.add_keyword_group(keyword, files);
+    }
+
+    export
+}
+
 /// 将树形结构扁平化为文件列表
 fn flatten_tree_to_files(tree: &crate::web::TreeNodeJson) -> Vec<FileEntry> {
     let mut files = Vec::new();
@@ -183,8 +435,8 @@ fn collect_files_recursive(node: &crate::web::TreeNodeJson, files: &mut Vec<File
     if node.is_leaf {
         files.push(FileEntry {
             path: node.path.clone(),
-            size: None,
-            modified: None,
+            size: node.size,
+            modified: node.mtime.and_then(format_mtime),
         });
     } else {
         for child in &node.children {
@@ -193,6 +445,12 @@ fn collect_files_recursive(node: &crate::web::TreeNodeJson, files: &mut Vec<File
     }
 }
 
+/// 将 Unix 时间戳（秒）格式化为 RFC 3339 字符串，超出范围时返回 None
+fn format_mtime(mtime: f64) -> Option<String> {
+    DateTime::<Utc>::from_timestamp_millis((mtime * 1000.0).round() as i64)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -234,4 +492,329 @@ mod tests {
         assert_eq!(imported.results[0].keyword, "keyword1");
         assert_eq!(imported.results[0].files.len(), 2);
     }
+
+    #[test]
+    fn test_import_with_utf8_bom() {
+        let export = ExportedSearchResults::new(
+            "bom".to_string(),
+            "all".to_string(),
+            false,
+            false,
+            None,
+            vec![],
+            vec![],
+        );
+
+        let toml_str = export.to_toml().unwrap();
+        assert!(
+            !toml_str.starts_with(UTF8_BOM),
+            "Exports must not carry a BOM"
+        );
+
+        let with_bom = format!("{}{}", UTF8_BOM, toml_str);
+        let imported = ExportedSearchResults::from_toml(&with_bom).unwrap();
+        assert_eq!(imported.search_params.query, "bom");
+
+        // Round-trip through a file edited by a BOM-writing editor
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("results.toml");
+        fs::write(&file_path, with_bom.as_bytes()).unwrap();
+        let imported = ExportedSearchResults::import_from_file(&file_path).unwrap();
+        assert_eq!(imported.search_params.selected_db, "all");
+    }
+
+    fn keyword_results(keyword: &str, paths: &[&str]) -> crate::web::KeywordResults {
+        use crate::web::{KeywordResults, TreeNodeJson};
+
+        let leaf = |path: &str| TreeNodeJson {
+            name: path.to_string(),
+            path: path.to_string(),
+            is_leaf: true,
+            children: vec![],
+            size: Some(path.len() as i64),
+            mtime: Some(1_704_067_200.0),
+        };
+        let tree = if paths.is_empty() {
+            // Web 端为无结果的关键词生成的占位节点
+            TreeNodeJson {
+                name: "无结果".to_string(),
+                size: None,
+                mtime: None,
+                ..leaf(".")
+            }
+        } else {
+            TreeNodeJson {
+                name: "root".to_string(),
+                path: "/".to_string(),
+                is_leaf: false,
+                children: paths.iter().map(|p| leaf(p)).collect(),
+                size: None,
+                mtime: None,
+            }
+        };
+
+        KeywordResults {
+            keyword: keyword.to_string(),
+            count: paths.len(),
+            total: paths.len(),
+            tree,
+            root_path: String::new(),
+        }
+    }
+
+    fn convert(include_empty: bool) -> ExportedSearchResults {
+        convert_from_web_results(ConvertParams {
+            query: "a b".to_string(),
+            selected_db: "all".to_string(),
+            name_only: false,
+            case_sensitive: false,
+            limit: None,
+            include_filters: vec![],
+            exclude_filters: vec![],
+            keyword_results: vec![
+                keyword_results("a", &["/x/a.txt"]),
+                keyword_results("b", &[]),
+            ],
+            include_empty,
+        })
+    }
+
+    #[test]
+    fn test_convert_includes_empty_keywords() {
+        let export = convert(true);
+        let groups: Vec<_> = export
+            .results
+            .iter()
+            .map(|g| (g.keyword.as_str(), g.count, g.files.len()))
+            .collect();
+        assert_eq!(groups, vec![("a", 1, 1), ("b", 0, 0)]);
+        assert_eq!(export.metadata.total_count, 1);
+
+        // 空组在 TOML 中也要保留
+        let imported = ExportedSearchResults::from_toml(&export.to_toml().unwrap()).unwrap();
+        assert_eq!(imported.results.len(), 2);
+        assert_eq!(imported.results[1].count, 0);
+    }
+
+    #[test]
+    fn test_convert_skips_empty_keywords() {
+        let export = convert(false);
+        assert_eq!(export.results.len(), 1);
+        assert_eq!(export.results[0].keyword, "a");
+    }
+
+    #[test]
+    fn test_convert_carries_file_metadata() {
+        let export = convert(false);
+        let file = &export.results[0].files[0];
+        assert_eq!(file.size, Some(file.path.len() as i64));
+        assert_eq!(file.modified.as_deref(), Some("2024-01-01T00:00:00Z"));
+        assert_eq!(format_mtime(f64::MAX), None);
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let mut export = ExportedSearchResults::new(
+            "q".into(),
+            "all".into(),
+            false,
+            false,
+            None,
+            vec![],
+            vec![],
+        );
+        export.add_keyword_group(
+            "a,b".to_string(),
+            vec![
+                FileEntry {
+                    path: "/x/one, \"two\".txt".to_string(),
+                    size: Some(12),
+                    modified: Some("2024-01-01T00:00:00Z".to_string()),
+                },
+                FileEntry {
+                    path: "/x/bare.txt".to_string(),
+                    size: None,
+                    modified: None,
+                },
+            ],
+        );
+        export.add_keyword_group("empty".to_string(), vec![]);
+
+        let csv = export.to_csv().unwrap();
+        assert_eq!(
+            csv,
+            "\u{feff}keyword,path,size,modified\n\
+             \"a,b\",\"/x/one, \"\"two\"\".txt\",12,2024-01-01T00:00:00Z\n\
+             \"a,b\",/x/bare.txt,,\n"
+        );
+
+        // 逗号和引号正确转义，可被 CSV 解析器还原
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let rows: Vec<csv::StringRecord> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][1], "/x/one, \"two\".txt");
+        assert_eq!(&rows[1][2], "");
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.csv");
+        export.export_to_csv_file(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), csv);
+    }
+
+    #[test]
+    fn test_export_format_from_extension() {
+        let mut export =
+            ExportedSearchResults::new("q".into(), "all".into(), true, false, None, vec![], vec![]);
+        export.add_keyword_group(
+            "k".to_string(),
+            vec![FileEntry {
+                path: "/x/a.txt".to_string(),
+                size: Some(3),
+                modified: None,
+            }],
+        );
+
+        assert_eq!(
+            ExportFormat::from_path(Path::new("r.JSON")),
+            Some(ExportFormat::Json)
+        );
+        assert_eq!(ExportFormat::from_path(Path::new("r.txt")), None);
+        assert_eq!(ExportFormat::from_path(Path::new("results")), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let written = |name: &str| {
+            let path = dir.path().join(name);
+            export.export_to_file(&path).unwrap();
+            fs::read_to_string(path).unwrap()
+        };
+        assert!(written("r.csv").starts_with("\u{feff}keyword,path,size,modified\n"));
+        // 未知扩展名仍按 TOML 导出，保持兼容
+        assert_eq!(written("r.txt"), export.to_toml().unwrap());
+        assert_eq!(written("r.toml"), export.to_toml().unwrap());
+
+        let json: serde_json::Value = serde_json::from_str(&written("r.json")).unwrap();
+        assert_eq!(json["search_params"]["name_only"], true);
+        assert_eq!(json["results"][0]["files"][0]["size"], 3);
+        assert!(json["results"][0]["files"][0].get("modified").is_none());
+    }
+
+    #[test]
+    fn test_ndjson_round_trip() {
+        let mut export = ExportedSearchResults::new(
+            "a; b".into(),
+            "all".into(),
+            false,
+            true,
+            Some(10),
+            vec![],
+            vec![],
+        );
+        export.add_keyword_group(
+            "a".to_string(),
+            vec![FileEntry {
+                path: "/x/a\nb.txt".to_string(),
+                size: Some(5),
+                modified: Some("2024-01-01T00:00:00Z".to_string()),
+            }],
+        );
+        export.add_keyword_group(
+            "b".to_string(),
+            vec![FileEntry {
+                path: "/x/b.txt".to_string(),
+                size: None,
+                modified: None,
+            }],
+        );
+
+        let ndjson = export.to_ndjson().unwrap();
+        let lines: Vec<serde_json::Value> = ndjson
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+
+        assert_eq!(lines[0]["_type"], "metadata");
+        assert_eq!(lines[0]["metadata"]["total_count"], 2);
+        let params: SearchParams =
+            serde_json::from_value(lines[0]["search_params"].clone()).unwrap();
+        assert_eq!(params.query, "a; b");
+        assert!(params.case_sensitive);
+
+        // 路径中的换行被转义，每条记录仍占一行
+        assert_eq!(lines[1]["_type"], "file");
+        assert_eq!(lines[1]["keyword"], "a");
+        let file: FileEntry = serde_json::from_value(lines[1].clone()).unwrap();
+        assert_eq!(file.path, "/x/a\nb.txt");
+        assert_eq!(file.size, Some(5));
+        assert_eq!(file.modified.as_deref(), Some("2024-01-01T00:00:00Z"));
+        assert_eq!(lines[2]["keyword"], "b");
+        assert!(lines[2].get("size").is_none());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("results.jsonl");
+        export.export_to_file(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), ndjson);
+    }
+
+    #[test]
+    fn test_convert_search_results_merges_databases() {
+        use crate::searcher::SearchResult;
+
+        let result = |path: &str, size: i64| SearchResult {
+            path: path.to_string(),
+            name: path.rsplit('/').next().unwrap().to_string(),
+            size: Some(size),
+            mtime: Some(1_704_067_200.0),
+            score: None,
+        };
+        let config = SearchConfig {
+            search_mode: SearchMode::Glob,
+            extensions: vec!["log".to_string()],
+            min_size: Some(10),
+            sort_by: SortBy::Size,
+            descending: true,
+            ..Default::default()
+        };
+        let export = convert_from_search_results(SearchConvertParams {
+            query: "*.log; none".to_string(),
+            selected_db: "all".to_string(),
+            config: &config,
+            results: vec![
+                (
+                    "a.db".to_string(),
+                    "*.log".to_string(),
+                    vec![result("/a/x.log", 20)],
+                ),
+                ("a.db".to_string(), "none".to_string(), vec![]),
+                (
+                    "b.db".to_string(),
+                    "*.log".to_string(),
+                    vec![result("/b/y.log", 90)],
+                ),
+            ],
+            include_empty: false,
+        });
+
+        assert_eq!(export.results.len(), 1);
+        let paths: Vec<_> = export.results[0]
+            .files
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["/b/y.log", "/a/x.log"]);
+        assert_eq!(export.metadata.total_count, 2);
+
+        // 搜索参数随结果一起导出，并能重新导入
+        let toml = export.to_toml().unwrap();
+        assert!(toml.contains("mode = \"glob\""));
+        assert!(toml.contains("sort = \"size\""));
+        let params = ExportedSearchResults::from_toml(&toml)
+            .unwrap()
+            .search_params;
+        assert_eq!(params.mode, Some(SearchMode::Glob));
+        assert_eq!(params.extensions, vec!["log"]);
+        assert_eq!(params.min_size, Some(10));
+        assert_eq!(params.limit, Some(2000));
+        assert!(params.descending && !params.stem);
+    }
 }
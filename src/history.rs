@@ -4,6 +4,12 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 
+/// 历史文件名
+const HISTORY_FILE_NAME: &str = "search_history.json";
+
+/// 默认保留的历史记录条数
+pub const DEFAULT_MAX_ENTRIES: usize = 500;
+
 /// 搜索历史记录项
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchHistoryItem {
@@ -26,22 +32,33 @@ pub struct SearchHistoryItem {
 /// 搜索历史管理器
 pub struct SearchHistory {
     history_file: PathBuf,
+    /// 旧版本的历史文件，新文件不存在时从这里读取
+    legacy_file: Option<PathBuf>,
     max_entries: usize,
 }
 
 impl SearchHistory {
     /// 创建新的历史管理器
+    ///
+    /// 使用默认路径时，若新位置还没有历史文件，会读取旧版本保存的位置，
+    /// 下次保存时写入新位置，完成一次性迁移
     pub fn new(history_file: PathBuf, max_entries: usize) -> Self {
+        let legacy_file = legacy_path()
+            .filter(|legacy| history_file == Self::default_path() && *legacy != history_file);
         Self {
             history_file,
+            legacy_file,
             max_entries,
         }
     }
 
     /// 获取默认历史文件路径
+    ///
+    /// 位于系统配置目录下：Linux 为 `$XDG_CONFIG_HOME` 或 `~/.config`，
+    /// macOS 为 `~/Library/Application Support`，Windows 为 `%APPDATA%`
     pub fn default_path() -> PathBuf {
         if let Some(config_dir) = dirs::config_dir() {
-            config_dir.join("reminex").join("search_history.json")
+            config_dir.join("reminex").join(HISTORY_FILE_NAME)
         } else {
             PathBuf::from(".reminex_history.json")
         }
@@ -90,11 +107,15 @@ impl SearchHistory {
 
     /// 加载历史记录
     fn load_history(&self) -> Result<Vec<SearchHistoryItem>> {
-        if !self.history_file.exists() {
+        let file = match &self.legacy_file {
+            Some(legacy) if !self.history_file.exists() && legacy.exists() => legacy,
+            _ => &self.history_file,
+        };
+        if !file.exists() {
             return Ok(vec![]);
         }
 
-        let content = fs::read_to_string(&self.history_file)?;
+        let content = fs::read_to_string(file)?;
         let history: Vec<SearchHistoryItem> = serde_json::from_str(&content)?;
         Ok(history)
     }
@@ -112,18 +133,93 @@ impl SearchHistory {
     }
 }
 
-// 需要添加 dirs crate 来获取配置目录
-// 暂时使用简化版本
-mod dirs {
-    use std::path::PathBuf;
+/// 旧版本使用的历史文件路径：Windows 为 `%APPDATA%`，其余系统为 `~/.config`
+fn legacy_path() -> Option<PathBuf> {
+    let config_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config"))
+    };
+    config_dir.map(|dir| dir.join("reminex").join(HISTORY_FILE_NAME))
+}
 
-    pub fn config_dir() -> Option<PathBuf> {
-        if cfg!(target_os = "windows") {
-            std::env::var("APPDATA").ok().map(PathBuf::from)
-        } else {
-            std::env::var("HOME")
-                .ok()
-                .map(|home| PathBuf::from(home).join(".config"))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn item(query: &str) -> SearchHistoryItem {
+        SearchHistoryItem {
+            query: query.to_string(),
+            selected_db: "all".to_string(),
+            timestamp: Utc::now(),
+            result_count: 1,
+            name_only: false,
+            case_sensitive: false,
         }
     }
+
+    #[test]
+    fn test_default_path_uses_platform_config_dir() {
+        let path = SearchHistory::default_path();
+        assert!(path.ends_with(Path::new("reminex").join(HISTORY_FILE_NAME)));
+
+        let config_dir = path.parent().unwrap().parent().unwrap();
+        #[cfg(target_os = "linux")]
+        {
+            let expected = std::env::var_os("XDG_CONFIG_HOME")
+                .map(PathBuf::from)
+                .filter(|dir| dir.is_absolute())
+                .or_else(|| dirs::home_dir().map(|home| home.join(".config")));
+            assert_eq!(Some(config_dir.to_path_buf()), expected);
+        }
+        #[cfg(target_os = "macos")]
+        assert!(config_dir.ends_with("Library/Application Support"));
+        #[cfg(windows)]
+        assert_eq!(
+            Some(config_dir.to_path_buf()),
+            std::env::var_os("APPDATA").map(PathBuf::from)
+        );
+    }
+
+    #[test]
+    fn test_reads_legacy_file_until_new_one_is_saved() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join("old").join(HISTORY_FILE_NAME);
+        let current = dir.path().join("new").join(HISTORY_FILE_NAME);
+        SearchHistory::new(legacy.clone(), 10)
+            .add_entry(item("old query"))
+            .unwrap();
+
+        let history = SearchHistory {
+            history_file: current.clone(),
+            legacy_file: Some(legacy.clone()),
+            max_entries: 10,
+        };
+        let queries = |history: &SearchHistory| -> Vec<String> {
+            history
+                .get_all()
+                .unwrap()
+                .into_iter()
+                .map(|i| i.query)
+                .collect()
+        };
+        assert_eq!(queries(&history), vec!["old query"]);
+
+        history.add_entry(item("new query")).unwrap();
+        assert!(current.exists());
+        assert_eq!(queries(&history), vec!["new query", "old query"]);
+
+        // 迁移后只使用新文件，旧文件保持不变
+        history.clear().unwrap();
+        assert!(queries(&history).is_empty());
+        assert_eq!(queries(&SearchHistory::new(legacy, 10)), vec!["old query"]);
+    }
+
+    #[test]
+    fn test_explicit_path_has_no_legacy_fallback() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = SearchHistory::new(dir.path().join("custom.json"), 10);
+        assert!(history.legacy_file.is_none());
+    }
 }
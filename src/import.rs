@@ -0,0 +1,357 @@
+use anyhow::{Context, Result, bail};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+use std::path::Path;
+
+use crate::db::{Database, Index};
+use crate::dump::CSV_DUMP_TITLE;
+
+/// Options for importing a file list from CSV.
+///
+/// Column numbers are 1-based, matching how spreadsheets number columns.
+#[derive(Debug, Clone)]
+pub struct CsvImportOptions {
+    /// Column containing the full file path
+    pub path_col: usize,
+    /// Column containing the file name (derived from the path if absent)
+    pub name_col: Option<usize>,
+    /// Column containing the file size in bytes
+    pub size_col: Option<usize>,
+    /// Column containing the modification time as a unix timestamp
+    pub mtime_col: Option<usize>,
+    /// Whether the first row is a header row
+    pub has_header: bool,
+    /// Number of rows to insert per transaction
+    pub batch_size: usize,
+}
+
+impl Default for CsvImportOptions {
+    fn default() -> Self {
+        Self {
+            path_col: 1,
+            name_col: None,
+            size_col: None,
+            mtime_col: None,
+            has_header: true,
+            batch_size: 5000,
+        }
+    }
+}
+
+impl CsvImportOptions {
+    /// Checks that column numbers are 1-based and the batch size is usable.
+    fn validate(&self) -> Result<()> {
+        let columns = [
+            ("path", Some(self.path_col)),
+            ("name", self.name_col),
+            ("size", self.size_col),
+            ("mtime", self.mtime_col),
+        ];
+
+        for (label, col) in columns {
+            if col == Some(0) {
+                bail!("Column numbers start at 1, got 0 for the {} column", label);
+            }
+        }
+
+        if self.batch_size == 0 {
+            bail!("Batch size must be greater than 0");
+        }
+
+        Ok(())
+    }
+}
+
+/// Imports a CSV file list into a database.
+///
+/// Rows are streamed from the file and written with [`Database::add_idxs`]
+/// in batches, so arbitrarily large lists can be imported. The `#` comment
+/// block at the start of a CSV dump is skipped; every other line is data,
+/// including ones starting with `#`.
+///
+/// # Arguments
+/// * `csv_path` - CSV file to read
+/// * `db` - Database to write entries to
+/// * `options` - Column mapping and batching options
+///
+/// # Returns
+/// Number of imported rows, or an error naming the offending line
+pub fn import_csv(csv_path: &Path, db: &Database, options: &CsvImportOptions) -> Result<usize> {
+    options.validate()?;
+
+    let file = File::open(csv_path)
+        .with_context(|| format!("Failed to open CSV file: {}", csv_path.display()))?;
+    let (input, skipped) = skip_dump_header(BufReader::new(file))
+        .with_context(|| format!("Failed to read CSV file: {}", csv_path.display()))?;
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(options.has_header)
+        .flexible(true)
+        .from_reader(input);
+
+    let mut batch = Vec::with_capacity(options.batch_size);
+    let mut imported = 0;
+
+    for record in reader.records() {
+        let record = record.context("Failed to parse CSV")?;
+        let line = record.position().map(|p| p.line()).unwrap_or(0) + skipped;
+
+        let idx = parse_record(&record, options).with_context(|| format!("CSV line {}", line))?;
+        batch.push(idx);
+
+        if batch.len() >= options.batch_size {
+            db.add_idxs(&batch)
+                .context("Failed to write batch to database")?;
+            imported += batch.len();
+            batch.clear();
+        }
+    }
+
+    if !batch.is_empty() {
+        db.add_idxs(&batch)
+            .context("Failed to write final batch to database")?;
+        imported += batch.len();
+    }
+
+    Ok(imported)
+}
+
+/// Skips the `#` comment block written before the table of a CSV dump.
+///
+/// The block is only recognized when the first line is [`CSV_DUMP_TITLE`];
+/// it then runs up to the first line not starting with `# `. Returns the
+/// remaining input and the number of lines skipped.
+fn skip_dump_header<R: BufRead>(mut reader: R) -> io::Result<(impl Read, u64)> {
+    let mut skipped = 0;
+    let mut line = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let in_header = if skipped == 0 {
+            line.trim_ascii_end() == CSV_DUMP_TITLE.as_bytes()
+        } else {
+            line.starts_with(b"# ")
+        };
+        if !in_header {
+            break;
+        }
+        skipped += 1;
+    }
+    // The line that ended the block is data and is read again
+    Ok((Cursor::new(line).chain(reader), skipped))
+}
+
+/// Converts one CSV record into an index entry.
+fn parse_record(record: &csv::StringRecord, options: &CsvImportOptions) -> Result<Index> {
+    let path = field(record, options.path_col, "path")?
+        .filter(|p| !p.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("Empty path in column {}", options.path_col))?;
+
+    let name = match options.name_col {
+        Some(col) => field(record, col, "name")?.filter(|n| !n.is_empty()),
+        None => None,
+    }
+    .unwrap_or_else(|| name_from_path(path));
+
+    let size = match options.size_col {
+        Some(col) => field(record, col, "size")?
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                v.parse::<i64>()
+                    .with_context(|| format!("Invalid size '{}' in column {}", v, col))
+            })
+            .transpose()?,
+        None => None,
+    };
+
+    let mtime = match options.mtime_col {
+        Some(col) => field(record, col, "mtime")?
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                v.parse::<f64>()
+                    .with_context(|| format!("Invalid mtime '{}' in column {}", v, col))
+            })
+            .transpose()?,
+        None => None,
+    };
+
+    let mut idx = Index::new(path.to_string(), name.to_string());
+    idx.size = size;
+    idx.mtime = mtime;
+    Ok(idx)
+}
+
+/// Returns the trimmed value of a 1-based column, failing if the row is too short.
+fn field<'a>(record: &'a csv::StringRecord, col: usize, label: &str) -> Result<Option<&'a str>> {
+    match record.get(col - 1) {
+        Some(value) => Ok(Some(value.trim())),
+        None => bail!(
+            "Row has {} columns, but the {} column is {}",
+            record.len(),
+            label,
+            col
+        ),
+    }
+}
+
+/// Derives a file name from a path using either separator style.
+fn name_from_path(path: &str) -> &str {
+    path.trim_end_matches(['/', '\\'])
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup(csv: &str) -> (TempDir, Database, std::path::PathBuf) {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("import.reminex.db")).unwrap();
+        let csv_path = temp_dir.path().join("files.csv");
+        fs::write(&csv_path, csv).unwrap();
+        (temp_dir, db, csv_path)
+    }
+
+    fn all_rows(db: &Database) -> Vec<(String, String, Option<f64>, Option<i64>)> {
+        db.batch_operation(|conn| {
+            let mut stmt =
+                conn.prepare("SELECT path, name, mtime, size FROM files ORDER BY path")?;
+            let rows = stmt
+                .query_map([], |row| {
+                    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(rows)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_import_with_all_columns() {
+        let (_temp, db, csv_path) = setup(
+            "path,name,size,mtime\n\
+             \"D:\\photos\\a, b.jpg\",\"a, b.jpg\",1024,1700000000.5\n\
+             /data/report.pdf,report.pdf,,\n",
+        );
+
+        let options = CsvImportOptions {
+            name_col: Some(2),
+            size_col: Some(3),
+            mtime_col: Some(4),
+            batch_size: 1,
+            ..Default::default()
+        };
+        let count = import_csv(&csv_path, &db, &options).unwrap();
+        assert_eq!(count, 2);
+
+        let rows = all_rows(&db);
+        assert_eq!(
+            rows[0],
+            (
+                "/data/report.pdf".to_string(),
+                "report.pdf".to_string(),
+                None,
+                None
+            )
+        );
+        assert_eq!(
+            rows[1],
+            (
+                "D:\\photos\\a, b.jpg".to_string(),
+                "a, b.jpg".to_string(),
+                Some(1700000000.5),
+                Some(1024)
+            )
+        );
+    }
+
+    #[test]
+    fn test_import_derives_name_from_path() {
+        let (_temp, db, csv_path) = setup("C:\\docs\\x.txt\n/home/u/y.txt\n");
+
+        let options = CsvImportOptions {
+            has_header: false,
+            ..Default::default()
+        };
+        assert_eq!(import_csv(&csv_path, &db, &options).unwrap(), 2);
+
+        let names: Vec<_> = all_rows(&db).into_iter().map(|r| r.1).collect();
+        assert_eq!(names, vec!["y.txt", "x.txt"]);
+    }
+
+    #[test]
+    fn test_import_reports_line_numbers() {
+        let (_temp, db, csv_path) = setup("path,size\n/a.txt,10\n/b.txt,big\n");
+
+        let options = CsvImportOptions {
+            size_col: Some(2),
+            ..Default::default()
+        };
+        let err = import_csv(&csv_path, &db, &options).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("line 3"), "unexpected error: {}", message);
+        assert!(message.contains("big"), "unexpected error: {}", message);
+    }
+
+    #[test]
+    fn test_import_keeps_rows_starting_with_hash() {
+        let (_temp, db, csv_path) = setup("path\n#drafts/a.txt\n# notes.txt\n/b.txt\n");
+
+        assert_eq!(
+            import_csv(&csv_path, &db, &CsvImportOptions::default()).unwrap(),
+            3
+        );
+        let paths: Vec<_> = all_rows(&db).into_iter().map(|r| r.0).collect();
+        assert_eq!(paths, vec!["# notes.txt", "#drafts/a.txt", "/b.txt"]);
+    }
+
+    #[test]
+    fn test_import_skips_dump_header() {
+        let (_temp, db, csv_path) = setup(&format!(
+            "{}\n# format_version: 1\npath,size\n#a.txt,10\n/b.txt,big\n",
+            CSV_DUMP_TITLE
+        ));
+
+        let options = CsvImportOptions {
+            size_col: Some(2),
+            ..Default::default()
+        };
+        let message = format!("{:#}", import_csv(&csv_path, &db, &options).unwrap_err());
+        // Line numbers count the skipped header
+        assert!(message.contains("line 5"), "unexpected error: {}", message);
+
+        fs::write(
+            &csv_path,
+            format!(
+                "{}\n# format_version: 1\npath,size\n#a.txt,10\n",
+                CSV_DUMP_TITLE
+            ),
+        )
+        .unwrap();
+        assert_eq!(import_csv(&csv_path, &db, &options).unwrap(), 1);
+        assert_eq!(all_rows(&db)[0].0, "#a.txt");
+    }
+
+    #[test]
+    fn test_import_validates_columns() {
+        let (_temp, db, csv_path) = setup("path\n/a.txt\n");
+
+        let options = CsvImportOptions {
+            path_col: 0,
+            ..Default::default()
+        };
+        assert!(import_csv(&csv_path, &db, &options).is_err());
+
+        let options = CsvImportOptions {
+            name_col: Some(5),
+            ..Default::default()
+        };
+        let message = format!("{:#}", import_csv(&csv_path, &db, &options).unwrap_err());
+        assert!(message.contains("line 2"), "unexpected error: {}", message);
+    }
+}
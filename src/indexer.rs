@@ -1,15 +1,21 @@
 use anyhow::{Context, Result};
 use crossbeam_channel::{Sender, bounded};
-use indicatif::{ProgressBar, ProgressStyle};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::Match;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use rayon::prelude::*;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::time::{Duration, Instant, SystemTime};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 
-use crate::db::{Database, Index};
+use crate::db::{Database, Index, StoredMetadata, file_metadata, normalize_path};
 
 /// Result of an indexing operation
 #[derive(Debug, Clone)]
@@ -18,85 +24,380 @@ pub struct IndexResult {
     pub duration: Duration,
     /// Paths that were skipped due to permission errors
     pub skipped_paths: Vec<String>,
+    /// Time breakdown, when [`IndexConfig::profile`] is set
+    pub profile: Option<IndexProfile>,
+    /// Sockets, FIFOs and device nodes left out of the index
+    pub skipped_special: usize,
+    /// Zero-byte files left out with [`IndexConfig::skip_empty`]
+    pub skipped_empty: usize,
+    /// Rows written for paths not indexed under this root before
+    ///
+    /// Only incremental scans tell new rows from updated ones; otherwise
+    /// every written row is counted here.
+    pub added: usize,
+    /// Rows rewritten because their size or modification time changed
+    pub updated: usize,
+    /// Files left alone because they were unchanged (incremental scans only)
+    pub skipped: usize,
+    /// Rows deleted because their files are gone, see [`IndexConfig::prune`]
+    pub removed: usize,
+    /// Directories not entered because of [`IndexConfig::max_depth`]
+    ///
+    /// Kept apart from `skipped_paths`, since nothing went wrong there.
+    pub depth_limited: usize,
+    /// Directory links that were not followed: all of them by default, and
+    /// broken links or links to targets already visited with
+    /// [`IndexConfig::follow_junctions`]
+    pub skipped_links: Vec<String>,
 }
 
-/// Scans a directory and collects file indices without metadata.
-///
-/// Uses parallel processing with work-stealing for efficient scanning.
-/// Results are sent to the database buffer channel as they are discovered.
-///
-/// # Arguments
-/// * `root` - Root directory to scan
-/// * `db` - Database instance to write indices to
-/// * `batch_size` - Number of indices to batch before writing (recommended: 1000-10000)
+/// Where the time of a scan went, collected with [`IndexConfig::profile`].
 ///
-/// # Returns
-/// IndexResult containing duration and skipped paths
-pub fn scan_idxs<P: AsRef<Path>>(root: P, db: &Database, batch_size: usize) -> Result<IndexResult> {
-    let start = Instant::now();
-    let root = root.as_ref();
+/// Traversal, metadata and send wait are summed over all worker threads, so
+/// together they can exceed `total` on a multi-core machine.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexProfile {
+    /// Wall time of the whole scan
+    pub total: Duration,
+    /// Time spent reading directories and classifying entries
+    pub traversal: Duration,
+    /// Time spent building entries, including metadata and permissions
+    pub metadata: Duration,
+    /// Time workers were blocked on a full channel, waiting for the writer
+    pub send_wait: Duration,
+    /// Time the writer spent committing batches
+    pub db_write: Duration,
+    /// Largest number of entries seen queued in the channel
+    pub peak_queue_depth: usize,
+    /// Capacity of the channel
+    pub queue_capacity: usize,
+}
 
-    if !root.exists() {
-        anyhow::bail!("Root path does not exist: {}", root.display());
+/// Per-phase counters shared by the scan workers.
+#[derive(Default)]
+struct ScanProfiler {
+    traversal_ns: AtomicU64,
+    metadata_ns: AtomicU64,
+    send_wait_ns: AtomicU64,
+    peak_queue_depth: AtomicUsize,
+}
+
+impl ScanProfiler {
+    fn add(counter: &AtomicU64, elapsed: Duration) {
+        counter.fetch_add(elapsed.as_nanos() as u64, Ordering::Relaxed);
     }
 
-    // Create progress bar
-    let progress = Arc::new(ProgressBar::new_spinner());
-    progress.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.green} [{elapsed_precise}] {msg} {pos} 个文件")
-            .unwrap(),
-    );
-    progress.set_message("扫描中");
+    fn read(counter: &AtomicU64) -> Duration {
+        Duration::from_nanos(counter.load(Ordering::Relaxed))
+    }
+}
 
-    let counter = Arc::new(AtomicU64::new(0));
-    let skipped_paths = Arc::new(Mutex::new(Vec::new()));
+/// Separates an archive's path from the path of an entry inside it
+pub const ARCHIVE_SEPARATOR: &str = "!/";
 
-    // Channel for collecting indices from parallel workers
-    let (tx, rx) = bounded::<Index>(batch_size * 2);
+/// Options controlling how a directory tree is scanned
+#[derive(Debug, Clone)]
+pub struct IndexConfig {
+    /// Record modification time and size for each file
+    pub with_metadata: bool,
+    /// Record owner and mode bits (Unix) or file attributes (Windows)
+    pub with_perms: bool,
+    /// Descend into directory junctions, reparse points and symlinks
+    ///
+    /// Off by default, since links can point back into the tree and cause
+    /// cycles or duplicate entries. When enabled, each directory is visited
+    /// at most once, keyed by its canonical path.
+    pub follow_junctions: bool,
+    /// Database files to leave out of the scan, along with their
+    /// `-wal`, `-shm` and `-journal` side files
+    pub exclude_paths: Vec<PathBuf>,
+    /// Advance the progress display every this many received files,
+    /// independently of how often batches are committed
+    pub progress_step: usize,
+    /// Also index the entries of `.zip` archives
+    ///
+    /// Off by default, since every archive has to be opened and its central
+    /// directory read. Entries are stored under a synthetic path of the form
+    /// `archive.zip!/inner/path.jpg`, see [`ARCHIVE_SEPARATOR`].
+    pub index_archives: bool,
+    /// Time each scan phase and gauge the channel depth, see [`IndexProfile`]
+    ///
+    /// Off by default; when off, no timers are read on the hot path.
+    pub profile: bool,
+    /// Leave out zero-byte files
+    ///
+    /// Sizes are only known when `with_metadata` is set, so this has no
+    /// effect without it.
+    pub skip_empty: bool,
+    /// Skip files whose size and modification time match the stored row
+    ///
+    /// The rows of the root are loaded into memory once when the scan
+    /// starts. Implies `with_metadata`, since there is nothing to compare
+    /// otherwise.
+    pub incremental: bool,
+    /// Skip entries matched by `.gitignore` and `.ignore` files found while
+    /// walking, and by the global git excludes file
+    ///
+    /// Each file applies to its own directory and below, with git
+    /// semantics: deeper files take precedence and `!` re-includes. In one
+    /// directory `.ignore` takes precedence over `.gitignore`, and both over
+    /// the global excludes (`core.excludesFile`, or `~/.config/git/ignore`).
+    /// `.git` directories are skipped as well. Ignore files above the scan
+    /// root are not read.
+    pub respect_gitignore: bool,
+    /// Leave out files and directories whose name starts with `.`, and on
+    /// Windows those with the hidden or system attribute
+    ///
+    /// Independent of `respect_gitignore`; the scan root itself is always
+    /// scanned.
+    pub skip_hidden: bool,
+    /// Extra `.gitignore`-style files whose patterns apply to the whole
+    /// scan, relative to the scan root
+    pub ignore_files: Vec<PathBuf>,
+    /// Shell-style globs of files and directories to leave out
+    ///
+    /// A pattern without `/`, such as `*.tmp` or `cache`, matches entry
+    /// names at any depth; one with `/`, such as `build/**/*.o`, matches the
+    /// path relative to the scan root. A matching directory is not
+    /// descended into.
+    pub exclude_globs: Vec<String>,
+    /// Deepest directory level to descend into, `None` for no limit
+    ///
+    /// Level 0 is the scan root, so `Some(0)` indexes only the files
+    /// directly inside it. Files at the last level are still indexed; their
+    /// subdirectories are not entered, and pruning keeps the rows below them.
+    pub max_depth: Option<usize>,
+    /// Worker threads for the walk, 0 for rayon's default of one per core
+    ///
+    /// A nonzero value runs the walk on a dedicated pool instead of the
+    /// global one. On an SSD the default is fastest, since many metadata
+    /// requests can be in flight at once. On a spinning disk every thread
+    /// pulls the head to a different directory and seeking dominates, so 1
+    /// or 2 threads are usually faster there.
+    pub threads: usize,
+    /// Delete rows below the scan root whose files were not found
+    ///
+    /// Rows below directories that could not be read are kept, and nothing
+    /// is deleted if writing fails. The paths seen are held in memory until
+    /// the scan ends.
+    pub prune: bool,
+}
 
-    // Clone db for the writer thread
-    let db_clone = db.clone();
-    let progress_clone = progress.clone();
-    let counter_clone = counter.clone();
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            with_metadata: false,
+            with_perms: false,
+            follow_junctions: false,
+            exclude_paths: Vec::new(),
+            progress_step: 100,
+            index_archives: false,
+            profile: false,
+            skip_empty: false,
+            incremental: false,
+            respect_gitignore: false,
+            skip_hidden: false,
+            ignore_files: Vec::new(),
+            exclude_globs: Vec::new(),
+            max_depth: None,
+            threads: 0,
+            prune: false,
+        }
+    }
+}
 
-    // Spawn writer thread to batch insert indices
-    let writer_handle = std::thread::spawn(move || {
-        write_indices_batched_with_progress(
-            rx,
-            &db_clone,
-            batch_size,
-            progress_clone,
-            counter_clone,
-        )
-    });
+/// Shared state for one scan, passed down the recursive walk
+struct ScanContext {
+    tx: Sender<Index>,
+    skipped_paths: Mutex<Vec<String>>,
+    /// Directories not entered because of `max_depth`
+    depth_limited: Mutex<Vec<String>>,
+    /// Directory links not descended into, see [`should_descend`]
+    skipped_links: Mutex<Vec<String>>,
+    config: IndexConfig,
+    /// Canonical paths of visited directories (only tracked when following links)
+    visited: Option<Mutex<HashSet<PathBuf>>>,
+    /// Canonical paths of files that must not be indexed
+    excluded: HashSet<PathBuf>,
+    /// Set once the writer has stopped receiving, so the walk can end early
+    writer_stopped: AtomicBool,
+    /// Phase timers, only present when profiling
+    profiler: Option<ScanProfiler>,
+    /// Count of non-regular files left out
+    skipped_special: AtomicUsize,
+    /// Count of zero-byte files left out
+    skipped_empty: AtomicUsize,
+    /// Compiled `exclude_globs`
+    excludes: ExcludeGlobs,
+}
+
+/// Compiled [`IndexConfig::exclude_globs`], split by what they match.
+struct ExcludeGlobs {
+    /// Patterns without `/`, matched against entry names
+    names: GlobSet,
+    /// Patterns with `/`, matched against paths relative to `root`
+    paths: GlobSet,
+    root: PathBuf,
+}
+
+impl ExcludeGlobs {
+    /// Compiles the patterns for a scan of `root`.
+    ///
+    /// # Errors
+    /// Returns an error naming the first pattern that is not a valid glob.
+    fn new(root: &Path, patterns: &[String]) -> Result<Self> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in patterns {
+            let pattern = pattern.replace('\\', "/");
+            let glob = GlobBuilder::new(pattern.trim_start_matches('/'))
+                .literal_separator(true)
+                .backslash_escape(false)
+                .build()
+                .with_context(|| format!("Invalid exclude pattern: {}", pattern))?;
+            if pattern.contains('/') {
+                paths.add(glob);
+            } else {
+                names.add(glob);
+            }
+        }
+        Ok(Self {
+            names: names.build()?,
+            paths: paths.build()?,
+            root: root.to_path_buf(),
+        })
+    }
 
-    // Parallel scanning
-    scan_directory_parallel(root, tx, skipped_paths.clone());
+    fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.paths.is_empty()
+    }
 
-    // Wait for writer to finish
-    let write_result = writer_handle
-        .join()
-        .map_err(|_| anyhow::anyhow!("Writer thread panicked"))?;
+    /// Returns true if the entry at `path` named `name` is excluded.
+    fn is_match(&self, path: &Path, name: &OsStr) -> bool {
+        if self.names.is_match(name) {
+            return true;
+        }
+        if self.paths.is_empty() {
+            return false;
+        }
+        path.strip_prefix(&self.root).is_ok_and(|relative| {
+            self.paths
+                .is_match(relative.to_string_lossy().replace('\\', "/"))
+        })
+    }
+}
 
-    write_result?;
+/// What a directory entry is, with links resolved to their target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    File,
+    Dir,
+    /// Socket, FIFO, block or character device
+    Special,
+}
 
-    progress.finish_with_message("完成");
+/// Classifies a directory entry.
+///
+/// The entry's own file type is usually known without a `stat` call; only
+/// links need their target looked up. Broken links count as directories,
+/// so they go through [`should_descend`] like any other link.
+fn classify_entry(entry: &fs::DirEntry) -> EntryKind {
+    let file_type = match entry.file_type() {
+        Ok(t) if t.is_symlink() => match fs::metadata(entry.path()) {
+            Ok(metadata) => metadata.file_type(),
+            Err(_) => return EntryKind::Dir,
+        },
+        Ok(t) => t,
+        Err(_) => return EntryKind::Dir,
+    };
 
-    // Report skipped paths
-    let skipped = skipped_paths.lock().unwrap();
-    if !skipped.is_empty() {
-        eprintln!("\n⚠️  以下 {} 个路径因权限不足被跳过:", skipped.len());
-        for path in skipped.iter() {
-            eprintln!("  ❌ {}", path);
+    if file_type.is_dir() {
+        EntryKind::Dir
+    } else if file_type.is_file() {
+        EntryKind::File
+    } else {
+        EntryKind::Special
+    }
+}
+
+impl ScanContext {
+    /// Sends an entry to the writer, timing the send and sampling the queue
+    /// depth when profiling.
+    ///
+    /// # Returns
+    /// False if the writer has stopped receiving
+    fn send(&self, idx: Index) -> bool {
+        let sent = match &self.profiler {
+            None => self.tx.send(idx).is_ok(),
+            Some(profiler) => {
+                let started = Instant::now();
+                let sent = self.tx.send(idx).is_ok();
+                ScanProfiler::add(&profiler.send_wait_ns, started.elapsed());
+                profiler
+                    .peak_queue_depth
+                    .fetch_max(self.tx.len(), Ordering::Relaxed);
+                sent
+            }
+        };
+
+        // The channel only closes when the writer stopped on an error
+        if !sent {
+            self.writer_stopped.store(true, Ordering::Relaxed);
         }
-        eprintln!("\n💡 提示: 以管理员权限运行可能可以索引这些路径");
+        sent
     }
+}
 
-    Ok(IndexResult {
-        duration: start.elapsed(),
-        skipped_paths: skipped.clone(),
-    })
+/// Suffixes of the files SQLite keeps next to a database
+const SQLITE_SIDE_FILES: [&str; 3] = ["-wal", "-shm", "-journal"];
+
+/// Number of directory entries held at once while indexing a directory's files
+const FILE_CHUNK_SIZE: usize = 4096;
+
+/// Resolves database paths to the canonical paths of the database and its side files.
+///
+/// Side files may not exist yet, so only the parent directory is canonicalized.
+fn excluded_files(db_paths: &[PathBuf]) -> HashSet<PathBuf> {
+    let mut excluded = HashSet::new();
+
+    for path in db_paths {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            continue;
+        };
+        let parent = if parent.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            parent
+        };
+        let Ok(parent) = fs::canonicalize(parent) else {
+            continue;
+        };
+
+        excluded.insert(parent.join(name));
+        for suffix in SQLITE_SIDE_FILES {
+            let mut side = name.to_owned();
+            side.push(suffix);
+            excluded.insert(parent.join(side));
+        }
+    }
+
+    excluded
+}
+
+/// Scans a directory and collects file indices without metadata.
+///
+/// Uses parallel processing with work-stealing for efficient scanning.
+/// Results are sent to the database buffer channel as they are discovered.
+///
+/// # Arguments
+/// * `root` - Root directory to scan
+/// * `db` - Database instance to write indices to
+/// * `batch_size` - Number of indices to batch before writing (recommended: 1000-10000)
+///
+/// # Returns
+/// IndexResult containing duration and skipped paths
+pub fn scan_idxs<P: AsRef<Path>>(root: P, db: &Database, batch_size: usize) -> Result<IndexResult> {
+    scan_idxs_with_config(root, db, batch_size, &IndexConfig::default())
 }
 
 /// Scans a directory and collects file indices with metadata (mtime, size).
@@ -116,6 +417,91 @@ pub fn scan_idxs_with_metadata<P: AsRef<Path>>(
     db: &Database,
     batch_size: usize,
 ) -> Result<IndexResult> {
+    let config = IndexConfig {
+        with_metadata: true,
+        ..Default::default()
+    };
+    scan_idxs_with_config(root, db, batch_size, &config)
+}
+
+/// Rescans a directory, writing only new and changed files.
+///
+/// Files whose size and modification time match the stored row are
+/// skipped, which makes re-indexing a mostly unchanged tree much faster.
+///
+/// # Returns
+/// IndexResult with added, updated and skipped counts
+pub fn scan_idxs_incremental<P: AsRef<Path>>(
+    root: P,
+    db: &Database,
+    batch_size: usize,
+) -> Result<IndexResult> {
+    let config = IndexConfig {
+        with_metadata: true,
+        incremental: true,
+        ..Default::default()
+    };
+    scan_idxs_with_config(root, db, batch_size, &config)
+}
+
+/// Scans a directory with the given options.
+///
+/// # Arguments
+/// * `root` - Root directory to scan
+/// * `db` - Database instance to write indices to
+/// * `batch_size` - Number of indices to batch before writing (recommended: 1000-10000)
+/// * `config` - Scan options
+///
+/// # Returns
+/// IndexResult containing duration and skipped paths
+pub fn scan_idxs_with_config<P: AsRef<Path>>(
+    root: P,
+    db: &Database,
+    batch_size: usize,
+    config: &IndexConfig,
+) -> Result<IndexResult> {
+    scan_idxs_with_progress(root, db, batch_size, config, |_| {})
+}
+
+/// Progress of a scan, reported by [`scan_idxs_with_progress`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// Files received from the walk so far, reported every
+    /// [`IndexConfig::progress_step`] files
+    Scanning(u64),
+    /// Rows of files no longer on disk are being removed, see
+    /// [`IndexConfig::prune`]
+    Pruning,
+    /// The scan finished with this many files written or left unchanged
+    Finished(u64),
+}
+
+/// Scans a directory with the given options, reporting progress.
+///
+/// `on_progress` is called from the writer thread while files are
+/// received, then from the calling thread; it is not called again once the
+/// scan fails.
+///
+/// # Arguments
+/// * `root` - Root directory to scan
+/// * `db` - Database instance to write indices to
+/// * `batch_size` - Number of indices to batch before writing (recommended: 1000-10000)
+/// * `config` - Scan options
+/// * `on_progress` - Progress callback, e.g. updating a spinner
+///
+/// # Returns
+/// IndexResult containing duration and skipped paths
+pub fn scan_idxs_with_progress<P, F>(
+    root: P,
+    db: &Database,
+    batch_size: usize,
+    config: &IndexConfig,
+    on_progress: F,
+) -> Result<IndexResult>
+where
+    P: AsRef<Path>,
+    F: Fn(ProgressEvent) + Send + Sync + 'static,
+{
     let start = Instant::now();
     let root = root.as_ref();
 
@@ -123,45 +509,146 @@ pub fn scan_idxs_with_metadata<P: AsRef<Path>>(
         anyhow::bail!("Root path does not exist: {}", root.display());
     }
 
-    // Create progress bar
-    let progress = Arc::new(ProgressBar::new_spinner());
-    progress.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.green} [{elapsed_precise}] {msg} {pos} 个文件")
-            .unwrap(),
-    );
-    progress.set_message("扫描中 (含元数据)");
+    // Incremental scans compare metadata, so they always read it
+    let mut config = config.clone();
+    config.with_metadata |= config.incremental;
+    let excludes = ExcludeGlobs::new(root, &config.exclude_globs)?;
 
+    let progress = Arc::new(on_progress);
     let counter = Arc::new(AtomicU64::new(0));
-    let skipped_paths = Arc::new(Mutex::new(Vec::new()));
 
-    let (tx, rx) = bounded::<Index>(batch_size * 2);
+    let pool = match config.threads {
+        0 => None,
+        threads => Some(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .context("Failed to create scan thread pool")?,
+        ),
+    };
+
+    // Channel for collecting indices from parallel workers
+    let queue_capacity = batch_size * 2;
+    let (tx, rx) = bounded::<Index>(queue_capacity);
+
+    // Rows are tagged with their root for root-scoped queries
+    let root_id = db
+        .register_root(&root.to_string_lossy())
+        .context("Failed to register scan root")?;
+
+    let options = WriteOptions {
+        root_id,
+        batch_size,
+        progress_step: config.progress_step,
+        existing: if config.incremental {
+            Some(
+                db.metadata_under(root)
+                    .context("Failed to load stored metadata")?,
+            )
+        } else {
+            None
+        },
+        collect_seen: config.prune,
+    };
+
+    // Clone db for the writer thread
     let db_clone = db.clone();
     let progress_clone = progress.clone();
     let counter_clone = counter.clone();
 
+    // Spawn writer thread to batch insert indices
     let writer_handle = std::thread::spawn(move || {
         write_indices_batched_with_progress(
             rx,
             &db_clone,
-            batch_size,
-            progress_clone,
+            options,
+            progress_clone.as_ref(),
             counter_clone,
         )
     });
 
-    scan_directory_parallel_with_metadata(root, tx, skipped_paths.clone());
+    let visited = config.follow_junctions.then(|| {
+        let mut visited = HashSet::new();
+        if let Ok(canonical) = fs::canonicalize(root) {
+            visited.insert(canonical);
+        }
+        Mutex::new(visited)
+    });
+
+    let ctx = ScanContext {
+        tx,
+        skipped_paths: Mutex::new(Vec::new()),
+        depth_limited: Mutex::new(Vec::new()),
+        skipped_links: Mutex::new(Vec::new()),
+        config: config.clone(),
+        visited,
+        excluded: excluded_files(&config.exclude_paths),
+        writer_stopped: AtomicBool::new(false),
+        profiler: config.profile.then(ScanProfiler::default),
+        skipped_special: AtomicUsize::new(0),
+        skipped_empty: AtomicUsize::new(0),
+        excludes,
+    };
 
-    let write_result = writer_handle
-        .join()
-        .map_err(|_| anyhow::anyhow!("Writer thread panicked"))?;
+    let ignores = root_ignores(root, &config)?;
 
-    write_result?;
+    // Parallel scanning, on the dedicated pool if one was requested
+    match &pool {
+        Some(pool) => pool.install(|| scan_directory_parallel(root, 0, &ignores, &ctx)),
+        None => scan_directory_parallel(root, 0, &ignores, &ctx),
+    }
 
-    progress.finish_with_message("完成");
+    // Close the channel so the writer can finish
+    let ScanContext {
+        tx,
+        skipped_paths,
+        depth_limited,
+        skipped_links,
+        profiler,
+        skipped_special,
+        skipped_empty,
+        ..
+    } = ctx;
+    drop(tx);
+
+    // Wait for writer to finish, reporting how far it got if it failed
+    let write_result = writer_handle.join();
+    let committed = counter.load(Ordering::Relaxed);
+    let summary = match write_result {
+        Ok(Ok(summary)) => summary,
+        Ok(Err(e)) => {
+            return Err(e.context(format!("Writer failed after committing {} rows", committed)));
+        }
+        Err(payload) => {
+            anyhow::bail!(
+                "Writer thread panicked after committing {} rows: {}",
+                committed,
+                panic_message(payload.as_ref())
+            );
+        }
+    };
 
     // Report skipped paths
-    let skipped = skipped_paths.lock().unwrap();
+    let skipped = skipped_paths.into_inner().unwrap();
+
+    let mut keep = depth_limited.into_inner().unwrap();
+    let depth_limited = keep.len();
+    let removed = match &summary.seen {
+        Some(seen) => {
+            progress(ProgressEvent::Pruning);
+            keep.extend(skipped.iter().cloned());
+            db.remove_missing_except(root, seen, &keep)
+                .context("Failed to remove missing files")?
+        }
+        None => 0,
+    };
+
+    db.record_scan(&root.to_string_lossy())
+        .context("Failed to record scan")?;
+    progress(ProgressEvent::Finished(
+        committed + summary.unchanged as u64,
+    ));
+
     if !skipped.is_empty() {
         eprintln!("\n⚠️  以下 {} 个路径因权限不足被跳过:", skipped.len());
         for path in skipped.iter() {
@@ -170,138 +657,484 @@ pub fn scan_idxs_with_metadata<P: AsRef<Path>>(
         eprintln!("\n💡 提示: 以管理员权限运行可能可以索引这些路径");
     }
 
+    let duration = start.elapsed();
+    let profile = profiler.map(|profiler| IndexProfile {
+        total: duration,
+        traversal: ScanProfiler::read(&profiler.traversal_ns),
+        metadata: ScanProfiler::read(&profiler.metadata_ns),
+        send_wait: ScanProfiler::read(&profiler.send_wait_ns),
+        db_write: summary.db_write,
+        peak_queue_depth: profiler.peak_queue_depth.into_inner(),
+        queue_capacity,
+    });
+
     Ok(IndexResult {
-        duration: start.elapsed(),
-        skipped_paths: skipped.clone(),
+        duration,
+        skipped_paths: skipped,
+        profile,
+        skipped_special: skipped_special.into_inner(),
+        skipped_empty: skipped_empty.into_inner(),
+        added: summary.added,
+        updated: summary.updated,
+        skipped: summary.unchanged,
+        removed,
+        depth_limited,
+        skipped_links: skipped_links.into_inner().unwrap(),
     })
 }
 
-/// Recursively scans directory in parallel without metadata.
-fn scan_directory_parallel<P: AsRef<Path>>(
-    root: P,
-    tx: Sender<Index>,
-    skipped_paths: Arc<Mutex<Vec<String>>>,
-) {
-    let root = root.as_ref();
+/// Names of the per-directory ignore files read with `respect_gitignore`,
+/// lowest precedence first
+const LOCAL_IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
 
-    // Read entries in current directory
-    let entries: Vec<_> = match fs::read_dir(root) {
-        Ok(entries) => entries.filter_map(|e| e.ok()).collect(),
-        Err(_) => {
-            // Record skipped path and continue
-            if let Ok(mut skipped) = skipped_paths.lock() {
-                skipped.push(root.display().to_string());
-            }
-            return;
+/// Loads the ignore files of one directory into a single matcher.
+///
+/// Later files take precedence, since the last matching pattern wins.
+/// Returns `None` if the directory has none or they cannot be read.
+fn load_local_ignores(dir: &Path) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut found = false;
+    for name in LOCAL_IGNORE_FILES {
+        let file = dir.join(name);
+        if file.is_file() {
+            // Invalid lines are reported, but the valid ones still apply
+            let _ = builder.add(file);
+            found = true;
         }
-    };
-
-    // Separate files and directories
-    let (files, dirs): (Vec<_>, Vec<_>) = entries
-        .into_iter()
-        .partition(|entry| entry.path().is_file());
-
-    // Process files in parallel
-    files.par_iter().for_each(|entry| {
-        let path = entry.path();
-        let path_str = path.to_string_lossy().to_string();
+    }
+    if !found {
+        return None;
+    }
+    builder.build().ok()
+}
 
-        let name = entry.file_name().to_string_lossy().to_string();
+/// Loads the ignore files that apply to the whole scan of `root`, lowest
+/// precedence first: the global git excludes with `respect_gitignore`, then
+/// `ignore_files`.
+///
+/// The ignore files found while walking go on top, see
+/// [`load_local_ignores`].
+fn root_ignores(root: &Path, config: &IndexConfig) -> Result<Vec<Arc<Gitignore>>> {
+    let mut ignores = Vec::new();
+    if config.respect_gitignore
+        && let Some(global) = load_global_ignores(root)
+    {
+        ignores.push(Arc::new(global));
+    }
+    for file in &config.ignore_files {
+        ignores.push(Arc::new(load_ignore_file(root, file)?));
+    }
+    Ok(ignores)
+}
 
-        let idx = Index::new(path_str, name);
+/// Loads git's global excludes file (`core.excludesFile`), with anchored
+/// patterns relative to the scan root rather than the working directory.
+///
+/// Returns `None` if there is none; a broken one is not worth failing the
+/// scan for, so its valid lines still apply.
+fn load_global_ignores(root: &Path) -> Option<Gitignore> {
+    let (global, _) = GitignoreBuilder::new(root).build_global();
+    (!global.is_empty()).then_some(global)
+}
 
-        // Ignore send errors (channel might be closed)
-        let _ = tx.send(idx);
-    });
+/// Loads a `.gitignore`-style file whose patterns are relative to `base`.
+fn load_ignore_file(base: &Path, file: &Path) -> Result<Gitignore> {
+    let mut builder = GitignoreBuilder::new(base);
+    // Invalid lines are reported as errors too, but the valid ones still apply
+    if let Some(e) = builder.add(file)
+        && !file.is_file()
+    {
+        return Err(e).with_context(|| format!("Failed to read ignore file: {}", file.display()));
+    }
+    builder
+        .build()
+        .with_context(|| format!("Invalid ignore file: {}", file.display()))
+}
 
-    // Recursively scan subdirectories in parallel
-    dirs.par_iter()
-        .for_each(|entry| scan_directory_parallel(entry.path(), tx.clone(), skipped_paths.clone()));
+/// Returns true if the innermost ignore file with an opinion on `path`
+/// ignores it.
+fn is_ignored(ignores: &[Arc<Gitignore>], path: &Path, is_dir: bool) -> bool {
+    for ignore in ignores.iter().rev() {
+        match ignore.matched(path, is_dir) {
+            Match::Ignore(_) => return true,
+            Match::Whitelist(_) => return false,
+            Match::None => {}
+        }
+    }
+    false
 }
 
-/// Recursively scans directory in parallel with metadata extraction.
-fn scan_directory_parallel_with_metadata<P: AsRef<Path>>(
-    root: P,
-    tx: Sender<Index>,
-    skipped_paths: Arc<Mutex<Vec<String>>>,
+/// Recursively scans a directory in parallel.
+///
+/// `depth` is the level of `root` below the scan root, which is level 0.
+/// `ignores` holds the ignore files of the enclosing directories, outermost
+/// first; the directory's own ignore files are added on top when enabled.
+fn scan_directory_parallel(
+    root: &Path,
+    depth: usize,
+    ignores: &[Arc<Gitignore>],
+    ctx: &ScanContext,
 ) {
-    let root = root.as_ref();
+    // Nothing more can be written once the writer has stopped
+    if ctx.writer_stopped.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let local_ignore = if ctx.config.respect_gitignore {
+        load_local_ignores(root)
+    } else {
+        None
+    };
+    let extended;
+    let ignores = match local_ignore {
+        Some(ignore) => {
+            extended = ignores
+                .iter()
+                .cloned()
+                .chain(std::iter::once(Arc::new(ignore)))
+                .collect::<Vec<_>>();
+            &extended[..]
+        }
+        None => ignores,
+    };
+
+    // Time spent in file chunks is metadata work, not traversal
+    let started = ctx.profiler.as_ref().map(|_| Instant::now());
+    let mut in_chunks = Duration::ZERO;
+    let mut index_chunk = |files: &[fs::DirEntry]| match started {
+        None => index_files(files, ctx),
+        Some(_) => {
+            let chunk_started = Instant::now();
+            index_files(files, ctx);
+            in_chunks += chunk_started.elapsed();
+        }
+    };
 
-    let entries: Vec<_> = match fs::read_dir(root) {
-        Ok(entries) => entries.filter_map(|e| e.ok()).collect(),
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
         Err(_) => {
             // Record skipped path and continue
-            if let Ok(mut skipped) = skipped_paths.lock() {
+            if let Ok(mut skipped) = ctx.skipped_paths.lock() {
                 skipped.push(root.display().to_string());
             }
             return;
         }
     };
 
-    let (files, dirs): (Vec<_>, Vec<_>) = entries
-        .into_iter()
-        .partition(|entry| entry.path().is_file());
-
-    // Process files with metadata in parallel
-    files.par_iter().for_each(|entry| {
-        let path = entry.path();
-        let path_str = path.to_string_lossy().to_string();
+    // Excluded databases are compared by canonical path
+    let canonical_dir = if ctx.excluded.is_empty() {
+        None
+    } else {
+        fs::canonicalize(root).ok()
+    };
 
-        let name = entry.file_name().to_string_lossy().to_string();
+    // Stream entries and process files in fixed-size chunks, so a huge flat
+    // directory never has all of its entries in memory at once. Directories
+    // are collected and descended into after the read handle is closed.
+    let mut files = Vec::with_capacity(FILE_CHUNK_SIZE);
+    let mut dirs = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let name = entry.file_name();
+        // DirEntry::metadata does not need another stat call on Windows
+        if ctx.config.skip_hidden && is_hidden(&name, || entry.metadata()) {
+            continue;
+        }
+        if !ctx.excludes.is_empty() && ctx.excludes.is_match(&entry.path(), &name) {
+            continue;
+        }
+        let kind = classify_entry(&entry);
+        if kind != EntryKind::Special && !ignores.is_empty() {
+            let is_dir = kind == EntryKind::Dir;
+            if is_ignored(ignores, &entry.path(), is_dir) {
+                continue;
+            }
+        }
 
-        // Extract metadata
-        let idx = match extract_metadata(&path) {
-            Ok((mtime, size)) => Index::with_metadata(path_str, name, mtime, size),
-            Err(_) => {
-                // Fallback to index without metadata if extraction fails
-                Index::new(path_str, name)
+        match kind {
+            EntryKind::File => {}
+            EntryKind::Dir => {
+                if ctx.config.respect_gitignore && entry.file_name() == ".git" {
+                    continue;
+                }
+                dirs.push(entry);
+                continue;
             }
-        };
+            EntryKind::Special => {
+                ctx.skipped_special.fetch_add(1, Ordering::Relaxed);
+                continue;
+            }
+        }
 
-        // Ignore send errors (channel might be closed)
-        let _ = tx.send(idx);
-    });
+        // Leave out excluded databases
+        if let Some(dir) = &canonical_dir
+            && ctx.excluded.contains(&dir.join(entry.file_name()))
+        {
+            continue;
+        }
+
+        files.push(entry);
+        if files.len() >= FILE_CHUNK_SIZE {
+            index_chunk(&files);
+            files.clear();
+        }
+    }
+    index_chunk(&files);
+    drop(files);
 
-    // Recursively scan subdirectories
+    if let (Some(profiler), Some(started)) = (&ctx.profiler, started) {
+        ScanProfiler::add(
+            &profiler.traversal_ns,
+            started.elapsed().saturating_sub(in_chunks),
+        );
+    }
+
+    // Stop at the depth limit, remembering what was left out for pruning
+    if ctx.config.max_depth.is_some_and(|max| depth >= max) {
+        if let Ok(mut limited) = ctx.depth_limited.lock() {
+            limited.extend(dirs.iter().map(|entry| entry.path().display().to_string()));
+        }
+        return;
+    }
+
+    // Recursively scan subdirectories in parallel
     dirs.par_iter().for_each(|entry| {
-        scan_directory_parallel_with_metadata(entry.path(), tx.clone(), skipped_paths.clone())
+        let path = entry.path();
+        if should_descend(entry, &path, ctx) {
+            scan_directory_parallel(&path, depth + 1, ignores, ctx);
+        } else if is_directory_link(entry)
+            && let Ok(mut skipped) = ctx.skipped_links.lock()
+        {
+            skipped.push(path.display().to_string());
+        }
     });
 }
 
-/// Extracts file metadata (modification time and size).
-fn extract_metadata<P: AsRef<Path>>(path: P) -> Result<(f64, i64)> {
-    let metadata = fs::metadata(path.as_ref()).context("Failed to read file metadata")?;
-
-    let mtime = metadata
-        .modified()
-        .context("Failed to get modification time")?
-        .duration_since(SystemTime::UNIX_EPOCH)
-        .context("Invalid modification time")?
-        .as_secs_f64();
+/// Builds index entries for a chunk of files in parallel and sends them to the writer.
+fn index_files(files: &[fs::DirEntry], ctx: &ScanContext) {
+    files.par_iter().for_each(|entry| {
+        let started = ctx.profiler.as_ref().map(|_| Instant::now());
 
-    let size = metadata.len() as i64;
+        // Fallback to index without metadata if extraction fails
+        let Ok(mut idx) = Index::from_dir_entry(entry, ctx.config.with_metadata)
+            .or_else(|_| Index::from_dir_entry(entry, false))
+        else {
+            return;
+        };
 
-    Ok((mtime, size))
-}
+        if ctx.config.skip_empty && idx.size == Some(0) {
+            ctx.skipped_empty.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
 
-/// Batches indices and writes them to database (used in tests).
-#[allow(dead_code)]
-fn write_indices_batched(
-    rx: crossbeam_channel::Receiver<Index>,
-    db: &Database,
-    batch_size: usize,
-) -> Result<()> {
-    let mut batch = Vec::with_capacity(batch_size);
+        if ctx.config.with_perms {
+            (idx.owner, idx.mode) = extract_permissions(&entry.path());
+        }
 
-    for idx in rx {
-        batch.push(idx);
+        let archive = (ctx.config.index_archives && is_zip_archive(&idx.name))
+            .then(|| (idx.path.clone(), idx.mtime));
 
-        if batch.len() >= batch_size {
-            db.add_idxs(&batch)
-                .context("Failed to write batch to database")?;
-            batch.clear();
+        if let (Some(profiler), Some(started)) = (&ctx.profiler, started) {
+            ScanProfiler::add(&profiler.metadata_ns, started.elapsed());
         }
-    }
+
+        if !ctx.send(idx) {
+            return;
+        }
+
+        // Unreadable or corrupt archives are still indexed as plain files
+        if let Some((path, mtime)) = archive
+            && index_archive_entries(&path, mtime, ctx).is_err()
+            && let Ok(mut skipped) = ctx.skipped_paths.lock()
+        {
+            skipped.push(path);
+        }
+    });
+}
+
+/// Returns true if the file name has a `.zip` extension (case-insensitive).
+fn is_zip_archive(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+/// Sends an index entry for every file stored in a zip archive.
+///
+/// Entries get the path `{archive}!/{inner}`. With metadata enabled they
+/// carry their uncompressed size and the archive's modification time, since
+/// zip timestamps have no time zone.
+fn index_archive_entries(archive: &str, mtime: Option<f64>, ctx: &ScanContext) -> Result<()> {
+    let file = fs::File::open(archive).context("Failed to open archive")?;
+    let mut zip =
+        zip::ZipArchive::new(io::BufReader::new(file)).context("Failed to read archive")?;
+
+    for i in 0..zip.len() {
+        let entry = zip
+            .by_index_raw(i)
+            .context("Failed to read archive entry")?;
+        if entry.is_dir() {
+            continue;
+        }
+
+        let inner = entry.name().context("Invalid archive entry name")?;
+        let inner = inner.trim_start_matches('/');
+        let name = inner.rsplit('/').next().unwrap_or(inner).to_string();
+        let path = format!("{}{}{}", archive, ARCHIVE_SEPARATOR, inner);
+        let idx = match (ctx.config.with_metadata, mtime) {
+            (true, Some(mtime)) => Index::with_metadata(path, name, mtime, entry.size() as i64),
+            (true, None) => Index {
+                size: Some(entry.size() as i64),
+                ..Index::new(path, name)
+            },
+            (false, _) => Index::new(path, name),
+        };
+
+        if !ctx.send(idx) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Splits a stored path into the archive file and the path inside it.
+///
+/// Only splits at an [`ARCHIVE_SEPARATOR`] that follows a `.zip` file name,
+/// so a file under a directory whose name ends in `!` stays an ordinary
+/// file. Returns `None` for ordinary files.
+pub fn split_archive_path(path: &str) -> Option<(&str, &str)> {
+    path.match_indices(ARCHIVE_SEPARATOR)
+        .map(|(at, _)| (&path[..at], &path[at + ARCHIVE_SEPARATOR.len()..]))
+        .find(|(archive, _)| is_zip_archive(archive))
+}
+
+/// Decides whether to descend into a subdirectory entry.
+///
+/// Links are skipped unless `follow_junctions` is set. When following links,
+/// directories already reached through another path are skipped, which
+/// breaks cycles and avoids indexing the same tree twice. Only skipped
+/// links are reported by the caller; an ordinary directory reached first
+/// through a link was indexed there, and one that cannot be resolved is
+/// recorded as unreadable here.
+fn should_descend(entry: &fs::DirEntry, path: &Path, ctx: &ScanContext) -> bool {
+    let Some(visited) = &ctx.visited else {
+        return !is_directory_link(entry);
+    };
+
+    match fs::canonicalize(path) {
+        Ok(canonical) => visited.lock().unwrap().insert(canonical),
+        // Broken links have no target to scan
+        Err(_) => {
+            if !is_directory_link(entry)
+                && let Ok(mut skipped) = ctx.skipped_paths.lock()
+            {
+                skipped.push(path.display().to_string());
+            }
+            false
+        }
+    }
+}
+
+/// Returns true if the entry is a junction, reparse point or symlink.
+///
+/// On Windows, directory junctions are not reported as symlinks by every API,
+/// so the reparse point attribute is checked directly.
+#[cfg(windows)]
+fn is_directory_link(entry: &fs::DirEntry) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_REPARSE_POINT: u32 = 0x400;
+
+    // DirEntry::metadata does not traverse reparse points on Windows
+    entry
+        .metadata()
+        .map(|m| m.file_attributes() & FILE_ATTRIBUTE_REPARSE_POINT != 0)
+        .unwrap_or(false)
+}
+
+/// Returns true if the entry is a junction, reparse point or symlink.
+#[cfg(not(windows))]
+fn is_directory_link(entry: &fs::DirEntry) -> bool {
+    entry.file_type().map(|t| t.is_symlink()).unwrap_or(false)
+}
+
+/// Returns true if a file or directory is hidden: its name starts with `.`,
+/// or it has the hidden or system attribute.
+#[cfg(windows)]
+fn is_hidden(name: &OsStr, metadata: impl FnOnce() -> io::Result<fs::Metadata>) -> bool {
+    use std::os::windows::fs::MetadataExt;
+
+    const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+    const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+
+    name.as_encoded_bytes().starts_with(b".")
+        || metadata().is_ok_and(|m| {
+            m.file_attributes() & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM) != 0
+        })
+}
+
+/// Returns true if a file or directory is hidden: its name starts with `.`.
+#[cfg(not(windows))]
+fn is_hidden(name: &OsStr, _metadata: impl FnOnce() -> io::Result<fs::Metadata>) -> bool {
+    name.as_encoded_bytes().starts_with(b".")
+}
+
+/// Extracts a readable message from a thread panic payload.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+/// Extracts the owner uid and mode bits of a file.
+#[cfg(unix)]
+fn extract_permissions(path: &Path) -> (Option<i64>, Option<i64>) {
+    use std::os::unix::fs::MetadataExt;
+
+    match fs::metadata(path) {
+        Ok(metadata) => (Some(metadata.uid() as i64), Some(metadata.mode() as i64)),
+        Err(_) => (None, None),
+    }
+}
+
+/// Extracts the file attributes of a file; Windows has no uid to record.
+#[cfg(windows)]
+fn extract_permissions(path: &Path) -> (Option<i64>, Option<i64>) {
+    use std::os::windows::fs::MetadataExt;
+
+    match fs::metadata(path) {
+        Ok(metadata) => (None, Some(metadata.file_attributes() as i64)),
+        Err(_) => (None, None),
+    }
+}
+
+/// Permissions are not recorded on other platforms.
+#[cfg(not(any(unix, windows)))]
+fn extract_permissions(_path: &Path) -> (Option<i64>, Option<i64>) {
+    (None, None)
+}
+
+/// Batches indices and writes them to database (used in tests).
+#[allow(dead_code)]
+fn write_indices_batched(
+    rx: crossbeam_channel::Receiver<Index>,
+    db: &Database,
+    batch_size: usize,
+) -> Result<()> {
+    let mut batch = Vec::with_capacity(batch_size);
+
+    for idx in rx {
+        batch.push(idx);
+
+        if batch.len() >= batch_size {
+            db.add_idxs(&batch)
+                .context("Failed to write batch to database")?;
+            batch.clear();
+        }
+    }
 
     // Write remaining indices
     if !batch.is_empty() {
@@ -312,39 +1145,585 @@ fn write_indices_batched(
     Ok(())
 }
 
+/// Settings of the writer thread.
+struct WriteOptions {
+    /// Root the written rows belong to
+    root_id: i64,
+    batch_size: usize,
+    progress_step: usize,
+    /// Stored metadata by normalized path, present for incremental scans
+    existing: Option<HashMap<String, StoredMetadata>>,
+    /// Record every received path, for pruning after the scan
+    collect_seen: bool,
+}
+
+/// What the writer thread did with the entries it received.
+#[derive(Debug, Default)]
+struct WriteSummary {
+    /// Time spent committing batches
+    db_write: Duration,
+    added: usize,
+    updated: usize,
+    unchanged: usize,
+    /// Normalized paths received, when `collect_seen` was set
+    seen: Option<HashSet<String>>,
+}
+
 /// Batches indices and writes them to database with progress tracking.
+///
+/// Progress is reported every `progress_step` received files, so large
+/// batches do not make a spinner jump. `counter` tracks committed rows.
+///
+/// For incremental scans, entries whose size and modification time match
+/// `existing` are dropped before batching.
 fn write_indices_batched_with_progress(
     rx: crossbeam_channel::Receiver<Index>,
     db: &Database,
-    batch_size: usize,
-    progress: Arc<ProgressBar>,
+    options: WriteOptions,
+    progress: &dyn Fn(ProgressEvent),
     counter: Arc<AtomicU64>,
-) -> Result<()> {
+) -> Result<WriteSummary> {
+    let WriteOptions {
+        root_id,
+        batch_size,
+        progress_step,
+        existing,
+        collect_seen,
+    } = options;
+    let separator = db.path_separator()?;
     let mut batch = Vec::with_capacity(batch_size);
+    let progress_step = progress_step.max(1) as u64;
+    let mut received = 0u64;
+    let mut summary = WriteSummary {
+        seen: collect_seen.then(HashSet::new),
+        ..Default::default()
+    };
 
     for idx in rx {
+        received += 1;
+        if received.is_multiple_of(progress_step) {
+            progress(ProgressEvent::Scanning(received));
+        }
+
+        let path =
+            (existing.is_some() || collect_seen).then(|| normalize_path(&idx.path, separator));
+        let stored = existing
+            .as_ref()
+            .zip(path.as_ref())
+            .and_then(|(existing, path)| existing.get(path).copied());
+        if let (Some(seen), Some(path)) = (&mut summary.seen, path) {
+            seen.insert(path);
+        }
+
+        match stored {
+            None => summary.added += 1,
+            Some((mtime, size))
+                if idx.mtime.is_some() && (mtime, size) == (idx.mtime, idx.size) =>
+            {
+                summary.unchanged += 1;
+                continue;
+            }
+            Some(_) => summary.updated += 1,
+        }
         batch.push(idx);
 
         if batch.len() >= batch_size {
-            db.add_idxs(&batch)
+            let started = Instant::now();
+            db.add_idxs_in_root(&batch, Some(root_id))
                 .context("Failed to write batch to database")?;
-
-            let count =
-                counter.fetch_add(batch.len() as u64, Ordering::Relaxed) + batch.len() as u64;
-            progress.set_position(count);
+            summary.db_write += started.elapsed();
+            counter.fetch_add(batch.len() as u64, Ordering::Relaxed);
             batch.clear();
         }
     }
 
     // Write remaining indices
     if !batch.is_empty() {
-        db.add_idxs(&batch)
+        let started = Instant::now();
+        db.add_idxs_in_root(&batch, Some(root_id))
             .context("Failed to write final batch to database")?;
-        let count = counter.fetch_add(batch.len() as u64, Ordering::Relaxed) + batch.len() as u64;
-        progress.set_position(count);
+        summary.db_write += started.elapsed();
+        counter.fetch_add(batch.len() as u64, Ordering::Relaxed);
     }
 
-    Ok(())
+    Ok(summary)
+}
+
+/// Options for [`watch_and_index`]
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Options of the initial scan, which also decide how changed files are
+    /// recorded
+    ///
+    /// `with_metadata`, `with_perms`, `skip_empty`, `skip_hidden`,
+    /// `respect_gitignore`, `ignore_files`, `exclude_globs`, `exclude_paths`
+    /// and `max_depth` apply to changes as well. Archive entries and
+    /// directory links are only handled by the initial scan.
+    pub config: IndexConfig,
+    /// Batch size of the initial scan
+    pub batch_size: usize,
+    /// Quiet period after the last filesystem event before changes are
+    /// written
+    ///
+    /// Events are coalesced by path, so a file rewritten many times within
+    /// the period is written once, and very high event rates cost one
+    /// write per period rather than one per event. Under a constant stream
+    /// of events, changes are still written every
+    /// [`MAX_DEBOUNCE_PERIODS`] periods.
+    pub debounce: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            config: IndexConfig::default(),
+            batch_size: 5000,
+            debounce: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Longest wait, in debounce periods, before pending changes are written
+/// even though events keep arriving
+pub const MAX_DEBOUNCE_PERIODS: u32 = 10;
+
+/// How often [`watch_and_index`] checks its stop flag while idle
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Counts of the changes applied by [`watch_and_index`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WatchSummary {
+    /// Rows written for created or modified files
+    pub written: usize,
+    /// Rows deleted for removed files
+    pub removed: usize,
+    /// Rows moved to a new path by directory renames
+    pub moved: usize,
+    /// Full rescans, run when the system dropped events
+    pub rescans: usize,
+}
+
+impl WatchSummary {
+    fn add(&mut self, other: &WatchSummary) {
+        self.written += other.written;
+        self.removed += other.removed;
+        self.moved += other.moved;
+        self.rescans += other.rescans;
+    }
+}
+
+/// Progress reported by [`watch_and_index`]
+#[derive(Debug)]
+pub enum WatchEvent<'a> {
+    /// The initial scan finished and changes are being watched
+    Scanned(&'a IndexResult),
+    /// A debounced batch of changes was written
+    Applied(&'a WatchSummary),
+    /// The filesystem watcher reported an error; the watch goes on unless
+    /// the callback returns one
+    WatcherError(&'a anyhow::Error),
+}
+
+/// Scans a directory, then keeps its rows up to date as files change.
+///
+/// The watch starts before the initial scan, so changes made while it runs
+/// are not lost. Events are debounced, see [`WatchOptions::debounce`];
+/// created and modified files are then written with their metadata, rows of
+/// removed files and directories are deleted, and directory renames move
+/// the rows below them. If the system reports dropped events, the whole
+/// tree is rescanned incrementally, pruning rows of missing files.
+///
+/// Runs until `stop` is set, writing the changes still pending first.
+///
+/// # Arguments
+/// * `root` - Root directory to scan and watch
+/// * `db` - Database instance to write indices to
+/// * `options` - Scan and debounce options
+/// * `stop` - Flag ending the watch, e.g. set by a Ctrl+C handler
+/// * `on_progress` - Progress callback of the initial scan, see
+///   [`scan_idxs_with_progress`]
+/// * `on_event` - Called after the initial scan, each applied batch and
+///   each watcher error; an error ends the watch
+///
+/// # Returns
+/// Totals of the changes applied after the initial scan
+pub fn watch_and_index<P, G, F>(
+    root: P,
+    db: &Database,
+    options: &WatchOptions,
+    stop: &AtomicBool,
+    on_progress: G,
+    mut on_event: F,
+) -> Result<WatchSummary>
+where
+    P: AsRef<Path>,
+    G: Fn(ProgressEvent) + Send + Sync + 'static,
+    F: FnMut(WatchEvent) -> Result<()>,
+{
+    use notify::{RecursiveMode, Watcher};
+    use std::sync::mpsc::{self, RecvTimeoutError};
+
+    let root = root.as_ref();
+    if !root.exists() {
+        anyhow::bail!("Root path does not exist: {}", root.display());
+    }
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher =
+        notify::recommended_watcher(tx).context("Failed to start filesystem watcher")?;
+    watcher
+        .watch(root, RecursiveMode::Recursive)
+        .with_context(|| format!("Failed to watch {}", root.display()))?;
+
+    let scanned =
+        scan_idxs_with_progress(root, db, options.batch_size, &options.config, on_progress)?;
+    on_event(WatchEvent::Scanned(&scanned))?;
+
+    let ctx = WatchContext::new(root, db, options)?;
+    let mut pending = PendingChanges::default();
+    // Arrival of the first and the latest event still pending
+    let mut window: Option<(Instant, Instant)> = None;
+    let mut total = WatchSummary::default();
+    let longest_wait = options.debounce * MAX_DEBOUNCE_PERIODS;
+
+    loop {
+        let stopping = stop.load(Ordering::SeqCst);
+        let due = window.is_some_and(|(first, last)| {
+            last.elapsed() >= options.debounce || first.elapsed() >= longest_wait
+        });
+        if window.is_some() && (due || stopping) {
+            let applied = ctx.apply(std::mem::take(&mut pending))?;
+            window = None;
+            total.add(&applied);
+            on_event(WatchEvent::Applied(&applied))?;
+        }
+        if stopping {
+            break;
+        }
+
+        let timeout = match window {
+            Some((first, last)) => (last + options.debounce)
+                .min(first + longest_wait)
+                .saturating_duration_since(Instant::now())
+                .min(WATCH_POLL_INTERVAL),
+            None => WATCH_POLL_INTERVAL,
+        };
+        match rx.recv_timeout(timeout) {
+            Ok(Ok(event)) => {
+                if pending.add(event, &ctx) {
+                    let now = Instant::now();
+                    window = Some((window.map_or(now, |(first, _)| first), now));
+                }
+            }
+            Ok(Err(e)) => {
+                let error = anyhow::Error::new(e).context("Filesystem watcher error");
+                on_event(WatchEvent::WatcherError(&error))?;
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    Ok(total)
+}
+
+/// Filesystem changes collected during one debounce period
+#[derive(Debug, Default)]
+struct PendingChanges {
+    /// Paths to look at again, with whether a directory found there is new
+    /// and its files must be indexed
+    paths: HashMap<PathBuf, bool>,
+    /// Renames reported with both ends, in order
+    renames: Vec<(PathBuf, PathBuf)>,
+    /// Set when the system dropped events
+    rescan: bool,
+}
+
+impl PendingChanges {
+    /// Records an event.
+    ///
+    /// # Returns
+    /// False if the event is irrelevant, such as a read or a write to the
+    /// database itself
+    fn add(&mut self, event: notify::Event, ctx: &WatchContext) -> bool {
+        use notify::EventKind;
+        use notify::event::{ModifyKind, RenameMode};
+
+        if event.need_rescan() {
+            self.rescan = true;
+            return true;
+        }
+        if let EventKind::Modify(ModifyKind::Name(RenameMode::Both)) = event.kind
+            && let [from, to] = &event.paths[..]
+            && let (Some(from), Some(to)) = (ctx.in_root(from), ctx.in_root(to))
+        {
+            self.add_rename(from, to);
+            return true;
+        }
+
+        let new_dir = match event.kind {
+            EventKind::Access(_) => return false,
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => false,
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_)) => true,
+            EventKind::Any | EventKind::Other => true,
+            EventKind::Modify(_) | EventKind::Remove(_) => false,
+        };
+
+        let mut added = false;
+        for path in event.paths {
+            if let Some(path) = ctx.in_root(&path)
+                && !ctx.is_excluded_file(&path)
+            {
+                *self.paths.entry(path).or_default() |= new_dir;
+                added = true;
+            }
+        }
+        added
+    }
+
+    /// Records a rename reported with both ends.
+    ///
+    /// Systems reporting both ends also report each end on its own first.
+    /// Those are dropped, since the rename moves the rows, unless the old
+    /// path is new in this period and has nothing to move yet. Changes
+    /// pending below the old path move with it.
+    fn add_rename(&mut self, from: PathBuf, to: PathBuf) {
+        let from_is_new = self.paths.remove(&from) == Some(true);
+        self.paths.remove(&to);
+        let moved: Vec<PathBuf> = self
+            .paths
+            .keys()
+            .filter(|path| path.starts_with(&from))
+            .cloned()
+            .collect();
+        for path in moved {
+            let new_dir = self.paths.remove(&path).unwrap_or_default();
+            let rest = path.strip_prefix(&from).unwrap_or(Path::new(""));
+            self.paths.insert(to.join(rest), new_dir);
+        }
+        if from_is_new {
+            self.paths.insert(to.clone(), true);
+        }
+        self.renames.push((from, to));
+    }
+}
+
+/// What [`watch_and_index`] needs to apply changes
+struct WatchContext<'a> {
+    root: &'a Path,
+    /// Canonical form of `root`, for systems reporting resolved paths
+    canonical_root: Option<PathBuf>,
+    db: &'a Database,
+    root_id: Option<i64>,
+    options: &'a WatchOptions,
+    excludes: ExcludeGlobs,
+    /// Canonical paths of files that must not be indexed
+    excluded: HashSet<PathBuf>,
+    /// Ignore files applying to the whole root, see [`root_ignores`]
+    ignores: Vec<Arc<Gitignore>>,
+    /// Ignore files of each directory looked at, reloaded for every batch
+    /// so edits to them take effect
+    local_ignores: RefCell<HashMap<PathBuf, Option<Arc<Gitignore>>>>,
+}
+
+impl<'a> WatchContext<'a> {
+    fn new(root: &'a Path, db: &'a Database, options: &'a WatchOptions) -> Result<Self> {
+        Ok(Self {
+            root,
+            canonical_root: fs::canonicalize(root).ok(),
+            db,
+            root_id: db.root_id(&root.to_string_lossy())?,
+            options,
+            excludes: ExcludeGlobs::new(root, &options.config.exclude_globs)?,
+            excluded: excluded_files(&options.config.exclude_paths),
+            ignores: root_ignores(root, &options.config)?,
+            local_ignores: RefCell::default(),
+        })
+    }
+
+    /// Returns an event path spelled the way the scan of `root` spells it,
+    /// or `None` if it lies outside the root.
+    fn in_root(&self, path: &Path) -> Option<PathBuf> {
+        if path.starts_with(self.root) {
+            return Some(path.to_path_buf());
+        }
+        let rest = path.strip_prefix(self.canonical_root.as_ref()?).ok()?;
+        Some(self.root.join(rest))
+    }
+
+    /// Returns true for the database and its side files.
+    fn is_excluded_file(&self, path: &Path) -> bool {
+        if self.excluded.is_empty() {
+            return false;
+        }
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return false;
+        };
+        fs::canonicalize(parent).is_ok_and(|parent| self.excluded.contains(&parent.join(name)))
+    }
+
+    /// Returns true if the scan would have visited `path`, by its name, the
+    /// names of the directories above it and the ignore files on the way.
+    fn is_watched(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(self.root) else {
+            return false;
+        };
+        let config = &self.options.config;
+        let depth = relative.components().count();
+        if depth == 0 || config.max_depth.is_some_and(|max| depth > max + 1) {
+            return false;
+        }
+
+        let mut ignores = self.ignores.clone();
+        let mut current = self.root.to_path_buf();
+        let mut names = relative.iter().peekable();
+        while let Some(name) = names.next() {
+            if config.respect_gitignore
+                && let Some(local) = self.local_ignores(&current)
+            {
+                ignores.push(local);
+            }
+            current.push(name);
+            if (config.skip_hidden && is_hidden(name, || fs::symlink_metadata(&current)))
+                || (config.respect_gitignore && name == ".git")
+                || self.excludes.is_match(&current, name)
+            {
+                return false;
+            }
+            if !ignores.is_empty() {
+                let is_dir = names.peek().is_some()
+                    || fs::symlink_metadata(&current).is_ok_and(|m| m.is_dir());
+                if is_ignored(&ignores, &current, is_dir) {
+                    return false;
+                }
+            }
+        }
+        !self.is_excluded_file(path)
+    }
+
+    /// Returns the ignore files of a directory, loading them on first use.
+    fn local_ignores(&self, dir: &Path) -> Option<Arc<Gitignore>> {
+        self.local_ignores
+            .borrow_mut()
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| load_local_ignores(dir).map(Arc::new))
+            .clone()
+    }
+
+    /// Writes a batch of changes to the database.
+    fn apply(&self, pending: PendingChanges) -> Result<WatchSummary> {
+        let mut summary = WatchSummary::default();
+        self.local_ignores.borrow_mut().clear();
+
+        if pending.rescan {
+            let config = IndexConfig {
+                incremental: true,
+                prune: true,
+                ..self.options.config.clone()
+            };
+            let result =
+                scan_idxs_with_config(self.root, self.db, self.options.batch_size, &config)?;
+            summary.written = result.added + result.updated;
+            summary.removed = result.removed;
+            summary.rescans = 1;
+            return Ok(summary);
+        }
+
+        let mut paths = pending.paths;
+        for (from, to) in pending.renames {
+            if to.is_dir() {
+                let from = self.db.normalize_root(&from.to_string_lossy())?;
+                let to = self.db.normalize_root(&to.to_string_lossy())?;
+                summary.moved += crate::maintenance::relocate(self.db, &from, &to, false)?.updated;
+            } else {
+                paths.entry(from).or_default();
+                *paths.entry(to).or_default() = true;
+            }
+        }
+
+        let mut files = Vec::new();
+        for (path, new_dir) in paths {
+            match fs::metadata(&path) {
+                Ok(metadata) if metadata.is_dir() => {
+                    if new_dir && self.is_watched(&path) {
+                        self.collect_files(&path, &mut files);
+                    }
+                }
+                Ok(metadata) if metadata.is_file() => {
+                    if !self.is_watched(&path) {
+                        continue;
+                    }
+                    match self.index_file(&path) {
+                        Some(idx) => files.push(idx),
+                        // A file emptied under `skip_empty` no longer belongs
+                        None => summary.removed += self.remove(&path, false)?,
+                    }
+                }
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    summary.removed += self.remove(&path, true)?;
+                }
+                // Unreadable for now; a later event or rescan picks it up
+                Err(_) => {}
+            }
+        }
+
+        // A new directory's files may also have events of their own
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        files.dedup_by(|a, b| a.path == b.path);
+        self.db.add_idxs_in_root(&files, self.root_id)?;
+        summary.written += files.len();
+        Ok(summary)
+    }
+
+    /// Deletes the row of a path and, if `below` is set, the rows below it.
+    fn remove(&self, path: &Path, below: bool) -> Result<usize> {
+        let path = path.to_string_lossy();
+        let mut removed = usize::from(self.db.remove_path(&path)?);
+        if below {
+            removed += self.db.remove_under(&path)?;
+        }
+        Ok(removed)
+    }
+
+    /// Builds the row of a file, or `None` if it is left out as empty.
+    fn index_file(&self, path: &Path) -> Option<Index> {
+        let config = &self.options.config;
+        let name = path.file_name()?.to_string_lossy().into_owned();
+        let path_text = path.to_string_lossy().into_owned();
+        let mut idx = match file_metadata(path) {
+            Ok((mtime, size)) if config.with_metadata => {
+                Index::with_metadata(path_text, name, mtime, size)
+            }
+            _ => Index::new(path_text, name),
+        };
+        if config.skip_empty && idx.size == Some(0) {
+            return None;
+        }
+        if config.with_perms {
+            (idx.owner, idx.mode) = extract_permissions(path);
+        }
+        Some(idx)
+    }
+
+    /// Collects the rows of the files in a new directory and below it,
+    /// without following directory links.
+    fn collect_files(&self, dir: &Path, files: &mut Vec<Index>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if !self.is_watched(&path) {
+                continue;
+            }
+            match classify_entry(&entry) {
+                EntryKind::Dir if !is_directory_link(&entry) => self.collect_files(&path, files),
+                EntryKind::File => files.extend(self.index_file(&path)),
+                _ => {}
+            }
+        }
+    }
 }
 
 /// Gets file metadata as a tuple (mtime, size).
@@ -355,7 +1734,7 @@ fn write_indices_batched_with_progress(
 /// # Returns
 /// Tuple of (modification_time_unix_timestamp, file_size_bytes)
 pub fn get_file_metadata<P: AsRef<Path>>(path: P) -> Result<(f64, i64)> {
-    extract_metadata(path)
+    file_metadata(path.as_ref())
 }
 
 /// Discover database files from given paths
@@ -454,10 +1833,7 @@ mod tests {
 
         let result = scan_idxs(temp_dir.path(), &db, 100).unwrap();
 
-        assert!(
-            result.duration.as_millis() > 0,
-            "Scan should take some time"
-        );
+        assert!(!result.duration.is_zero(), "Scan should take some time");
 
         // Verify files were indexed
         let count = db
@@ -482,7 +1858,7 @@ mod tests {
 
         let result = scan_idxs_with_metadata(temp_dir.path(), &db, 5).unwrap();
 
-        assert!(result.duration.as_millis() > 0);
+        assert!(!result.duration.is_zero());
 
         // Verify files have metadata
         let has_metadata = db
@@ -501,6 +1877,604 @@ mod tests {
         let _ = fs::remove_file(db_path);
     }
 
+    #[test]
+    fn test_scan_flat_directory_across_chunks() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("flat");
+        fs::create_dir(&root).unwrap();
+        let file_count = FILE_CHUNK_SIZE * 2 + 7;
+        for i in 0..file_count {
+            File::create(root.join(format!("f{}.txt", i))).unwrap();
+        }
+        fs::create_dir(root.join("sub")).unwrap();
+        File::create(root.join("sub").join("nested.txt")).unwrap();
+
+        let db = Database::init(temp_dir.path().join("flat.reminex.db")).unwrap();
+        scan_idxs(&root, &db, 1000).unwrap();
+
+        let count: i64 = db
+            .batch_operation(|conn| {
+                Ok(conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))?)
+            })
+            .unwrap();
+        assert_eq!(count as usize, file_count + 1);
+    }
+
+    #[test]
+    fn test_incremental_scan_stamps_indexed_at() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("files");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("old.txt"), b"old").unwrap();
+        let db = Database::init(temp_dir.path().join("stamp.reminex.db")).unwrap();
+
+        let indexed_at = |db: &Database| -> Vec<(String, f64)> {
+            db.batch_operation(|conn| {
+                let mut stmt = conn.prepare("SELECT name, indexed_at FROM files ORDER BY name")?;
+                let rows = stmt
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(rows)
+            })
+            .unwrap()
+        };
+
+        scan_idxs(&root, &db, 100).unwrap();
+        let first = indexed_at(&db);
+        assert_eq!(first.len(), 1);
+
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(root.join("new.txt"), b"new").unwrap();
+        scan_idxs(&root, &db, 100).unwrap();
+        let second = indexed_at(&db);
+
+        // Rescanned rows keep their first stamp, new rows get a later one
+        assert_eq!(second[1], first[0]);
+        assert_eq!(second[0].0, "new.txt");
+        assert!(second[0].1 > first[0].1);
+    }
+
+    #[test]
+    fn test_index_archives_adds_zip_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("files");
+        fs::create_dir(&root).unwrap();
+        let archive = root.join("photos.zip");
+        {
+            let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+            let options = zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored);
+            zip.add_directory("trip/", options).unwrap();
+            zip.start_file("trip/beach.jpg", options).unwrap();
+            zip.write_all(b"jpeg").unwrap();
+            zip.finish().unwrap();
+        }
+
+        let names = |db: &Database| -> Vec<(String, Option<i64>)> {
+            db.batch_operation(|conn| {
+                let mut stmt = conn.prepare("SELECT path, size FROM files ORDER BY path")?;
+                let rows = stmt
+                    .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(rows)
+            })
+            .unwrap()
+        };
+
+        // Off by default: only the archive itself is indexed
+        let db = Database::init(temp_dir.path().join("plain.reminex.db")).unwrap();
+        scan_idxs(&root, &db, 100).unwrap();
+        assert_eq!(names(&db).len(), 1);
+
+        let db = Database::init(temp_dir.path().join("zip.reminex.db")).unwrap();
+        let config = IndexConfig {
+            with_metadata: true,
+            index_archives: true,
+            ..Default::default()
+        };
+        scan_idxs_with_config(&root, &db, 100, &config).unwrap();
+        let archive_path = archive.to_string_lossy().into_owned();
+        let inner_path = format!("{}!/trip/beach.jpg", archive_path);
+        assert_eq!(
+            names(&db),
+            vec![
+                (
+                    archive_path.clone(),
+                    Some(fs::metadata(&archive).unwrap().len() as i64)
+                ),
+                (inner_path.clone(), Some(4)),
+            ]
+        );
+        assert_eq!(
+            split_archive_path(&inner_path),
+            Some((archive_path.as_str(), "trip/beach.jpg"))
+        );
+        assert_eq!(
+            split_archive_path("/d/wow!/a.zip!/b.txt"),
+            Some(("/d/wow!/a.zip", "b.txt"))
+        );
+        assert_eq!(split_archive_path("/d/wow!/real.txt"), None);
+    }
+
+    #[test]
+    fn test_profile_only_collected_when_enabled() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("files");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        for i in 0..50 {
+            File::create(root.join(format!("f{}.txt", i))).unwrap();
+        }
+        File::create(root.join("sub").join("nested.txt")).unwrap();
+
+        let db = Database::init(temp_dir.path().join("plain.reminex.db")).unwrap();
+        let result = scan_idxs(&root, &db, 10).unwrap();
+        assert!(result.profile.is_none());
+
+        let db = Database::init(temp_dir.path().join("profiled.reminex.db")).unwrap();
+        let config = IndexConfig {
+            with_metadata: true,
+            profile: true,
+            ..Default::default()
+        };
+        let result = scan_idxs_with_config(&root, &db, 10, &config).unwrap();
+        let profile = result.profile.expect("profile should be collected");
+        assert_eq!(profile.total, result.duration);
+        assert_eq!(profile.queue_capacity, 20);
+        assert!(profile.peak_queue_depth <= profile.queue_capacity);
+        assert!(profile.traversal > Duration::ZERO);
+        assert!(profile.metadata > Duration::ZERO);
+        assert!(profile.db_write > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_scans_tag_rows_with_their_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let photos = temp_dir.path().join("photos");
+        let docs = temp_dir.path().join("docs");
+        fs::create_dir_all(photos.join("2023")).unwrap();
+        fs::create_dir(&docs).unwrap();
+        File::create(photos.join("a.jpg")).unwrap();
+        File::create(photos.join("2023").join("b.jpg")).unwrap();
+        File::create(docs.join("c.pdf")).unwrap();
+
+        let db = Database::init(temp_dir.path().join("roots.reminex.db")).unwrap();
+        scan_idxs(&photos, &db, 100).unwrap();
+        scan_idxs(&docs, &db, 100).unwrap();
+
+        let roots = db.roots().unwrap();
+        assert_eq!(roots.len(), 2);
+        let photos_id = db.root_id(&photos.to_string_lossy()).unwrap().unwrap();
+        assert_eq!(db.count_in_root(photos_id).unwrap(), 2);
+
+        assert_eq!(db.delete_root(photos_id).unwrap(), 2);
+        assert_eq!(indexed_paths(&db).len(), 1);
+    }
+
+    #[test]
+    fn test_scans_keep_rows_of_nested_roots() {
+        let temp_dir = TempDir::new().unwrap();
+        let top = temp_dir.path().join("top");
+        let sub = top.join("sub");
+        fs::create_dir_all(&sub).unwrap();
+        File::create(top.join("a.txt")).unwrap();
+        File::create(sub.join("b.txt")).unwrap();
+        let sub_path = sub.to_string_lossy().into_owned();
+
+        let db = Database::init(temp_dir.path().join("nested.reminex.db")).unwrap();
+        scan_idxs(&sub, &db, 100).unwrap();
+        scan_idxs(&top, &db, 100).unwrap();
+        let sub_id = db.root_id(&sub_path).unwrap().unwrap();
+        let top_id = db.root_id(&top.to_string_lossy()).unwrap().unwrap();
+        assert_eq!(db.count_in_root(sub_id).unwrap(), 1);
+        assert_eq!(db.count_in_root(top_id).unwrap(), 1);
+        assert_eq!(db.paths_under(&sub_path).unwrap().len(), 1);
+
+        fs::remove_file(sub.join("b.txt")).unwrap();
+        let report = crate::maintenance::prune_missing(&db, Some(&sub_path), false).unwrap();
+        assert_eq!((report.checked, report.deleted), (1, 1));
+    }
+
+    #[test]
+    fn test_skip_empty_files_when_requested() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("files");
+        fs::create_dir(&root).unwrap();
+        File::create(root.join("placeholder.txt")).unwrap();
+        fs::write(root.join("data.txt"), b"data").unwrap();
+
+        let db = Database::init(temp_dir.path().join("all.reminex.db")).unwrap();
+        let result = scan_idxs(&root, &db, 100).unwrap();
+        assert_eq!(result.skipped_empty, 0);
+        assert_eq!(indexed_paths(&db).len(), 2);
+
+        let db = Database::init(temp_dir.path().join("nonempty.reminex.db")).unwrap();
+        let config = IndexConfig {
+            with_metadata: true,
+            skip_empty: true,
+            ..Default::default()
+        };
+        let result = scan_idxs_with_config(&root, &db, 100, &config).unwrap();
+        assert_eq!(result.skipped_empty, 1);
+        let paths = indexed_paths(&db);
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].ends_with("data.txt"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_special_files_are_skipped_and_counted() {
+        use std::os::unix::net::UnixListener;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("files");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("data.txt"), b"data").unwrap();
+        let _socket = UnixListener::bind(root.join("app.sock")).unwrap();
+
+        let entries: Vec<_> = fs::read_dir(&root).unwrap().map(|e| e.unwrap()).collect();
+        let sock = entries
+            .iter()
+            .find(|e| e.file_name() == "app.sock")
+            .unwrap();
+        assert_eq!(classify_entry(sock), EntryKind::Special);
+
+        let db = Database::init(temp_dir.path().join("special.reminex.db")).unwrap();
+        let result = scan_idxs(&root, &db, 100).unwrap();
+        assert_eq!(result.skipped_special, 1);
+        assert!(result.skipped_paths.is_empty());
+        let paths = indexed_paths(&db);
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].ends_with("data.txt"));
+    }
+
+    #[test]
+    fn test_incremental_scan_skips_unchanged_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("files");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("same.txt"), b"same").unwrap();
+        fs::write(root.join("edited.txt"), b"before").unwrap();
+        let db = Database::init(temp_dir.path().join("inc.reminex.db")).unwrap();
+
+        let first = scan_idxs_incremental(&root, &db, 100).unwrap();
+        assert_eq!((first.added, first.updated, first.skipped), (2, 0, 0));
+
+        fs::write(root.join("edited.txt"), b"after, longer").unwrap();
+        fs::write(root.join("new.txt"), b"new").unwrap();
+        let second = scan_idxs_incremental(&root, &db, 100).unwrap();
+        assert_eq!((second.added, second.updated, second.skipped), (1, 1, 1));
+        assert_eq!(indexed_paths(&db).len(), 3);
+
+        let size: i64 = db
+            .batch_operation(|conn| {
+                Ok(conn.query_row(
+                    "SELECT size FROM files WHERE name = 'edited.txt'",
+                    [],
+                    |row| row.get(0),
+                )?)
+            })
+            .unwrap();
+        assert_eq!(size, 13);
+
+        let third = scan_idxs_incremental(&root, &db, 100).unwrap();
+        assert_eq!((third.added, third.updated, third.skipped), (0, 0, 3));
+    }
+
+    #[test]
+    fn test_incremental_scan_of_subdirectory_sees_parent_rows() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("files");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::create_dir_all(root.join("sub2")).unwrap();
+        fs::write(root.join("sub/a.txt"), b"a").unwrap();
+        fs::write(root.join("sub/b.txt"), b"b").unwrap();
+        fs::write(root.join("sub2/c.txt"), b"c").unwrap();
+        let db = Database::init(temp_dir.path().join("sub.reminex.db")).unwrap();
+        scan_idxs_incremental(&root, &db, 100).unwrap();
+
+        fs::write(root.join("sub/b.txt"), b"b, edited").unwrap();
+        let result = scan_idxs_incremental(root.join("sub"), &db, 100).unwrap();
+        assert_eq!((result.added, result.updated, result.skipped), (0, 1, 1));
+    }
+
+    #[test]
+    fn test_prune_removes_missing_files_under_scanned_root_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("files");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::create_dir_all(root.join("sibling")).unwrap();
+        fs::write(root.join("top.txt"), b"top").unwrap();
+        fs::write(root.join("sub/kept.txt"), b"kept").unwrap();
+        fs::write(root.join("sub/gone.txt"), b"gone").unwrap();
+        fs::write(root.join("sibling/other.txt"), b"other").unwrap();
+        let db = Database::init(temp_dir.path().join("prune.reminex.db")).unwrap();
+        scan_idxs(&root, &db, 100).unwrap();
+        assert_eq!(indexed_paths(&db).len(), 4);
+
+        fs::remove_file(root.join("sub/gone.txt")).unwrap();
+        fs::remove_file(root.join("sibling/other.txt")).unwrap();
+        let config = IndexConfig {
+            prune: true,
+            incremental: true,
+            ..Default::default()
+        };
+        let result = scan_idxs_with_config(root.join("sub"), &db, 100, &config).unwrap();
+        assert_eq!(result.removed, 1);
+
+        let path_of = |name: &str| root.join(name).to_string_lossy().into_owned();
+        assert_eq!(
+            indexed_paths(&db),
+            vec![
+                path_of("sibling/other.txt"),
+                path_of("sub/kept.txt"),
+                path_of("top.txt")
+            ]
+        );
+
+        // Without the option nothing is removed
+        let result = scan_idxs(&root, &db, 100).unwrap();
+        assert_eq!(result.removed, 0);
+        assert_eq!(indexed_paths(&db).len(), 3);
+
+        let result = scan_idxs_with_config(&root, &db, 100, &config).unwrap();
+        assert_eq!(result.removed, 1);
+        assert_eq!(indexed_paths(&db).len(), 2);
+    }
+
+    #[test]
+    fn test_max_depth_limits_recursion_and_prune_keeps_deeper_rows() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("files");
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        for file in ["top.txt", "a/one.txt", "a/b/two.txt", "a/b/c/three.txt"] {
+            File::create(root.join(file)).unwrap();
+        }
+
+        for (max_depth, expected) in [(Some(0), 1), (Some(1), 2), (Some(2), 3), (None, 4)] {
+            let db_path = temp_dir
+                .path()
+                .join(format!("depth-{max_depth:?}.reminex.db"));
+            let db = Database::init(db_path).unwrap();
+            let config = IndexConfig {
+                max_depth,
+                ..Default::default()
+            };
+            let result = scan_idxs_with_config(&root, &db, 100, &config).unwrap();
+            assert_eq!(
+                indexed_paths(&db).len(),
+                expected,
+                "max_depth {max_depth:?}"
+            );
+            // Directories below the limit are not permission errors
+            assert!(result.skipped_paths.is_empty());
+            assert_eq!(result.depth_limited, usize::from(expected < 4));
+        }
+
+        // Rows below the limit are not seen, but must survive pruning
+        let db = Database::init(temp_dir.path().join("prune.reminex.db")).unwrap();
+        scan_idxs(&root, &db, 100).unwrap();
+        fs::remove_file(root.join("top.txt")).unwrap();
+        let config = IndexConfig {
+            max_depth: Some(0),
+            prune: true,
+            ..Default::default()
+        };
+        let result = scan_idxs_with_config(&root, &db, 100, &config).unwrap();
+        assert_eq!(result.removed, 1);
+        assert_eq!(indexed_paths(&db).len(), 3);
+    }
+
+    #[test]
+    fn test_dedicated_thread_pool_indexes_everything() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("files");
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        for file in ["top.txt", "a/one.txt", "a/b/two.txt"] {
+            File::create(root.join(file)).unwrap();
+        }
+
+        for threads in [1, 3] {
+            let db_path = temp_dir
+                .path()
+                .join(format!("threads-{threads}.reminex.db"));
+            let db = Database::init(db_path).unwrap();
+            let config = IndexConfig {
+                threads,
+                ..Default::default()
+            };
+            scan_idxs_with_config(&root, &db, 100, &config).unwrap();
+            assert_eq!(indexed_paths(&db).len(), 3, "threads {threads}");
+        }
+    }
+
+    #[test]
+    fn test_respect_gitignore_with_nested_files_and_negation() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("repo");
+        for dir in ["target/debug", "src", ".git", "docs"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        fs::write(root.join(".gitignore"), "target/\n*.log\n!keep.log\n").unwrap();
+        fs::write(
+            root.join("src").join(".gitignore"),
+            "gen.rs\n!special.log\n",
+        )
+        .unwrap();
+        for file in [
+            "target/debug/app",
+            "a.log",
+            "keep.log",
+            "src/main.rs",
+            "src/gen.rs",
+            "src/special.log",
+            "src/other.log",
+            ".git/HEAD",
+            "docs/readme.md",
+        ] {
+            File::create(root.join(file)).unwrap();
+        }
+
+        let relative = |db: &Database| -> Vec<String> {
+            indexed_paths(db)
+                .iter()
+                .map(|p| {
+                    Path::new(p)
+                        .strip_prefix(&root)
+                        .unwrap()
+                        .to_string_lossy()
+                        .replace('\\', "/")
+                })
+                .collect()
+        };
+
+        // Without the option everything is indexed
+        let db = Database::init(temp_dir.path().join("all.reminex.db")).unwrap();
+        scan_idxs(&root, &db, 100).unwrap();
+        assert_eq!(relative(&db).len(), 11);
+
+        let db = Database::init(temp_dir.path().join("git.reminex.db")).unwrap();
+        let config = IndexConfig {
+            respect_gitignore: true,
+            ..Default::default()
+        };
+        scan_idxs_with_config(&root, &db, 100, &config).unwrap();
+        assert_eq!(
+            relative(&db),
+            vec![
+                ".gitignore",
+                "docs/readme.md",
+                "keep.log",
+                "src/.gitignore",
+                "src/main.rs",
+                "src/special.log",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extra_ignore_file_is_relative_to_scan_root() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("files");
+        fs::create_dir_all(root.join("cache")).unwrap();
+        File::create(root.join("cache").join("blob")).unwrap();
+        File::create(root.join("photo.jpg")).unwrap();
+        File::create(root.join("notes.tmp")).unwrap();
+        let ignore_file = temp_dir.path().join("reminex.ignore");
+        fs::write(&ignore_file, "/cache\n*.tmp\n").unwrap();
+
+        let db = Database::init(temp_dir.path().join("extra.reminex.db")).unwrap();
+        let config = IndexConfig {
+            ignore_files: vec![ignore_file],
+            ..Default::default()
+        };
+        scan_idxs_with_config(&root, &db, 100, &config).unwrap();
+        let paths = indexed_paths(&db);
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].ends_with("photo.jpg"));
+
+        let config = IndexConfig {
+            ignore_files: vec![temp_dir.path().join("missing.ignore")],
+            ..Default::default()
+        };
+        assert!(scan_idxs_with_config(&root, &db, 100, &config).is_err());
+    }
+
+    #[test]
+    fn test_exclude_globs_prune_names_and_relative_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join("files");
+        for dir in ["cache/deep", "src/cache", "build/x/y", "docs"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "cache/deep/blob",
+            "src/cache/blob",
+            "src/main.rs",
+            "src/notes.tmp",
+            "build/x/y/app.o",
+            "build/x/keep.txt",
+            "docs/build.md",
+        ] {
+            File::create(root.join(file)).unwrap();
+        }
+
+        let db = Database::init(temp_dir.path().join("exclude.reminex.db")).unwrap();
+        let config = IndexConfig {
+            exclude_globs: vec!["cache".into(), "*.tmp".into(), "/build/**/*.o".into()],
+            ..Default::default()
+        };
+        scan_idxs_with_config(&root, &db, 100, &config).unwrap();
+        let mut relative: Vec<String> = indexed_paths(&db)
+            .iter()
+            .map(|p| {
+                Path::new(p)
+                    .strip_prefix(&root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+        relative.sort();
+        assert_eq!(
+            relative,
+            vec!["build/x/keep.txt", "docs/build.md", "src/main.rs"]
+        );
+
+        let config = IndexConfig {
+            exclude_globs: vec!["[".into()],
+            ..Default::default()
+        };
+        let error = scan_idxs_with_config(&root, &db, 100, &config).unwrap_err();
+        assert!(error.to_string().contains("Invalid exclude pattern"));
+    }
+
+    #[test]
+    fn test_dot_ignore_overrides_gitignore_and_hidden_is_separate() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().join(".project");
+        fs::create_dir_all(root.join(".cache")).unwrap();
+        fs::write(root.join(".gitignore"), "*.bin\n").unwrap();
+        fs::write(root.join(".ignore"), "!keep.bin\n*.tmp\n").unwrap();
+        for file in ["a.bin", "keep.bin", "b.tmp", "c.txt", ".env", ".cache/blob"] {
+            File::create(root.join(file)).unwrap();
+        }
+        let names = |db_name: &str, config: &IndexConfig| -> Vec<String> {
+            let db = Database::init(temp_dir.path().join(db_name)).unwrap();
+            scan_idxs_with_config(&root, &db, 100, config).unwrap();
+            let mut names: Vec<String> = indexed_paths(&db)
+                .iter()
+                .map(|p| {
+                    Path::new(p)
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect();
+            names.sort();
+            names
+        };
+
+        let config = IndexConfig {
+            respect_gitignore: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            names("git.reminex.db", &config),
+            vec![".env", ".gitignore", ".ignore", "blob", "c.txt", "keep.bin"]
+        );
+
+        // A hidden scan root is still scanned
+        let config = IndexConfig {
+            skip_hidden: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            names("visible.reminex.db", &config),
+            vec!["a.bin", "b.tmp", "c.txt", "keep.bin"]
+        );
+    }
+
     #[test]
     fn test_get_file_metadata() {
         let temp_dir = TempDir::new().unwrap();
@@ -538,7 +2512,7 @@ mod tests {
         // Use very large batch size
         let result = scan_idxs(temp_dir.path(), &db, 10000).unwrap();
 
-        assert!(result.duration.as_millis() > 0);
+        assert!(!result.duration.is_zero());
 
         let count = db
             .batch_operation(|conn| {
@@ -552,4 +2526,503 @@ mod tests {
 
         let _ = fs::remove_file(db_path);
     }
+
+    fn indexed_paths(db: &Database) -> Vec<String> {
+        db.batch_operation(|conn| {
+            let mut stmt = conn.prepare("SELECT path FROM files ORDER BY path")?;
+            let paths = stmt
+                .query_map([], |row| row.get(0))?
+                .collect::<rusqlite::Result<Vec<String>>>()?;
+            Ok(paths)
+        })
+        .unwrap()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_directory_links_skipped_by_default() {
+        let temp_dir = create_test_directory();
+        let base = temp_dir.path();
+        // A link back to the root would loop forever if followed blindly
+        std::os::unix::fs::symlink(base, base.join("dir1/loop")).unwrap();
+        std::os::unix::fs::symlink(base.join("dir2"), base.join("alias")).unwrap();
+
+        let db_dir = TempDir::new().unwrap();
+        let db = Database::init(db_dir.path().join("links.reminex.db")).unwrap();
+        scan_idxs(base, &db, 100).unwrap();
+        assert_eq!(indexed_paths(&db).len(), 5);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_junctions_breaks_cycles() {
+        let temp_dir = create_test_directory();
+        let base = temp_dir.path();
+        std::os::unix::fs::symlink(base, base.join("dir1/loop")).unwrap();
+        let outside = TempDir::new().unwrap();
+        File::create(outside.path().join("linked.txt")).unwrap();
+        std::os::unix::fs::symlink(outside.path(), base.join("external")).unwrap();
+
+        let db_dir = TempDir::new().unwrap();
+        let db = Database::init(db_dir.path().join("links.reminex.db")).unwrap();
+        let config = IndexConfig {
+            follow_junctions: true,
+            ..Default::default()
+        };
+        scan_idxs_with_config(base, &db, 100, &config).unwrap();
+
+        let paths = indexed_paths(&db);
+        assert_eq!(paths.len(), 6, "unexpected paths: {:?}", paths);
+        assert!(paths.iter().any(|p| p.ends_with("linked.txt")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_self_referential_symlinks_are_reported_not_followed() {
+        let temp_dir = create_test_directory();
+        let base = temp_dir.path();
+        std::os::unix::fs::symlink(base, base.join("dir1/loop")).unwrap();
+        std::os::unix::fs::symlink(base.join("self"), base.join("self")).unwrap();
+        let db_dir = TempDir::new().unwrap();
+
+        for follow_junctions in [false, true] {
+            let db_path = db_dir.path().join(format!("{follow_junctions}.reminex.db"));
+            let db = Database::init(db_path).unwrap();
+            let config = IndexConfig {
+                follow_junctions,
+                ..Default::default()
+            };
+            let result = scan_idxs_with_config(base, &db, 100, &config).unwrap();
+
+            assert_eq!(indexed_paths(&db).len(), 5);
+            let mut skipped = result.skipped_links;
+            skipped.sort();
+            assert_eq!(
+                skipped,
+                vec![
+                    base.join("dir1/loop").display().to_string(),
+                    base.join("self").display().to_string()
+                ]
+            );
+            assert!(result.skipped_paths.is_empty());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_directories_reached_through_links_are_not_reported_as_links() {
+        let temp_dir = create_test_directory();
+        let base = temp_dir.path();
+        // Following `up` reaches the scan root again as a plain directory
+        std::os::unix::fs::symlink(base, base.join("dir2/up")).unwrap();
+
+        let db_dir = TempDir::new().unwrap();
+        let db = Database::init(db_dir.path().join("links.reminex.db")).unwrap();
+        let config = IndexConfig {
+            follow_junctions: true,
+            ..Default::default()
+        };
+        let result = scan_idxs_with_config(base.join("dir2"), &db, 100, &config).unwrap();
+
+        assert!(
+            result.skipped_links.is_empty(),
+            "{:?}",
+            result.skipped_links
+        );
+        assert!(result.skipped_paths.is_empty());
+        assert!(
+            indexed_paths(&db)
+                .iter()
+                .any(|p| p.ends_with("dir1/file3.txt"))
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_junctions_skipped_by_default() {
+        let temp_dir = create_test_directory();
+        let base = temp_dir.path();
+        let junction = base.join("dir1").join("loop");
+        let status = std::process::Command::new("cmd")
+            .args(["/C", "mklink", "/J"])
+            .arg(&junction)
+            .arg(base)
+            .status()
+            .unwrap();
+        assert!(status.success(), "failed to create junction");
+
+        let db_dir = TempDir::new().unwrap();
+        let db = Database::init(db_dir.path().join("links.reminex.db")).unwrap();
+        scan_idxs(base, &db, 100).unwrap();
+        assert_eq!(indexed_paths(&db).len(), 5);
+
+        let db = Database::init(db_dir.path().join("follow.reminex.db")).unwrap();
+        let config = IndexConfig {
+            follow_junctions: true,
+            ..Default::default()
+        };
+        scan_idxs_with_config(base, &db, 100, &config).unwrap();
+        assert_eq!(indexed_paths(&db).len(), 5);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_skip_hidden_uses_file_attributes() {
+        let temp_dir = create_test_directory();
+        let base = temp_dir.path();
+        for (path, flag) in [("file1.txt", "+h"), ("dir1", "+h"), ("file2.txt", "+s")] {
+            let status = std::process::Command::new("attrib")
+                .arg(flag)
+                .arg(base.join(path))
+                .status()
+                .unwrap();
+            assert!(status.success(), "failed to set attribute on {path}");
+        }
+
+        let db_dir = TempDir::new().unwrap();
+        let db = Database::init(db_dir.path().join("all.reminex.db")).unwrap();
+        scan_idxs(base, &db, 100).unwrap();
+        assert_eq!(indexed_paths(&db).len(), 5);
+
+        let db = Database::init(db_dir.path().join("visible.reminex.db")).unwrap();
+        let config = IndexConfig {
+            skip_hidden: true,
+            ..Default::default()
+        };
+        scan_idxs_with_config(base, &db, 100, &config).unwrap();
+        // Only dir2's files are left
+        assert_eq!(indexed_paths(&db).len(), 2);
+    }
+
+    #[test]
+    fn test_exclude_paths_skips_databases_and_side_files() {
+        let temp_dir = create_test_directory();
+        let base = temp_dir.path();
+        let managed = base.join("dir1/managed.reminex.db");
+        fs::write(&managed, b"db").unwrap();
+        fs::write(base.join("dir1/managed.reminex.db-wal"), b"wal").unwrap();
+
+        let db_path = base.join(".reminex.db");
+        let db = Database::init(&db_path).unwrap();
+        let config = IndexConfig {
+            exclude_paths: vec![managed, db_path],
+            ..Default::default()
+        };
+        scan_idxs_with_config(base, &db, 100, &config).unwrap();
+
+        let paths = indexed_paths(&db);
+        assert_eq!(paths.len(), 5, "unexpected paths: {:?}", paths);
+        assert!(paths.iter().all(|p| !p.contains(".reminex.db")));
+    }
+
+    #[test]
+    fn test_progress_tracks_received_files() {
+        let db_dir = TempDir::new().unwrap();
+        let db = Database::init(db_dir.path().join("progress.reminex.db")).unwrap();
+        let reported = Mutex::new(Vec::new());
+        let counter = Arc::new(AtomicU64::new(0));
+
+        let (tx, rx) = bounded::<Index>(1000);
+        for i in 0..250 {
+            tx.send(Index::new(format!("/f{}", i), format!("f{}", i)))
+                .unwrap();
+        }
+        drop(tx);
+
+        let options = WriteOptions {
+            root_id: db.register_root("/").unwrap(),
+            batch_size: 100,
+            progress_step: 7,
+            existing: None,
+            collect_seen: false,
+        };
+        let progress = |event| reported.lock().unwrap().push(event);
+        write_indices_batched_with_progress(rx, &db, options, &progress, counter.clone()).unwrap();
+
+        assert_eq!(counter.load(Ordering::Relaxed), 250);
+        let expected: Vec<_> = (7..=250).step_by(7).map(ProgressEvent::Scanning).collect();
+        assert_eq!(reported.into_inner().unwrap(), expected);
+        assert_eq!(indexed_paths(&db).len(), 250);
+    }
+
+    #[test]
+    fn test_scan_reports_progress_events() {
+        let temp_dir = create_test_directory();
+        let db_dir = TempDir::new().unwrap();
+        let db = Database::init(db_dir.path().join("events.reminex.db")).unwrap();
+        let config = IndexConfig {
+            progress_step: 2,
+            incremental: true,
+            prune: true,
+            ..Default::default()
+        };
+
+        let scan = |db: &Database| {
+            let reported = Arc::new(Mutex::new(Vec::new()));
+            let sink = reported.clone();
+            scan_idxs_with_progress(temp_dir.path(), db, 100, &config, move |event| {
+                sink.lock().unwrap().push(event)
+            })
+            .unwrap();
+            Arc::try_unwrap(reported).unwrap().into_inner().unwrap()
+        };
+
+        use ProgressEvent::*;
+        assert_eq!(
+            scan(&db),
+            vec![Scanning(2), Scanning(4), Pruning, Finished(5)]
+        );
+
+        // Unchanged files are counted as well
+        fs::remove_file(temp_dir.path().join("file1.txt")).unwrap();
+        assert_eq!(
+            scan(&db),
+            vec![Scanning(2), Scanning(4), Pruning, Finished(4)]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_extract_permissions_unix() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("owned.txt");
+        File::create(&file_path).unwrap();
+        fs::set_permissions(&file_path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        let (owner, mode) = extract_permissions(&file_path);
+        let uid = fs::metadata(&file_path).unwrap().uid() as i64;
+        assert_eq!(owner, Some(uid));
+        assert_eq!(mode.map(|m| m & 0o7777), Some(0o640));
+
+        let db = Database::init(temp_dir.path().join("perms.reminex.db")).unwrap();
+        let config = IndexConfig {
+            with_perms: true,
+            exclude_paths: vec![temp_dir.path().join("perms.reminex.db")],
+            ..Default::default()
+        };
+        scan_idxs_with_config(temp_dir.path(), &db, 10, &config).unwrap();
+        let stored: (Option<i64>, Option<i64>) = db
+            .batch_operation(|conn| {
+                Ok(conn.query_row("SELECT owner, mode FROM files", [], |row| {
+                    Ok((row.get(0)?, row.get(1)?))
+                })?)
+            })
+            .unwrap();
+        assert_eq!(stored, (owner, mode));
+    }
+
+    #[test]
+    fn test_write_failure_reports_committed_rows() {
+        let temp_dir = create_test_directory();
+        let db_dir = TempDir::new().unwrap();
+        let db = Database::init(db_dir.path().join("failing.reminex.db")).unwrap();
+
+        // Simulate the disk filling up after two rows
+        db.batch_operation(|conn| {
+            conn.execute_batch(
+                "CREATE TRIGGER disk_full BEFORE INSERT ON files
+                 WHEN (SELECT COUNT(*) FROM files) >= 2
+                 BEGIN SELECT RAISE(FAIL, 'database or disk is full'); END;",
+            )?;
+            Ok(())
+        })
+        .unwrap();
+
+        let err = scan_idxs(temp_dir.path(), &db, 2).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(
+            message.contains("after committing 2 rows"),
+            "unexpected error: {}",
+            message
+        );
+        assert!(
+            message.contains("disk is full"),
+            "unexpected error: {}",
+            message
+        );
+    }
+
+    #[test]
+    fn test_rename_events_coalesce() {
+        use notify::event::{CreateKind, EventKind, ModifyKind, RenameMode};
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let db = Database::init(root.join("w.reminex.db")).unwrap();
+        let options = WatchOptions {
+            config: IndexConfig {
+                exclude_paths: vec![root.join("w.reminex.db")],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let ctx = WatchContext::new(root, &db, &options).unwrap();
+        let event = |kind, paths: &[&str]| {
+            paths.iter().fold(notify::Event::new(kind), |event, path| {
+                event.add_path(root.join(path))
+            })
+        };
+        let rename = |mode| EventKind::Modify(ModifyKind::Name(mode));
+
+        // A rename reported in halves and then whole only moves rows
+        let mut pending = PendingChanges::default();
+        assert!(pending.add(event(rename(RenameMode::From), &["a"]), &ctx));
+        assert!(pending.add(event(rename(RenameMode::To), &["b"]), &ctx));
+        assert!(pending.add(event(rename(RenameMode::Both), &["a", "b"]), &ctx));
+        assert!(pending.paths.is_empty());
+        assert_eq!(pending.renames, vec![(root.join("a"), root.join("b"))]);
+
+        // A directory created and renamed within one period is indexed anew
+        let mut pending = PendingChanges::default();
+        pending.add(event(EventKind::Create(CreateKind::Folder), &["new"]), &ctx);
+        pending.add(
+            event(EventKind::Create(CreateKind::File), &["new/x.txt"]),
+            &ctx,
+        );
+        pending.add(event(rename(RenameMode::Both), &["new", "moved"]), &ctx);
+        let mut paths: Vec<_> = pending.paths.into_iter().collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![(root.join("moved"), true), (root.join("moved/x.txt"), true)]
+        );
+
+        // Reads and the database's own files are ignored
+        let mut pending = PendingChanges::default();
+        let read = EventKind::Access(notify::event::AccessKind::Any);
+        assert!(!pending.add(event(read, &["x.txt"]), &ctx));
+        let write = EventKind::Modify(ModifyKind::Any);
+        assert!(!pending.add(event(write, &["w.reminex.db-wal"]), &ctx));
+        assert!(pending.paths.is_empty());
+    }
+
+    #[test]
+    fn test_watch_respects_ignore_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src/.ignore"), "*.tmp\n").unwrap();
+        let db_path = root.join("w.reminex.db");
+        let db = Database::init(&db_path).unwrap();
+        let options = WatchOptions {
+            config: IndexConfig {
+                exclude_paths: vec![db_path],
+                respect_gitignore: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let ctx = WatchContext::new(root, &db, &options).unwrap();
+
+        // Files written after the initial scan, e.g. by a build
+        fs::create_dir_all(root.join("target/debug")).unwrap();
+        fs::write(root.join("target/debug/app"), b"bin").unwrap();
+        fs::write(root.join("src/main.rs"), b"fn main() {}").unwrap();
+        fs::write(root.join("src/scratch.tmp"), b"tmp").unwrap();
+
+        let mut pending = PendingChanges::default();
+        for (path, new_dir) in [
+            ("target", true),
+            ("target/debug/app", false),
+            ("src/main.rs", false),
+            ("src/scratch.tmp", false),
+        ] {
+            pending.paths.insert(root.join(path), new_dir);
+        }
+        ctx.apply(pending).unwrap();
+
+        assert_eq!(
+            indexed_paths(&db),
+            vec![root.join("src/main.rs").to_string_lossy().into_owned()]
+        );
+    }
+
+    #[test]
+    fn test_watch_applies_changes() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path().to_path_buf();
+        fs::create_dir_all(root.join("old/deep")).unwrap();
+        fs::write(root.join("old/deep/a.txt"), b"a").unwrap();
+        fs::write(root.join("gone.txt"), b"gone").unwrap();
+        let db_path = root.join(".reminex.db");
+        let db = Database::init(&db_path).unwrap();
+        let options = WatchOptions {
+            config: IndexConfig {
+                with_metadata: true,
+                exclude_paths: vec![db_path],
+                ..Default::default()
+            },
+            debounce: Duration::from_millis(50),
+            ..Default::default()
+        };
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let (scanned_tx, scanned_rx) = std::sync::mpsc::channel();
+        let watch = {
+            let (root, db, stop) = (root.clone(), db.clone(), stop.clone());
+            std::thread::spawn(move || {
+                watch_and_index(
+                    &root,
+                    &db,
+                    &options,
+                    &stop,
+                    |_| {},
+                    |event| {
+                        if let WatchEvent::Scanned(_) = event {
+                            scanned_tx.send(()).unwrap();
+                        }
+                        Ok(())
+                    },
+                )
+            })
+        };
+        scanned_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+
+        fs::write(root.join("new.txt"), b"hello").unwrap();
+        fs::remove_file(root.join("gone.txt")).unwrap();
+        fs::rename(root.join("old"), root.join("renamed")).unwrap();
+        fs::create_dir(root.join("fresh")).unwrap();
+        fs::write(root.join("fresh/b.txt"), b"b").unwrap();
+
+        let path = |rel: &str| root.join(rel).to_string_lossy().into_owned();
+        let mut expected = vec![
+            path("fresh/b.txt"),
+            path("new.txt"),
+            path("renamed/deep/a.txt"),
+        ];
+        expected.sort();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while indexed_paths(&db) != expected && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        stop.store(true, Ordering::SeqCst);
+        let summary = watch.join().unwrap().unwrap();
+        assert_eq!(indexed_paths(&db), expected);
+        assert!(summary.written >= 2, "{:?}", summary);
+        assert!(summary.removed >= 1, "{:?}", summary);
+        let size: i64 = db
+            .batch_operation(|conn| {
+                Ok(conn.query_row(
+                    "SELECT size FROM files WHERE path = ?1",
+                    [path("new.txt")],
+                    |row| row.get(0),
+                )?)
+            })
+            .unwrap();
+        assert_eq!(size, 5);
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload: Box<dyn std::any::Any + Send> = Box::new("boom");
+        assert_eq!(panic_message(payload.as_ref()), "boom");
+        let payload: Box<dyn std::any::Any + Send> = Box::new(String::from("bang"));
+        assert_eq!(panic_message(payload.as_ref()), "bang");
+        let payload: Box<dyn std::any::Any + Send> = Box::new(42);
+        assert_eq!(panic_message(payload.as_ref()), "unknown panic payload");
+    }
 }
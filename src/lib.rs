@@ -1,6 +1,14 @@
+pub mod cache;
+pub mod category;
 pub mod db;
+pub mod diff;
+pub mod dump;
+pub mod duplicates;
 pub mod export;
 pub mod history;
+pub mod import;
 pub mod indexer;
+pub mod maintenance;
 pub mod searcher;
+pub mod stats;
 pub mod web;
@@ -1,13 +1,79 @@
 use anyhow::{Context, Result};
-use clap::{Args, Parser, Subcommand};
-use std::io::{self, Write};
-use std::path::PathBuf;
-
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use reminex::cache::CacheConfig;
+use reminex::category::CategoryMap;
 use reminex::db::Database;
-use reminex::indexer::{discover_databases, scan_idxs, scan_idxs_with_metadata};
-use reminex::searcher::{SearchConfig, build_tree, print_tree, search_in_selected_database};
+use reminex::diff::{DiffReport, diff_against_disk, diff_databases};
+use reminex::dump::{DumpFormat, dump_database};
+use reminex::duplicates::{
+    DuplicateGroup, DuplicateKey, find_duplicates_across, sort_by_wasted_bytes,
+};
+use reminex::export::{ExportFormat, SearchConvertParams, convert_from_search_results};
+use reminex::history::{DEFAULT_MAX_ENTRIES, SearchHistory, SearchHistoryItem};
+use reminex::import::{CsvImportOptions, import_csv};
+use reminex::indexer::{
+    IndexConfig, IndexProfile, IndexResult, ProgressEvent, WatchEvent, WatchOptions,
+    discover_databases, scan_idxs_with_progress, watch_and_index,
+};
+use reminex::maintenance::{prune_missing, relocate};
+use reminex::searcher::{
+    DEFAULT_FUZZY_THRESHOLD, DatabaseResults, KeywordCombine, Relaxation, ResultWindow,
+    SearchConfig, SearchMode, SearchResult, SortBy, TreeRootStrategy, TreeStyle,
+    build_tree_with_root, counts_in_sql, drop_empty_keywords, format_tree_node_with_style,
+    search_in_selected_database, search_in_selected_database_counted, search_with_min_results,
+    suggest_keywords,
+};
+use reminex::stats::{DbStats, collect_root_stats, collect_stats, format_bytes, parse_bytes};
 use reminex::web;
 
+/// Maximum number of "did you mean" suggestions per keyword
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Default number of extensions shown in a stats report
+const STATS_TOP: usize = 20;
+
+/// Default number of entries listed by `history`
+const HISTORY_LIST_LIMIT: usize = 20;
+
+/// Set by Ctrl+C in interactive search; cleared when the next query starts
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Set by Ctrl+C to end `index --watch`
+static STOP_WATCHING: AtomicBool = AtomicBool::new(false);
+
+/// Whether the interactive search is waiting at the `搜索>` prompt
+static AT_PROMPT: AtomicBool = AtomicBool::new(false);
+
+/// Returns true once Ctrl+C was pressed during the current query.
+fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Makes Ctrl+C abort the current output instead of the process.
+///
+/// A second Ctrl+C before the next query starts exits.
+fn install_interrupt_handler() -> Result<()> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            println!("\n再见！");
+            std::process::exit(130);
+        }
+        if AT_PROMPT.load(Ordering::SeqCst) {
+            print!("\n(再次按 Ctrl+C 或按 Ctrl+D 退出)\n搜索> ");
+            let _ = io::stdout().flush();
+        }
+    })
+    .context("无法注册 Ctrl+C 处理程序")
+}
+
 #[tokio::main]
 async fn main() {
     if let Err(e) = run().await {
@@ -29,11 +95,50 @@ async fn run() -> Result<()> {
         Some(Commands::Web(args)) | Some(Commands::W(args)) => {
             handle_web_command(args).await?;
         }
+        Some(Commands::Import(args)) => {
+            handle_import_command(args)?;
+        }
+        Some(Commands::Stats(args)) => {
+            handle_stats_command(args)?;
+        }
+        Some(Commands::Export(args)) => {
+            handle_export_command(args)?;
+        }
+        Some(Commands::ExportDb(args)) => {
+            handle_export_db_command(args)?;
+        }
+        Some(Commands::Label(args)) => {
+            handle_label_command(args)?;
+        }
+        Some(Commands::Prune(args)) => {
+            handle_prune_command(args)?;
+        }
+        Some(Commands::Roots(args)) => {
+            handle_roots_command(args)?;
+        }
+        Some(Commands::History(args)) => {
+            handle_history_command(args)?;
+        }
+        Some(Commands::Diff(args)) => {
+            handle_diff_command(args)?;
+        }
+        Some(Commands::Relocate(args)) => {
+            handle_relocate_command(args)?;
+        }
+        Some(Commands::Optimize(args)) => {
+            handle_optimize_command(args)?;
+        }
+        Some(Commands::Dedupe(args)) => {
+            handle_dedupe_command(args)?;
+        }
         None => {
             // 默认行为：启动 Web 服务器
             let default_args = WebArgs {
                 db: None,
                 port: None,
+                cache_size: 64,
+                cache_ttl: 60,
+                once: false,
             };
             handle_web_command(default_args).await?;
         }
@@ -44,14 +149,17 @@ async fn run() -> Result<()> {
 
 fn handle_index_command(args: IndexArgs) -> Result<()> {
     // 确定根目录路径
-    let root_path = args.path.unwrap_or_else(|| PathBuf::from("./"));
+    let root_path = args.path.clone().unwrap_or_else(|| PathBuf::from("./"));
 
     if !root_path.exists() {
         anyhow::bail!("路径不存在: {}", root_path.display());
     }
 
     // 确定数据库路径
-    let db_path = args.db.unwrap_or_else(|| root_path.join(".reminex.db"));
+    let db_path = args
+        .db
+        .clone()
+        .unwrap_or_else(|| root_path.join(".reminex.db"));
 
     println!("📁 索引目录: {}", root_path.display());
     println!("💾 数据库文件: {}", db_path.display());
@@ -59,7 +167,7 @@ fn handle_index_command(args: IndexArgs) -> Result<()> {
     // 初始化或打开数据库
     let db = if db_path.exists() && !args.full {
         println!("📂 使用现有数据库");
-        Database::new(&db_path)
+        Database::init(&db_path)?
     } else {
         if args.full {
             println!("🔄 执行全量重建");
@@ -79,14 +187,202 @@ fn handle_index_command(args: IndexArgs) -> Result<()> {
     println!("🚀 开始扫描...");
     println!("   批量大小: {}", batch_size);
 
-    let result = if args.no_metadata {
+    if args.no_metadata {
         println!("   模式: 快速扫描（无元数据）");
-        scan_idxs(&root_path, &db, batch_size)?
+    } else if args.incremental {
+        println!("   模式: 增量扫描（跳过大小和修改时间未变的文件）");
     } else {
         println!("   模式: 完整扫描（含元数据）");
-        scan_idxs_with_metadata(&root_path, &db, batch_size)?
+    }
+    if args.follow_junctions {
+        println!("   跟随目录联接点/符号链接");
+    }
+    if args.perms {
+        println!("   记录文件所有者和权限");
+    }
+    if args.index_archives {
+        println!("   索引 zip 压缩包内的文件");
+    }
+    if args.skip_empty {
+        println!("   跳过空文件");
+    }
+    if args.skip_hidden {
+        println!("   跳过隐藏文件");
+    }
+    for pattern in &args.exclude {
+        println!("   排除: {}", pattern);
+    }
+    if let Some(depth) = args.max_depth {
+        println!("   最大递归深度: {}", depth);
+    }
+    if args.threads > 0 {
+        println!("   扫描线程数: {}", args.threads);
+    }
+    if args.respect_gitignore {
+        println!("   遵循 .gitignore 规则");
+    }
+    for file in &args.ignore_file {
+        println!("   忽略规则文件: {}", file.display());
+    }
+    if args.prune {
+        println!("   清理磁盘上已删除文件的记录");
+    }
+
+    if let Some(label) = &args.label {
+        db.set_label(label)?;
+        println!("   标签: {}", label.trim());
+    }
+
+    if let Some(style) = args.path_separator {
+        let separator = style.separator();
+        db.set_path_separator(separator)?;
+        match separator {
+            Some(c) => println!("   路径分隔符统一为: {}", c),
+            None => println!("   路径分隔符保持原样"),
+        }
+    }
+
+    let config = IndexConfig {
+        with_metadata: !args.no_metadata,
+        with_perms: args.perms,
+        follow_junctions: args.follow_junctions,
+        exclude_paths: vec![db_path.clone()],
+        progress_step: args.progress_step,
+        index_archives: args.index_archives,
+        profile: args.profile,
+        skip_empty: args.skip_empty,
+        incremental: args.incremental,
+        respect_gitignore: args.respect_gitignore,
+        skip_hidden: args.skip_hidden,
+        exclude_globs: args.exclude.clone(),
+        max_depth: args.max_depth,
+        threads: args.threads,
+        ignore_files: args.ignore_file.clone(),
+        prune: args.prune,
+    };
+    if args.watch {
+        return watch_index(&args, &root_path, &db, &db_path, batch_size, config);
+    }
+
+    let (spinner, on_progress) = scan_spinner(config.with_metadata);
+    let result = scan_idxs_with_progress(&root_path, &db, batch_size, &config, on_progress)
+        .inspect_err(|_| spinner.abandon_with_message("扫描失败"))?;
+    report_index_result(&args, &db, &db_path, &result)
+}
+
+/// 创建扫描进度条，返回进度条和更新它的进度回调
+fn scan_spinner(
+    with_metadata: bool,
+) -> (ProgressBar, impl Fn(ProgressEvent) + Send + Sync + 'static) {
+    let spinner = ProgressBar::new_spinner();
+    spinner.set_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.green} [{elapsed_precise}] {msg} {pos} 个文件")
+            .unwrap(),
+    );
+    spinner.set_message(if with_metadata {
+        "扫描中 (含元数据)"
+    } else {
+        "扫描中"
+    });
+
+    let bar = spinner.clone();
+    let on_progress = move |event| match event {
+        ProgressEvent::Scanning(files) => bar.set_position(files),
+        ProgressEvent::Pruning => bar.set_message("清理已删除的文件"),
+        ProgressEvent::Finished(files) => {
+            bar.set_position(files);
+            bar.finish_with_message("完成");
+        }
+    };
+    (spinner, on_progress)
+}
+
+/// 初始扫描后持续监视目录变化并更新索引，直到按下 Ctrl+C
+fn watch_index(
+    args: &IndexArgs,
+    root_path: &Path,
+    db: &Database,
+    db_path: &Path,
+    batch_size: usize,
+    config: IndexConfig,
+) -> Result<()> {
+    let options = WatchOptions {
+        config,
+        batch_size,
+        debounce: Duration::from_millis(args.debounce_ms),
     };
 
+    let (spinner, on_progress) = scan_spinner(options.config.with_metadata);
+    let total = watch_and_index(
+        root_path,
+        db,
+        &options,
+        &STOP_WATCHING,
+        on_progress,
+        |event| {
+            match event {
+                WatchEvent::Scanned(result) => {
+                    report_index_result(args, db, db_path, result)?;
+                    // 初始扫描期间 Ctrl+C 直接退出；之后改为停止监视，再按一次才退出
+                    ctrlc::set_handler(|| {
+                        if STOP_WATCHING.swap(true, Ordering::SeqCst) {
+                            std::process::exit(130);
+                        }
+                    })
+                    .context("无法注册 Ctrl+C 处理程序")?;
+                    println!("\n👀 正在监视文件变化，按 Ctrl+C 停止");
+                }
+                WatchEvent::Applied(summary) => {
+                    if summary.written + summary.removed + summary.moved + summary.rescans == 0 {
+                        return Ok(());
+                    }
+                    print!(
+                        "[{}] 写入: {}  删除: {}",
+                        chrono::Local::now().format("%H:%M:%S"),
+                        summary.written,
+                        summary.removed
+                    );
+                    if summary.moved > 0 {
+                        print!("  移动: {}", summary.moved);
+                    }
+                    if summary.rescans > 0 {
+                        print!("  （系统丢失了事件，已重新扫描）");
+                    }
+                    println!();
+                }
+                WatchEvent::WatcherError(e) => eprintln!("⚠️  监视出错: {:#}", e),
+            }
+            Ok(())
+        },
+    )
+    .inspect_err(|_| {
+        if !spinner.is_finished() {
+            spinner.abandon_with_message("扫描失败");
+        }
+    })?;
+
+    println!("\n⏹️  已停止监视");
+    println!(
+        "   共写入: {}  删除: {}  移动: {}",
+        total.written, total.removed, total.moved
+    );
+    Ok(())
+}
+
+/// 扫描完成后建立全文索引并输出统计
+fn report_index_result(
+    args: &IndexArgs,
+    db: &Database,
+    db_path: &Path,
+    result: &IndexResult,
+) -> Result<()> {
+    // 扫描后一次性建立全文索引，比逐行触发器更新快；之后由触发器保持同步
+    if args.fts && !db.has_fts()? {
+        println!("🔎 建立全文索引...");
+        db.enable_fts()?;
+    }
+
     // 统计信息
     let count = db.batch_operation(|conn| {
         let count: i64 = conn.query_row("SELECT COUNT(*) FROM files", [], |row| row.get(0))?;
@@ -100,280 +396,2156 @@ fn handle_index_command(args: IndexArgs) -> Result<()> {
         "   速度: {:.0} 文件/秒",
         count as f64 / result.duration.as_secs_f64()
     );
+    if args.incremental {
+        println!(
+            "   新增: {}  更新: {}  未变跳过: {}",
+            result.added, result.updated, result.skipped
+        );
+    }
+    if args.prune {
+        println!("   清理已删除: {}", result.removed);
+    }
+    if result.skipped_special > 0 {
+        println!(
+            "   跳过特殊文件（套接字、管道、设备）: {}",
+            result.skipped_special
+        );
+    }
+    if result.skipped_empty > 0 {
+        println!("   跳过空文件: {}", result.skipped_empty);
+    }
+    if !result.skipped_links.is_empty() {
+        println!("   未跟随的目录链接: {}", result.skipped_links.len());
+    }
+    if result.depth_limited > 0 {
+        println!("   超过最大深度未进入的目录: {}", result.depth_limited);
+    }
+
+    if let Some(profile) = &result.profile {
+        print_profile(profile);
+    }
+
+    if args.stats_after_index {
+        if args.no_metadata {
+            println!("\n💡 提示: 快速模式未记录文件大小，统计中不含大小信息");
+        }
+        println!();
+        print_stats(&listing_name(db_path), &collect_stats(db)?, STATS_TOP);
+    }
 
     Ok(())
 }
 
-fn handle_search_command(args: SearchArgs) -> Result<()> {
-    // Discover databases
-    let db_paths = if let Some(paths) = args.db.clone() {
-        discover_databases(&paths)
-    } else {
-        // Use current directory to search for databases
-        let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-        let discovered = discover_databases(&[current_dir]);
+fn handle_import_command(args: ImportArgs) -> Result<()> {
+    if !args.csv.exists() {
+        anyhow::bail!("CSV 文件不存在: {}", args.csv.display());
+    }
 
-        if discovered.is_empty() {
-            // Also check for default .reminex.db in current directory
-            let default_path = PathBuf::from("./.reminex.db");
-            if default_path.exists() {
-                vec![default_path]
-            } else {
-                Vec::new()
-            }
-        } else {
-            discovered
-        }
+    println!("📄 导入文件: {}", args.csv.display());
+    println!("💾 数据库文件: {}", args.db.display());
+
+    let db = Database::init(&args.db)?;
+    let options = CsvImportOptions {
+        path_col: args.path_col,
+        name_col: args.name_col,
+        size_col: args.size_col,
+        mtime_col: args.mtime_col,
+        has_header: !args.no_header,
+        batch_size: args.batch_size,
     };
 
-    if db_paths.is_empty() {
-        anyhow::bail!(
-            "未找到任何数据库文件\n请先运行索引命令创建数据库，或使用 --db 指定数据库路径"
-        );
-    }
+    let start = std::time::Instant::now();
+    let count = import_csv(&args.csv, &db, &options)?;
 
-    // Display discovered databases
-    println!("📚 发现 {} 个数据库:", db_paths.len());
-    for (i, db_path) in db_paths.iter().enumerate() {
-        let db_name = db_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown");
-        println!("   {}. {}", i + 1, db_name);
-    }
-    println!();
+    println!("\n✅ 导入完成！");
+    println!("   耗时: {:.2}s", start.elapsed().as_secs_f64());
+    println!("   导入行数: {}", count);
 
-    // 配置搜索参数
-    let config = SearchConfig {
-        max_results: args.limit.unwrap_or(2000),
-        search_in_path: !args.name_only,
-        case_sensitive: args.case_sensitive,
-        include_filters: Vec::new(),
-        exclude_filters: Vec::new(),
+    Ok(())
+}
+
+fn handle_export_command(args: ExportArgs) -> Result<()> {
+    let search = &args.search;
+    let Some(ref query) = search.keywords else {
+        anyhow::bail!("请提供要导出的搜索关键词");
     };
+    let db_paths = resolve_db_paths(search.db.as_deref())?;
+    let config = search_config(search)?;
+
+    println!("📚 发现 {} 个数据库", db_paths.len());
+    println!("📄 导出文件: {}", args.output.display());
+
+    let keywords = reminex::searcher::parse_search_keywords(query);
+    let results = search_in_selected_database(&db_paths, &search.select_db, &keywords, &config)?;
+    let exported = convert_from_search_results(SearchConvertParams {
+        query: query.clone(),
+        selected_db: search.select_db.clone(),
+        config: &config,
+        results,
+        include_empty: search.include_empty.unwrap_or(false),
+    });
+    let format = match args.format {
+        Some(format) => format.into(),
+        None => ExportFormat::from_path(&args.output).unwrap_or(ExportFormat::Toml),
+    };
+    exported
+        .export_to_file_as(&args.output, format)
+        .with_context(|| format!("无法写入导出文件: {}", args.output.display()))?;
 
-    // 如果提供了关键词，直接搜索
-    if let Some(ref keywords) = args.keywords {
-        perform_multi_db_search(&db_paths, &args.select_db, keywords, &config, &args)?;
-        return Ok(());
-    }
+    println!("\n✅ 导出完成！");
+    println!("   关键词数: {}", exported.results.len());
+    println!("   文件数: {}", exported.metadata.total_count);
 
-    // 交互模式
-    println!("🔍 reminex 搜索模式");
-    println!("   搜索范围: {}", args.select_db);
-    println!("   输入关键词搜索，多个关键词用 ; 或空格分隔");
-    println!("   输入 :q 退出\n");
+    Ok(())
+}
 
-    loop {
-        print!("搜索> ");
-        io::stdout().flush()?;
+fn handle_export_db_command(args: ExportDbArgs) -> Result<()> {
+    if !args.db.exists() {
+        anyhow::bail!("数据库文件不存在: {}", args.db.display());
+    }
 
-        let mut input = String::new();
-        io::stdin().read_line(&mut input)?;
-        let input = input.trim();
+    println!("💾 数据库文件: {}", args.db.display());
+    println!("📄 导出文件: {}", args.out.display());
 
-        if input.is_empty() {
-            continue;
-        }
+    let db = Database::new(&args.db);
+    let file = std::fs::File::create(&args.out)
+        .with_context(|| format!("无法创建输出文件: {}", args.out.display()))?;
 
-        if input == ":q" || input == "exit" || input == "quit" {
-            println!("再见！");
-            break;
-        }
+    let start = std::time::Instant::now();
+    let count = dump_database(&db, args.format.into(), io::BufWriter::new(file))?;
 
-        perform_multi_db_search(&db_paths, &args.select_db, input, &config, &args)?;
-    }
+    println!("\n✅ 导出完成！");
+    println!("   耗时: {:.2}s", start.elapsed().as_secs_f64());
+    println!("   导出行数: {}", count);
 
     Ok(())
 }
 
-fn perform_multi_db_search(
-    db_paths: &[PathBuf],
-    selected_db: &str,
-    input: &str,
-    config: &SearchConfig,
-    args: &SearchArgs,
-) -> Result<()> {
-    use reminex::searcher::parse_search_keywords;
-
-    let keywords = parse_search_keywords(input);
-    let results = search_in_selected_database(db_paths, selected_db, &keywords, config)?;
+/// Number of paths listed by `prune --dry-run` before summarizing the rest
+const PRUNE_PREVIEW: usize = 20;
 
-    if results.is_empty() {
-        println!("\n❌ 未找到任何结果\n");
-        return Ok(());
+fn handle_prune_command(args: PruneArgs) -> Result<()> {
+    if !args.db.exists() {
+        anyhow::bail!("数据库文件不存在: {}", args.db.display());
     }
 
-    // Group results by database and keyword
-    let mut current_db = String::new();
-    let mut current_keyword = String::new();
-
-    for (db_name, keyword, items) in results {
-        // Print database header if changed
-        if db_name != current_db {
-            if !current_db.is_empty() {
-                println!();
-            }
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("📁 数据库: {}", db_name);
-            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            current_db = db_name.clone();
-        }
-
-        // Print keyword results
-        if keyword != current_keyword || db_name != current_db {
-            current_keyword = keyword.clone();
-        }
+    let dry_run = args.dry_run.dry_run;
+    println!("💾 数据库文件: {}", args.db.display());
+    if dry_run {
+        println!("🔍 预览模式，不会修改数据库");
+    }
 
-        if items.is_empty() {
-            println!("\n「{}」未找到任何结果", keyword);
-            continue;
-        }
+    let db = Database::init(&args.db)?;
+    let report = prune_missing(&db, args.prefix.as_deref(), dry_run)?;
 
-        println!("\n「{}」找到 {} 项结果：", keyword, items.len());
+    println!("\n   检查记录: {}", report.checked);
+    println!("   已不存在: {}", report.missing.len());
 
-        if args.tree {
-            // 树形显示
-            let root_name = args.root_name.as_deref().unwrap_or("搜索结果");
-            let tree = build_tree(&items, root_name);
-            println!();
-            print_tree(&tree);
+    if dry_run || args.list {
+        let shown = if args.list {
+            report.missing.len()
         } else {
-            // 列表显示
-            println!();
-            for item in &items {
-                println!("  {}", item.path);
-            }
+            PRUNE_PREVIEW
+        };
+        for path in report.missing.iter().take(shown) {
+            println!("   - {}", path);
+        }
+        if report.missing.len() > shown {
+            println!(
+                "   ... 还有 {} 项（使用 --list 显示全部）",
+                report.missing.len() - shown
+            );
         }
     }
+    if !dry_run {
+        println!("\n✅ 已删除 {} 条记录", report.deleted);
+    }
 
-    println!();
     Ok(())
 }
 
-async fn handle_web_command(args: WebArgs) -> Result<()> {
-    // Discover databases
-    let db_paths = if let Some(paths) = args.db {
-        discover_databases(&paths)
-    } else {
-        // Use current directory to search for databases
-        let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
-        let discovered = discover_databases(&[current_dir]);
+fn handle_relocate_command(args: RelocateArgs) -> Result<()> {
+    if !args.db.exists() {
+        anyhow::bail!("数据库文件不存在: {}", args.db.display());
+    }
 
-        if discovered.is_empty() {
-            // Also check for default .reminex.db in current directory
-            let default_path = PathBuf::from("./.reminex.db");
-            if default_path.exists() {
-                vec![default_path]
-            } else {
-                Vec::new()
-            }
-        } else {
-            discovered
-        }
-    };
+    let dry_run = args.dry_run.dry_run;
+    println!("💾 数据库文件: {}", args.db.display());
+    println!("🔀 {} → {}", args.from, args.to);
+    if dry_run {
+        println!("🔍 预览模式，不会修改数据库");
+    }
 
-    if db_paths.is_empty() {
-        anyhow::bail!(
-            "未找到任何数据库文件\n请先运行索引命令创建数据库，或使用 --db 指定数据库路径"
-        );
+    let db = Database::init(&args.db)?;
+    let report = relocate(&db, &args.from, &args.to, dry_run)?;
+
+    println!("\n   匹配记录: {}", report.matched);
+    println!("   路径冲突（将覆盖已有记录）: {}", report.conflicts);
+
+    if dry_run {
+        for (old, new) in &report.samples {
+            println!("   {}\n     → {}", old, new);
+        }
+        if report.matched > report.samples.len() {
+            println!("   ... 还有 {} 项", report.matched - report.samples.len());
+        }
+    } else {
+        println!("\n✅ 已更新 {} 条记录", report.updated);
     }
 
-    println!("🌐 启动 Web 服务器");
-    println!("📚 发现 {} 个数据库:", db_paths.len());
-    for db_path in &db_paths {
-        let db_name = db_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown");
-        println!("   - {}", db_name);
+    Ok(())
+}
+
+fn handle_optimize_command(args: OptimizeArgs) -> Result<()> {
+    if !args.db.exists() {
+        anyhow::bail!("数据库文件不存在: {}", args.db.display());
     }
-    println!();
 
-    let port = args.port.unwrap_or(3000);
-    let auto_retry = args.port.is_none();
-    web::run_server_with_retry(db_paths, port, auto_retry).await?;
+    println!("💾 数据库文件: {}", args.db.display());
+    println!("🧹 压缩数据库（VACUUM）并合并预写日志...");
+    let report = Database::new(&args.db).optimize()?;
+
+    println!("\n✅ 优化完成！");
+    println!("   优化前: {}", format_bytes(report.size_before));
+    println!("   优化后: {}", format_bytes(report.size_after));
+    println!("   回收空间: {}", format_bytes(report.reclaimed()));
 
     Ok(())
 }
 
-#[derive(Parser)]
-#[command(name = "reminex")]
-#[command(about = "快速文件索引和搜索工具 - 双击运行即启动 Web 服务器", long_about = None)]
-#[command(version)]
-struct App {
-    #[command(subcommand)]
-    commands: Option<Commands>,
-}
+fn handle_roots_command(args: RootsArgs) -> Result<()> {
+    if !args.db.exists() {
+        anyhow::bail!("数据库文件不存在: {}", args.db.display());
+    }
 
-#[derive(Subcommand)]
-enum Commands {
-    #[command(about = "索引文件 (index)")]
-    Index(IndexArgs),
+    let db = Database::init(&args.db)?;
+    if let Some(root) = &args.remove {
+        let root_id = db
+            .root_id(root)?
+            .with_context(|| format!("未找到索引根目录: {}", root))?;
+        let deleted = db.delete_root(root_id)?;
+        println!("✅ 已删除根目录 {} 下的 {} 条记录", root, deleted);
+        return Ok(());
+    }
 
-    #[command(about = "索引文件 (index 简写)")]
-    I(IndexArgs),
+    let roots = db.roots()?;
+    if roots.is_empty() {
+        println!("（没有记录索引根目录）");
+        return Ok(());
+    }
+    for root in roots {
+        println!("📂 {}  ({} 个文件)", root.path, root.files);
+    }
 
-    #[command(about = "搜索文件 (search)")]
-    Search(SearchArgs),
+    Ok(())
+}
 
-    #[command(about = "搜索文件 (search 简写)")]
-    S(SearchArgs),
+fn handle_history_command(args: HistoryArgs) -> Result<()> {
+    let path = args
+        .history_file
+        .clone()
+        .unwrap_or_else(SearchHistory::default_path);
+    let history = SearchHistory::new(path.clone(), DEFAULT_MAX_ENTRIES);
+
+    // 列表编号从 1 开始，最新的记录为 1
+    let entry = |n: usize| -> Result<SearchHistoryItem> {
+        let items = history.get_all()?;
+        n.checked_sub(1)
+            .and_then(|i| items.into_iter().nth(i))
+            .with_context(|| format!("没有第 {} 条搜索历史", n))
+    };
 
-    #[command(about = "Web 界面服务器 (web)")]
-    Web(WebArgs),
+    match args.action.unwrap_or(HistoryAction::List {
+        limit: HISTORY_LIST_LIMIT,
+    }) {
+        HistoryAction::List { limit } => print_history(&history.get_recent(limit)?),
+        HistoryAction::Clear => {
+            history.clear()?;
+            println!("✅ 已清空搜索历史");
+        }
+        HistoryAction::Remove { index } => {
+            let item = entry(index)?;
+            history.remove(index - 1)?;
+            println!("✅ 已删除第 {} 条记录: {}", index, item.query);
+        }
+        HistoryAction::Replay { index, db } => {
+            let item = entry(index)?;
+            println!("🔁 重新执行: {}\n", item.query);
+            let mut argv = vec![
+                "reminex".into(),
+                "--select-db".into(),
+                item.selected_db.into(),
+                "--history-file".into(),
+                path.into_os_string(),
+            ];
+            if item.name_only {
+                argv.push("--name-only".into());
+            }
+            if item.case_sensitive {
+                argv.push("--case-sensitive".into());
+            }
+            if let Some(dbs) = db {
+                argv.push("--db".into());
+                argv.extend(dbs.into_iter().map(PathBuf::into_os_string));
+            }
+            // 放在 -- 之后，以 - 开头的查询也不会被当作选项
+            argv.push("--".into());
+            argv.push(item.query.into());
+            let replay = ReplayCli::try_parse_from(argv)?;
+            handle_search_command(replay.search)?;
+        }
+    }
 
-    #[command(about = "Web 界面服务器 (web 简写)")]
-    W(WebArgs),
+    Ok(())
 }
 
-#[derive(Args, Clone)]
+/// Prints history entries as a numbered table, newest first.
+fn print_history(items: &[SearchHistoryItem]) {
+    if items.is_empty() {
+        println!("（没有搜索历史）");
+        return;
+    }
+    // 中文字符占两列宽，表头宽度相应减去字数以对齐数据列
+    println!(
+        "   {:>3}  {:<17}  {:>6}  {:<10}  查询",
+        "#", "时间", "结果", "数据库"
+    );
+    for (i, item) in items.iter().enumerate() {
+        let time = item.timestamp.with_timezone(&chrono::Local);
+        println!(
+            "   {:>3}  {:<19}  {:>8}  {:<13}  {}",
+            i + 1,
+            time.format("%Y-%m-%d %H:%M:%S"),
+            item.result_count,
+            item.selected_db,
+            item.query
+        );
+    }
+}
+
+fn handle_diff_command(args: DiffArgs) -> Result<()> {
+    // 只读打开，不迁移结构也不写入元数据
+    let open = |path: &Path| -> Result<Database> {
+        if !path.exists() {
+            anyhow::bail!("数据库文件不存在: {}", path.display());
+        }
+        Ok(Database::new(path))
+    };
+
+    let report = match (&args.old, &args.new, &args.db, &args.against_disk) {
+        (Some(old), Some(new), None, None) => {
+            println!("🔀 {} → {}", old.display(), new.display());
+            diff_databases(&open(old)?, &open(new)?, None)?
+        }
+        (None, None, Some(db), Some(root)) => {
+            if !root.exists() {
+                anyhow::bail!("目录不存在: {}", root.display());
+            }
+            println!("🔀 {} → 磁盘 {}", db.display(), root.display());
+            diff_against_disk(&open(db)?, root)?
+        }
+        _ => anyhow::bail!("请指定 --old 和 --new，或 --db 和 --against-disk"),
+    };
+
+    print_diff(&report, args.list);
+    Ok(())
+}
+
+fn print_diff(report: &DiffReport, list: bool) {
+    println!("\n   新增: {}", report.added.len());
+    println!("   删除: {}", report.removed.len());
+    println!("   变更: {}", report.changed.len());
+
+    if report.is_empty() {
+        println!("\n✅ 没有差异");
+        return;
+    }
+    if !list {
+        println!("\n💡 提示: 使用 --list 显示完整列表");
+        return;
+    }
+
+    println!();
+    let sections = [
+        ('+', &report.added),
+        ('-', &report.removed),
+        ('~', &report.changed),
+    ];
+    for (marker, paths) in sections {
+        for path in paths {
+            if interrupted() {
+                return;
+            }
+            println!("{} {}", marker, path);
+        }
+    }
+}
+
+fn handle_dedupe_command(args: DedupeArgs) -> Result<()> {
+    let db_paths = resolve_db_paths(args.db.as_deref())?;
+    let dbs: Vec<Database> = db_paths.iter().map(Database::new).collect();
+
+    let mut groups = find_duplicates_across(&dbs, args.by.into())?;
+    if args.sort_by_waste {
+        sort_by_wasted_bytes(&mut groups);
+    }
+    let total = groups.len();
+    let wasted: u64 = groups.iter().map(|group| group.wasted_bytes).sum();
+    if let Some(limit) = args.limit {
+        groups.truncate(limit);
+    }
+
+    if args.json {
+        let mut stdout = io::stdout().lock();
+        serde_json::to_writer(&mut stdout, &groups)?;
+        writeln!(stdout)?;
+        return Ok(());
+    }
+
+    println!("📚 在 {} 个数据库中查找重复文件", db_paths.len());
+    // 数据库中没有文件内容的哈希，只能按文件名和大小推测
+    match args.by {
+        DuplicateKeyArg::NameSize => {
+            println!("⚠️  按文件名和大小判断，未比较文件内容，同组文件不一定完全相同")
+        }
+        DuplicateKeyArg::Name => {
+            println!("⚠️  仅按文件名判断，同组文件的内容和大小可能不同")
+        }
+    }
+
+    if groups.is_empty() {
+        println!("\n✅ 未发现重复文件");
+        return Ok(());
+    }
+
+    for group in &groups {
+        print_duplicate_group(group, args.by);
+    }
+
+    println!("\n━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    if groups.len() < total {
+        println!("   （仅显示 {} / {} 组）", groups.len(), total);
+    }
+    println!(
+        "共 {} 组重复文件，每组只保留一份可节省 {}",
+        total,
+        format_bytes(wasted)
+    );
+    Ok(())
+}
+
+/// Prints one group of probable duplicates with its paths.
+fn print_duplicate_group(group: &DuplicateGroup, by: DuplicateKeyArg) {
+    let each = match (by, group.files[0].size) {
+        (DuplicateKeyArg::NameSize, Some(size)) => format!("，各 {}", format_bytes(size)),
+        _ => String::new(),
+    };
+    println!(
+        "\n📄 {}  {} 份{}，可节省 {}",
+        group.name,
+        group.files.len(),
+        each,
+        format_bytes(group.wasted_bytes)
+    );
+    for file in &group.files {
+        match (by, file.size) {
+            (DuplicateKeyArg::Name, Some(size)) => {
+                println!("   {}  ({})", file.path, format_bytes(size))
+            }
+            _ => println!("   {}", file.path),
+        }
+    }
+}
+
+/// Parses octal permission bits such as `644` or `0o755`.
+fn parse_mode(mode: &str) -> Result<i64> {
+    let digits = mode.trim().trim_start_matches("0o");
+    i64::from_str_radix(digits, 8).with_context(|| format!("无效的权限位: {}", mode))
+}
+
+/// Parses a human-readable size such as `10MB` or `1.5GB` into bytes.
+fn parse_size(size: &str) -> Result<i64> {
+    let bytes = parse_bytes(size).with_context(|| format!("无效的大小: {}", size))?;
+    i64::try_from(bytes).with_context(|| format!("大小超出范围: {}", size))
+}
+
+/// Parses a local date, local date and time, or unix timestamp into unix seconds.
+fn parse_time(time: &str) -> Result<f64> {
+    use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone};
+
+    let time = time.trim();
+    if let Ok(seconds) = time.parse::<f64>() {
+        return Ok(seconds);
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(time, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(time, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .with_context(|| format!("无效的时间: {}", time))?;
+
+    let local = Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .with_context(|| format!("本地时区中不存在该时间: {}", time))?;
+    Ok(local.timestamp() as f64)
+}
+
+/// Names a database in CLI listings: `label (file name)`, or just the file name.
+fn listing_name(db_path: &Path) -> String {
+    let file_name = db_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown");
+    match Database::new(db_path).label() {
+        Ok(Some(label)) if !label.is_empty() => format!("{} ({})", label, file_name),
+        _ => file_name.to_string(),
+    }
+}
+
+fn handle_label_command(args: LabelArgs) -> Result<()> {
+    if !args.db.exists() {
+        anyhow::bail!("数据库文件不存在: {}", args.db.display());
+    }
+
+    match args.label {
+        Some(label) => {
+            Database::init(&args.db)?.set_label(&label)?;
+            println!("🏷️  已设置标签: {}", label.trim());
+        }
+        None => match Database::new(&args.db).label()? {
+            Some(label) => println!("🏷️  {}", label),
+            None => println!("（未设置标签）"),
+        },
+    }
+
+    Ok(())
+}
+
+/// Discovers databases from `--db` paths, or from the current directory.
+fn resolve_db_paths(paths: Option<&[PathBuf]>) -> Result<Vec<PathBuf>> {
+    let db_paths = if let Some(paths) = paths {
+        discover_databases(paths)
+    } else {
+        // Use current directory to search for databases
+        let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+        let discovered = discover_databases(&[current_dir]);
+
+        if discovered.is_empty() {
+            // Also check for default .reminex.db in current directory
+            let default_path = PathBuf::from("./.reminex.db");
+            if default_path.exists() {
+                vec![default_path]
+            } else {
+                Vec::new()
+            }
+        } else {
+            discovered
+        }
+    };
+
+    if db_paths.is_empty() {
+        anyhow::bail!(
+            "未找到任何数据库文件\n请先运行索引命令创建数据库，或使用 --db 指定数据库路径"
+        );
+    }
+
+    Ok(db_paths)
+}
+
+fn handle_stats_command(args: StatsArgs) -> Result<()> {
+    let db_paths = resolve_db_paths(args.db.as_deref())?;
+    let collect = |db_path: &PathBuf| -> Result<DbStats> {
+        let db = Database::new(db_path);
+        match &args.root {
+            Some(root) => {
+                let root_id = db.root_id(root)?.with_context(|| {
+                    format!("{} 中未找到索引根目录: {}", db_path.display(), root)
+                })?;
+                collect_root_stats(&db, root_id)
+            }
+            None => collect_stats(&db),
+        }
+    };
+
+    if args.json {
+        let databases = db_paths
+            .iter()
+            .map(|db_path| {
+                Ok(JsonStatsOutput {
+                    database: listing_name(db_path),
+                    stats: collect(db_path)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut stdout = io::stdout().lock();
+        serde_json::to_writer(&mut stdout, &databases)?;
+        writeln!(stdout)?;
+        return Ok(());
+    }
+
+    let mut all = Vec::with_capacity(db_paths.len());
+    for db_path in &db_paths {
+        let stats = collect(db_path)?;
+
+        print_stats(&listing_name(db_path), &stats, args.top);
+        all.push(stats);
+    }
+
+    if all.len() > 1 {
+        print_stats("合计", &DbStats::combine(&all), args.top);
+    }
+
+    Ok(())
+}
+
+/// Prints the per-phase timing of a scan.
+fn print_profile(profile: &IndexProfile) {
+    let share = |phase: Duration| {
+        100.0 * phase.as_secs_f64() / profile.total.as_secs_f64().max(f64::EPSILON)
+    };
+    // 中文字符占两列宽，按字数减少填充以对齐数据列
+    let pad = |label: &str| 16 - label.chars().filter(|c| !c.is_ascii()).count();
+    let rows = [
+        ("总计", profile.total),
+        ("目录遍历*", profile.traversal),
+        ("元数据读取*", profile.metadata),
+        ("等待写入线程*", profile.send_wait),
+        ("数据库写入", profile.db_write),
+    ];
+
+    println!("\n⏱️  性能分析:");
+    println!(
+        "   {:<w$} {:>8} {:>5}",
+        "阶段",
+        "耗时",
+        "占比",
+        w = pad("阶段")
+    );
+    for (phase, elapsed) in rows {
+        println!(
+            "   {:<w$} {:>9.2}s {:>6.1}%",
+            phase,
+            elapsed.as_secs_f64(),
+            share(elapsed),
+            w = pad(phase)
+        );
+    }
+    println!(
+        "   队列峰值深度: {} / {}",
+        profile.peak_queue_depth, profile.queue_capacity
+    );
+    println!("   * 各工作线程耗时之和，可能超过总计");
+}
+
+/// Prints the stats report for one database.
+fn print_stats(db_name: &str, stats: &DbStats, top: usize) {
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("📁 数据库: {}", db_name);
+    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("   文件数: {}", stats.file_count);
+    println!("   总大小: {}", format_bytes(stats.total_bytes));
+    if stats.unknown_size_count > 0 {
+        println!(
+            "   未知大小: {} 个文件（未计入大小统计）",
+            stats.unknown_size_count
+        );
+    }
+    let format = |time: f64| {
+        chrono::DateTime::from_timestamp(time as i64, 0)
+            .map(|time| {
+                time.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            })
+            .unwrap_or_else(|| "?".to_string())
+    };
+    if let (Some(oldest), Some(newest)) = (stats.oldest_mtime, stats.newest_mtime) {
+        println!("   修改时间: {} ~ {}", format(oldest), format(newest));
+    }
+    let info = &stats.index_info;
+    if let Some(ref root) = info.indexed_root {
+        println!("   索引根目录: {}", root);
+    }
+    if let Some(indexed_at) = info.indexed_at {
+        println!("   最近索引: {}", format(indexed_at));
+    }
+    if let Some(ref version) = info.reminex_version {
+        println!("   reminex 版本: {}", version);
+    }
+    println!();
+    // 中文字符占两列宽，表头宽度相应减去字数以对齐数据列
+    println!(
+        "   {:<9} {:>7} {:>10} {:>6}",
+        "扩展名", "文件数", "大小", "占比"
+    );
+
+    for ext in stats.extensions.iter().take(top) {
+        let label = if ext.ext.is_empty() {
+            "(无)".to_string()
+        } else {
+            format!(".{}", ext.ext)
+        };
+        println!(
+            "   {:<12} {:>10} {:>12} {:>7.1}%",
+            label,
+            ext.count,
+            format_bytes(ext.bytes),
+            stats.byte_share(ext)
+        );
+    }
+
+    if stats.extensions.len() > top {
+        println!("   ... 另有 {} 种扩展名", stats.extensions.len() - top);
+    }
+
+    if !stats.largest_files.is_empty() {
+        println!();
+        println!("   最大的文件:");
+        for file in &stats.largest_files {
+            println!("   {:>12}  {}", format_bytes(file.size), file.path);
+        }
+    }
+    println!();
+}
+
+fn handle_search_command(args: SearchArgs) -> Result<()> {
+    let db_paths = resolve_db_paths(args.db.as_deref())?;
+
+    // Display discovered databases (keep stdout clean for JSON output)
+    if !args.json {
+        println!("📚 发现 {} 个数据库:", db_paths.len());
+        for (i, db_path) in db_paths.iter().enumerate() {
+            println!("   {}. {}", i + 1, listing_name(db_path));
+        }
+        println!();
+    }
+
+    // 配置搜索参数
+    let config = search_config(&args)?;
+    // 内容类别映射（仅在按类别分组时加载）
+    let categories = match args.group_by {
+        Some(GroupBy::Category) => Some(match args.category_map {
+            Some(ref path) => CategoryMap::from_file(path)?,
+            None => CategoryMap::default(),
+        }),
+        None => None,
+    };
+
+    // 从标准输入批量读取查询，每行一个
+    if args.stdin_query {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let line = line.context("无法读取标准输入")?;
+            let query = line.trim();
+            if query.is_empty() {
+                continue;
+            }
+
+            if args.json {
+                print_json_search(&db_paths, &args.select_db, query, &config, &args)?;
+            } else {
+                println!("🔍 查询: {}", query);
+                perform_multi_db_search(
+                    &db_paths,
+                    &args.select_db,
+                    query,
+                    &config,
+                    &args,
+                    categories.as_ref(),
+                    false,
+                )?;
+            }
+        }
+        return Ok(());
+    }
+
+    // 如果提供了关键词，直接搜索
+    if let Some(ref keywords) = args.keywords {
+        if args.json {
+            print_json_search(&db_paths, &args.select_db, keywords, &config, &args)?;
+            return Ok(());
+        }
+
+        perform_multi_db_search(
+            &db_paths,
+            &args.select_db,
+            keywords,
+            &config,
+            &args,
+            categories.as_ref(),
+            false,
+        )?;
+        return Ok(());
+    }
+
+    // 交互模式
+    println!("🔍 reminex 搜索模式");
+    println!("   搜索范围: {}", args.select_db);
+    println!("   输入关键词搜索，多个关键词用 ; 或空格分隔");
+    println!("   输入 :q 退出，Ctrl+C 中断当前输出\n");
+
+    install_interrupt_handler()?;
+
+    loop {
+        print!("搜索> ");
+        io::stdout().flush()?;
+
+        AT_PROMPT.store(true, Ordering::SeqCst);
+        let mut input = String::new();
+        let read = io::stdin().read_line(&mut input)?;
+        AT_PROMPT.store(false, Ordering::SeqCst);
+
+        // Ctrl+D (end of input)
+        if read == 0 {
+            println!("\n再见！");
+            break;
+        }
+        let input = input.trim();
+
+        if input.is_empty() {
+            continue;
+        }
+
+        if input == ":q" || input == "exit" || input == "quit" {
+            println!("再见！");
+            break;
+        }
+
+        INTERRUPTED.store(false, Ordering::SeqCst);
+
+        if args.json {
+            print_json_search(&db_paths, &args.select_db, input, &config, &args)?;
+        } else {
+            perform_multi_db_search(
+                &db_paths,
+                &args.select_db,
+                input,
+                &config,
+                &args,
+                categories.as_ref(),
+                true,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds the search configuration from the `search` / `export` arguments.
+fn search_config(args: &SearchArgs) -> Result<SearchConfig> {
+    let max_results = args.limit.unwrap_or(2000);
+    Ok(SearchConfig {
+        max_results,
+        offset: args
+            .page
+            .map_or(0, |page| (page as usize - 1).saturating_mul(max_results)),
+        search_in_path: !args.name_only,
+        case_sensitive: args.case_sensitive,
+        match_stem: args.stem,
+        search_mode: search_mode(args),
+        use_fts: args.fts,
+        owner: args.owner,
+        mode: args.mode.as_deref().map(parse_mode).transpose()?,
+        modified_after: args.modified_after.as_deref().map(parse_time).transpose()?,
+        indexed_after: args.indexed_after.as_deref().map(parse_time).transpose()?,
+        min_size: args.min_size.as_deref().map(parse_size).transpose()?,
+        max_size: args.max_size.as_deref().map(parse_size).transpose()?,
+        extensions: args.ext.clone(),
+        sort_by: match args.sort {
+            Some(sort) => sort.into(),
+            None if args.fuzzy => SortBy::Score,
+            None => SortBy::Path,
+        },
+        fuzzy_threshold: args.fuzzy_threshold,
+        combine: if args.and {
+            KeywordCombine::And
+        } else if args.or {
+            KeywordCombine::Or
+        } else {
+            KeywordCombine::Separate
+        },
+        descending: args.desc,
+        ..Default::default()
+    })
+}
+
+/// Asks before printing more than `threshold` results in interactive mode.
+///
+/// Looks at the results of the search already run, so nothing is searched
+/// twice. The user chooses between the full output and a preview of the
+/// first `threshold` items of each keyword.
+///
+/// # Returns
+/// The preview size if the user chose the preview
+fn confirm_large_results(
+    threshold: usize,
+    results: &[(String, String, Vec<SearchResult>)],
+) -> Result<Option<usize>> {
+    if threshold == 0 || !results.iter().any(|(_, _, items)| items.len() > threshold) {
+        return Ok(None);
+    }
+
+    print!("⚠️  结果超过 {} 项，全部显示？[y/N] ", threshold);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        Ok(None)
+    } else {
+        println!("   仅显示前 {} 项预览", threshold);
+        Ok(Some(threshold))
+    }
+}
+
+/// Searches and prints the results grouped by database and keyword.
+///
+/// In `interactive` mode, suggestions are on unless `--no-suggest` is
+/// given, and large results are confirmed first, see
+/// [`confirm_large_results`].
+fn perform_multi_db_search(
+    db_paths: &[PathBuf],
+    selected_db: &str,
+    input: &str,
+    config: &SearchConfig,
+    args: &SearchArgs,
+    categories: Option<&CategoryMap>,
+    interactive: bool,
+) -> Result<()> {
+    use reminex::searcher::parse_search_keywords;
+
+    let suggest = if interactive {
+        !args.no_suggest
+    } else {
+        args.suggest
+    };
+
+    let keywords = parse_search_keywords(input);
+    let mut totals = MatchTotals::new();
+    let mut results = match args.min_results {
+        Some(min_results) => {
+            let (level, results) =
+                search_with_min_results(db_paths, selected_db, &keywords, config, min_results)?;
+            if level != Relaxation::None {
+                println!(
+                    "🔎 结果不足 {} 项，已放宽为: {}",
+                    min_results,
+                    level.describe()
+                );
+            }
+            results
+        }
+        None => {
+            let (results, match_totals) = search_counted(db_paths, selected_db, &keywords, config)?;
+            totals = match_totals;
+            results
+        }
+    };
+    record_history(args, input, config, &results);
+
+    let mut limit = config.max_results;
+    if interactive && let Some(preview) = confirm_large_results(args.confirm_above, &results)? {
+        for (_, _, items) in &mut results {
+            items.truncate(preview);
+        }
+        limit = preview;
+    }
+
+    // 无结果的关键词仅在 --include-empty 或需要显示建议时保留；
+    // 其他页有结果的关键词也保留，以提示本页为空
+    if !args.include_empty.unwrap_or(false) && !suggest {
+        results.retain(|(database, keyword, items)| {
+            !items.is_empty()
+                || totals
+                    .get(&(database.clone(), keyword.clone()))
+                    .is_some_and(|&total| total > 0)
+        });
+    }
+
+    if results.is_empty() {
+        println!("\n❌ 未找到任何结果\n");
+        return Ok(());
+    }
+
+    // Group results by database and keyword
+    let mut current_db = String::new();
+    let mut current_keyword = String::new();
+
+    for (db_name, keyword, items) in results {
+        if interrupted() {
+            println!("\n⏹  已中断输出");
+            return Ok(());
+        }
+
+        // Print database header if changed
+        if db_name != current_db {
+            if !current_db.is_empty() {
+                println!();
+            }
+            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            println!("📁 数据库: {}", db_name);
+            println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+            current_db = db_name.clone();
+        }
+
+        // Print keyword results
+        if keyword != current_keyword || db_name != current_db {
+            current_keyword = keyword.clone();
+        }
+
+        let total = totals.get(&(db_name.clone(), keyword.clone())).copied();
+        if items.is_empty() {
+            match total {
+                Some(total) if total > 0 => {
+                    println!(
+                        "\n「{}」共 {} 项结果，第 {} 页没有结果",
+                        keyword,
+                        total,
+                        args.page.unwrap_or(1)
+                    );
+                }
+                _ => {
+                    println!("\n「{}」未找到任何结果", keyword);
+                    if suggest {
+                        print_suggestions(db_paths, &db_name, &keyword);
+                    }
+                }
+            }
+            continue;
+        }
+
+        match (total, args.page) {
+            (Some(total), Some(page)) => println!(
+                "\n「{}」共 {} 项结果，第 {}/{} 页（{} 项）：",
+                keyword,
+                total,
+                page,
+                total.div_ceil(config.max_results.max(1)),
+                items.len()
+            ),
+            // 结果被 --limit 截断时提示总数，避免误以为已是全部结果
+            (Some(total), None) if total > items.len() => println!(
+                "\n「{}」共 {} 项结果，显示前 {} 项：",
+                keyword,
+                total,
+                items.len()
+            ),
+            (None, _) if items.len() >= limit => {
+                println!(
+                    "\n「{}」显示前 {} 项结果（可能还有更多）：",
+                    keyword,
+                    items.len()
+                )
+            }
+            _ => println!("\n「{}」找到 {} 项结果：", keyword, items.len()),
+        }
+
+        let items = match result_window(args) {
+            Some(window) => {
+                let shown = window.apply(&items);
+                if shown.len() < items.len() {
+                    let side = match window {
+                        ResultWindow::Head(_) => "前",
+                        ResultWindow::Tail(_) => "后",
+                    };
+                    println!("   （仅显示{} {} 项）", side, shown.len());
+                }
+                shown
+            }
+            None => &items[..],
+        };
+
+        match categories {
+            Some(categories) => {
+                // 按内容类别分组显示
+                for (category, group) in categories.group(items) {
+                    if interrupted() {
+                        break;
+                    }
+                    println!("\n▸ {} ({})", category, group.len());
+                    print_items(&group, args);
+                }
+            }
+            None => print_items(items, args),
+        }
+    }
+
+    if interrupted() {
+        println!("\n⏹  已中断输出");
+    }
+    println!();
+    Ok(())
+}
+
+/// Prints "did you mean" suggestions for a keyword that found nothing.
+fn print_suggestions(db_paths: &[PathBuf], db_name: &str, keyword: &str) {
+    let Some(db_path) = db_paths
+        .iter()
+        .find(|p| p.file_name().and_then(|n| n.to_str()) == Some(db_name))
+    else {
+        return;
+    };
+
+    // Suggestions are best-effort, a failure here must not abort the search
+    if let Ok(suggestions) = suggest_keywords(&Database::new(db_path), keyword, MAX_SUGGESTIONS)
+        && !suggestions.is_empty()
+    {
+        println!("   您是否想搜索: {}?", suggestions.join(", "));
+    }
+}
+
+/// Prints search results as a tree or a flat list.
+///
+/// Stops early when Ctrl+C is pressed in interactive mode.
+fn print_items(items: &[SearchResult], args: &SearchArgs) {
+    if args.tree {
+        // 树形显示
+        let root_name = args.root_name.as_deref().unwrap_or("搜索结果");
+        let tree = build_tree_with_root(items, root_name, &tree_root_strategy(args));
+        let style = if args.ascii_tree {
+            TreeStyle::Ascii
+        } else {
+            TreeStyle::detect()
+        };
+        println!();
+        println!("{}", tree.name);
+        for (i, child) in tree.children.iter().enumerate() {
+            let is_last = i == tree.children.len() - 1;
+            for line in format_tree_node_with_style(child, "", is_last, style).lines() {
+                if interrupted() {
+                    return;
+                }
+                println!("{}", line);
+            }
+        }
+    } else {
+        // 列表显示
+        println!();
+        // 按大小筛选或排序时同时显示文件大小
+        let show_size = args.min_size.is_some()
+            || args.max_size.is_some()
+            || matches!(args.sort, Some(SortArg::Size));
+        for item in items {
+            if interrupted() {
+                return;
+            }
+            match item.size {
+                Some(size) if show_size => {
+                    println!("  {}  ({})", item.path, format_bytes(size as u64))
+                }
+                _ => println!("  {}", item.path),
+            }
+        }
+    }
+}
+
+/// Appends a query to the search history unless `--no-history` is given.
+///
+/// Recording is best effort: a history file that cannot be written, e.g.
+/// because the config directory cannot be created, only prints a warning
+/// and never fails the search.
+fn record_history(
+    args: &SearchArgs,
+    query: &str,
+    config: &SearchConfig,
+    results: &[(String, String, Vec<SearchResult>)],
+) {
+    if args.no_history {
+        return;
+    }
+    let path = args
+        .history_file
+        .clone()
+        .unwrap_or_else(SearchHistory::default_path);
+    let item = SearchHistoryItem {
+        query: query.to_string(),
+        selected_db: args.select_db.clone(),
+        timestamp: chrono::Utc::now(),
+        result_count: results.iter().map(|(_, _, items)| items.len()).sum(),
+        name_only: !config.search_in_path,
+        case_sensitive: config.case_sensitive,
+    };
+    if let Err(e) = SearchHistory::new(path, DEFAULT_MAX_ENTRIES).add_entry(item) {
+        eprintln!("⚠️  无法保存搜索历史: {:#}", e);
+    }
+}
+
+/// Resolves `--head` / `--tail` into a display window.
+fn result_window(args: &SearchArgs) -> Option<ResultWindow> {
+    match (args.head, args.tail) {
+        (Some(n), _) => Some(ResultWindow::Head(n)),
+        (None, Some(n)) => Some(ResultWindow::Tail(n)),
+        (None, None) => None,
+    }
+}
+
+/// Resolves `--glob` / `--regex` into a matching mode.
+fn search_mode(args: &SearchArgs) -> SearchMode {
+    if args.regex {
+        SearchMode::Regex
+    } else if args.fuzzy {
+        SearchMode::Fuzzy
+    } else if args.glob {
+        SearchMode::Glob
+    } else {
+        SearchMode::Substring
+    }
+}
+
+/// Resolves `--tree-root-strategy` and `--tree-root`; a root alone implies `fixed`.
+fn tree_root_strategy(args: &SearchArgs) -> TreeRootStrategy {
+    match (args.tree_root_strategy, &args.tree_root) {
+        (Some(TreeRootArg::Auto), _) => TreeRootStrategy::Auto,
+        (Some(TreeRootArg::None), _) => TreeRootStrategy::None,
+        (_, Some(root)) => TreeRootStrategy::Fixed(root.clone()),
+        (_, None) => TreeRootStrategy::Auto,
+    }
+}
+
+/// JSON output for a single query
+#[derive(Serialize)]
+struct JsonQueryOutput {
+    query: String,
+    results: Vec<JsonKeywordOutput>,
+}
+
+/// JSON output of the stats command for one database
+#[derive(Serialize)]
+struct JsonStatsOutput {
+    database: String,
+    #[serde(flatten)]
+    stats: DbStats,
+}
+
+/// JSON output for one keyword in one database
+#[derive(Serialize)]
+struct JsonKeywordOutput {
+    database: String,
+    keyword: String,
+    count: usize,
+    /// Matches in total, beyond `--limit` and `--page`; only counted when
+    /// that is cheap, see [`search_counted`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    total: Option<usize>,
+    items: Vec<SearchResult>,
+}
+
+/// Total match count per (database, keyword)
+type MatchTotals = HashMap<(String, String), usize>;
+
+/// Searches one page of results, counting each keyword's matches on all pages.
+///
+/// Only searches counted in SQL are counted. The others, such as regex,
+/// glob, fuzzy or non-ASCII searches, would have to load every match just
+/// for the total, so their full pages are shown as possibly incomplete.
+fn search_counted(
+    db_paths: &[PathBuf],
+    selected_db: &str,
+    keywords: &[String],
+    config: &SearchConfig,
+) -> Result<(DatabaseResults, MatchTotals)> {
+    let mut totals = MatchTotals::new();
+    if !counts_in_sql(keywords, config) {
+        let results = search_in_selected_database(db_paths, selected_db, keywords, config)?;
+        return Ok((results, totals));
+    }
+    let results = search_in_selected_database_counted(db_paths, selected_db, keywords, config)?
+        .into_iter()
+        .map(|(database, keyword, total, items)| {
+            totals.insert((database.clone(), keyword.clone()), total);
+            (database, keyword, items)
+        })
+        .collect();
+    Ok((results, totals))
+}
+
+/// Searches and prints the results of one query as a single line of JSON.
+fn print_json_search(
+    db_paths: &[PathBuf],
+    selected_db: &str,
+    input: &str,
+    config: &SearchConfig,
+    args: &SearchArgs,
+) -> Result<()> {
+    let mut stdout = io::stdout().lock();
+    write_json_search(&mut stdout, db_paths, selected_db, input, config, args)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Writes the line printed by [`print_json_search`] to `out`.
+fn write_json_search(
+    out: &mut impl Write,
+    db_paths: &[PathBuf],
+    selected_db: &str,
+    input: &str,
+    config: &SearchConfig,
+    args: &SearchArgs,
+) -> Result<()> {
+    use reminex::searcher::parse_search_keywords;
+
+    let keywords = parse_search_keywords(input);
+    let (mut results, totals) = search_counted(db_paths, selected_db, &keywords, config)?;
+    record_history(args, input, config, &results);
+    if !args.include_empty.unwrap_or(true) {
+        drop_empty_keywords(&mut results);
+    }
+
+    let output = JsonQueryOutput {
+        query: input.to_string(),
+        results: results
+            .into_iter()
+            .map(|(database, keyword, items)| JsonKeywordOutput {
+                total: totals.get(&(database.clone(), keyword.clone())).copied(),
+                database,
+                keyword,
+                count: items.len(),
+                items,
+            })
+            .collect(),
+    };
+
+    serde_json::to_writer(&mut *out, &output)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+async fn handle_web_command(args: WebArgs) -> Result<()> {
+    let db_paths = resolve_db_paths(args.db.as_deref())?;
+
+    println!("🌐 启动 Web 服务器");
+    println!("📚 发现 {} 个数据库:", db_paths.len());
+    for db_path in &db_paths {
+        println!("   - {}", listing_name(db_path));
+    }
+    println!();
+
+    let port = args.port.unwrap_or(3000);
+    let auto_retry = args.port.is_none();
+    let options = web::ServerOptions {
+        cache: CacheConfig {
+            capacity: args.cache_size,
+            ttl: Duration::from_secs(args.cache_ttl),
+        },
+        once: args.once,
+        ..Default::default()
+    };
+    web::run_server_with_options(db_paths, port, auto_retry, options).await?;
+
+    Ok(())
+}
+
+#[derive(Parser)]
+#[command(name = "reminex")]
+#[command(about = "快速文件索引和搜索工具 - 双击运行即启动 Web 服务器", long_about = None)]
+#[command(version)]
+struct App {
+    #[command(subcommand)]
+    commands: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    #[command(about = "索引文件 (index)")]
+    Index(IndexArgs),
+
+    #[command(about = "索引文件 (index 简写)")]
+    I(IndexArgs),
+
+    #[command(about = "搜索文件 (search)")]
+    Search(SearchArgs),
+
+    #[command(about = "搜索文件 (search 简写)")]
+    S(SearchArgs),
+
+    #[command(about = "Web 界面服务器 (web)")]
+    Web(WebArgs),
+
+    #[command(about = "Web 界面服务器 (web 简写)")]
+    W(WebArgs),
+
+    #[command(about = "从 CSV 文件列表导入数据库 (import)")]
+    Import(ImportArgs),
+
+    #[command(about = "按扩展名统计文件数和大小 (stats)")]
+    Stats(StatsArgs),
+
+    #[command(about = "搜索并将结果导出为 TOML、CSV 或 JSON 文件 (export)")]
+    Export(ExportArgs),
+
+    #[command(about = "导出整个数据库为 JSON 或 CSV (export-db)")]
+    ExportDb(ExportDbArgs),
+
+    #[command(about = "查看或设置数据库的显示标签 (label)")]
+    Label(LabelArgs),
+
+    #[command(about = "删除磁盘上已不存在的文件记录 (prune)")]
+    Prune(PruneArgs),
+
+    #[command(about = "批量替换路径前缀，如盘符或挂载点变化后 (relocate)")]
+    Relocate(RelocateArgs),
+
+    #[command(about = "列出或删除数据库中的索引根目录 (roots)")]
+    Roots(RootsArgs),
+
+    #[command(about = "查看、清除或重新执行搜索历史 (history)")]
+    History(HistoryArgs),
+
+    #[command(about = "比较两个数据库，或数据库与磁盘上的当前文件 (diff)")]
+    Diff(DiffArgs),
+
+    #[command(about = "压缩数据库文件，回收删除记录后留下的空间 (optimize)")]
+    Optimize(OptimizeArgs),
+
+    #[command(about = "按文件名和大小查找可能重复的文件 (dedupe)")]
+    Dedupe(DedupeArgs),
+}
+
+#[derive(Args, Clone)]
 struct IndexArgs {
     #[arg(short, long, help = "要索引的目录路径")]
     path: Option<PathBuf>,
 
-    #[arg(short, long, help = "数据库文件路径")]
-    db: Option<PathBuf>,
+    #[arg(short, long, help = "数据库文件路径")]
+    db: Option<PathBuf>,
+
+    #[arg(short, long, help = "全量重建索引（删除旧数据）")]
+    full: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["full", "no_metadata"],
+        help = "增量更新：跳过大小和修改时间未变的文件"
+    )]
+    incremental: bool,
+
+    #[arg(short = 'n', long, help = "快速模式（不扫描文件元数据）")]
+    no_metadata: bool,
+
+    #[arg(short, long, help = "批量写入大小")]
+    batch_size: Option<usize>,
+
+    #[arg(
+        long,
+        visible_alias = "follow-symlinks",
+        help = "进入目录联接点（junction）和符号链接（带循环检测）"
+    )]
+    follow_junctions: bool,
+
+    #[arg(long, help = "进度显示的更新间隔（文件数）", default_value = "100")]
+    progress_step: usize,
+
+    #[arg(
+        long,
+        help = "记录文件所有者 uid 和权限位（Unix）或文件属性（Windows）"
+    )]
+    perms: bool,
+
+    #[arg(
+        long,
+        help = "同时索引 .zip 压缩包内的文件（路径形如 a.zip!/内部/路径，会增加 I/O）"
+    )]
+    index_archives: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "no_metadata",
+        help = "跳过大小为 0 的文件（需要读取元数据）"
+    )]
+    skip_empty: bool,
+
+    #[arg(
+        long,
+        visible_alias = "gitignore",
+        help = "跳过 .gitignore、.ignore 和全局 git 排除规则匹配的文件和目录（以及 .git 目录）"
+    )]
+    respect_gitignore: bool,
+
+    #[arg(
+        long,
+        visible_alias = "no-hidden",
+        help = "跳过以 . 开头的隐藏文件和目录（Windows 上还跳过带隐藏或系统属性的文件）"
+    )]
+    skip_hidden: bool,
+
+    #[arg(
+        long,
+        value_name = "GLOB",
+        help = "跳过匹配的文件和目录（可多次指定）；不含 / 时匹配名称，如 cache、*.tmp，含 / 时匹配相对索引根目录的路径"
+    )]
+    exclude: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "最大递归深度，0 表示只索引根目录下的文件（默认不限制）"
+    )]
+    max_depth: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        help = "扫描线程数，0 表示每个 CPU 核心一个（默认）；机械硬盘上多线程会导致磁头来回寻道，设为 1 或 2 通常更快，固态硬盘保持默认即可"
+    )]
+    threads: usize,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "额外的 .gitignore 格式规则文件，相对索引根目录匹配（可多次指定）"
+    )]
+    ignore_file: Vec<PathBuf>,
+
+    #[arg(
+        long,
+        help = "删除索引根目录下本次扫描未找到的文件记录（不影响根目录之外的记录）"
+    )]
+    prune: bool,
+
+    #[arg(long, help = "索引完成后显示各阶段耗时和写入队列峰值深度")]
+    profile: bool,
+
+    #[arg(
+        long,
+        help = "初始扫描后持续监视目录变化并实时更新索引，按 Ctrl+C 停止（不读取 .gitignore 规则和压缩包内的变化）"
+    )]
+    watch: bool,
+
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value_t = 500,
+        requires = "watch",
+        help = "监视模式下等待事件平息的毫秒数，期间的高频变化合并为一次写入"
+    )]
+    debounce_ms: u64,
+
+    #[arg(long, help = "索引完成后显示内容统计（扩展名、大小、最大文件）")]
+    stats_after_index: bool,
+
+    #[arg(
+        long,
+        help = "建立 FTS5 全文索引以加速大数据库的搜索（按词匹配，约占用与文件表相当的空间）"
+    )]
+    fts: bool,
+
+    #[arg(long, help = "为数据库设置显示标签，如 \"Photos Archive\"")]
+    label: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "统一存储路径的分隔符并保存到数据库（已有记录不变，建议配合 --full）"
+    )]
+    path_separator: Option<PathSeparatorArg>,
+}
+
+#[derive(Args, Clone)]
+struct SearchArgs {
+    #[arg(help = "搜索关键词（可选，不提供则进入交互模式）")]
+    keywords: Option<String>,
+
+    #[arg(short, long, help = "数据库文件路径或包含数据库的文件夹（可多个）", num_args = 1..)]
+    db: Option<Vec<PathBuf>>,
+
+    #[arg(
+        long,
+        help = "选择搜索的数据库名称（默认: all）",
+        default_value = "all"
+    )]
+    select_db: String,
+
+    #[arg(
+        short,
+        long,
+        visible_alias = "page-size",
+        help = "结果数量限制（配合 --page 时为每页数量）",
+        default_value = "2000"
+    )]
+    limit: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with = "min_results",
+        help = "显示第 N 页结果（从 1 开始，每页 --limit 项）"
+    )]
+    page: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "每个关键词只显示前 N 项结果（在 --limit 之后应用）"
+    )]
+    head: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with = "head",
+        help = "每个关键词只显示后 N 项结果（在 --limit 之后应用）"
+    )]
+    tail: Option<usize>,
+
+    #[arg(short = 't', long, help = "树形显示结果")]
+    tree: bool,
+
+    #[arg(
+        long,
+        help = "树形显示使用 ASCII 连接符（默认根据 LANG 等区域设置自动选择）"
+    )]
+    ascii_tree: bool,
+
+    #[arg(short = 'N', long, help = "仅搜索文件名（不搜索路径）")]
+    name_only: bool,
+
+    #[arg(short = 'c', long, help = "区分大小写")]
+    case_sensitive: bool,
+
+    #[arg(
+        long,
+        help = "按文件名主干精确匹配（忽略扩展名，如 IMG_1234 匹配 IMG_1234.jpg）"
+    )]
+    stem: bool,
+
+    #[arg(
+        long,
+        help = "关键词作为通配符匹配完整文件名；含 / 时匹配路径，* 不跨目录，** 跨任意层（如 \"*.jpg\"、\"photos/**/summer*\"）"
+    )]
+    glob: bool,
 
-    #[arg(short, long, help = "全量重建索引（删除旧数据）")]
-    full: bool,
+    #[arg(
+        short = 'r',
+        long,
+        conflicts_with_all = ["glob", "stem"],
+        help = "关键词作为正则表达式匹配文件名或路径（如 \"\\.rs$\"；逗号和分号仍是关键词分隔符）"
+    )]
+    regex: bool,
 
-    #[arg(short = 'n', long, help = "快速模式（不扫描文件元数据）")]
-    no_metadata: bool,
+    #[arg(
+        long,
+        conflicts_with_all = ["glob", "regex", "stem"],
+        help = "模糊匹配文件名，允许拼写错误（如 reciept 找到 receipt），默认按相似度排序；需逐行比较，大数据库上较慢"
+    )]
+    fuzzy: bool,
 
-    #[arg(short, long, help = "批量写入大小")]
-    batch_size: Option<usize>,
+    #[arg(
+        long,
+        value_name = "0-100",
+        default_value_t = DEFAULT_FUZZY_THRESHOLD,
+        value_parser = clap::value_parser!(i64).range(0..=100),
+        help = "模糊匹配的最低相似度"
+    )]
+    fuzzy_threshold: i64,
+
+    #[arg(
+        long,
+        help = "使用全文索引按词搜索（支持前缀如 \"summ*\"；数据库未用 --fts 索引时退回普通搜索）"
+    )]
+    fts: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "or",
+        help = "只显示同时匹配所有关键词的文件，合为一组（空格也分隔关键词，如 \"invoice 2023 pdf\"）"
+    )]
+    and: bool,
+
+    #[arg(long, help = "显示匹配任一关键词的文件，合为一组并去重")]
+    or: bool,
+
+    #[arg(long, help = "树形显示的根目录名称", default_value = "搜索结果")]
+    root_name: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "树形显示的根目录策略（auto: 公共父目录，none: 完整路径，fixed: 使用 --tree-root）",
+        requires_if("fixed", "tree_root")
+    )]
+    tree_root_strategy: Option<TreeRootArg>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "树形显示的固定根目录（隐含 fixed 策略）"
+    )]
+    tree_root: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "从标准输入逐行读取查询（每行一个查询）",
+        conflicts_with = "keywords"
+    )]
+    stdin_query: bool,
+
+    #[arg(long, help = "以 JSON 格式输出结果（每个查询一行）")]
+    json: bool,
+
+    #[arg(
+        long,
+        help = "保留没有结果的关键词（count 为 0）；默认 JSON 输出保留，文本输出省略",
+        num_args = 0..=1,
+        default_missing_value = "true",
+        value_name = "BOOL"
+    )]
+    include_empty: Option<bool>,
+
+    #[arg(long, value_enum, help = "结果分组方式（category: 按内容类别）")]
+    group_by: Option<GroupBy>,
+
+    #[arg(long, help = "自定义类别映射文件（TOML，覆盖内置扩展名表）")]
+    category_map: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "交互模式下结果超过该数量时先确认再全部显示（0 表示不确认）",
+        default_value = "200"
+    )]
+    confirm_above: usize,
+
+    #[arg(
+        long,
+        help = "结果少于该数量时逐级放宽匹配：忽略大小写 → 匹配完整路径 → 子序列匹配（按顺序包含关键词的字符，与 --fuzzy 不同）"
+    )]
+    min_results: Option<usize>,
+
+    #[arg(long, help = "只显示属于该 uid 的文件（需要以 --perms 索引）")]
+    owner: Option<i64>,
+
+    #[arg(
+        long,
+        help = "只显示该权限位的文件，八进制，如 644（需要以 --perms 索引）"
+    )]
+    mode: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "TIME",
+        help = "只显示该时间之后修改的文件（需要元数据），如 2024-05-01、\"2024-05-01 18:00\" 或 Unix 时间戳"
+    )]
+    modified_after: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "TIME",
+        help = "只显示该时间之后首次被索引的文件（格式同 --modified-after）"
+    )]
+    indexed_after: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SIZE",
+        help = "只显示不小于该大小的文件（需要元数据），如 10MB、1.5GB（按 1024 换算）"
+    )]
+    min_size: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SIZE",
+        help = "只显示不大于该大小的文件（格式同 --min-size）"
+    )]
+    max_size: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "EXT",
+        value_delimiter = ',',
+        help = "只显示这些扩展名的文件，逗号分隔，不区分大小写，如 jpg,png,gif"
+    )]
+    ext: Vec<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "列表显示的排序方式，默认 path，--fuzzy 时默认 score（size、mtime 需要元数据，缺失的排在最后）"
+    )]
+    sort: Option<SortArg>,
+
+    #[arg(long, help = "倒序排列，如最大或最新的在前")]
+    desc: bool,
+
+    #[arg(long, help = "不记录本次搜索到搜索历史")]
+    no_history: bool,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "搜索历史文件路径（默认与 Web 界面共用配置目录下的 search_history.json）"
+    )]
+    history_file: Option<PathBuf>,
+
+    #[arg(long, help = "无结果时给出相近的关键词建议（交互模式默认开启）")]
+    suggest: bool,
+
+    #[arg(long, help = "交互模式下不显示关键词建议", conflicts_with = "suggest")]
+    no_suggest: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TreeRootArg {
+    /// 使用所有结果的公共父目录
+    Auto,
+    /// 不折叠，从文件系统根目录显示完整路径
+    None,
+    /// 使用 --tree-root 指定的目录
+    Fixed,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SortArg {
+    /// 按完整路径
+    Path,
+    /// 按文件名
+    Name,
+    /// 按文件大小
+    Size,
+    /// 按修改时间
+    Mtime,
+    /// 按模糊匹配相似度，最相似的在前
+    Score,
+}
+
+impl From<SortArg> for SortBy {
+    fn from(arg: SortArg) -> Self {
+        match arg {
+            SortArg::Path => SortBy::Path,
+            SortArg::Name => SortBy::Name,
+            SortArg::Size => SortBy::Size,
+            SortArg::Mtime => SortBy::Mtime,
+            SortArg::Score => SortBy::Score,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PathSeparatorArg {
+    /// 统一为 /
+    Unix,
+    /// 统一为 \
+    Windows,
+    /// 只合并重复分隔符，保留原有风格
+    Keep,
+}
+
+impl PathSeparatorArg {
+    fn separator(self) -> Option<char> {
+        match self {
+            PathSeparatorArg::Unix => Some('/'),
+            PathSeparatorArg::Windows => Some('\\'),
+            PathSeparatorArg::Keep => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GroupBy {
+    /// 按内容类别分组（Images、Videos、Documents 等）
+    Category,
 }
 
 #[derive(Args, Clone)]
-struct SearchArgs {
-    #[arg(help = "搜索关键词（可选，不提供则进入交互模式）")]
-    keywords: Option<String>,
+struct ImportArgs {
+    #[arg(short, long, help = "数据库文件路径")]
+    db: PathBuf,
+
+    #[arg(long, help = "CSV 文件路径")]
+    csv: PathBuf,
+
+    #[arg(long, help = "路径所在列（从 1 开始）", default_value = "1")]
+    path_col: usize,
+
+    #[arg(long, help = "文件名所在列（不指定则从路径推导）")]
+    name_col: Option<usize>,
+
+    #[arg(long, help = "文件大小所在列（字节）")]
+    size_col: Option<usize>,
 
+    #[arg(long, help = "修改时间所在列（Unix 时间戳）")]
+    mtime_col: Option<usize>,
+
+    #[arg(long, help = "CSV 没有表头行")]
+    no_header: bool,
+
+    #[arg(short, long, help = "批量写入大小", default_value = "5000")]
+    batch_size: usize,
+}
+
+#[derive(Args, Clone)]
+struct StatsArgs {
     #[arg(short, long, help = "数据库文件路径或包含数据库的文件夹（可多个）", num_args = 1..)]
     db: Option<Vec<PathBuf>>,
 
+    #[arg(short, long, help = "显示的扩展名数量", default_value_t = STATS_TOP)]
+    top: usize,
+
+    #[arg(long, help = "只统计该索引根目录下的文件（见 roots 命令）")]
+    root: Option<String>,
+
+    #[arg(long, help = "以 JSON 格式输出（包含全部扩展名）")]
+    json: bool,
+}
+
+/// Preview flag shared by commands that modify a database
+#[derive(Args, Clone)]
+struct DryRunArgs {
+    #[arg(long, help = "仅预览将要进行的修改，不改动数据库")]
+    dry_run: bool,
+}
+
+#[derive(Args, Clone)]
+struct LabelArgs {
+    #[arg(short, long, help = "数据库文件路径")]
+    db: PathBuf,
+
+    #[arg(help = "新标签，如 \"Photos Archive\"（不提供则显示当前标签）")]
+    label: Option<String>,
+}
+
+#[derive(Args, Clone)]
+struct PruneArgs {
+    #[arg(short, long, help = "数据库文件路径")]
+    db: PathBuf,
+
+    #[arg(long, help = "只检查该路径下的记录")]
+    prefix: Option<String>,
+
     #[arg(
+        short,
         long,
-        help = "选择搜索的数据库名称（默认: all）",
-        default_value = "all"
+        help = "显示全部已不存在的路径（预览模式默认只显示前 20 项）"
     )]
-    select_db: String,
+    list: bool,
+
+    #[command(flatten)]
+    dry_run: DryRunArgs,
+}
+
+#[derive(Args, Clone)]
+struct DiffArgs {
+    #[arg(long, requires = "new", help = "旧的数据库快照")]
+    old: Option<PathBuf>,
+
+    #[arg(long, requires = "old", help = "新的数据库快照")]
+    new: Option<PathBuf>,
+
+    #[arg(
+        short,
+        long,
+        requires = "against_disk",
+        conflicts_with_all = ["old", "new"],
+        help = "与磁盘比较的数据库文件路径"
+    )]
+    db: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "ROOT",
+        requires = "db",
+        help = "重新扫描该目录并与数据库比较（不修改数据库）"
+    )]
+    against_disk: Option<PathBuf>,
+
+    #[arg(short, long, help = "显示完整的新增 (+)、删除 (-) 和变更 (~) 列表")]
+    list: bool,
+}
+
+#[derive(Args, Clone)]
+struct RootsArgs {
+    #[arg(short, long, help = "数据库文件路径")]
+    db: PathBuf,
+
+    #[arg(long, value_name = "ROOT", help = "删除该根目录下索引的所有记录")]
+    remove: Option<String>,
+}
+
+#[derive(Args, Clone)]
+struct HistoryArgs {
+    #[command(subcommand)]
+    action: Option<HistoryAction>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        help = "搜索历史文件路径（默认为配置目录下的 search_history.json）"
+    )]
+    history_file: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Clone)]
+enum HistoryAction {
+    #[command(about = "列出最近的搜索（默认）")]
+    List {
+        #[arg(short = 'n', long, help = "显示最近的条数", default_value_t = HISTORY_LIST_LIMIT)]
+        limit: usize,
+    },
+
+    #[command(about = "清空搜索历史")]
+    Clear,
+
+    #[command(about = "删除一条记录")]
+    Remove {
+        #[arg(value_name = "INDEX", help = "记录编号（见 list 输出）")]
+        index: usize,
+    },
+
+    #[command(about = "按原有选项重新执行一条搜索")]
+    Replay {
+        #[arg(value_name = "INDEX", help = "记录编号（见 list 输出）")]
+        index: usize,
+
+        #[arg(short, long, help = "数据库文件路径或包含数据库的文件夹（可多个）", num_args = 1..)]
+        db: Option<Vec<PathBuf>>,
+    },
+}
+
+/// Parses a replayed history entry into search arguments.
+#[derive(Parser)]
+struct ReplayCli {
+    #[command(flatten)]
+    search: SearchArgs,
+}
+
+#[derive(Args, Clone)]
+struct RelocateArgs {
+    #[arg(short, long, help = "数据库文件路径")]
+    db: PathBuf,
+
+    #[arg(long, help = "原路径前缀，如 D:\\Media")]
+    from: String,
+
+    #[arg(long, help = "新路径前缀，如 E:\\Media")]
+    to: String,
+
+    #[command(flatten)]
+    dry_run: DryRunArgs,
+}
+
+#[derive(Args, Clone)]
+struct OptimizeArgs {
+    #[arg(short, long, help = "数据库文件路径")]
+    db: PathBuf,
+}
+
+#[derive(Args, Clone)]
+struct DedupeArgs {
+    #[arg(
+        short,
+        long,
+        help = "数据库文件路径或包含数据库的文件夹（可多个，跨数据库查找）",
+        num_args = 1..
+    )]
+    db: Option<Vec<PathBuf>>,
+
+    #[arg(long, value_enum, default_value = "name-size", help = "判断重复的依据")]
+    by: DuplicateKeyArg,
+
+    #[arg(long, help = "按可节省的空间排序（大小 × (份数 - 1)），默认按文件名")]
+    sort_by_waste: bool,
 
-    #[arg(short, long, help = "结果数量限制", default_value = "2000")]
+    #[arg(short, long, value_name = "N", help = "最多显示的组数")]
     limit: Option<usize>,
 
-    #[arg(short = 't', long, help = "树形显示结果")]
-    tree: bool,
+    #[arg(long, help = "以 JSON 格式输出")]
+    json: bool,
+}
 
-    #[arg(short = 'N', long, help = "仅搜索文件名（不搜索路径）")]
-    name_only: bool,
+#[derive(Clone, Copy, ValueEnum)]
+enum DuplicateKeyArg {
+    /// 文件名和大小都相同（未记录大小的文件不参与）
+    NameSize,
+    /// 仅文件名相同
+    Name,
+}
 
-    #[arg(short = 'c', long, help = "区分大小写")]
-    case_sensitive: bool,
+impl From<DuplicateKeyArg> for DuplicateKey {
+    fn from(arg: DuplicateKeyArg) -> Self {
+        match arg {
+            DuplicateKeyArg::NameSize => DuplicateKey::NameAndSize,
+            DuplicateKeyArg::Name => DuplicateKey::Name,
+        }
+    }
+}
 
-    #[arg(long, help = "树形显示的根目录名称", default_value = "搜索结果")]
-    root_name: Option<String>,
+#[derive(Args, Clone)]
+struct ExportArgs {
+    #[command(flatten)]
+    search: SearchArgs,
+
+    #[arg(
+        short,
+        long,
+        help = "输出文件路径（按扩展名 .toml、.csv、.json、.ndjson 选择格式）"
+    )]
+    output: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "导出格式（默认按输出文件扩展名，无法判断时为 TOML）",
+        value_enum
+    )]
+    format: Option<ExportFormatArg>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormatArg {
+    /// 带搜索参数的 TOML，可在 Web 界面中重新导入
+    Toml,
+    /// 每个文件一行的 CSV（keyword、path、size、modified），便于用表格软件打开
+    Csv,
+    /// 与 TOML 结构相同的 JSON
+    Json,
+    /// 每行一个 JSON 对象（首行为元数据），便于管道处理
+    Ndjson,
+}
+
+impl From<ExportFormatArg> for ExportFormat {
+    fn from(format: ExportFormatArg) -> Self {
+        match format {
+            ExportFormatArg::Toml => ExportFormat::Toml,
+            ExportFormatArg::Csv => ExportFormat::Csv,
+            ExportFormatArg::Json => ExportFormat::Json,
+            ExportFormatArg::Ndjson => ExportFormat::Ndjson,
+        }
+    }
+}
+
+#[derive(Args, Clone)]
+struct ExportDbArgs {
+    #[arg(short, long, help = "数据库文件路径")]
+    db: PathBuf,
+
+    #[arg(short, long, help = "导出格式", value_enum, default_value = "json")]
+    format: DumpFormatArg,
+
+    #[arg(short, long, help = "输出文件路径")]
+    out: PathBuf,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum DumpFormatArg {
+    /// 带表头信息的单个 JSON 文档
+    Json,
+    /// CSV（表头信息写在 # 注释行中，可用 import 重新导入）
+    Csv,
+}
+
+impl From<DumpFormatArg> for DumpFormat {
+    fn from(format: DumpFormatArg) -> Self {
+        match format {
+            DumpFormatArg::Json => DumpFormat::Json,
+            DumpFormatArg::Csv => DumpFormat::Csv,
+        }
+    }
 }
 
 #[derive(Args, Clone)]
@@ -387,4 +2559,130 @@ struct WebArgs {
         help = "Web 服务器端口（默认 3000，若被占用则自动尝试下一个端口）"
     )]
     port: Option<u16>,
+
+    #[arg(
+        long,
+        help = "搜索结果缓存条数（0 表示禁用缓存）",
+        default_value = "64"
+    )]
+    cache_size: usize,
+
+    #[arg(long, help = "搜索结果缓存有效期（秒）", default_value = "60")]
+    cache_ttl: u64,
+
+    #[arg(long, help = "处理一次 /api/search 请求后自动退出（用于脚本和测试）")]
+    once: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reminex::db::Index;
+    use std::fs;
+    use std::time::SystemTime;
+
+    /// Contents and modification time of a database file, and the size of
+    /// its write-ahead log, to check it was not written.
+    fn snapshot(path: &Path) -> (Vec<u8>, SystemTime, u64) {
+        let bytes = fs::read(path).unwrap();
+        let modified = fs::metadata(path).unwrap().modified().unwrap();
+        let mut wal = path.as_os_str().to_os_string();
+        wal.push("-wal");
+        let wal_len = fs::metadata(wal).map(|m| m.len()).unwrap_or(0);
+        (bytes, modified, wal_len)
+    }
+
+    #[test]
+    fn test_diff_command_leaves_databases_untouched() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("files");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("a.txt"), b"a").unwrap();
+
+        let old_path = temp_dir.path().join("old.reminex.db");
+        let new_path = temp_dir.path().join("new.reminex.db");
+        for path in [&old_path, &new_path] {
+            let db = Database::init(path).unwrap();
+            db.add_idx(&Index::new("/d/a.txt".into(), "a.txt".into()))
+                .unwrap();
+        }
+        let before = [snapshot(&old_path), snapshot(&new_path)];
+
+        handle_diff_command(DiffArgs {
+            old: Some(old_path.clone()),
+            new: Some(new_path.clone()),
+            db: None,
+            against_disk: None,
+            list: false,
+        })
+        .unwrap();
+        handle_diff_command(DiffArgs {
+            old: None,
+            new: None,
+            db: Some(old_path.clone()),
+            against_disk: Some(root),
+            list: false,
+        })
+        .unwrap();
+
+        assert_eq!([snapshot(&old_path), snapshot(&new_path)], before);
+    }
+
+    #[test]
+    fn test_print_json_search_writes_one_line() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("docs.reminex.db");
+        Database::init(&db_path)
+            .unwrap()
+            .add_idxs(&[
+                Index::with_metadata("/d/report.txt".into(), "report.txt".into(), 0.0, 7),
+                Index::new("/d/report.md".into(), "report.md".into()),
+                Index::new("/d/notes.txt".into(), "notes.txt".into()),
+            ])
+            .unwrap();
+
+        let db = db_path.to_string_lossy();
+        let app = App::try_parse_from([
+            "reminex",
+            "search",
+            "--json",
+            "--no-history",
+            "--limit",
+            "1",
+            "--db",
+            &db,
+        ])
+        .unwrap();
+        let Some(Commands::Search(args)) = app.commands else {
+            panic!("expected the search command");
+        };
+        let config = search_config(&args).unwrap();
+
+        let mut out = Vec::new();
+        write_json_search(
+            &mut out,
+            std::slice::from_ref(&db_path),
+            &args.select_db,
+            "report;missing",
+            &config,
+            &args,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 1);
+        assert!(out.ends_with('\n'));
+
+        let json: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(json["query"], "report;missing");
+        let results = json["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["keyword"], "report");
+        assert_eq!(results[0]["count"], 1);
+        assert_eq!(results[0]["total"], 2);
+        assert_eq!(results[0]["items"][0]["path"], "/d/report.md");
+        // Keywords without matches are kept by default
+        assert_eq!(results[1]["keyword"], "missing");
+        assert_eq!(results[1]["count"], 0);
+        assert!(results[1]["items"].as_array().unwrap().is_empty());
+    }
 }
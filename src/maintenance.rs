@@ -0,0 +1,441 @@
+use anyhow::{Context, Result, bail};
+use rusqlite::OptionalExtension;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::db::{Database, deepest_root, read_roots};
+use crate::indexer::split_archive_path;
+
+/// Number of before/after pairs kept in [`RelocateReport::samples`]
+pub const RELOCATE_SAMPLES: usize = 10;
+
+/// Prefix of the temporary paths rows and roots pass through while being
+/// relocated; no real path starts with a NUL character
+const RELOCATE_TEMP_PREFIX: &str = "\0relocate\0";
+
+/// Outcome of [`prune_missing`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PruneReport {
+    /// Number of rows checked against the filesystem
+    pub checked: usize,
+    /// Paths of rows whose file no longer exists, in path order
+    pub missing: Vec<String>,
+    /// Number of rows deleted (always 0 for a dry run)
+    pub deleted: usize,
+}
+
+/// Outcome of [`relocate`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RelocateReport {
+    /// Number of rows under the old prefix
+    pub matched: usize,
+    /// The first few rows as (old path, new path)
+    pub samples: Vec<(String, String)>,
+    /// Rows whose new path is already indexed and would be replaced
+    pub conflicts: usize,
+    /// Number of rows rewritten (always 0 for a dry run)
+    pub updated: usize,
+}
+
+/// Returns true if `path` is `prefix` itself or lies below it.
+///
+/// Both `/` and `\` count as separators, since databases may hold paths
+/// indexed on either platform.
+pub(crate) fn is_under(path: &str, prefix: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => {
+            rest.is_empty() || prefix.ends_with(['/', '\\']) || rest.starts_with(['/', '\\'])
+        }
+        None => false,
+    }
+}
+
+/// Removes rows for files that no longer exist on disk.
+///
+/// Entries indexed from inside an archive are kept as long as the archive
+/// itself exists. With a `prefix`, only the rows below it are read, see
+/// [`Database::paths_under`].
+///
+/// # Arguments
+/// * `db` - Database to prune
+/// * `prefix` - Only check rows at or below this path
+/// * `dry_run` - Report the missing rows without deleting them
+///
+/// # Returns
+/// The checked row count and the missing paths
+pub fn prune_missing(db: &Database, prefix: Option<&str>, dry_run: bool) -> Result<PruneReport> {
+    let mut report = PruneReport::default();
+    let mut check = |path: String| {
+        report.checked += 1;
+        let on_disk = split_archive_path(&path).map_or(path.as_str(), |(archive, _)| archive);
+        if !Path::new(on_disk).exists() {
+            report.missing.push(path);
+        }
+    };
+    match prefix {
+        Some(prefix) => db.paths_under(prefix)?.into_iter().for_each(check),
+        None => {
+            db.iter_all(|idx| {
+                check(idx.path);
+                Ok(())
+            })?;
+        }
+    }
+
+    if dry_run || report.missing.is_empty() {
+        return Ok(report);
+    }
+
+    report.deleted = db.batch_operation(|conn| {
+        let tx = conn.transaction().context("Failed to start transaction")?;
+        let mut deleted = 0;
+        {
+            let mut stmt = tx
+                .prepare("DELETE FROM files WHERE path = ?1")
+                .context("Failed to prepare delete statement")?;
+            for path in &report.missing {
+                deleted += stmt.execute([path]).context("Failed to delete row")?;
+            }
+        }
+        tx.commit().context("Failed to commit transaction")?;
+        Ok(deleted)
+    })?;
+
+    Ok(report)
+}
+
+/// Rewrites the path prefix `from` to `to`, e.g. after a drive was remounted.
+///
+/// A relocated row replaces any row already indexed under its new path
+/// that is not itself relocated; those are counted as conflicts. All rows
+/// are moved together, so `to` may lie under `from`. Registered scan roots
+/// under `from` are renamed too, merging into an existing root with the
+/// new path.
+///
+/// # Arguments
+/// * `db` - Database to update
+/// * `from` - Old path prefix
+/// * `to` - New path prefix
+/// * `dry_run` - Report what would change without modifying the database
+///
+/// # Returns
+/// Matched and conflicting row counts with sample before/after paths
+pub fn relocate(db: &Database, from: &str, to: &str, dry_run: bool) -> Result<RelocateReport> {
+    if from.is_empty() {
+        bail!("The old path prefix must not be empty");
+    }
+
+    db.batch_operation(|conn| {
+        // Rows replaced by `UPDATE OR REPLACE` only fire the full-text
+        // index's delete trigger with recursive triggers on
+        conn.pragma_update(None, "recursive_triggers", true)
+            .context("Failed to enable recursive triggers")?;
+        let tx = conn.transaction().context("Failed to start transaction")?;
+        let mut report = RelocateReport::default();
+        let mut moves = Vec::new();
+
+        {
+            let mut select = tx
+                .prepare("SELECT path FROM files WHERE substr(path, 1, ?1) = ?2 ORDER BY path")
+                .context("Failed to prepare relocate query")?;
+            let rows = select
+                .query_map(rusqlite::params![from.chars().count(), from], |row| {
+                    row.get::<_, String>(0)
+                })
+                .context("Failed to query rows to relocate")?;
+
+            for path in rows {
+                let path = path.context("Failed to read row")?;
+                if !is_under(&path, from) {
+                    continue;
+                }
+
+                let new_path = format!("{}{}", to, &path[from.len()..]);
+                report.matched += 1;
+                if report.samples.len() < RELOCATE_SAMPLES {
+                    report.samples.push((path.clone(), new_path.clone()));
+                }
+                moves.push((path, new_path));
+            }
+        }
+
+        // Rows that are relocated themselves free their paths
+        let moved: HashSet<&str> = moves.iter().map(|(path, _)| path.as_str()).collect();
+        {
+            let mut exists = tx
+                .prepare("SELECT 1 FROM files WHERE path = ?1")
+                .context("Failed to prepare conflict query")?;
+            for (_, new_path) in &moves {
+                if !moved.contains(new_path.as_str()) && exists.exists([new_path])? {
+                    report.conflicts += 1;
+                }
+            }
+        }
+
+        if dry_run {
+            return Ok(report);
+        }
+
+        // Moving through temporary paths keeps a row from replacing one that
+        // has yet to move
+        {
+            let mut update = tx
+                .prepare("UPDATE files SET path = ?2 WHERE path = ?1")
+                .context("Failed to prepare relocate statement")?;
+            for (path, _) in &moves {
+                update
+                    .execute([path, &format!("{}{}", RELOCATE_TEMP_PREFIX, path)])
+                    .context("Failed to relocate row")?;
+            }
+            let mut update = tx
+                .prepare("UPDATE OR REPLACE files SET path = ?2 WHERE path = ?1")
+                .context("Failed to prepare relocate statement")?;
+            for (path, new_path) in &moves {
+                report.updated += update
+                    .execute([&format!("{}{}", RELOCATE_TEMP_PREFIX, path), new_path])
+                    .context("Failed to relocate row")?;
+            }
+        }
+        relocate_roots(&tx, from, to)?;
+        retag_rows(&tx, moves.iter().map(|(_, new_path)| new_path.as_str()))?;
+        tx.commit().context("Failed to commit transaction")?;
+
+        Ok(report)
+    })
+}
+
+/// Renames the scan roots under `from`, merging each into an existing root
+/// that already has the new path and is not renamed itself.
+fn relocate_roots(tx: &rusqlite::Transaction, from: &str, to: &str) -> Result<()> {
+    let roots: Vec<(i64, String)> = read_roots(tx)?
+        .into_iter()
+        .filter(|(_, path)| is_under(path, from))
+        .collect();
+
+    // Free the old paths first, as for rows in `relocate`
+    for (id, path) in &roots {
+        tx.execute(
+            "UPDATE roots SET path = ?1 WHERE id = ?2",
+            rusqlite::params![format!("{}{}", RELOCATE_TEMP_PREFIX, path), id],
+        )
+        .context("Failed to relocate root")?;
+    }
+
+    for (id, path) in roots {
+        let new_path = format!("{}{}", to, &path[from.len()..]);
+        let existing: Option<i64> = tx
+            .query_row("SELECT id FROM roots WHERE path = ?1", [&new_path], |row| {
+                row.get(0)
+            })
+            .optional()
+            .context("Failed to read root id")?;
+
+        match existing {
+            Some(existing) if existing != id => {
+                tx.execute(
+                    "UPDATE files SET root_id = ?1 WHERE root_id = ?2",
+                    [existing, id],
+                )
+                .context("Failed to merge root")?;
+                tx.execute("DELETE FROM roots WHERE id = ?1", [id])
+                    .context("Failed to merge root")?;
+            }
+            _ => {
+                tx.execute(
+                    "UPDATE roots SET path = ?1 WHERE id = ?2",
+                    rusqlite::params![new_path, id],
+                )
+                .context("Failed to relocate root")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Assigns each relocated row to the innermost root that now contains it.
+///
+/// A row moved out of its root, such as a sub-path relocated elsewhere,
+/// would otherwise keep the old `root_id`.
+fn retag_rows<'a>(
+    tx: &rusqlite::Transaction,
+    paths: impl IntoIterator<Item = &'a str>,
+) -> Result<()> {
+    let roots = read_roots(tx)?;
+
+    let mut update = tx
+        .prepare("UPDATE files SET root_id = ?2 WHERE path = ?1")
+        .context("Failed to prepare root update")?;
+    for path in paths {
+        let root_id = deepest_root(&roots, path);
+        update
+            .execute(rusqlite::params![path, root_id])
+            .context("Failed to update row root")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Index;
+
+    fn paths(db: &Database) -> Vec<String> {
+        let mut paths = Vec::new();
+        db.iter_all(|idx| {
+            paths.push(idx.path);
+            Ok(())
+        })
+        .unwrap();
+        paths
+    }
+
+    #[test]
+    fn test_is_under() {
+        assert!(is_under("/a/b", "/a/b"));
+        assert!(is_under("/a/b/c.txt", "/a/b"));
+        assert!(is_under("/a/b/c.txt", "/a/b/"));
+        assert!(is_under("D:\\a\\c.txt", "D:\\a"));
+        assert!(!is_under("/a/bc/c.txt", "/a/b"));
+        assert!(!is_under("/x/a/b", "/a/b"));
+    }
+
+    #[test]
+    fn test_prune_missing_keeps_files_under_bang_directories() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path().join("wow!");
+        std::fs::create_dir(&dir).unwrap();
+        let real = dir.join("real.txt");
+        std::fs::write(&real, b"real").unwrap();
+        let real = real.to_string_lossy().into_owned();
+        let db = Database::init(temp_dir.path().join("bang.reminex.db")).unwrap();
+        db.add_idx(&Index::new(real.clone(), "real.txt".into()))
+            .unwrap();
+
+        let report = prune_missing(&db, None, false).unwrap();
+        assert_eq!(report.deleted, 0);
+        assert_eq!(paths(&db), vec![real.clone()]);
+
+        let result = crate::searcher::SearchResult {
+            path: real,
+            ..Default::default()
+        };
+        assert_eq!(result.archive(), None);
+    }
+
+    #[test]
+    fn test_prune_missing_dry_run_keeps_rows() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let kept = temp_dir.path().join("kept.txt");
+        std::fs::write(&kept, b"kept").unwrap();
+        let gone = temp_dir.path().join("gone.txt");
+        let db = Database::init(temp_dir.path().join("prune.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new(kept.to_string_lossy().into(), "kept.txt".into()),
+            Index::new(gone.to_string_lossy().into(), "gone.txt".into()),
+            Index::new("/elsewhere/gone.txt".into(), "gone.txt".into()),
+        ])
+        .unwrap();
+
+        let scope = temp_dir.path().to_string_lossy().into_owned();
+        let report = prune_missing(&db, Some(&scope), true).unwrap();
+        assert_eq!(report.checked, 2);
+        assert_eq!(report.missing, vec![gone.to_string_lossy().into_owned()]);
+        assert_eq!(report.deleted, 0);
+        assert_eq!(paths(&db).len(), 3);
+
+        let report = prune_missing(&db, Some(&scope), false).unwrap();
+        assert_eq!(report.deleted, 1);
+        assert_eq!(
+            paths(&db),
+            vec![
+                "/elsewhere/gone.txt".to_string(),
+                kept.to_string_lossy().into()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_relocate_dry_run_reports_conflicts() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("relocate.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/mnt/old/a.txt".into(), "a.txt".into()),
+            Index::new("/mnt/old/sub/b.txt".into(), "b.txt".into()),
+            Index::new("/mnt/older/c.txt".into(), "c.txt".into()),
+            Index::new("/mnt/new/a.txt".into(), "a.txt".into()),
+        ])
+        .unwrap();
+        let old_root = db.register_root("/mnt/old").unwrap();
+        let before = paths(&db);
+
+        let report = relocate(&db, "/mnt/old", "/mnt/new", true).unwrap();
+        assert_eq!(report.matched, 2);
+        assert_eq!(report.conflicts, 1);
+        assert_eq!(report.updated, 0);
+        assert_eq!(
+            report.samples[1],
+            (
+                "/mnt/old/sub/b.txt".to_string(),
+                "/mnt/new/sub/b.txt".to_string()
+            )
+        );
+        assert_eq!(paths(&db), before);
+
+        let report = relocate(&db, "/mnt/old", "/mnt/new", false).unwrap();
+        assert_eq!(report.updated, 2);
+        assert_eq!(db.root_id("/mnt/new").unwrap(), Some(old_root));
+        assert_eq!(db.count_in_root(old_root).unwrap(), 2);
+        assert_eq!(
+            paths(&db),
+            vec!["/mnt/new/a.txt", "/mnt/new/sub/b.txt", "/mnt/older/c.txt"]
+        );
+    }
+
+    #[test]
+    fn test_relocate_into_own_subdirectory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("relocate.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/a/x".into(), "x".into()),
+            Index::new("/a/z/x".into(), "x".into()),
+        ])
+        .unwrap();
+        let outer = db.register_root("/a").unwrap();
+        let inner = db.register_root("/a/z").unwrap();
+
+        let report = relocate(&db, "/a", "/a/z", true).unwrap();
+        assert_eq!(report.conflicts, 0);
+
+        let report = relocate(&db, "/a", "/a/z", false).unwrap();
+        assert_eq!((report.updated, report.conflicts), (2, 0));
+        assert_eq!(paths(&db), vec!["/a/z/x", "/a/z/z/x"]);
+        assert_eq!(db.root_id("/a/z").unwrap(), Some(outer));
+        assert_eq!(db.root_id("/a/z/z").unwrap(), Some(inner));
+    }
+
+    #[test]
+    fn test_relocate_sub_path_retags_rows() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("relocate.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/mnt/old/a.txt".into(), "a.txt".into()),
+            Index::new("/mnt/old/sub/b.txt".into(), "b.txt".into()),
+            Index::new("/mnt/other/c.txt".into(), "c.txt".into()),
+        ])
+        .unwrap();
+        let old_root = db.register_root("/mnt/old").unwrap();
+        let other_root = db.register_root("/mnt/other").unwrap();
+
+        relocate(&db, "/mnt/old/sub", "/mnt/other/sub", false).unwrap();
+        assert_eq!(db.count_in_root(old_root).unwrap(), 1);
+        assert_eq!(db.count_in_root(other_root).unwrap(), 2);
+
+        relocate(&db, "/mnt/other/sub", "/srv/sub", false).unwrap();
+        assert_eq!(db.count_in_root(other_root).unwrap(), 1);
+        assert_eq!(db.paths_under("/srv").unwrap(), vec!["/srv/sub/b.txt"]);
+        assert_eq!(
+            db.paths_under("/mnt/other").unwrap(),
+            vec!["/mnt/other/c.txt"]
+        );
+    }
+}
@@ -1,14 +1,94 @@
 use anyhow::{Context, Result};
-use rusqlite::params;
+use globset::GlobBuilder;
+use regex::RegexBuilder;
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
-use crate::db::Database;
+use crate::db::{Database, has_column};
+use crate::indexer::split_archive_path;
 
 /// Represents a search result item.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
 pub struct SearchResult {
     pub path: String,
     pub name: String,
+    /// File size in bytes, if indexed with metadata
+    pub size: Option<i64>,
+    /// Modification time as unix seconds, if indexed with metadata
+    pub mtime: Option<f64>,
+    /// Closeness to the keyword from 0 to 100, in [`SearchMode::Fuzzy`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<i64>,
+}
+
+/// Columns selected for a [`SearchResult`], in the order read by
+/// [`SearchResult::from_row`]
+const RESULT_COLUMNS: &str = "path, name, size, mtime";
+
+/// Path separators recognized in indexed paths, whichever platform indexed them
+const SEPARATORS: [char; 2] = ['/', '\\'];
+
+impl SearchResult {
+    /// Reads a row selected with [`RESULT_COLUMNS`].
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            path: row.get(0)?,
+            name: row.get(1)?,
+            size: row.get(2)?,
+            mtime: row.get(3)?,
+            score: None,
+        })
+    }
+
+    /// Returns the full path as a [`Path`].
+    pub fn as_path(&self) -> &Path {
+        Path::new(&self.path)
+    }
+
+    /// Returns the last path component, splitting on both `/` and `\`.
+    pub fn file_name(&self) -> &str {
+        let trimmed = self.path.trim_end_matches(SEPARATORS);
+        trimmed.rsplit(SEPARATORS).next().unwrap_or(trimmed)
+    }
+
+    /// Returns the extension of the file name without the dot, as stored.
+    ///
+    /// Dotfiles such as `.bashrc` have no extension.
+    pub fn extension(&self) -> Option<&str> {
+        match self.file_name().rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() && !ext.is_empty() => Some(ext),
+            _ => None,
+        }
+    }
+
+    /// Returns the path relative to `base`, or `None` if it lies outside it.
+    ///
+    /// Paths indexed on another platform are compared as text, so a Windows
+    /// path such as `D:\Media\a.mp4` can be made relative to `D:\Media` on
+    /// any system.
+    pub fn relative_to(&self, base: impl AsRef<Path>) -> Option<PathBuf> {
+        let base = base.as_ref();
+        if let Ok(relative) = self.as_path().strip_prefix(base) {
+            return Some(relative.to_path_buf());
+        }
+
+        let base = base.to_str()?.trim_end_matches(SEPARATORS);
+        let rest = self.path.strip_prefix(base)?;
+        if !rest.is_empty() && !rest.starts_with(SEPARATORS) {
+            return None;
+        }
+        Some(PathBuf::from(rest.trim_start_matches(SEPARATORS)))
+    }
+
+    /// Returns the path of the archive containing this entry, if it was
+    /// indexed from inside a `.zip` file.
+    pub fn archive(&self) -> Option<&str> {
+        split_archive_path(&self.path).map(|(archive, _)| archive)
+    }
 }
 
 /// Represents a tree node for hierarchical display of search results.
@@ -17,6 +97,10 @@ pub struct TreeNode {
     pub name: String,
     pub path: PathBuf,
     pub children: Vec<TreeNode>,
+    /// Size of the file a leaf stands for; `None` on directories
+    pub size: Option<i64>,
+    /// Modification time of the file a leaf stands for; `None` on directories
+    pub mtime: Option<f64>,
 }
 
 impl TreeNode {
@@ -26,6 +110,8 @@ impl TreeNode {
             name,
             path,
             children: Vec::new(),
+            size: None,
+            mtime: None,
         }
     }
 
@@ -43,11 +129,171 @@ impl TreeNode {
     }
 }
 
+/// How a keyword is matched against names and paths.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// The keyword appears anywhere, e.g. `summer` in `summer_2023.jpg`
+    #[default]
+    Substring,
+    /// Keywords with `*` or `?` are shell-style globs matching the whole
+    /// name, or the path if they contain `/`, e.g. `*.jpg` or
+    /// `photos/**/summer*`; keywords without them match as substrings
+    Glob,
+    /// The keyword is a regular expression, matched anywhere in the name
+    /// (or path) unless anchored, e.g. `^IMG_\d+\.jpe?g$`
+    Regex,
+    /// The keyword is compared with names allowing typos, and results are
+    /// scored by closeness, e.g. `reciept` finds `receipt.pdf`; see
+    /// [`fuzzy_score`] and [`SearchConfig::fuzzy_threshold`]
+    Fuzzy,
+}
+
+/// How the results of several keywords are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeywordCombine {
+    /// Each keyword is searched on its own, in a result group of its own
+    #[default]
+    Separate,
+    /// One result group of the files matching every keyword. Words
+    /// separated by whitespace count as keywords of their own, so
+    /// `invoice 2023 pdf` finds `2023_invoice.pdf`
+    And,
+    /// One result group of the files matching any keyword, each listed once
+    Or,
+}
+
+impl KeywordCombine {
+    /// Returns the keywords combined by this mode, without blank ones.
+    fn terms(self, keywords: &[String]) -> Vec<&str> {
+        let terms = keywords.iter().map(|keyword| keyword.trim());
+        match self {
+            KeywordCombine::And => terms.flat_map(str::split_whitespace).collect(),
+            _ => terms.filter(|term| !term.is_empty()).collect(),
+        }
+    }
+
+    /// Returns the keyword of the combined result group, e.g. `invoice & pdf`.
+    pub fn label(self, keywords: &[String]) -> String {
+        let separator = match self {
+            KeywordCombine::And => " & ",
+            _ => " | ",
+        };
+        self.terms(keywords).join(separator)
+    }
+}
+
+/// Order of search results.
+///
+/// Files without a size or modification time, such as those indexed
+/// without metadata, come last in either direction. Ties are broken by
+/// path.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    /// Sort by full path
+    #[default]
+    Path,
+    /// Sort by file name
+    Name,
+    /// Sort by size in bytes (requires metadata)
+    Size,
+    /// Sort by modification time (requires metadata)
+    Mtime,
+    /// Sort by [`SearchResult::score`], best first; results without a score
+    /// are in path order
+    Score,
+}
+
+impl SortBy {
+    /// Returns the SQL `ORDER BY` clause for this order.
+    fn order_by_sql(self, descending: bool) -> String {
+        let direction = if descending { " DESC" } else { "" };
+        match self {
+            SortBy::Path => format!("ORDER BY path{}", direction),
+            SortBy::Name => format!("ORDER BY name{}, path", direction),
+            SortBy::Size => format!("ORDER BY size{} NULLS LAST, path", direction),
+            SortBy::Mtime => format!("ORDER BY mtime{} NULLS LAST, path", direction),
+            // Scores are computed after the query
+            SortBy::Score => format!("ORDER BY path{}", direction),
+        }
+    }
+
+    /// Sorts results the way [`SortBy::order_by_sql`] orders rows, for
+    /// results merged from several queries.
+    pub fn sort(self, results: &mut [SearchResult], descending: bool) {
+        // Missing values stay last regardless of direction
+        fn nulls_last<T: PartialOrd>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+            match (a, b) {
+                (Some(a), Some(b)) => {
+                    let ordering = a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+                    if descending {
+                        ordering.reverse()
+                    } else {
+                        ordering
+                    }
+                }
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        }
+        let directed = |ordering: Ordering| {
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        };
+
+        results.sort_by(|a, b| {
+            let primary = match self {
+                SortBy::Path => directed(a.path.cmp(&b.path)),
+                SortBy::Name => directed(a.name.cmp(&b.name)),
+                SortBy::Size => nulls_last(a.size, b.size, descending),
+                SortBy::Mtime => nulls_last(a.mtime, b.mtime, descending),
+                // Higher scores are better, so best first is descending
+                SortBy::Score => nulls_last(a.score, b.score, !descending),
+            };
+            primary.then_with(|| a.path.cmp(&b.path))
+        });
+    }
+}
+
+/// Merges per-database results by keyword into a deterministic order.
+///
+/// Keywords keep their query order and each keyword's items are sorted by
+/// `sort`, so searching several databases renders the same way every time.
+pub(crate) fn merge_by_keyword(
+    results: Vec<(String, String, Vec<SearchResult>)>,
+    sort: SortBy,
+    descending: bool,
+) -> Vec<(String, Vec<SearchResult>)> {
+    let mut merged: Vec<(String, Vec<SearchResult>)> = Vec::new();
+
+    for (_db_name, keyword, items) in results {
+        match merged.iter_mut().find(|(k, _)| *k == keyword) {
+            Some((_, existing)) => existing.extend(items),
+            None => merged.push((keyword, items)),
+        }
+    }
+
+    for (_, items) in &mut merged {
+        sort.sort(items, descending);
+    }
+
+    merged
+}
+
 /// Configuration for search operations.
 #[derive(Debug, Clone)]
 pub struct SearchConfig {
     /// Maximum number of results to return per keyword
     pub max_results: usize,
+    /// Number of matches to skip per keyword before the returned ones, for
+    /// paging through results in `sort_by` order
+    pub offset: usize,
     /// Whether to search in path (true) or only filename (false)
     pub search_in_path: bool,
     /// Case sensitive search
@@ -56,16 +302,66 @@ pub struct SearchConfig {
     pub include_filters: Vec<String>,
     /// Exclude results containing these keywords (OR logic)
     pub exclude_filters: Vec<String>,
+    /// Match the keyword exactly against the filename stem (name without extension)
+    pub match_stem: bool,
+    /// Match the keyword's characters in order with anything in between
+    pub subsequence: bool,
+    /// How keywords are matched
+    pub search_mode: SearchMode,
+    /// Use the database's full-text index when it has one, see
+    /// [`search_by_keyword_fts`]
+    pub use_fts: bool,
+    /// Only return files owned by this uid (requires a `--perms` index)
+    pub owner: Option<i64>,
+    /// Only return files with these permission bits, e.g. `0o644` (requires a `--perms` index)
+    pub mode: Option<i64>,
+    /// Only return files modified at or after this unix time (requires metadata)
+    pub modified_after: Option<f64>,
+    /// Only return files first indexed at or after this unix time
+    pub indexed_after: Option<f64>,
+    /// Only return files of at least this many bytes (requires metadata)
+    pub min_size: Option<i64>,
+    /// Only return files of at most this many bytes (requires metadata)
+    pub max_size: Option<i64>,
+    /// Only return files with one of these extensions, compared without
+    /// case and with or without a leading dot, e.g. `jpg` or `.PNG`
+    pub extensions: Vec<String>,
+    /// Order of the returned results; also decides which results are kept
+    /// when there are more than `max_results`
+    pub sort_by: SortBy,
+    /// Reverse `sort_by`, e.g. largest or newest first
+    pub descending: bool,
+    /// Lowest [`fuzzy_score`] kept by [`SearchMode::Fuzzy`], from 0 to 100
+    pub fuzzy_threshold: i64,
+    /// How [`search_multiple_keywords`] and the functions searching several
+    /// databases combine keywords
+    pub combine: KeywordCombine,
 }
 
 impl Default for SearchConfig {
     fn default() -> Self {
         Self {
             max_results: 2000,
+            offset: 0,
             search_in_path: true,
             case_sensitive: false,
             include_filters: Vec::new(),
             exclude_filters: Vec::new(),
+            match_stem: false,
+            subsequence: false,
+            search_mode: SearchMode::Substring,
+            use_fts: false,
+            owner: None,
+            mode: None,
+            modified_after: None,
+            indexed_after: None,
+            min_size: None,
+            max_size: None,
+            extensions: Vec::new(),
+            sort_by: SortBy::Path,
+            descending: false,
+            fuzzy_threshold: DEFAULT_FUZZY_THRESHOLD,
+            combine: KeywordCombine::Separate,
         }
     }
 }
@@ -154,7 +450,7 @@ fn apply_filters(results: Vec<SearchResult>, config: &SearchConfig) -> Vec<Searc
             let full_text = if config.case_sensitive {
                 format!("{} {}", result.path, result.name)
             } else {
-                format!("{} {}", result.path, result.name).to_lowercase()
+                fold_case(&format!("{} {}", result.path, result.name))
             };
 
             // Check include filters (must match ALL)
@@ -165,7 +461,7 @@ fn apply_filters(results: Vec<SearchResult>, config: &SearchConfig) -> Vec<Searc
                     let filter_text = if config.case_sensitive {
                         filter.clone()
                     } else {
-                        filter.to_lowercase()
+                        fold_case(filter)
                     };
                     full_text.contains(&filter_text)
                 })
@@ -176,7 +472,7 @@ fn apply_filters(results: Vec<SearchResult>, config: &SearchConfig) -> Vec<Searc
                 let filter_text = if config.case_sensitive {
                     filter.clone()
                 } else {
-                    filter.to_lowercase()
+                    fold_case(filter)
                 };
                 full_text.contains(&filter_text)
             });
@@ -204,618 +500,3828 @@ pub fn search_by_keyword(
         return Ok(Vec::new());
     }
 
-    db.batch_operation(|conn| {
-        let like_pattern = format!("%{}%", keyword);
-        let query = if config.search_in_path {
-            format!(
-                "SELECT path, name FROM files WHERE name LIKE ?1 OR path LIKE ?1 ORDER BY path LIMIT {}",
-                config.max_results
-            )
-        } else {
-            format!(
-                "SELECT path, name FROM files WHERE name LIKE ?1 ORDER BY path LIMIT {}",
-                config.max_results
-            )
+    match config.search_mode {
+        SearchMode::Regex => return search_by_regex(db, keyword, config),
+        SearchMode::Fuzzy => return search_by_fuzzy(db, keyword, config),
+        SearchMode::Glob if is_glob(keyword) => return search_by_glob(db, keyword, config),
+        _ => {}
+    }
+    if config.use_fts {
+        return search_by_keyword_fts(db, keyword, config);
+    }
+    search_by_like(db, keyword, config)
+}
+
+/// Searches like [`search_by_keyword`], also returning how many files match
+/// in total, beyond `offset` and `max_results`.
+///
+/// Pages that end before `max_results` hold the last match, so only full
+/// or empty pages need a separate count.
+///
+/// # Returns
+/// `(total, results)`, where `results` is the requested page
+pub fn search_by_keyword_counted(
+    db: &Database,
+    keyword: &str,
+    config: &SearchConfig,
+) -> Result<(usize, Vec<SearchResult>)> {
+    let results = search_by_keyword(db, keyword, config)?;
+    let total = match total_from_page(config, results.len()) {
+        Some(total) => total,
+        None => count_matches(db, keyword, config)?,
+    };
+    Ok((total, results))
+}
+
+/// Returns the total match count if a page of `found` results shows it:
+/// a page cut short by the end of the matches, rather than by
+/// `max_results`, ends on the last one.
+///
+/// Include and exclude filters drop results after the limit is applied,
+/// so filtered pages are always counted.
+fn total_from_page(config: &SearchConfig, found: usize) -> Option<usize> {
+    let unfiltered = config.include_filters.is_empty() && config.exclude_filters.is_empty();
+    (unfiltered && found < config.max_results && (found > 0 || config.offset == 0))
+        .then(|| config.offset + found)
+}
+
+/// Counts every file matching a keyword, ignoring `offset` and `max_results`.
+///
+/// Substring and full-text searches are counted in SQL, with an unordered
+/// `COUNT(*)` over the search's conditions. Glob, regex, stem and fuzzy
+/// matching, include or exclude filters, and case folding of non-ASCII
+/// keywords (see [`folds_outside_sql`]) are applied outside SQL, so
+/// those searches are run without a limit and their results counted; a
+/// fuzzy count reads every row.
+pub fn count_matches(db: &Database, keyword: &str, config: &SearchConfig) -> Result<usize> {
+    if keyword.trim().is_empty() {
+        return Ok(0);
+    }
+
+    let in_sql = matches_in_sql(keyword, config);
+    let fts = if in_sql && config.use_fts {
+        fts_match(db, keyword, config)?
+    } else {
+        None
+    };
+    if !in_sql || (fts.is_none() && folds_outside_sql(keyword, config)) {
+        let unlimited = SearchConfig {
+            offset: 0,
+            max_results: UNLIMITED,
+            ..config.clone()
         };
+        return search_by_keyword(db, keyword, &unlimited).map(|results| results.len());
+    }
 
-        let mut stmt = conn.prepare(&query)
-            .context("Failed to prepare search query")?;
+    db.batch_operation_readonly(|conn| {
+        let (conditions, values) = match fts {
+            Some(query) => fts_conditions(conn, query, config)?,
+            None => {
+                set_like_case_sensitivity(conn, config)?;
+                like_conditions(conn, keyword, config)?
+            }
+        };
+        let count: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM files WHERE {}", conditions),
+                params_from_iter(&values),
+                |row| row.get(0),
+            )
+            .context("Failed to count search matches")?;
+        Ok(count as usize)
+    })
+}
 
-        let rows = stmt.query_map(params![like_pattern], |row| {
-            Ok(SearchResult {
-                path: row.get(0)?,
-                name: row.get(1)?,
-            })
-        })
-        .context("Failed to execute search query")?;
+/// A `max_results` returning every match, and still a valid SQL `LIMIT`
+const UNLIMITED: usize = i64::MAX as usize;
 
-        let mut results = Vec::new();
-        for row in rows {
-            results.push(row?);
+/// Returns true if the search's conditions for `keyword` are all applied
+/// in SQL, apart from case folding, see [`count_matches`].
+fn matches_in_sql(keyword: &str, config: &SearchConfig) -> bool {
+    config.include_filters.is_empty()
+        && config.exclude_filters.is_empty()
+        && !config.match_stem
+        && match config.search_mode {
+            SearchMode::Regex | SearchMode::Fuzzy => false,
+            SearchMode::Glob => !is_glob(keyword),
+            SearchMode::Substring => true,
         }
-
-        Ok(results)
-    }).map(|results| apply_filters(results, config))
 }
 
-/// Searches for files matching multiple keywords.
+/// Returns true if the total matches of a search for `keywords` are
+/// counted with a `COUNT(*)` in SQL, rather than by running the search
+/// again without a limit, see [`count_matches`] and
+/// [`search_combined_counted`].
 ///
-/// Each keyword is searched independently, and results are combined.
+/// Callers that only want a total when it is cheap check this first. A
+/// non-ASCII keyword counts as expensive even where a full-text index
+/// would count it in SQL.
+pub fn counts_in_sql(keywords: &[String], config: &SearchConfig) -> bool {
+    let term_in_sql = |term: &str| matches_in_sql(term, config) && !folds_outside_sql(term, config);
+    match config.combine {
+        KeywordCombine::Separate => keywords.iter().all(|keyword| term_in_sql(keyword)),
+        KeywordCombine::And => {
+            !config.use_fts && config.combine.terms(keywords).into_iter().all(term_in_sql)
+        }
+        KeywordCombine::Or => false,
+    }
+}
+
+/// Default of [`SearchConfig::fuzzy_threshold`], low enough to allow one
+/// typo in a four-letter word or two in a seven-letter one
+pub const DEFAULT_FUZZY_THRESHOLD: i64 = 60;
+
+/// Rows read per requested result by a fuzzy search, see [`search_by_fuzzy`]
+const FUZZY_ROWS_PER_RESULT: usize = 500;
+
+/// Fewest matches a fuzzy search collects before trimming them to the
+/// best, so small pages are not re-sorted after every few rows
+const FUZZY_TRIM_AT: usize = 1024;
+
+/// Searches with typo-tolerant matching, best [`fuzzy_score`] first.
 ///
-/// # Arguments
-/// * `db` - Database instance to search in
-/// * `keywords` - Vector of search keywords
-/// * `config` - Search configuration
+/// Edit distances cannot be narrowed down by an index, so rows are read
+/// and scored one by one: a fuzzy search costs a pass over the database
+/// rather than an index lookup, however few files match. To bound that,
+/// at most `(offset + max_results) * 500` rows are read (a million with
+/// the default settings), taken in path order so the same rows are
+/// considered on every run, and rows past that are never considered. Only
+/// names are scored, whatever `search_in_path` says; the metadata and
+/// extension filters are still applied in SQL.
 ///
-/// # Returns
-/// Vector of tuples (keyword, results) for each keyword
-pub fn search_multiple_keywords(
+/// Only the best `offset + max_results` matches are held at a time, so a
+/// keyword matching most rows does not keep them all in memory, and once
+/// that many are held, words that cannot beat the worst of them are
+/// skipped.
+fn search_by_fuzzy(
     db: &Database,
-    keywords: &[String],
+    keyword: &str,
     config: &SearchConfig,
-) -> Result<Vec<(String, Vec<SearchResult>)>> {
-    let mut all_results = Vec::new();
+) -> Result<Vec<SearchResult>> {
+    let keyword = keyword.trim().to_lowercase();
+    let row_limit = config
+        .max_results
+        .saturating_add(config.offset)
+        .saturating_mul(FUZZY_ROWS_PER_RESULT)
+        .min(i64::MAX as usize);
 
-    for keyword in keywords {
-        let results = search_by_keyword(db, keyword, config)?;
-        all_results.push((keyword.clone(), results));
+    let keep = config.offset.saturating_add(config.max_results);
+    // Keeps the best `keep` matches, in score order
+    let trim = |results: Vec<SearchResult>| {
+        let mut results = apply_filters(results, config);
+        SortBy::Score.sort(&mut results, false);
+        results.truncate(keep);
+        results
+    };
+
+    let mut results = db.batch_operation_readonly(|conn| {
+        let (filters, values) = metadata_filter_sql(conn, config, 1)?;
+        let query = format!(
+            "SELECT {} FROM files WHERE 1 = 1{} ORDER BY path LIMIT {}",
+            RESULT_COLUMNS, filters, row_limit
+        );
+        let mut stmt = conn
+            .prepare(&query)
+            .context("Failed to prepare fuzzy search query")?;
+        let mut rows = stmt
+            .query(params_from_iter(&values))
+            .context("Failed to execute fuzzy search query")?;
+
+        let mut results = Vec::new();
+        let mut min_score = config.fuzzy_threshold;
+        while let Some(row) = rows.next()? {
+            let mut result = SearchResult::from_row(row)?;
+            if let Some(score) = score_at_least(&keyword, &result.name, min_score) {
+                result.score = Some(score);
+                results.push(result);
+            }
+            if results.len() >= keep.saturating_mul(2).max(FUZZY_TRIM_AT) {
+                results = trim(results);
+                // Ties still compete on path, so the worst kept score stays in
+                if results.len() == keep
+                    && let Some(worst) = results.last().and_then(|r| r.score)
+                {
+                    min_score = worst;
+                }
+            }
+        }
+        Ok(trim(results))
+    })?;
+
+    // Drop the skipped best matches, then order the rest as configured
+    results.drain(..config.offset.min(results.len()));
+    results.truncate(config.max_results);
+    config.sort_by.sort(&mut results, config.descending);
+    Ok(results)
+}
+
+/// Scores how closely a name matches a lowercased keyword, from 0 to 100.
+///
+/// A name containing the keyword scores 100. Otherwise the keyword is
+/// compared with the name's stem and with each of its words (runs of
+/// letters and digits), and the closest one counts: a word `d` typos away
+/// scores `100 * (1 - d / n)`, where `n` is the length of the longer of the
+/// two. A typo is an inserted, deleted or replaced character, or two
+/// adjacent characters swapped, so `reciept` against `receipt.pdf` scores 85.
+pub fn fuzzy_score(keyword: &str, name: &str) -> i64 {
+    score_at_least(keyword, name, 0).unwrap_or(0)
+}
+
+/// Computes [`fuzzy_score`] if it is at least `min`.
+///
+/// Words whose length alone rules out reaching `min` are not compared,
+/// which saves most of the work on long names.
+fn score_at_least(keyword: &str, name: &str, min: i64) -> Option<i64> {
+    let keyword_len = keyword.chars().count();
+    if keyword_len == 0 {
+        return (min <= 0).then_some(0);
+    }
+    let name = name.to_lowercase();
+    if name.contains(keyword) {
+        return Some(100);
     }
 
-    Ok(all_results)
+    let score = |longest: usize, distance: usize| (100 * (longest - distance) / longest) as i64;
+    let stem = match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => name.as_str(),
+    };
+    std::iter::once(stem)
+        .chain(name.split(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .filter_map(|word| {
+            let word_len = word.chars().count();
+            let longest = keyword_len.max(word_len);
+            // Every missing or extra character is at least one typo
+            if score(longest, keyword_len.abs_diff(word_len)) < min {
+                return None;
+            }
+            Some(score(
+                longest,
+                edit_distance(keyword, word, true).min(longest),
+            ))
+        })
+        .max()
+        .filter(|&best| best >= min)
 }
 
-/// Searches databases from user input string.
+/// Searches for files matching a shell-style glob.
 ///
-/// Convenience function that combines keyword parsing and searching.
+/// A pattern without `/` is matched against file names, so `*.jpg` finds
+/// JPEGs in any directory. A pattern with `/` is matched against the whole
+/// path, with `\` read as `/`, and only with `search_in_path`; unless it
+/// starts with `/` it may begin at any directory, so `photos/**/summer*`
+/// finds `/home/me/photos/2023/summer.jpg`. `*` and `?` stay within one
+/// path segment while `**` spans any number of them, and `[abc]` and
+/// `{a,b}` work as in the shell.
 ///
-/// # Arguments
-/// * `db` - Database instance to search in
-/// * `input` - Raw user input (may contain multiple keywords)
-/// * `config` - Search configuration
+/// SQLite narrows the rows down with a looser `LIKE` pattern, and the glob
+/// itself is matched here.
 ///
-/// # Returns
-/// Vector of tuples (keyword, results) for each parsed keyword
-pub fn search_from_input(
+/// # Errors
+/// Returns an error if the keyword is not a valid glob.
+fn search_by_glob(
     db: &Database,
-    input: &str,
+    keyword: &str,
     config: &SearchConfig,
-) -> Result<Vec<(String, Vec<SearchResult>)>> {
-    let keywords = parse_search_keywords(input);
-
-    if keywords.is_empty() {
+) -> Result<Vec<SearchResult>> {
+    let keyword = keyword.replace('\\', "/");
+    let on_path = keyword.contains('/');
+    if on_path && !config.search_in_path {
         return Ok(Vec::new());
     }
+    let pattern = if on_path && !keyword.starts_with('/') {
+        format!("**/{}", keyword)
+    } else {
+        keyword.clone()
+    };
+    // The matcher only ignores ASCII case, so other keywords are matched
+    // folded against folded names
+    let folds = needs_folding(&pattern, config);
+    let fold = |text: String| if folds { fold_case(&text) } else { text };
+    let matcher = GlobBuilder::new(&fold(pattern.clone()))
+        .literal_separator(true)
+        .backslash_escape(false)
+        .case_insensitive(!config.case_sensitive)
+        .build()
+        .with_context(|| format!("Invalid glob pattern: {}", keyword))?
+        .compile_matcher();
 
-    search_multiple_keywords(db, &keywords, config)
+    db.batch_operation_readonly(|conn| {
+        set_like_case_sensitivity(conn, config)?;
+
+        let column = if on_path {
+            "REPLACE(path, '\\', '/')"
+        } else {
+            "name"
+        };
+        let (filters, values) = metadata_filter_sql(conn, config, 2)?;
+        let query = format!(
+            "SELECT {} FROM files WHERE {} LIKE ?1 ESCAPE '\\'{} {}",
+            RESULT_COLUMNS,
+            column,
+            filters,
+            order_by_sql(config)
+        );
+        let mut stmt = conn
+            .prepare(&query)
+            .context("Failed to prepare glob search query")?;
+        let like_pattern = loosen_for_folding(glob_pattern(&pattern), &pattern, config);
+        let mut rows = stmt
+            .query(params_from_iter(
+                std::iter::once(Value::Text(like_pattern)).chain(values),
+            ))
+            .context("Failed to execute glob search query")?;
+
+        let mut page = Page::new(config);
+        while !page.is_full()
+            && let Some(row) = rows.next()?
+        {
+            let result = SearchResult::from_row(row)?;
+            let matched = if on_path {
+                matcher.is_match(fold(result.path.replace('\\', "/")))
+            } else {
+                if folds {
+                    matcher.is_match(fold_case(&result.name))
+                } else {
+                    matcher.is_match(&result.name)
+                }
+            };
+            if matched {
+                page.push(result);
+            }
+        }
+        Ok(page.results)
+    })
+    .map(|results| apply_filters(results, config))
 }
 
-/// Builds a tree structure from search results.
+/// Searches for files whose name, or path with `search_in_path`, matches a
+/// regular expression.
 ///
-/// Automatically identifies the common prefix path from all results.
+/// SQLite cannot evaluate the expression, so every row passing the
+/// metadata filters is read and matched here, in `sort_by` order, until
+/// `max_results` are found. Paths are matched as stored, with their
+/// original separators.
 ///
-/// # Arguments
-/// * `results` - Search results to build tree from
-/// * `root_name` - Display name for root node (e.g., "搜索结果")
+/// # Errors
+/// Returns an error if the keyword is not a valid regular expression.
+fn search_by_regex(
+    db: &Database,
+    keyword: &str,
+    config: &SearchConfig,
+) -> Result<Vec<SearchResult>> {
+    let regex = RegexBuilder::new(keyword)
+        .case_insensitive(!config.case_sensitive)
+        .build()
+        .with_context(|| format!("Invalid regular expression: {}", keyword))?;
+
+    db.batch_operation_readonly(|conn| {
+        let (filters, values) = metadata_filter_sql(conn, config, 1)?;
+        let query = format!(
+            "SELECT {} FROM files WHERE TRUE{} {}",
+            RESULT_COLUMNS,
+            filters,
+            order_by_sql(config)
+        );
+        let mut stmt = conn
+            .prepare(&query)
+            .context("Failed to prepare regex search query")?;
+        let mut rows = stmt
+            .query(params_from_iter(&values))
+            .context("Failed to execute regex search query")?;
+
+        let mut page = Page::new(config);
+        while !page.is_full()
+            && let Some(row) = rows.next()?
+        {
+            let result = SearchResult::from_row(row)?;
+            if regex.is_match(&result.name)
+                || (config.search_in_path && regex.is_match(&result.path))
+            {
+                page.push(result);
+            }
+        }
+        Ok(page.results)
+    })
+    .map(|results| apply_filters(results, config))
+}
+
+/// Searches for files matching a keyword through the full-text index.
 ///
-/// # Returns
-/// Root TreeNode containing the hierarchical structure
-pub fn build_tree(results: &[SearchResult], root_name: &str) -> TreeNode {
-    if results.is_empty() {
-        return TreeNode::new(root_name.to_string(), PathBuf::new());
-    }
+/// The index matches whole words rather than substrings: names and paths
+/// are split at anything but letters and digits, so `summer` finds
+/// `summer_2023.jpg` but `umme` does not. A trailing `*` matches word
+/// prefixes, as in `summ*`. Words of the keyword must all appear, in any
+/// order.
+///
+/// Falls back to the `LIKE` search of [`search_by_keyword`] when the
+/// database has no full-text index (see [`Database::enable_fts`]), when the
+/// keyword has no letters or digits, or when stem, subsequence, glob or
+/// case-sensitive matching is requested, since the index supports none of
+/// these.
+pub fn search_by_keyword_fts(
+    db: &Database,
+    keyword: &str,
+    config: &SearchConfig,
+) -> Result<Vec<SearchResult>> {
+    let Some(query) = fts_match(db, keyword, config)? else {
+        return search_by_like(db, keyword, config);
+    };
 
-    // Find common prefix from all paths
-    let common_prefix = find_common_prefix(results);
-    let mut root = TreeNode::new(
-        format!("{} ({})", root_name, common_prefix.display()),
-        common_prefix.clone(),
-    );
+    db.batch_operation_readonly(|conn| {
+        let (conditions, values) = fts_conditions(conn, query, config)?;
+        let sql = format!(
+            "SELECT {} FROM files WHERE {} {} LIMIT {} OFFSET {}",
+            RESULT_COLUMNS,
+            conditions,
+            order_by_sql(config),
+            config.max_results,
+            config.offset
+        );
+        let mut stmt = conn
+            .prepare(&sql)
+            .context("Failed to prepare full-text search query")?;
+        let results = stmt
+            .query_map(params_from_iter(&values), SearchResult::from_row)
+            .context("Failed to execute full-text search query")?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(results)
+    })
+    .map(|results| apply_filters(results, config))
+}
 
-    for result in results {
-        insert_path_into_tree(&mut root, &PathBuf::from(&result.path));
-    }
+/// Returns the `MATCH` query a full-text search would run, or `None` if it
+/// falls back to `LIKE`, see [`search_by_keyword_fts`].
+fn fts_match(db: &Database, keyword: &str, config: &SearchConfig) -> Result<Option<String>> {
+    let like_only = config.match_stem || config.subsequence || config.case_sensitive;
+    let query = match fts_query(keyword) {
+        Some(query) if !like_only && db.has_fts()? => query,
+        _ => return Ok(None),
+    };
+    Ok(Some(if config.search_in_path {
+        query
+    } else {
+        format!("name : ({})", query)
+    }))
+}
 
-    root.sort_children();
-    root
+/// Builds the `WHERE` conditions of a full-text search and the values to
+/// bind, the `MATCH` query first.
+fn fts_conditions(
+    conn: &rusqlite::Connection,
+    query: String,
+    config: &SearchConfig,
+) -> Result<(String, Vec<Value>)> {
+    let (filters, values) = metadata_filter_sql(conn, config, 2)?;
+    Ok((
+        format!(
+            "rowid IN (SELECT rowid FROM files_fts WHERE files_fts MATCH ?1){}",
+            filters
+        ),
+        std::iter::once(Value::Text(query)).chain(values).collect(),
+    ))
 }
 
-/// Finds the common directory prefix for all search results.
+/// Translates a keyword into an FTS5 query of quoted words.
 ///
-/// Returns the deepest common directory shared by all paths.
-fn find_common_prefix(results: &[SearchResult]) -> PathBuf {
-    if results.is_empty() {
-        return PathBuf::from(".");
+/// The keyword is split like the indexed text, at anything but letters and
+/// digits, so operators and quotes in it are never interpreted. A word
+/// followed by `*` becomes a prefix query.
+///
+/// # Returns
+/// The query, or `None` if the keyword has no letters or digits
+fn fts_query(keyword: &str) -> Option<String> {
+    let mut terms = Vec::new();
+    let mut word = String::new();
+    // A trailing separator flushes the last word
+    for c in keyword.chars().chain([' ']) {
+        if c.is_alphanumeric() {
+            word.push(c);
+        } else if !word.is_empty() {
+            let prefix = if c == '*' { "*" } else { "" };
+            terms.push(format!("\"{}\"{}", word, prefix));
+            word.clear();
+        }
     }
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
 
-    if results.len() == 1 {
-        let path = PathBuf::from(&results[0].path);
-        return path.parent().unwrap_or(Path::new(".")).to_path_buf();
+/// Searches with `LIKE` patterns, the default matching mode.
+fn search_by_like(
+    db: &Database,
+    keyword: &str,
+    config: &SearchConfig,
+) -> Result<Vec<SearchResult>> {
+    if config.match_stem {
+        return search_by_stem(db, keyword, config).map(|results| apply_filters(results, config));
+    }
+    if folds_outside_sql(keyword, config) {
+        return search_by_folded_like(db, keyword, config)
+            .map(|results| apply_filters(results, config));
     }
 
-    // Start with the first path's parent directory
-    let first_path = PathBuf::from(&results[0].path);
-    let mut common = first_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    db.batch_operation_readonly(|conn| {
+        set_like_case_sensitivity(conn, config)?;
 
-    // Iterate through all results to find common prefix
-    for result in results.iter().skip(1) {
-        let path = PathBuf::from(&result.path);
-        let parent = path.parent().unwrap_or(Path::new("."));
+        let (conditions, values) = like_conditions(conn, keyword, config)?;
+        let query = format!(
+            "SELECT {} FROM files WHERE {} {} LIMIT {} OFFSET {}",
+            RESULT_COLUMNS,
+            conditions,
+            order_by_sql(config),
+            config.max_results,
+            config.offset
+        );
 
-        // Find common path between current common and this path
-        common = find_common_path(&common, parent);
+        let mut stmt = conn
+            .prepare(&query)
+            .context("Failed to prepare search query")?;
 
-        // If we've reduced to root or current dir, no point continuing
-        if common == Path::new(".") || common == Path::new("/") || common == Path::new("") {
-            break;
+        let rows = stmt
+            .query_map(params_from_iter(&values), SearchResult::from_row)
+            .context("Failed to execute search query")?;
+
+        let mut results = Vec::new();
+        for row in rows {
+            results.push(row?);
         }
-    }
 
-    common
+        Ok(results)
+    })
+    .map(|results| apply_filters(results, config))
 }
 
-/// Finds the common path between two paths.
-fn find_common_path(path1: &Path, path2: &Path) -> PathBuf {
-    let components1: Vec<_> = path1.components().collect();
-    let components2: Vec<_> = path2.components().collect();
-
-    let mut common = PathBuf::new();
-    let min_len = components1.len().min(components2.len());
-
-    for i in 0..min_len {
-        if components1[i] == components2[i] {
-            common.push(components1[i]);
+/// Searches like [`search_by_like`] for a keyword whose case SQLite cannot
+/// fold, see [`folds_outside_sql`].
+fn search_by_folded_like(
+    db: &Database,
+    keyword: &str,
+    config: &SearchConfig,
+) -> Result<Vec<SearchResult>> {
+    let target = fold_case(&keyword.replace('\\', "/"));
+    let matches = |text: &str| {
+        let text = fold_case(&text.replace('\\', "/"));
+        if config.subsequence {
+            let mut chars = text.chars();
+            target
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .all(|c| chars.any(|t| t == c))
         } else {
-            break;
+            text.contains(&target)
         }
-    }
+    };
 
-    if common.as_os_str().is_empty() {
-        PathBuf::from(".")
-    } else {
-        common
-    }
+    db.batch_operation_readonly(|conn| {
+        set_like_case_sensitivity(conn, config)?;
+
+        let (conditions, values) = like_conditions(conn, keyword, config)?;
+        let query = format!(
+            "SELECT {} FROM files WHERE {} {}",
+            RESULT_COLUMNS,
+            conditions,
+            order_by_sql(config)
+        );
+        let mut stmt = conn
+            .prepare(&query)
+            .context("Failed to prepare search query")?;
+        let mut rows = stmt
+            .query(params_from_iter(&values))
+            .context("Failed to execute search query")?;
+
+        let mut page = Page::new(config);
+        while !page.is_full()
+            && let Some(row) = rows.next()?
+        {
+            let result = SearchResult::from_row(row)?;
+            if matches(&result.name) || (config.search_in_path && matches(&result.path)) {
+                page.push(result);
+            }
+        }
+        Ok(page.results)
+    })
 }
 
-/// Inserts a file path into the tree structure.
-fn insert_path_into_tree(root: &mut TreeNode, target_path: &Path) {
-    let Ok(relative) = target_path.strip_prefix(&root.path) else {
-        // If strip_prefix fails, use the full path
-        insert_full_path_into_tree(root, target_path);
-        return;
+/// Builds the `WHERE` conditions of a `LIKE` search and the values to
+/// bind, the pattern first.
+fn like_conditions(
+    conn: &rusqlite::Connection,
+    keyword: &str,
+    config: &SearchConfig,
+) -> Result<(String, Vec<Value>)> {
+    let (matched, like_pattern) = like_condition(keyword, config, 1);
+    let (filters, values) = metadata_filter_sql(conn, config, 2)?;
+    Ok((
+        format!("{}{}", matched, filters),
+        std::iter::once(Value::Text(like_pattern))
+            .chain(values)
+            .collect(),
+    ))
+}
+
+/// Builds the condition matching one keyword with `LIKE`, without the
+/// metadata filters, and the pattern to bind to `?param`.
+fn like_condition(keyword: &str, config: &SearchConfig, param: usize) -> (String, String) {
+    // A path fragment like "2023/summer" should match paths indexed on any
+    // platform, so compare it against paths with separators normalized to '/'
+    let has_separator = keyword.contains(['/', '\\']);
+    let keyword = if has_separator {
+        keyword.replace('\\', "/")
+    } else {
+        keyword.to_string()
+    };
+    let path_column = if has_separator {
+        "REPLACE(path, '\\', '/')"
+    } else {
+        "path"
+    };
+    let (name_column, path_column) = if folds_sharp_s(&keyword, config) {
+        (fold_sharp_s_sql("name"), fold_sharp_s_sql(path_column))
+    } else {
+        ("name".to_string(), path_column.to_string())
     };
 
-    if relative == Path::new("") {
-        return;
+    let like_pattern = if config.subsequence {
+        subsequence_pattern(&keyword)
+    } else {
+        format!("%{}%", escape_like(&keyword))
+    };
+    let like_pattern = if folds_outside_sql(&keyword, config) {
+        loosen_for_folding(like_pattern, &keyword, config)
+    } else {
+        like_pattern
+    };
+    let matched = if config.search_in_path {
+        format!(
+            "({name_column} LIKE ?{param} ESCAPE '\\' OR {path_column} LIKE ?{param} ESCAPE '\\')"
+        )
+    } else {
+        format!("{name_column} LIKE ?{param} ESCAPE '\\'")
+    };
+    (matched, like_pattern)
+}
+
+/// Matches collected one page at a time, for searches that filter rows
+/// outside SQL: the first `offset` are skipped and at most `max_results`
+/// kept.
+struct Page {
+    results: Vec<SearchResult>,
+    to_skip: usize,
+    limit: usize,
+}
+
+impl Page {
+    fn new(config: &SearchConfig) -> Self {
+        Self {
+            results: Vec::new(),
+            to_skip: config.offset,
+            limit: config.max_results,
+        }
     }
 
-    let mut current = root;
-    for comp in relative.components() {
-        let part_str = comp.as_os_str().to_string_lossy().to_string();
-        let child_path = current.path.join(&part_str);
+    fn is_full(&self) -> bool {
+        self.results.len() >= self.limit
+    }
 
-        let child_index = current.children.iter().position(|c| c.path == child_path);
-        if let Some(idx) = child_index {
-            current = &mut current.children[idx];
+    fn push(&mut self, result: SearchResult) {
+        if self.to_skip > 0 {
+            self.to_skip -= 1;
         } else {
-            let new_node = TreeNode::new(part_str, child_path);
-            current.children.push(new_node);
-            let len = current.children.len();
-            current = &mut current.children[len - 1];
+            self.results.push(result);
         }
     }
 }
 
-/// Inserts a full file path into the tree structure (fallback method).
-fn insert_full_path_into_tree(root: &mut TreeNode, target_path: &Path) {
-    let mut current = root;
+/// Returns true if a case-insensitive search for `keyword` needs more than
+/// ASCII case folding, see [`fold_case`]: the keyword has non-ASCII
+/// characters, or an `ss` that may be a folded `ß`.
+fn needs_folding(keyword: &str, config: &SearchConfig) -> bool {
+    !config.case_sensitive && (!keyword.is_ascii() || folds_sharp_s(keyword, config))
+}
 
-    for comp in target_path.components() {
-        let part_str = comp.as_os_str().to_string_lossy().to_string();
-        let child_path = if current.path.as_os_str().is_empty() {
-            PathBuf::from(&part_str)
-        } else {
-            current.path.join(&part_str)
-        };
+/// Returns true if a case-insensitive search for an ASCII `keyword` should
+/// also find `ß` where the keyword has `ss`.
+///
+/// `LIKE` handles these itself on names and paths with `ß` spelled out,
+/// see [`fold_sharp_s_sql`], so common keywords such as `class` or `.css`
+/// stay on the SQL path.
+fn folds_sharp_s(keyword: &str, config: &SearchConfig) -> bool {
+    !config.case_sensitive && keyword.is_ascii() && keyword.to_ascii_lowercase().contains("ss")
+}
 
-        let child_index = current.children.iter().position(|c| c.name == part_str);
-        if let Some(idx) = child_index {
-            current = &mut current.children[idx];
+/// Spells `ß` and `ẞ` in a column out as `ss`, as [`fold_case`] does.
+fn fold_sharp_s_sql(column: &str) -> String {
+    format!("REPLACE(REPLACE({column}, 'ß', 'ss'), 'ẞ', 'ss')")
+}
+
+/// Returns true if a case-insensitive search for `keyword` needs case
+/// folding that SQLite cannot do.
+///
+/// `LIKE` only ignores the case of ASCII letters, so `CAFÉ` would miss
+/// `café` and `МОСКВА` would miss `москва`. For keywords with other
+/// characters, `LIKE` only narrows the rows down (see
+/// [`loosen_for_folding`]) and names and paths are compared with
+/// [`fold_case`] here.
+fn folds_outside_sql(keyword: &str, config: &SearchConfig) -> bool {
+    !config.case_sensitive && !keyword.is_ascii()
+}
+
+/// Folds the case of a name, path or keyword for comparison outside SQL.
+///
+/// Lowercasing alone leaves `ß` apart from `ss`, and turns a trailing `Σ`
+/// into the final form `ς`, which then misses `σ` inside a word. Both are
+/// folded here, so `STRASSE` matches `Straße` and `ΚΟΣ` matches `κοσμος`.
+fn fold_case(text: &str) -> String {
+    let lower = text.to_lowercase();
+    if lower.is_ascii() {
+        return lower;
+    }
+    let mut folded = String::with_capacity(lower.len());
+    for c in lower.chars() {
+        match c {
+            'ß' => folded.push_str("ss"),
+            'ς' => folded.push('σ'),
+            c => folded.push(c),
+        }
+    }
+    folded
+}
+
+/// Loosens the `LIKE` pattern built for `keyword` when the search folds
+/// case outside SQL: each non-ASCII character, and each `ss`, becomes `%`,
+/// so the pattern matches whatever case or spelling they have in a name.
+fn loosen_for_folding(pattern: String, keyword: &str, config: &SearchConfig) -> String {
+    if !needs_folding(keyword, config) {
+        return pattern;
+    }
+    let mut loosened = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        if !c.is_ascii() {
+            loosened.push('%');
+        } else if c.eq_ignore_ascii_case(&'s')
+            && chars
+                .peek()
+                .is_some_and(|next| next.eq_ignore_ascii_case(&'s'))
+        {
+            chars.next();
+            loosened.push('%');
         } else {
-            let new_node = TreeNode::new(part_str, child_path);
-            current.children.push(new_node);
-            let len = current.children.len();
-            current = &mut current.children[len - 1];
+            loosened.push(c);
         }
     }
+    loosened
 }
 
-/// Formats a tree node as a string with tree-style display.
+/// Makes `LIKE` on this connection follow `config.case_sensitive`.
 ///
-/// Uses box-drawing characters for a clean hierarchical view.
+/// SQLite's `LIKE` ignores ASCII case by default, which would let a
+/// case-sensitive search match `Report.txt` for `report`.
+fn set_like_case_sensitivity(conn: &rusqlite::Connection, config: &SearchConfig) -> Result<()> {
+    conn.pragma_update(None, "case_sensitive_like", config.case_sensitive)
+        .context("Failed to set LIKE case sensitivity")
+}
+
+/// Builds the SQL conditions for the owner, mode, timestamp, size and
+/// extension filters.
 ///
-/// # Arguments
-/// * `node` - Tree node to format
-/// * `prefix` - Current prefix for indentation
-/// * `is_last` - Whether this is the last child of its parent
+/// Rows without the filtered column, such as files indexed without
+/// metadata, never match an active filter.
 ///
-/// # Returns
-/// Formatted string representation
-pub fn format_tree_node(node: &TreeNode, prefix: &str, is_last: bool) -> String {
-    let mut output = String::new();
+/// The numbers are bound to `?first_param` and up, in the order of the
+/// returned values; the extensions are escaped literals and inlined.
+fn metadata_filter_sql(
+    conn: &rusqlite::Connection,
+    config: &SearchConfig,
+    first_param: usize,
+) -> Result<(String, Vec<Value>)> {
+    // Columns added after the first schema are looked up first, so that
+    // databases not yet upgraded match nothing instead of failing
+    let filters = [
+        (Some("owner"), "owner =", config.owner.map(Value::Integer)),
+        (
+            Some("mode"),
+            "(mode & 4095) =",
+            config.mode.map(Value::Integer),
+        ),
+        (None, "mtime >=", config.modified_after.map(Value::Real)),
+        (
+            Some("indexed_at"),
+            "indexed_at >=",
+            config.indexed_after.map(Value::Real),
+        ),
+        (None, "size >=", config.min_size.map(Value::Integer)),
+        (None, "size <=", config.max_size.map(Value::Integer)),
+    ];
 
-    let connector = if is_last { "└─ " } else { "├─ " };
-    let display_name = if node.is_leaf() {
-        node.name.clone()
-    } else {
-        format!("{}/", node.name)
-    };
+    let mut sql = String::new();
+    let mut values = Vec::new();
+    for (column, condition, value) in filters {
+        if let Some(value) = value {
+            if let Some(column) = column
+                && !has_column(conn, column)?
+            {
+                sql.push_str(" AND 0");
+                continue;
+            }
+            sql.push_str(&format!(
+                " AND {} ?{}",
+                condition,
+                first_param + values.len()
+            ));
+            values.push(value);
+        }
+    }
+    if !config.extensions.is_empty() {
+        let has_ext_column = has_column(conn, "ext")?;
+        sql.push_str(&extension_filter_sql(&config.extensions, has_ext_column));
+    }
+    Ok((sql, values))
+}
 
-    output.push_str(&format!("{}{}{}\n", prefix, connector, display_name));
+/// Builds the SQL condition keeping names that end in one of `extensions`.
+///
+/// `gz` matches `backup.tar.gz`. Names need at least one character before
+/// the dot, so dotfiles such as `.gz` and names without an extension never
+/// match. Single extensions are looked up in the indexed `ext` column;
+/// compound ones such as `tar.gz`, and every extension on databases not yet
+/// upgraded to have the column, fall back to a suffix `LIKE`. `lower()`
+/// folds ASCII only, like the extensions here, so the comparison ignores
+/// case whatever `case_sensitive_like` is set to.
+fn extension_filter_sql(extensions: &[String], has_ext_column: bool) -> String {
+    let quote = |text: &str| text.replace('\'', "''");
+    let extensions: Vec<String> = extensions
+        .iter()
+        .map(|ext| ext.trim().trim_start_matches('.').to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect();
+    let (simple, compound): (Vec<_>, Vec<_>) = extensions
+        .iter()
+        .partition(|ext| has_ext_column && !ext.contains('.'));
 
-    let new_prefix = format!("{}{}", prefix, if is_last { "   " } else { "│  " });
-    for (i, child) in node.children.iter().enumerate() {
-        let is_last_child = i == node.children.len() - 1;
-        output.push_str(&format_tree_node(child, &new_prefix, is_last_child));
+    let mut conditions: Vec<String> = compound
+        .iter()
+        .map(|ext| {
+            format!(
+                "lower(name) LIKE '_%.{}' ESCAPE '\\'",
+                quote(&escape_like(ext))
+            )
+        })
+        .collect();
+    if !simple.is_empty() {
+        let list: Vec<String> = simple
+            .iter()
+            .map(|ext| format!("'{}'", quote(ext)))
+            .collect();
+        conditions.push(format!("ext IN ({})", list.join(", ")));
+    }
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" AND ({})", conditions.join(" OR "))
     }
+}
 
-    output
+/// Builds the SQL `ORDER BY` clause for `config.sort_by`.
+fn order_by_sql(config: &SearchConfig) -> String {
+    config.sort_by.order_by_sql(config.descending)
 }
 
-/// Prints a tree structure to stdout.
+/// Builds a LIKE pattern matching the keyword's characters as a subsequence.
 ///
-/// Convenience function for displaying search results in tree format.
+/// `smr` becomes `%s%m%r%`, which matches `summer.jpg`. Whitespace in the
+/// keyword is ignored.
+fn subsequence_pattern(keyword: &str) -> String {
+    let mut pattern = String::from("%");
+    for c in keyword.chars().filter(|c| !c.is_whitespace()) {
+        pattern.push_str(&escape_like(c.encode_utf8(&mut [0; 4])));
+        pattern.push('%');
+    }
+    pattern
+}
+
+/// Returns true if the keyword contains glob wildcards.
+fn is_glob(keyword: &str) -> bool {
+    keyword.contains(['*', '?'])
+}
+
+/// Translates a glob into an anchored `LIKE` pattern matching at least
+/// everything the glob matches.
 ///
-/// # Arguments
-/// * `root` - Root node of the tree
-pub fn print_tree(root: &TreeNode) {
-    println!("{}", root.name);
-    for (i, child) in root.children.iter().enumerate() {
-        let is_last = i == root.children.len() - 1;
-        print!("{}", format_tree_node(child, "", is_last));
+/// `*`, `**` and `**/` become `%`, `?` and `[...]` become `_`, `{...}`
+/// becomes `%`, and everything else matches literally. `LIKE` cannot keep
+/// `*` within a path segment, so the result is only a prefilter.
+fn glob_pattern(keyword: &str) -> String {
+    let mut pattern = String::with_capacity(keyword.len());
+    let mut chars = keyword.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => {
+                // `**/` may also match no directory at all
+                if chars.next_if_eq(&'*').is_some() {
+                    chars.next_if_eq(&'/');
+                }
+                pattern.push('%');
+            }
+            '?' => pattern.push('_'),
+            '[' | '{' => {
+                let close = if c == '[' { ']' } else { '}' };
+                chars.by_ref().find(|&c| c == close);
+                pattern.push(if c == '[' { '_' } else { '%' });
+            }
+            c => pattern.push_str(&escape_like(c.encode_utf8(&mut [0; 4]))),
+        }
     }
+    pattern
 }
 
-/// Search across multiple databases
+/// Escapes `%`, `_` and `\` so a keyword matches literally in
+/// `LIKE ... ESCAPE '\'`.
+pub(crate) fn escape_like(keyword: &str) -> String {
+    let mut escaped = String::with_capacity(keyword.len());
+    for c in keyword.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Returns the stem of a filename (the name without its last extension).
 ///
-/// # Arguments
-/// * `db_paths` - Vector of database file paths
-/// * `keywords` - Vector of search keywords
-/// * `config` - Search configuration
+/// Dotfiles such as `.bashrc` are treated as having no extension.
+fn file_stem(name: &str) -> &str {
+    match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => name,
+    }
+}
+
+/// Searches for files whose stem equals the keyword.
 ///
-/// # Returns
-/// Vector of tuples (database_name, keyword, results) for each database and keyword
-pub fn search_multiple_databases(
-    db_paths: &[PathBuf],
-    keywords: &[String],
+/// Uses a `LIKE 'keyword%'` prefilter in SQL and compares the exact stem in Rust,
+/// so `IMG_1234` matches `IMG_1234.jpg` and `IMG_1234.cr2` but not `IMG_12345.jpg`.
+fn search_by_stem(
+    db: &Database,
+    keyword: &str,
     config: &SearchConfig,
-) -> Result<Vec<(String, String, Vec<SearchResult>)>> {
-    let mut all_results = Vec::new();
+) -> Result<Vec<SearchResult>> {
+    let keyword = keyword.trim();
+    let target = if config.case_sensitive {
+        keyword.to_string()
+    } else {
+        fold_case(keyword)
+    };
 
-    for db_path in db_paths {
-        let db_name = db_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
+    db.batch_operation_readonly(|conn| {
+        set_like_case_sensitivity(conn, config)?;
 
-        let db = Database::new(db_path);
+        let (filters, values) = metadata_filter_sql(conn, config, 2)?;
+        let query = format!(
+            "SELECT {} FROM files WHERE name LIKE ?1 ESCAPE '\\'{} {}",
+            RESULT_COLUMNS,
+            filters,
+            order_by_sql(config)
+        );
+        let mut stmt = conn
+            .prepare(&query)
+            .context("Failed to prepare stem search query")?;
 
-        for keyword in keywords {
-            let results = search_by_keyword(&db, keyword, config)?;
-            all_results.push((db_name.clone(), keyword.clone(), results));
+        let like_pattern =
+            loosen_for_folding(format!("{}%", escape_like(keyword)), keyword, config);
+        let rows = stmt
+            .query_map(
+                params_from_iter(std::iter::once(Value::Text(like_pattern)).chain(values)),
+                SearchResult::from_row,
+            )
+            .context("Failed to execute stem search query")?;
+
+        let mut page = Page::new(config);
+        for row in rows {
+            let result = row?;
+            let stem = file_stem(&result.name);
+            let matches = if config.case_sensitive {
+                stem == target
+            } else {
+                fold_case(stem) == target
+            };
+
+            if matches {
+                page.push(result);
+                if page.is_full() {
+                    break;
+                }
+            }
         }
-    }
 
-    Ok(all_results)
+        Ok(page.results)
+    })
 }
 
-/// Search in a specific database from multiple available databases
+/// Searches for files matching multiple keywords.
+///
+/// Each keyword is searched independently, and results are combined.
+/// With [`KeywordCombine::And`] or [`KeywordCombine::Or`] in
+/// `config.combine`, there is a single group instead, see
+/// [`search_combined`].
 ///
 /// # Arguments
-/// * `db_paths` - Vector of available database file paths
-/// * `db_name` - Name of the database to search in (or "all" for all databases)
+/// * `db` - Database instance to search in
 /// * `keywords` - Vector of search keywords
 /// * `config` - Search configuration
 ///
 /// # Returns
-/// Vector of tuples (database_name, keyword, results)
-pub fn search_in_selected_database(
-    db_paths: &[PathBuf],
-    db_name: &str,
+/// Vector of tuples (keyword, results) for each keyword
+pub fn search_multiple_keywords(
+    db: &Database,
     keywords: &[String],
     config: &SearchConfig,
-) -> Result<Vec<(String, String, Vec<SearchResult>)>> {
-    if db_name == "all" {
-        return search_multiple_databases(db_paths, keywords, config);
+) -> Result<Vec<(String, Vec<SearchResult>)>> {
+    if config.combine != KeywordCombine::Separate {
+        let results = search_combined(db, keywords, config)?;
+        return Ok(vec![(config.combine.label(keywords), results)]);
     }
 
-    // Find the specific database
-    let db_path = db_paths
-        .iter()
-        .find(|p| {
-            p.file_name()
-                .and_then(|n| n.to_str())
-                .map(|n| n == db_name)
-                .unwrap_or(false)
-        })
-        .ok_or_else(|| anyhow::anyhow!("数据库不存在: {}", db_name))?;
-
-    let db = Database::new(db_path);
-    let mut results = Vec::new();
+    let mut all_results = Vec::new();
 
     for keyword in keywords {
-        let search_results = search_by_keyword(&db, keyword, config)?;
-        results.push((db_name.to_string(), keyword.clone(), search_results));
+        let results = search_by_keyword(db, keyword, config)?;
+        all_results.push((keyword.clone(), results));
     }
 
-    Ok(results)
+    Ok(all_results)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::db::Index;
-    use tempfile::TempDir;
+/// Searches for files matching every keyword, or any keyword, as
+/// `config.combine` says.
+///
+/// Substring `AND` searches run as a single query with one `LIKE` per
+/// keyword. Other searches run once per keyword and merge the results,
+/// each file listed once; for `AND` this means finding every match of
+/// every keyword first.
+pub fn search_combined(
+    db: &Database,
+    keywords: &[String],
+    config: &SearchConfig,
+) -> Result<Vec<SearchResult>> {
+    let terms = config.combine.terms(keywords);
+    if terms.is_empty() {
+        return Ok(Vec::new());
+    }
 
-    fn create_test_db_with_data() -> (TempDir, Database) {
-        let temp_dir = TempDir::new().unwrap();
-        let db_path = temp_dir.path().join("test.reminex.db");
-        let db = Database::init(&db_path).unwrap();
+    let all_in_sql = db.batch_operation_readonly(|conn| {
+        let Some((conditions, patterns)) = and_like_conditions(conn, &terms, config)? else {
+            return Ok(None);
+        };
+        set_like_case_sensitivity(conn, config)?;
+        let query = format!(
+            "SELECT {} FROM files WHERE {} {} LIMIT {} OFFSET {}",
+            RESULT_COLUMNS,
+            conditions,
+            order_by_sql(config),
+            config.max_results,
+            config.offset
+        );
+        let mut stmt = conn
+            .prepare(&query)
+            .context("Failed to prepare search query")?;
+        let results = stmt
+            .query_map(params_from_iter(&patterns), SearchResult::from_row)
+            .context("Failed to execute search query")?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(Some(results))
+    })?;
+    if let Some(results) = all_in_sql {
+        return Ok(apply_filters(results, config));
+    }
 
-        // Insert test data
-        let indices = vec![
-            Index::new(
-                "Z:\\photos\\2023\\summer.jpg".to_string(),
-                "summer.jpg".to_string(),
-            ),
-            Index::new(
-                "Z:\\photos\\2023\\winter.jpg".to_string(),
-                "winter.jpg".to_string(),
-            ),
-            Index::new(
-                "Z:\\documents\\report.pdf".to_string(),
-                "report.pdf".to_string(),
-            ),
+    let mut results = merge_keyword_matches(db, &terms, config)?;
+    results.drain(..config.offset.min(results.len()));
+    results.truncate(config.max_results);
+    Ok(results)
+}
+
+/// Like [`search_combined`], also returning how many files match in total,
+/// beyond `offset` and `max_results`.
+pub fn search_combined_counted(
+    db: &Database,
+    keywords: &[String],
+    config: &SearchConfig,
+) -> Result<(usize, Vec<SearchResult>)> {
+    let results = search_combined(db, keywords, config)?;
+    let terms = config.combine.terms(keywords);
+    if terms.is_empty() {
+        return Ok((0, results));
+    }
+    if let Some(total) = total_from_page(config, results.len()) {
+        return Ok((total, results));
+    }
+
+    let counted_in_sql = db.batch_operation_readonly(|conn| {
+        if !config.include_filters.is_empty() || !config.exclude_filters.is_empty() {
+            return Ok(None);
+        }
+        let Some((conditions, patterns)) = and_like_conditions(conn, &terms, config)? else {
+            return Ok(None);
+        };
+        set_like_case_sensitivity(conn, config)?;
+        let count: i64 = conn
+            .query_row(
+                &format!("SELECT COUNT(*) FROM files WHERE {}", conditions),
+                params_from_iter(&patterns),
+                |row| row.get(0),
+            )
+            .context("Failed to count search matches")?;
+        Ok(Some(count as usize))
+    })?;
+    let total = match counted_in_sql {
+        Some(total) => total,
+        None => {
+            let unlimited = SearchConfig {
+                offset: 0,
+                max_results: UNLIMITED,
+                ..config.clone()
+            };
+            merge_keyword_matches(db, &terms, &unlimited)?.len()
+        }
+    };
+    Ok((total, results))
+}
+
+/// Builds the `WHERE` conditions of an `AND` search matching every term
+/// with `LIKE`, and the values to bind to `?1`, `?2` and so on, the
+/// patterns first.
+///
+/// # Returns
+/// The conditions, or `None` if the search is not an `AND` search or some
+/// term has to be matched outside SQL
+fn and_like_conditions(
+    conn: &rusqlite::Connection,
+    terms: &[&str],
+    config: &SearchConfig,
+) -> Result<Option<(String, Vec<Value>)>> {
+    let in_sql = config.combine == KeywordCombine::And
+        && !config.match_stem
+        && !config.use_fts
+        && match config.search_mode {
+            SearchMode::Substring => true,
+            SearchMode::Glob => !terms.iter().any(|term| is_glob(term)),
+            SearchMode::Regex | SearchMode::Fuzzy => false,
+        }
+        && !terms.iter().any(|term| folds_outside_sql(term, config));
+    if !in_sql {
+        return Ok(None);
+    }
+
+    let (conditions, patterns): (Vec<String>, Vec<String>) = terms
+        .iter()
+        .enumerate()
+        .map(|(i, term)| like_condition(term, config, i + 1))
+        .unzip();
+    let (filters, values) = metadata_filter_sql(conn, config, terms.len() + 1)?;
+    Ok(Some((
+        format!("{}{}", conditions.join(" AND "), filters),
+        patterns
+            .into_iter()
+            .map(Value::Text)
+            .chain(values)
+            .collect(),
+    )))
+}
+
+/// Searches each term on its own and merges the results as
+/// `config.combine` says, in `sort_by` order.
+///
+/// Files matched by several terms are listed once, with their best
+/// [`SearchResult::score`] for `OR` and their worst for `AND`. An `OR`
+/// search reads each term's first `offset + max_results` matches, which
+/// include the first `offset + max_results` of the union; an `AND` search
+/// reads every match of every term.
+fn merge_keyword_matches(
+    db: &Database,
+    terms: &[&str],
+    config: &SearchConfig,
+) -> Result<Vec<SearchResult>> {
+    let and = config.combine == KeywordCombine::And;
+    let per_term = SearchConfig {
+        offset: 0,
+        max_results: if and {
+            UNLIMITED
+        } else {
+            config.offset.saturating_add(config.max_results)
+        },
+        combine: KeywordCombine::Separate,
+        ..config.clone()
+    };
+
+    let mut merged: Vec<SearchResult> = Vec::new();
+    for (i, term) in terms.iter().enumerate() {
+        let results = search_by_keyword(db, term, &per_term)?;
+        if and && i > 0 {
+            let scores: HashMap<&str, Option<i64>> = results
+                .iter()
+                .map(|result| (result.path.as_str(), result.score))
+                .collect();
+            merged.retain_mut(|result| match scores.get(result.path.as_str()) {
+                Some(&score) => {
+                    result.score = result.score.min(score);
+                    true
+                }
+                None => false,
+            });
+        } else {
+            let mut seen: HashMap<String, usize> = merged
+                .iter()
+                .enumerate()
+                .map(|(i, result)| (result.path.clone(), i))
+                .collect();
+            for result in results {
+                match seen.get(&result.path) {
+                    Some(&i) => merged[i].score = merged[i].score.max(result.score),
+                    None => {
+                        seen.insert(result.path.clone(), merged.len());
+                        merged.push(result);
+                    }
+                }
+            }
+        }
+    }
+
+    config.sort_by.sort(&mut merged, config.descending);
+    Ok(merged)
+}
+
+/// Searches databases from user input string.
+///
+/// Convenience function that combines keyword parsing and searching.
+///
+/// # Arguments
+/// * `db` - Database instance to search in
+/// * `input` - Raw user input (may contain multiple keywords)
+/// * `config` - Search configuration
+///
+/// # Returns
+/// Vector of tuples (keyword, results) for each parsed keyword
+pub fn search_from_input(
+    db: &Database,
+    input: &str,
+    config: &SearchConfig,
+) -> Result<Vec<(String, Vec<SearchResult>)>> {
+    let keywords = parse_search_keywords(input);
+
+    if keywords.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    search_multiple_keywords(db, &keywords, config)
+}
+
+/// A display window over an already limited and ordered result list.
+///
+/// Unlike [`SearchConfig::max_results`], which caps what the database
+/// returns, this only trims what is shown. Building a tree from the window
+/// trims leaves while keeping their directories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultWindow {
+    /// The first N items, like `head`
+    Head(usize),
+    /// The last N items, like `tail`
+    Tail(usize),
+}
+
+impl ResultWindow {
+    /// Returns the part of `items` inside the window.
+    pub fn apply<T>(self, items: &[T]) -> &[T] {
+        match self {
+            ResultWindow::Head(n) => &items[..n.min(items.len())],
+            ResultWindow::Tail(n) => &items[items.len().saturating_sub(n)..],
+        }
+    }
+}
+
+/// How [`build_tree_with_root`] chooses the directory the tree starts at.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TreeRootStrategy {
+    /// Deepest directory shared by all results (a single result uses its parent)
+    #[default]
+    Auto,
+    /// No collapsing: full paths from the filesystem root
+    None,
+    /// A user-specified root; results outside it keep their full path
+    Fixed(PathBuf),
+}
+
+/// Builds a tree structure from search results.
+///
+/// Automatically identifies the common prefix path from all results.
+///
+/// # Arguments
+/// * `results` - Search results to build tree from
+/// * `root_name` - Display name for root node (e.g., "搜索结果")
+///
+/// # Returns
+/// Root TreeNode containing the hierarchical structure
+pub fn build_tree(results: &[SearchResult], root_name: &str) -> TreeNode {
+    build_tree_with_root(results, root_name, &TreeRootStrategy::Auto)
+}
+
+/// Builds a tree structure from search results with an explicit root strategy.
+///
+/// # Arguments
+/// * `results` - Search results to build tree from
+/// * `root_name` - Display name for root node (e.g., "搜索结果")
+/// * `strategy` - How much of the leading path to hide under the root
+///
+/// # Returns
+/// Root TreeNode containing the hierarchical structure
+pub fn build_tree_with_root(
+    results: &[SearchResult],
+    root_name: &str,
+    strategy: &TreeRootStrategy,
+) -> TreeNode {
+    if results.is_empty() {
+        return TreeNode::new(root_name.to_string(), PathBuf::new());
+    }
+
+    let mut root = match strategy {
+        TreeRootStrategy::Auto => {
+            let common_prefix = find_common_prefix(results);
+            TreeNode::new(
+                format!("{} ({})", root_name, common_prefix.display()),
+                common_prefix,
+            )
+        }
+        TreeRootStrategy::None => TreeNode::new(root_name.to_string(), PathBuf::new()),
+        TreeRootStrategy::Fixed(root) => {
+            TreeNode::new(format!("{} ({})", root_name, root.display()), root.clone())
+        }
+    };
+
+    for result in results {
+        if let Some(leaf) = insert_path_into_tree(&mut root, &PathBuf::from(&result.path)) {
+            leaf.size = result.size;
+            leaf.mtime = result.mtime;
+        }
+    }
+
+    root.sort_children();
+    root
+}
+
+/// Finds the common directory prefix for all search results.
+///
+/// Returns the deepest common directory shared by all paths.
+fn find_common_prefix(results: &[SearchResult]) -> PathBuf {
+    if results.is_empty() {
+        return PathBuf::from(".");
+    }
+
+    if results.len() == 1 {
+        let path = PathBuf::from(&results[0].path);
+        return path.parent().unwrap_or(Path::new(".")).to_path_buf();
+    }
+
+    // Start with the first path's parent directory
+    let first_path = PathBuf::from(&results[0].path);
+    let mut common = first_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+
+    // Iterate through all results to find common prefix
+    for result in results.iter().skip(1) {
+        let path = PathBuf::from(&result.path);
+        let parent = path.parent().unwrap_or(Path::new("."));
+
+        // Find common path between current common and this path
+        common = find_common_path(&common, parent);
+
+        // If we've reduced to root or current dir, no point continuing
+        if common == Path::new(".") || common == Path::new("/") || common == Path::new("") {
+            break;
+        }
+    }
+
+    common
+}
+
+/// Finds the common path between two paths.
+fn find_common_path(path1: &Path, path2: &Path) -> PathBuf {
+    let components1: Vec<_> = path1.components().collect();
+    let components2: Vec<_> = path2.components().collect();
+
+    let mut common = PathBuf::new();
+    let min_len = components1.len().min(components2.len());
+
+    for i in 0..min_len {
+        if components1[i] == components2[i] {
+            common.push(components1[i]);
+        } else {
+            break;
+        }
+    }
+
+    if common.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        common
+    }
+}
+
+/// Inserts a file path into the tree structure.
+///
+/// # Returns
+/// The node for the path, or `None` if it is the root itself
+fn insert_path_into_tree<'a>(
+    root: &'a mut TreeNode,
+    target_path: &Path,
+) -> Option<&'a mut TreeNode> {
+    let Ok(relative) = target_path.strip_prefix(&root.path) else {
+        // If strip_prefix fails, use the full path
+        return Some(insert_full_path_into_tree(root, target_path));
+    };
+
+    if relative == Path::new("") {
+        return None;
+    }
+
+    let mut current = root;
+    for comp in relative.components() {
+        let part_str = comp.as_os_str().to_string_lossy().to_string();
+        let child_path = current.path.join(&part_str);
+
+        let child_index = current.children.iter().position(|c| c.path == child_path);
+        if let Some(idx) = child_index {
+            current = &mut current.children[idx];
+        } else {
+            let new_node = TreeNode::new(part_str, child_path);
+            current.children.push(new_node);
+            let len = current.children.len();
+            current = &mut current.children[len - 1];
+        }
+    }
+    Some(current)
+}
+
+/// Inserts a full file path into the tree structure (fallback method).
+///
+/// # Returns
+/// The node for the path
+fn insert_full_path_into_tree<'a>(root: &'a mut TreeNode, target_path: &Path) -> &'a mut TreeNode {
+    let mut current = root;
+
+    for comp in target_path.components() {
+        let part_str = comp.as_os_str().to_string_lossy().to_string();
+        let child_path = if current.path.as_os_str().is_empty() {
+            PathBuf::from(&part_str)
+        } else {
+            current.path.join(&part_str)
+        };
+
+        let child_index = current.children.iter().position(|c| c.name == part_str);
+        if let Some(idx) = child_index {
+            current = &mut current.children[idx];
+        } else {
+            let new_node = TreeNode::new(part_str, child_path);
+            current.children.push(new_node);
+            let len = current.children.len();
+            current = &mut current.children[len - 1];
+        }
+    }
+    current
+}
+
+/// Characters used to draw tree connectors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TreeStyle {
+    /// Box-drawing characters: `├─`, `└─`, `│`
+    #[default]
+    Unicode,
+    /// Plain ASCII for terminals without Unicode: `|--`, `` `-- ``, `|`
+    Ascii,
+}
+
+impl TreeStyle {
+    /// Picks a style from the locale environment variables.
+    ///
+    /// On Unix the first non-empty of `LC_ALL`, `LC_CTYPE` and `LANG` must
+    /// name a UTF-8 charset, otherwise (including the `C` locale) ASCII is
+    /// used. Windows consoles are written through the wide-character API,
+    /// so Unicode is kept there unless a locale variable says otherwise.
+    pub fn detect() -> Self {
+        Self::detect_from(|name| std::env::var(name).ok(), cfg!(windows))
+    }
+
+    fn detect_from(var: impl Fn(&str) -> Option<String>, windows: bool) -> Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .filter_map(|name| var(name))
+            .find(|value| !value.is_empty());
+
+        match locale {
+            Some(locale) => {
+                let locale = locale.to_ascii_lowercase();
+                if locale.contains("utf-8") || locale.contains("utf8") {
+                    TreeStyle::Unicode
+                } else {
+                    TreeStyle::Ascii
+                }
+            }
+            None if windows => TreeStyle::Unicode,
+            None => TreeStyle::Ascii,
+        }
+    }
+
+    /// Returns the (branch, last branch, continuation, blank) prefixes.
+    fn connectors(self) -> [&'static str; 4] {
+        match self {
+            TreeStyle::Unicode => ["├─ ", "└─ ", "│  ", "   "],
+            TreeStyle::Ascii => ["|-- ", "`-- ", "|   ", "    "],
+        }
+    }
+}
+
+/// Formats a tree node as a string with tree-style display.
+///
+/// Uses box-drawing characters for a clean hierarchical view.
+///
+/// # Arguments
+/// * `node` - Tree node to format
+/// * `prefix` - Current prefix for indentation
+/// * `is_last` - Whether this is the last child of its parent
+///
+/// # Returns
+/// Formatted string representation
+pub fn format_tree_node(node: &TreeNode, prefix: &str, is_last: bool) -> String {
+    format_tree_node_with_style(node, prefix, is_last, TreeStyle::Unicode)
+}
+
+/// Formats a tree node like [`format_tree_node`], drawing connectors in `style`.
+pub fn format_tree_node_with_style(
+    node: &TreeNode,
+    prefix: &str,
+    is_last: bool,
+    style: TreeStyle,
+) -> String {
+    let mut output = String::new();
+
+    let [branch, last_branch, continuation, blank] = style.connectors();
+    let connector = if is_last { last_branch } else { branch };
+    let display_name = if node.is_leaf() {
+        node.name.clone()
+    } else {
+        format!("{}/", node.name)
+    };
+
+    output.push_str(&format!("{}{}{}\n", prefix, connector, display_name));
+
+    let new_prefix = format!("{}{}", prefix, if is_last { blank } else { continuation });
+    for (i, child) in node.children.iter().enumerate() {
+        let is_last_child = i == node.children.len() - 1;
+        output.push_str(&format_tree_node_with_style(
+            child,
+            &new_prefix,
+            is_last_child,
+            style,
+        ));
+    }
+
+    output
+}
+
+/// Prints a tree structure to stdout.
+///
+/// Convenience function for displaying search results in tree format.
+///
+/// # Arguments
+/// * `root` - Root node of the tree
+pub fn print_tree(root: &TreeNode) {
+    print_tree_with_style(root, TreeStyle::Unicode);
+}
+
+/// Prints a tree structure to stdout, drawing connectors in `style`.
+pub fn print_tree_with_style(root: &TreeNode, style: TreeStyle) {
+    println!("{}", root.name);
+    for (i, child) in root.children.iter().enumerate() {
+        let is_last = i == root.children.len() - 1;
+        print!("{}", format_tree_node_with_style(child, "", is_last, style));
+    }
+}
+
+/// Search across multiple databases
+///
+/// # Arguments
+/// * `db_paths` - Vector of database file paths
+/// * `keywords` - Vector of search keywords
+/// * `config` - Search configuration
+///
+/// # Returns
+/// Vector of tuples (database_name, keyword, results) for each database and keyword
+pub fn search_multiple_databases(
+    db_paths: &[PathBuf],
+    keywords: &[String],
+    config: &SearchConfig,
+) -> Result<Vec<(String, String, Vec<SearchResult>)>> {
+    let mut all_results = Vec::new();
+
+    for db_path in db_paths {
+        let db_name = db_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        let db = Database::new(db_path);
+
+        for (keyword, results) in search_multiple_keywords(&db, keywords, config)? {
+            all_results.push((db_name.clone(), keyword, results));
+        }
+    }
+
+    Ok(all_results)
+}
+
+/// Maximum number of distinct filenames sampled when building suggestions
+const SUGGESTION_SAMPLE: usize = 100_000;
+
+/// Computes the Levenshtein edit distance between two strings, by character.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    edit_distance(a, b, false)
+}
+
+/// Counts the character edits turning `a` into `b`.
+///
+/// With `transpositions`, swapping two adjacent characters is one edit
+/// rather than two, giving the optimal string alignment distance used by
+/// [`fuzzy_score`].
+fn edit_distance(a: &str, b: &str, transpositions: bool) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Rows i - 2, i - 1 and i of the distance table
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        curr[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            curr[j] = (prev[j - 1] + cost).min(prev[j] + 1).min(curr[j - 1] + 1);
+            if transpositions && i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                curr[j] = curr[j].min(before[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before, &mut prev);
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+/// Suggests filename tokens close to a keyword that found nothing.
+///
+/// Filenames are split into alphanumeric tokens, and tokens within a small
+/// edit distance of the keyword (1 for short keywords, up to 3 for long
+/// ones) are returned closest first. Only a sample of distinct names is
+/// read, so this stays fast on large databases.
+///
+/// # Arguments
+/// * `db` - Database to draw suggestions from
+/// * `keyword` - Keyword that returned no results
+/// * `max_suggestions` - Maximum number of suggestions to return
+pub fn suggest_keywords(
+    db: &Database,
+    keyword: &str,
+    max_suggestions: usize,
+) -> Result<Vec<String>> {
+    let keyword = keyword.trim().to_lowercase();
+    let keyword_len = keyword.chars().count();
+    if keyword_len == 0 || max_suggestions == 0 {
+        return Ok(Vec::new());
+    }
+    let max_distance = (keyword_len / 3).clamp(1, 3);
+
+    let names: Vec<String> = db.batch_operation_readonly(|conn| {
+        let mut stmt = conn
+            .prepare("SELECT DISTINCT name FROM files LIMIT ?1")
+            .context("Failed to prepare suggestion query")?;
+        let names = stmt
+            .query_map(params![SUGGESTION_SAMPLE as i64], |row| row.get(0))
+            .context("Failed to execute suggestion query")?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(names)
+    })?;
+
+    let mut seen = std::collections::HashSet::new();
+    let mut candidates = Vec::new();
+    for name in &names {
+        for token in name
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty())
+        {
+            let token_len = token.chars().count();
+            if token_len.abs_diff(keyword_len) > max_distance || !seen.insert(token.to_string()) {
+                continue;
+            }
+
+            let distance = levenshtein(&keyword, token);
+            if distance > 0 && distance <= max_distance {
+                candidates.push((distance, token.to_string()));
+            }
+        }
+    }
+
+    candidates.sort();
+    Ok(candidates
+        .into_iter()
+        .take(max_suggestions)
+        .map(|(_, token)| token)
+        .collect())
+}
+
+/// Results as (database_name, keyword, results) tuples
+pub type DatabaseResults = Vec<(String, String, Vec<SearchResult>)>;
+
+/// Results as (database_name, keyword, total, results) tuples, see
+/// [`search_in_selected_database_counted`]
+pub type CountedDatabaseResults = Vec<(String, String, usize, Vec<SearchResult>)>;
+
+/// Removes keywords without hits from search results.
+///
+/// Searches return one entry per keyword and database even when nothing
+/// matched; human-facing output usually drops those, while machine output
+/// keeps them as `count: 0` entries for stable parsing.
+pub fn drop_empty_keywords(results: &mut DatabaseResults) {
+    results.retain(|(_, _, items)| !items.is_empty());
+}
+
+/// A relaxation applied to broaden a search that found too few results.
+///
+/// Levels are tried in declaration order, each keeping the previous ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relaxation {
+    /// The search as configured
+    None,
+    /// Case-insensitive matching
+    IgnoreCase,
+    /// Match against the full path, not only the filename (disables stem matching)
+    IncludePath,
+    /// Subsequence matching of the keyword's characters, see
+    /// [`SearchConfig::subsequence`]
+    Subsequence,
+}
+
+impl Relaxation {
+    /// All levels in the order they are tried.
+    pub const ORDER: [Relaxation; 4] = [
+        Relaxation::None,
+        Relaxation::IgnoreCase,
+        Relaxation::IncludePath,
+        Relaxation::Subsequence,
+    ];
+
+    /// Human-readable description of the level.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Relaxation::None => "精确匹配",
+            Relaxation::IgnoreCase => "忽略大小写",
+            Relaxation::IncludePath => "匹配完整路径",
+            Relaxation::Subsequence => "子序列匹配",
+        }
+    }
+
+    /// Applies this level on top of `config`.
+    ///
+    /// Returns `None` if the level would not change the search, so it can be skipped.
+    fn apply(&self, config: &SearchConfig) -> Option<SearchConfig> {
+        let mut relaxed = config.clone();
+        match self {
+            Relaxation::None => return Some(relaxed),
+            Relaxation::IgnoreCase => relaxed.case_sensitive = false,
+            Relaxation::IncludePath => {
+                relaxed.search_in_path = true;
+                relaxed.match_stem = false;
+            }
+            Relaxation::Subsequence => relaxed.subsequence = true,
+        }
+
+        let changed = relaxed.case_sensitive != config.case_sensitive
+            || relaxed.search_in_path != config.search_in_path
+            || relaxed.match_stem != config.match_stem
+            || relaxed.subsequence != config.subsequence;
+        changed.then_some(relaxed)
+    }
+}
+
+/// Searches with progressively relaxed matching until enough results are found.
+///
+/// Tries each [`Relaxation`] in order, cumulatively, and stops at the first
+/// level whose total result count reaches `min_results`. If no level does,
+/// the broadest level's results are returned.
+///
+/// # Returns
+/// The relaxation level that produced the results, and the results
+pub fn search_with_min_results(
+    db_paths: &[PathBuf],
+    db_name: &str,
+    keywords: &[String],
+    config: &SearchConfig,
+    min_results: usize,
+) -> Result<(Relaxation, DatabaseResults)> {
+    let mut current = config.clone();
+    let mut last = None;
+
+    for level in Relaxation::ORDER {
+        let Some(relaxed) = level.apply(&current) else {
+            continue;
+        };
+        current = relaxed;
+
+        let results = search_in_selected_database(db_paths, db_name, keywords, &current)?;
+        let total: usize = results.iter().map(|(_, _, items)| items.len()).sum();
+        if total >= min_results {
+            return Ok((level, results));
+        }
+        last = Some((level, results));
+    }
+
+    // The unrelaxed level always runs, so `last` is set
+    Ok(last.expect("at least one search level runs"))
+}
+
+/// Search in a specific database from multiple available databases
+///
+/// # Arguments
+/// * `db_paths` - Vector of available database file paths
+/// * `db_name` - Name of the database to search in (or "all" for all databases)
+/// * `keywords` - Vector of search keywords
+/// * `config` - Search configuration
+///
+/// # Returns
+/// Vector of tuples (database_name, keyword, results)
+pub fn search_in_selected_database(
+    db_paths: &[PathBuf],
+    db_name: &str,
+    keywords: &[String],
+    config: &SearchConfig,
+) -> Result<Vec<(String, String, Vec<SearchResult>)>> {
+    if db_name == "all" {
+        return search_multiple_databases(db_paths, keywords, config);
+    }
+
+    let db = Database::new(find_database(db_paths, db_name)?);
+    let mut results = Vec::new();
+
+    for (keyword, search_results) in search_multiple_keywords(&db, keywords, config)? {
+        results.push((db_name.to_string(), keyword, search_results));
+    }
+
+    Ok(results)
+}
+
+/// Like [`search_in_selected_database`], with each keyword's total match
+/// count from [`search_by_keyword_counted`].
+///
+/// # Returns
+/// Vector of tuples (database_name, keyword, total, results)
+pub fn search_in_selected_database_counted(
+    db_paths: &[PathBuf],
+    db_name: &str,
+    keywords: &[String],
+    config: &SearchConfig,
+) -> Result<CountedDatabaseResults> {
+    let selected = if db_name == "all" {
+        db_paths.iter().collect()
+    } else {
+        vec![find_database(db_paths, db_name)?]
+    };
+
+    let mut results = Vec::new();
+    for db_path in selected {
+        let name = db_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        let db = Database::new(db_path);
+        if config.combine != KeywordCombine::Separate {
+            let (total, items) = search_combined_counted(&db, keywords, config)?;
+            results.push((name, config.combine.label(keywords), total, items));
+            continue;
+        }
+        for keyword in keywords {
+            let (total, items) = search_by_keyword_counted(&db, keyword, config)?;
+            results.push((name.clone(), keyword.clone(), total, items));
+        }
+    }
+    Ok(results)
+}
+
+/// Finds the database file named `db_name` among `db_paths`.
+fn find_database<'a>(db_paths: &'a [PathBuf], db_name: &str) -> Result<&'a PathBuf> {
+    db_paths
+        .iter()
+        .find(|p| {
+            p.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n == db_name)
+                .unwrap_or(false)
+        })
+        .ok_or_else(|| anyhow::anyhow!("数据库不存在: {}", db_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Index;
+    use tempfile::TempDir;
+
+    fn create_test_db_with_data() -> (TempDir, Database) {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("test.reminex.db");
+        let db = Database::init(&db_path).unwrap();
+
+        // Insert test data
+        let indices = vec![
+            Index::new(
+                "Z:\\photos\\2023\\summer.jpg".to_string(),
+                "summer.jpg".to_string(),
+            ),
+            Index::new(
+                "Z:\\photos\\2023\\winter.jpg".to_string(),
+                "winter.jpg".to_string(),
+            ),
+            Index::new(
+                "Z:\\documents\\report.pdf".to_string(),
+                "report.pdf".to_string(),
+            ),
+            Index::new(
+                "Z:\\videos\\summer_vacation.mp4".to_string(),
+                "summer_vacation.mp4".to_string(),
+            ),
+            Index::new(
+                "Z:\\music\\summer_hits.mp3".to_string(),
+                "summer_hits.mp3".to_string(),
+            ),
+        ];
+        db.add_idxs(&indices).unwrap();
+
+        (temp_dir, db)
+    }
+
+    #[test]
+    fn test_parse_search_keywords() {
+        assert_eq!(
+            parse_search_keywords("photo;video;music"),
+            vec!["photo", "video", "music"]
+        );
+
+        // Test with space in keyword (should not split)
+        assert_eq!(
+            parse_search_keywords("my photo,video music"),
+            vec!["my photo", "video music"]
+        );
+
+        assert_eq!(
+            parse_search_keywords("photo; video, music"),
+            vec!["photo", "video", "music"]
+        );
+
+        assert_eq!(
+            parse_search_keywords("photo；video，music"),
+            vec!["photo", "video", "music"]
+        );
+
+        assert_eq!(
+            parse_search_keywords("  photo  ;  video  "),
+            vec!["photo", "video"]
+        );
+
+        assert_eq!(parse_search_keywords(""), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_parse_search_keywords_with_custom_delimiters() {
+        // Test with custom delimiter '|'
+        assert_eq!(
+            parse_search_keywords_with_delimiters("photo|video|music", &['|']),
+            vec!["photo", "video", "music"]
+        );
+
+        // Test with multiple custom delimiters
+        assert_eq!(
+            parse_search_keywords_with_delimiters("photo|video;music", &['|', ';']),
+            vec!["photo", "video", "music"]
+        );
+
+        // Test that spaces are NOT delimiters when not specified
+        assert_eq!(
+            parse_search_keywords_with_delimiters("my photo|video music", &['|']),
+            vec!["my photo", "video music"]
+        );
+
+        // Test with space as custom delimiter
+        assert_eq!(
+            parse_search_keywords_with_delimiters("photo video music", &[' ']),
+            vec!["photo", "video", "music"]
+        );
+
+        // Test with empty delimiters (should treat whole input as one keyword)
+        assert_eq!(
+            parse_search_keywords_with_delimiters("photo;video", &[]),
+            vec!["photo;video"]
+        );
+
+        // Test with whitespace trimming
+        assert_eq!(
+            parse_search_keywords_with_delimiters("  photo  |  video  ", &['|']),
+            vec!["photo", "video"]
+        );
+
+        // Test with empty input
+        assert_eq!(
+            parse_search_keywords_with_delimiters("", &['|']),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_search_by_keyword() {
+        let (_temp, db) = create_test_db_with_data();
+        let config = SearchConfig::default();
+
+        let results = search_by_keyword(&db, "summer", &config).unwrap();
+        assert_eq!(results.len(), 3); // summer.jpg, summer_vacation.mp4, summer_hits.mp3
+
+        let results = search_by_keyword(&db, "winter", &config).unwrap();
+        assert_eq!(results.len(), 1);
+
+        let results = search_by_keyword(&db, "nonexistent", &config).unwrap();
+        assert_eq!(results.len(), 0);
+    }
+
+    #[test]
+    fn test_search_multiple_keywords() {
+        let (_temp, db) = create_test_db_with_data();
+        let config = SearchConfig::default();
+        let keywords = vec!["summer".to_string(), "winter".to_string()];
+
+        let results = search_multiple_keywords(&db, &keywords, &config).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "summer");
+        assert_eq!(results[0].1.len(), 3);
+        assert_eq!(results[1].0, "winter");
+        assert_eq!(results[1].1.len(), 1);
+    }
+
+    #[test]
+    fn test_search_from_input() {
+        let (_temp, db) = create_test_db_with_data();
+        let config = SearchConfig::default();
+
+        let results = search_from_input(&db, "summer; winter", &config).unwrap();
+        assert_eq!(results.len(), 2);
+
+        let results = search_from_input(&db, "", &config).unwrap();
+        assert_eq!(results.len(), 0);
+    }
+
+    #[test]
+    fn test_search_config() {
+        let (_temp, db) = create_test_db_with_data();
+
+        // Test max_results limit
+        let config = SearchConfig {
+            max_results: 1,
+            ..Default::default()
+        };
+        let results = search_by_keyword(&db, "summer", &config).unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn test_build_tree() {
+        // Use platform-independent path construction
+        use std::path::MAIN_SEPARATOR;
+        let sep = MAIN_SEPARATOR.to_string();
+
+        let base = if cfg!(windows) {
+            "Z:".to_string()
+        } else {
+            "".to_string()
+        };
+
+        let results = vec![
+            SearchResult {
+                path: format!("{}{sep}photos{sep}2023{sep}summer.jpg", base),
+                name: "summer.jpg".to_string(),
+                ..Default::default()
+            },
+            SearchResult {
+                path: format!("{}{sep}photos{sep}2023{sep}winter.jpg", base),
+                name: "winter.jpg".to_string(),
+                ..Default::default()
+            },
+            SearchResult {
+                path: format!("{}{sep}documents{sep}report.pdf", base),
+                name: "report.pdf".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let tree = build_tree(&results, "搜索结果");
+
+        assert!(tree.name.contains("搜索结果"));
+
+        // The tree structure depends on the platform and common prefix detection
+        // Just verify we have a valid tree structure
+        assert!(!tree.children.is_empty(), "Tree should have children");
+
+        // Find photos folder (might be nested under platform-specific root)
+        fn find_node_recursive<'a>(node: &'a TreeNode, name: &str) -> Option<&'a TreeNode> {
+            if node.name == name {
+                return Some(node);
+            }
+            for child in &node.children {
+                if let Some(found) = find_node_recursive(child, name) {
+                    return Some(found);
+                }
+            }
+            None
+        }
+
+        let photos = find_node_recursive(&tree, "photos").expect("Should find photos folder");
+        assert_eq!(photos.children.len(), 1); // 2023 folder
+
+        let year_2023 = &photos.children[0];
+        assert_eq!(year_2023.name, "2023");
+        assert_eq!(year_2023.children.len(), 2); // summer.jpg and winter.jpg
+    }
+
+    #[test]
+    fn test_results_and_tree_leaves_carry_metadata() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("meta.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::with_metadata(
+                "/photos/2023/summer.jpg".into(),
+                "summer.jpg".into(),
+                1_700_000_000.5,
+                2048,
+            ),
+            Index::new("/photos/2023/summer.txt".into(), "summer.txt".into()),
+        ])
+        .unwrap();
+
+        let results = search_by_keyword(&db, "summer", &SearchConfig::default()).unwrap();
+        assert_eq!(
+            results
+                .iter()
+                .map(|r| (r.size, r.mtime))
+                .collect::<Vec<_>>(),
+            vec![(Some(2048), Some(1_700_000_000.5)), (None, None)]
+        );
+
+        let tree = build_tree_with_root(&results, "root", &TreeRootStrategy::None);
+        let mut dir = &tree;
+        while !dir.children[0].is_leaf() {
+            dir = &dir.children[0];
+            assert_eq!((dir.size, dir.mtime), (None, None));
+        }
+        assert_eq!(dir.name, "2023");
+        assert_eq!(dir.children[0].size, Some(2048));
+        assert_eq!(dir.children[0].mtime, Some(1_700_000_000.5));
+        assert_eq!(dir.children[1].size, None);
+    }
+
+    #[test]
+    fn test_normalized_paths_build_nested_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("messy.reminex.db")).unwrap();
+        db.set_path_separator(Some('/')).unwrap();
+        db.add_idxs(&[
+            Index::new("/photos//2023/summer.jpg".into(), "summer.jpg".into()),
+            Index::new("/photos\\2023\\\\winter.jpg".into(), "winter.jpg".into()),
+            Index::new("/photos/2023/summer.jpg".into(), "summer.jpg".into()),
+        ])
+        .unwrap();
+
+        let mut results = search_by_keyword(&db, ".jpg", &SearchConfig::default()).unwrap();
+        results.sort_by(|a, b| a.path.cmp(&b.path));
+        let paths: Vec<_> = results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec!["/photos/2023/summer.jpg", "/photos/2023/winter.jpg"]
+        );
+
+        let tree = build_tree_with_root(&results, "搜索结果", &TreeRootStrategy::Fixed("/".into()));
+        assert_eq!(tree.children.len(), 1);
+        let photos = &tree.children[0];
+        assert_eq!(photos.name, "photos");
+        assert_eq!(photos.children.len(), 1);
+        assert_eq!(photos.children[0].name, "2023");
+        assert_eq!(photos.children[0].children.len(), 2);
+    }
+
+    fn tree_root_results() -> (String, Vec<SearchResult>) {
+        use std::path::MAIN_SEPARATOR;
+        let sep = MAIN_SEPARATOR.to_string();
+        let base = if cfg!(windows) { "Z:" } else { "" };
+
+        let results = ["summer.jpg", "winter.jpg"]
+            .iter()
+            .map(|name| SearchResult {
+                path: format!("{base}{sep}photos{sep}2023{sep}{name}"),
+                name: name.to_string(),
+                ..Default::default()
+            })
+            .chain(std::iter::once(SearchResult {
+                path: format!("{base}{sep}photos{sep}raw{sep}a.cr2"),
+                name: "a.cr2".to_string(),
+                ..Default::default()
+            }))
+            .collect();
+        (format!("{base}{sep}photos"), results)
+    }
+
+    fn child_names(node: &TreeNode) -> Vec<&str> {
+        node.children.iter().map(|c| c.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_tree_root_strategy_auto() {
+        let (photos, results) = tree_root_results();
+        let tree = build_tree_with_root(&results, "搜索结果", &TreeRootStrategy::Auto);
+
+        assert_eq!(tree.path, PathBuf::from(&photos));
+        assert_eq!(child_names(&tree), vec!["2023", "raw"]);
+        assert_eq!(
+            build_tree(&results, "搜索结果").name,
+            tree.name,
+            "build_tree defaults to Auto"
+        );
+    }
+
+    #[test]
+    fn test_tree_root_strategy_none() {
+        let (photos, results) = tree_root_results();
+        let tree = build_tree_with_root(&results, "搜索结果", &TreeRootStrategy::None);
+
+        assert_eq!(tree.name, "搜索结果");
+        assert_eq!(tree.path, PathBuf::new());
+
+        // Walk down the single chain of directories from the filesystem root
+        let mut node = &tree;
+        while node.children.len() == 1 {
+            node = &node.children[0];
+        }
+        assert_eq!(node.path, PathBuf::from(&photos));
+        assert_eq!(child_names(node), vec!["2023", "raw"]);
+    }
+
+    #[test]
+    fn test_tree_root_strategy_fixed() {
+        let (photos, results) = tree_root_results();
+        let root = PathBuf::from(&photos).join("2023");
+        let tree =
+            build_tree_with_root(&results, "搜索结果", &TreeRootStrategy::Fixed(root.clone()));
+
+        assert_eq!(tree.path, root);
+        assert!(tree.name.contains(&root.display().to_string()));
+        let names = child_names(&tree);
+        assert_eq!(names.len(), 3);
+        assert!(names.contains(&"summer.jpg") && names.contains(&"winter.jpg"));
+
+        // a.cr2 lies outside the fixed root and keeps its full path
+        let outside = tree.children.iter().find(|c| !c.is_leaf()).unwrap();
+        let mut node = outside;
+        while let Some(child) = node.children.first() {
+            node = child;
+        }
+        assert_eq!(node.name, "a.cr2");
+    }
+
+    #[test]
+    fn test_result_window_flat_and_tree() {
+        let (_, results) = tree_root_results();
+        assert_eq!(ResultWindow::Head(2).apply(&results), &results[..2]);
+        assert_eq!(ResultWindow::Tail(1).apply(&results), &results[2..]);
+        assert_eq!(ResultWindow::Head(10).apply(&results), &results[..]);
+        assert_eq!(ResultWindow::Tail(10).apply(&results), &results[..]);
+        assert!(ResultWindow::Head(0).apply(&results).is_empty());
+
+        // The tree keeps the directories of the remaining leaves
+        let tail = ResultWindow::Tail(1).apply(&results);
+        let tree = build_tree_with_root(tail, "搜索结果", &TreeRootStrategy::None);
+        let mut node = &tree;
+        let mut depth = 0;
+        while let Some(child) = node.children.first() {
+            assert_eq!(node.children.len(), 1);
+            node = child;
+            depth += 1;
+        }
+        assert_eq!(node.name, results[2].file_name());
+        assert!(depth > 1);
+    }
+
+    fn search_result(path: &str) -> SearchResult {
+        SearchResult {
+            path: path.to_string(),
+            name: String::new(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_search_result_path_helpers_unix() {
+        let result = search_result("/home/me/photos/summer.JPG");
+        assert_eq!(result.as_path(), Path::new("/home/me/photos/summer.JPG"));
+        assert_eq!(result.file_name(), "summer.JPG");
+        assert_eq!(result.extension(), Some("JPG"));
+        assert_eq!(
+            result.relative_to("/home/me"),
+            Some(PathBuf::from("photos/summer.JPG"))
+        );
+        assert_eq!(
+            result.relative_to("/home/me/"),
+            Some(PathBuf::from("photos/summer.JPG"))
+        );
+        assert_eq!(result.relative_to("/home/m"), None);
+        assert_eq!(result.relative_to("/srv"), None);
+
+        assert_eq!(search_result("/home/me/.bashrc").extension(), None);
+        assert_eq!(search_result("/home/me/Makefile").extension(), None);
+        assert_eq!(search_result("/home/me/dir/").file_name(), "dir");
+
+        assert_eq!(result.archive(), None);
+        let inner = search_result("/home/me/photos.zip!/trip/beach.jpg");
+        assert_eq!(inner.archive(), Some("/home/me/photos.zip"));
+        assert_eq!(inner.file_name(), "beach.jpg");
+    }
+
+    #[test]
+    fn test_search_result_path_helpers_windows() {
+        let result = search_result("D:\\Media\\Movies\\film.final.mkv");
+        assert_eq!(result.file_name(), "film.final.mkv");
+        assert_eq!(result.extension(), Some("mkv"));
+        assert_eq!(
+            result.relative_to("D:\\Media"),
+            Some(PathBuf::from("Movies\\film.final.mkv"))
+        );
+        assert_eq!(
+            result.relative_to("D:\\Media\\"),
+            Some(PathBuf::from("Movies\\film.final.mkv"))
+        );
+        assert_eq!(result.relative_to("D:\\Med"), None);
+        assert_eq!(result.relative_to("E:\\Media"), None);
+
+        // Mixed separators, as produced by some tools on Windows
+        assert_eq!(
+            search_result("C:/Users/me\\notes.txt").file_name(),
+            "notes.txt"
+        );
+    }
+
+    #[test]
+    fn test_tree_node_is_leaf() {
+        let mut node = TreeNode::new("file.txt".to_string(), PathBuf::from("Z:\\file.txt"));
+        assert!(node.is_leaf());
+
+        node.children.push(TreeNode::new(
+            "child".to_string(),
+            PathBuf::from("Z:\\child"),
+        ));
+        assert!(!node.is_leaf());
+    }
+
+    #[test]
+    fn test_format_tree_node() {
+        let mut root = TreeNode::new("root".to_string(), PathBuf::from("Z:\\"));
+        root.children.push(TreeNode::new(
+            "file1.txt".to_string(),
+            PathBuf::from("Z:\\file1.txt"),
+        ));
+        root.children.push(TreeNode::new(
+            "file2.txt".to_string(),
+            PathBuf::from("Z:\\file2.txt"),
+        ));
+
+        let output = format_tree_node(&root.children[0], "", false);
+        assert!(output.contains("├─ file1.txt"));
+
+        let output = format_tree_node(&root.children[1], "", true);
+        assert!(output.contains("└─ file2.txt"));
+    }
+
+    #[test]
+    fn test_format_tree_node_ascii() {
+        let mut dir = TreeNode::new("photos".to_string(), PathBuf::from("/photos"));
+        dir.children.push(TreeNode::new(
+            "a.jpg".to_string(),
+            PathBuf::from("/photos/a.jpg"),
+        ));
+        dir.children.push(TreeNode::new(
+            "b.jpg".to_string(),
+            PathBuf::from("/photos/b.jpg"),
+        ));
+
+        let output = format_tree_node_with_style(&dir, "", false, TreeStyle::Ascii);
+        assert_eq!(output, "|-- photos/\n|   |-- a.jpg\n|   `-- b.jpg\n");
+        assert!(output.is_ascii());
+
+        let output = format_tree_node_with_style(&dir, "", true, TreeStyle::Ascii);
+        assert_eq!(output, "`-- photos/\n    |-- a.jpg\n    `-- b.jpg\n");
+    }
+
+    #[test]
+    fn test_tree_style_detection() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(k, _)| *k == name)
+                    .map(|(_, v)| v.to_string())
+            }
+        };
+
+        let detect = |vars, windows| TreeStyle::detect_from(env(vars), windows);
+        assert_eq!(
+            detect(&[("LANG", "en_US.UTF-8")], false),
+            TreeStyle::Unicode
+        );
+        assert_eq!(detect(&[("LANG", "zh_CN.utf8")], false), TreeStyle::Unicode);
+        assert_eq!(detect(&[("LANG", "C")], false), TreeStyle::Ascii);
+        assert_eq!(
+            detect(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")], false),
+            TreeStyle::Ascii
+        );
+        assert_eq!(
+            detect(&[("LC_ALL", ""), ("LANG", "en_US.UTF-8")], false),
+            TreeStyle::Unicode
+        );
+        assert_eq!(detect(&[], false), TreeStyle::Ascii);
+        assert_eq!(detect(&[], true), TreeStyle::Unicode);
+    }
+
+    #[test]
+    fn test_search_by_stem() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("stem.reminex.db")).unwrap();
+        db.add_idxs(&[
             Index::new(
-                "Z:\\videos\\summer_vacation.mp4".to_string(),
-                "summer_vacation.mp4".to_string(),
+                "Z:\\raw\\IMG_1234.cr2".to_string(),
+                "IMG_1234.cr2".to_string(),
             ),
             Index::new(
-                "Z:\\music\\summer_hits.mp3".to_string(),
-                "summer_hits.mp3".to_string(),
+                "Z:\\jpg\\IMG_1234.jpg".to_string(),
+                "IMG_1234.jpg".to_string(),
             ),
-        ];
-        db.add_idxs(&indices).unwrap();
+            Index::new(
+                "Z:\\jpg\\IMG_12345.jpg".to_string(),
+                "IMG_12345.jpg".to_string(),
+            ),
+            Index::new(
+                "Z:\\jpg\\xIMG_1234y.jpg".to_string(),
+                "xIMG_1234y.jpg".to_string(),
+            ),
+            Index::new(
+                "Z:\\IMG_1234\\notes.txt".to_string(),
+                "notes.txt".to_string(),
+            ),
+        ])
+        .unwrap();
+
+        let config = SearchConfig {
+            match_stem: true,
+            ..Default::default()
+        };
+
+        let results = search_by_keyword(&db, "IMG_1234", &config).unwrap();
+        let names: Vec<_> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["IMG_1234.jpg", "IMG_1234.cr2"]);
+
+        // Case-insensitive by default
+        let results = search_by_keyword(&db, "img_1234", &config).unwrap();
+        assert_eq!(results.len(), 2);
+
+        let config = SearchConfig {
+            match_stem: true,
+            case_sensitive: true,
+            ..Default::default()
+        };
+        let results = search_by_keyword(&db, "img_1234", &config).unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_case_sensitive_search_in_sql() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("case.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/docs/Report.txt".into(), "Report.txt".into()),
+            Index::new("/docs/report.txt".into(), "report.txt".into()),
+            Index::new("/REPORTS/notes.txt".into(), "notes.txt".into()),
+        ])
+        .unwrap();
+
+        let names = |config: &SearchConfig, keyword: &str| -> Vec<String> {
+            search_by_keyword(&db, keyword, config)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.path)
+                .collect()
+        };
+
+        let insensitive = SearchConfig::default();
+        assert_eq!(names(&insensitive, "report").len(), 3);
+
+        let sensitive = SearchConfig {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        assert_eq!(names(&sensitive, "report"), vec!["/docs/report.txt"]);
+        assert_eq!(names(&sensitive, "REPORT"), vec!["/REPORTS/notes.txt"]);
 
-        (temp_dir, db)
+        // The limit applies after case matching, not before
+        let limited = SearchConfig {
+            max_results: 1,
+            ..sensitive
+        };
+        assert_eq!(names(&limited, "report"), vec!["/docs/report.txt"]);
     }
 
     #[test]
-    fn test_parse_search_keywords() {
+    fn test_like_wildcards_match_literally() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("like.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/d/my_file.txt".into(), "my_file.txt".into()),
+            Index::new("/d/myXfile.txt".into(), "myXfile.txt".into()),
+            Index::new("/d/50% off.pdf".into(), "50% off.pdf".into()),
+            Index::new("/d/500 off.pdf".into(), "500 off.pdf".into()),
+        ])
+        .unwrap();
+
+        let names = |keyword: &str, config: &SearchConfig| -> Vec<String> {
+            search_by_keyword(&db, keyword, config)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.name)
+                .collect()
+        };
+
+        let config = SearchConfig::default();
+        assert_eq!(names("my_file", &config), vec!["my_file.txt"]);
+        assert_eq!(names("50%", &config), vec!["50% off.pdf"]);
+
+        let subsequence = SearchConfig {
+            subsequence: true,
+            ..Default::default()
+        };
+        assert_eq!(names("y_f", &subsequence), vec!["my_file.txt"]);
+
+        let stem = SearchConfig {
+            match_stem: true,
+            ..Default::default()
+        };
+        assert_eq!(names("my_file", &stem), vec!["my_file.txt"]);
+    }
+
+    #[test]
+    fn test_like_wildcards_match_literally_in_paths() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("like_path.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/work/a_b/notes.txt".into(), "notes.txt".into()),
+            Index::new("/work/aXb/notes.txt".into(), "notes.txt".into()),
+            Index::new("/work/100%/é_b.txt".into(), "é_b.txt".into()),
+            Index::new("/work/100x/éXb.txt".into(), "éXb.txt".into()),
+            Index::new("D:\\work\\a_b\\plan.txt".into(), "plan.txt".into()),
+        ])
+        .unwrap();
+        let paths = |keyword: &str, config: &SearchConfig| -> Vec<String> {
+            let mut paths: Vec<String> = search_by_keyword(&db, keyword, config)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.path)
+                .collect();
+            paths.sort();
+            paths
+        };
+        let config = SearchConfig::default();
+
         assert_eq!(
-            parse_search_keywords("photo;video;music"),
-            vec!["photo", "video", "music"]
+            paths("a_b", &config),
+            vec!["/work/a_b/notes.txt", "D:\\work\\a_b\\plan.txt"]
+        );
+        assert_eq!(
+            paths("a_b/", &config),
+            vec!["/work/a_b/notes.txt", "D:\\work\\a_b\\plan.txt"]
         );
+        assert_eq!(paths("100%/", &config), vec!["/work/100%/é_b.txt"]);
+        // Non-ASCII keywords are folded outside SQL, still literally
+        assert_eq!(paths("É_B", &config), vec!["/work/100%/é_b.txt"]);
 
-        // Test with space in keyword (should not split)
+        let and = SearchConfig {
+            combine: KeywordCombine::And,
+            ..Default::default()
+        };
+        let combined: Vec<String> = search_combined(&db, &["work a_b".to_string()], &and)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.path)
+            .collect();
+        assert_eq!(combined.len(), 2);
+        assert!(combined.iter().all(|path| path.contains("a_b")));
+
+        let glob = SearchConfig {
+            search_mode: SearchMode::Glob,
+            ..Default::default()
+        };
+        assert_eq!(paths("/work/a_b/*", &glob), vec!["/work/a_b/notes.txt"]);
+    }
+
+    #[test]
+    fn test_fts_search_matches_words_and_prefixes() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("fts.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/docs/summary_2023.pdf".into(), "summary_2023.pdf".into()),
+            Index::new("/docs/summer/beach.jpg".into(), "beach.jpg".into()),
+            Index::new("/docs/consumer.txt".into(), "consumer.txt".into()),
+        ])
+        .unwrap();
+        let fts = SearchConfig {
+            use_fts: true,
+            ..Default::default()
+        };
+        let paths = |keyword: &str, config: &SearchConfig| -> Vec<String> {
+            search_by_keyword(&db, keyword, config)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.path)
+                .collect()
+        };
+
+        // No index yet: substring matching through LIKE
+        assert!(!db.has_fts().unwrap());
+        assert_eq!(paths("mary", &fts), vec!["/docs/summary_2023.pdf"]);
+
+        db.enable_fts().unwrap();
+        db.enable_fts().unwrap();
+        assert!(db.has_fts().unwrap());
+
+        // Whole words only, with * for prefixes
+        assert_eq!(paths("mary", &fts), Vec::<String>::new());
+        assert_eq!(paths("SUMMARY", &fts), vec!["/docs/summary_2023.pdf"]);
         assert_eq!(
-            parse_search_keywords("my photo,video music"),
-            vec!["my photo", "video music"]
+            paths("summ*", &fts),
+            vec!["/docs/summary_2023.pdf", "/docs/summer/beach.jpg"]
         );
+        assert_eq!(paths("2023 summary", &fts), vec!["/docs/summary_2023.pdf"]);
+        assert_eq!(paths("summer beach", &fts), vec!["/docs/summer/beach.jpg"]);
 
+        let name_only = SearchConfig {
+            search_in_path: false,
+            ..fts.clone()
+        };
+        assert_eq!(paths("summ*", &name_only), vec!["/docs/summary_2023.pdf"]);
+
+        // Rows written later are indexed by the triggers
+        db.add_idxs(&[Index::new("/new/summit.png".into(), "summit.png".into())])
+            .unwrap();
+        crate::maintenance::prune_missing(&db, Some("/docs/summer"), false).unwrap();
+        crate::maintenance::relocate(&db, "/docs", "/archive", false).unwrap();
         assert_eq!(
-            parse_search_keywords("photo; video, music"),
-            vec!["photo", "video", "music"]
+            paths("summ*", &fts),
+            vec!["/archive/summary_2023.pdf", "/new/summit.png"]
         );
+        assert_eq!(paths("docs", &fts), Vec::<String>::new());
 
+        // Substring matching stays available
         assert_eq!(
-            parse_search_keywords("photo；video，music"),
-            vec!["photo", "video", "music"]
+            paths("mary", &SearchConfig::default()),
+            vec!["/archive/summary_2023.pdf"]
+        );
+        assert_eq!(
+            paths(
+                "mary",
+                &SearchConfig {
+                    subsequence: true,
+                    ..fts
+                }
+            ),
+            vec!["/archive/summary_2023.pdf"]
         );
+    }
 
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("summ*").as_deref(), Some("\"summ\"*"));
         assert_eq!(
-            parse_search_keywords("  photo  ;  video  "),
-            vec!["photo", "video"]
+            fts_query("a-b \"OR\" c*d").as_deref(),
+            Some("\"a\" \"b\" \"OR\" \"c\"* \"d\"")
         );
+        assert_eq!(fts_query("照片2023").as_deref(), Some("\"照片2023\""));
+        assert_eq!(fts_query(" *.- "), None);
+    }
 
-        assert_eq!(parse_search_keywords(""), Vec::<String>::new());
+    #[test]
+    fn test_glob_segments() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("segments.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new(
+                "/home/me/photos/2023/summer.jpg".into(),
+                "summer.jpg".into(),
+            ),
+            Index::new("/home/me/photos/summer.png".into(), "summer.png".into()),
+            Index::new(
+                "/home/me/photos/a/b/summer_c.gif".into(),
+                "summer_c.gif".into(),
+            ),
+            Index::new("/home/me/docs/photos.txt".into(), "photos.txt".into()),
+            Index::new("D:\\photos\\winter.jpg".into(), "winter.jpg".into()),
+        ])
+        .unwrap();
+        let glob = SearchConfig {
+            search_mode: SearchMode::Glob,
+            ..Default::default()
+        };
+        let names = |keyword: &str, config: &SearchConfig| -> Vec<String> {
+            search_by_keyword(&db, keyword, config)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.name)
+                .collect()
+        };
+
+        assert_eq!(names("*.jpg", &glob), vec!["summer.jpg", "winter.jpg"]);
+        assert_eq!(
+            names("summer*", &glob),
+            vec!["summer.jpg", "summer_c.gif", "summer.png"]
+        );
+        assert_eq!(names("summer.{png,gif}", &glob), Vec::<String>::new());
+        assert_eq!(
+            names("summer*.{png,gif}", &glob),
+            vec!["summer_c.gif", "summer.png"]
+        );
+
+        // `*` stays within a segment, `**` crosses any number of them
+        assert_eq!(
+            names("photos/**/summer*", &glob),
+            vec!["summer.jpg", "summer_c.gif", "summer.png"]
+        );
+        assert_eq!(names("photos/*/summer*", &glob), vec!["summer.jpg"]);
+        assert_eq!(names("photos/**", &glob).len(), 4);
+        assert_eq!(names("photos\\*.jpg", &glob), vec!["winter.jpg"]);
+        assert_eq!(names("/home/*/summer*", &glob), Vec::<String>::new());
+        assert_eq!(names("/home/**/summer.p?g", &glob), vec!["summer.png"]);
+
+        let name_only = SearchConfig {
+            search_in_path: false,
+            ..glob.clone()
+        };
+        assert_eq!(names("photos/**", &name_only), Vec::<String>::new());
+        assert_eq!(names("photos*", &name_only), vec!["photos.txt"]);
+
+        let error = search_by_keyword(&db, "[a*", &glob).unwrap_err();
+        assert!(error.to_string().contains("Invalid glob pattern"));
     }
 
     #[test]
-    fn test_parse_search_keywords_with_custom_delimiters() {
-        // Test with custom delimiter '|'
+    fn test_regex_keywords() {
+        let (_temp_dir, db) = create_test_db_with_data();
+        let regex = SearchConfig {
+            search_mode: SearchMode::Regex,
+            ..Default::default()
+        };
+        let names = |keyword: &str, config: &SearchConfig| -> Vec<String> {
+            search_by_keyword(&db, keyword, config)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.name)
+                .collect()
+        };
+
         assert_eq!(
-            parse_search_keywords_with_delimiters("photo|video|music", &['|']),
-            vec!["photo", "video", "music"]
+            names(r"^summer_.*\.mp[34]$", &regex),
+            vec!["summer_hits.mp3", "summer_vacation.mp4"]
+        );
+        assert_eq!(names("^SUMMER", &regex).len(), 3);
+        assert_eq!(
+            names(
+                "^SUMMER",
+                &SearchConfig {
+                    case_sensitive: true,
+                    ..regex.clone()
+                }
+            ),
+            Vec::<String>::new()
         );
 
-        // Test with multiple custom delimiters
+        // Paths are matched as stored, and only with search_in_path
+        assert_eq!(names(r"photos\\2023", &regex).len(), 2);
+        let name_only = SearchConfig {
+            search_in_path: false,
+            ..regex.clone()
+        };
+        assert_eq!(names(r"photos\\2023", &name_only), Vec::<String>::new());
+
+        let limited = SearchConfig {
+            max_results: 1,
+            ..regex.clone()
+        };
+        assert_eq!(names("summer", &limited), vec!["summer_hits.mp3"]);
+
+        let error = search_by_keyword(&db, "summer(", &regex).unwrap_err();
+        assert!(error.to_string().contains("Invalid regular expression"));
+    }
+
+    #[test]
+    fn test_glob_keywords() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("glob.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/p/2023/beach.jpg".into(), "beach.jpg".into()),
+            Index::new("/p/2023/beach.jpg.txt".into(), "beach.jpg.txt".into()),
+            Index::new("/p/2024/snow.JPG".into(), "snow.JPG".into()),
+            Index::new("/p/photo1.png".into(), "photo1.png".into()),
+            Index::new("/p/photo12.png".into(), "photo12.png".into()),
+            Index::new("/p/100%_*.png".into(), "100%_*.png".into()),
+            Index::new("/p/100x_a.png".into(), "100x_a.png".into()),
+        ])
+        .unwrap();
+
+        let glob = SearchConfig {
+            search_mode: SearchMode::Glob,
+            ..Default::default()
+        };
+        let paths = |keyword: &str, config: &SearchConfig| -> Vec<String> {
+            search_by_keyword(&db, keyword, config)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.path)
+                .collect()
+        };
+
         assert_eq!(
-            parse_search_keywords_with_delimiters("photo|video;music", &['|', ';']),
-            vec!["photo", "video", "music"]
+            paths("*.jpg", &glob),
+            vec!["/p/2023/beach.jpg", "/p/2024/snow.JPG"]
+        );
+        assert_eq!(paths("*/2023/*.jpg", &glob), vec!["/p/2023/beach.jpg"]);
+        assert_eq!(paths("photo?.png", &glob), vec!["/p/photo1.png"]);
+        // % and _ stay literal inside a glob
+        assert_eq!(paths("100%_*", &glob), vec!["/p/100%_*.png"]);
+
+        let case_sensitive = SearchConfig {
+            case_sensitive: true,
+            ..glob.clone()
+        };
+        assert_eq!(paths("*.jpg", &case_sensitive), vec!["/p/2023/beach.jpg"]);
+
+        // Without the option, * and ? are ordinary characters
+        assert_eq!(
+            paths("*.jpg", &SearchConfig::default()),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            paths("100%_*", &SearchConfig::default()),
+            vec!["/p/100%_*.png"]
+        );
+    }
+
+    #[test]
+    fn test_escape_like() {
+        assert_eq!(escape_like("100%_a\\b"), "100\\%\\_a\\\\b");
+        assert_eq!(subsequence_pattern("a_"), "%a%\\_%");
+    }
+
+    #[test]
+    fn test_glob_pattern() {
+        assert!(is_glob("*.jpg") && is_glob("a?b") && !is_glob("a.jpg"));
+        assert_eq!(glob_pattern("*.jpg"), "%.jpg");
+        assert_eq!(glob_pattern("photo?_1%*"), "photo_\\_1\\%%");
+        assert_eq!(glob_pattern("**/photos/**/summer*"), "%photos/%summer%");
+        assert_eq!(glob_pattern("[ab]{x,y}.jpg"), "_%.jpg");
+    }
+
+    #[test]
+    fn test_file_stem() {
+        assert_eq!(file_stem("IMG_1234.jpg"), "IMG_1234");
+        assert_eq!(file_stem("archive.tar.gz"), "archive.tar");
+        assert_eq!(file_stem("README"), "README");
+        assert_eq!(file_stem(".bashrc"), ".bashrc");
+    }
+
+    #[test]
+    fn test_search_empty_keyword() {
+        let (_temp, db) = create_test_db_with_data();
+        let config = SearchConfig::default();
+
+        let results = search_by_keyword(&db, "", &config).unwrap();
+        assert_eq!(results.len(), 0);
+
+        let results = search_by_keyword(&db, "   ", &config).unwrap();
+        assert_eq!(results.len(), 0);
+    }
+
+    #[test]
+    fn test_drop_empty_keywords() {
+        let (_temp_dir, db) = create_test_db_with_data();
+        let db_paths = vec![db.path.clone()];
+        let keywords = vec!["summer".to_string(), "missing".to_string()];
+
+        let mut results =
+            search_in_selected_database(&db_paths, "all", &keywords, &SearchConfig::default())
+                .unwrap();
+        let keywords_of = |results: &DatabaseResults| -> Vec<String> {
+            results.iter().map(|(_, k, _)| k.clone()).collect()
+        };
+        assert_eq!(keywords_of(&results), vec!["summer", "missing"]);
+
+        drop_empty_keywords(&mut results);
+        assert_eq!(keywords_of(&results), vec!["summer"]);
+    }
+
+    #[test]
+    fn test_search_with_min_results_broadens() {
+        let (temp_dir, _db) = create_test_db_with_data();
+        let db_paths = vec![temp_dir.path().join("test.reminex.db")];
+        let keywords = vec!["smr".to_string()];
+
+        let config = SearchConfig {
+            search_in_path: false,
+            ..Default::default()
+        };
+        let (level, results) =
+            search_with_min_results(&db_paths, "all", &keywords, &config, 1).unwrap();
+        assert_eq!(level, Relaxation::Subsequence);
+        assert!(results[0].2.iter().any(|r| r.name == "summer.jpg"));
+
+        // Enough exact results stop at the first level
+        let keywords = vec!["summer".to_string()];
+        let (level, _) = search_with_min_results(&db_paths, "all", &keywords, &config, 1).unwrap();
+        assert_eq!(level, Relaxation::None);
+    }
+
+    #[test]
+    fn test_case_insensitive_search_folds_non_ascii() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("unicode.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/menus/café.txt".into(), "café.txt".into()),
+            Index::new("/menus/CAFÉ_old.txt".into(), "CAFÉ_old.txt".into()),
+            Index::new("/ПОЕЗДКИ/москва.jpg".into(), "москва.jpg".into()),
+            Index::new("/trips/Москва.png".into(), "Москва.png".into()),
+            Index::new("/trips/cafe.txt".into(), "cafe.txt".into()),
+        ])
+        .unwrap();
+        let names = |keyword: &str, config: &SearchConfig| -> Vec<String> {
+            let mut names: Vec<String> = search_by_keyword(&db, keyword, config)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.name)
+                .collect();
+            names.sort();
+            names
+        };
+        let config = SearchConfig::default();
+
+        assert_eq!(names("CAFÉ", &config), vec!["CAFÉ_old.txt", "café.txt"]);
+        assert_eq!(names("МОСКВА", &config), vec!["Москва.png", "москва.jpg"]);
+        // Paths are folded too, and ASCII keywords still match as before
+        assert_eq!(names("поездки/", &config), vec!["москва.jpg"]);
+        assert_eq!(names("CAFE", &config), vec!["cafe.txt"]);
+
+        let sensitive = SearchConfig {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        assert_eq!(names("CAFÉ", &sensitive), vec!["CAFÉ_old.txt"]);
+        assert_eq!(names("Москва", &sensitive), vec!["Москва.png"]);
+
+        let stem = SearchConfig {
+            match_stem: true,
+            ..Default::default()
+        };
+        assert_eq!(names("МОСКВА", &stem), vec!["Москва.png", "москва.jpg"]);
+        let glob = SearchConfig {
+            search_mode: SearchMode::Glob,
+            ..Default::default()
+        };
+        assert_eq!(names("Café*", &glob), vec!["CAFÉ_old.txt", "café.txt"]);
+        let subsequence = SearchConfig {
+            subsequence: true,
+            ..Default::default()
+        };
+        assert_eq!(names("МСК", &subsequence), vec!["Москва.png", "москва.jpg"]);
+
+        let page = SearchConfig {
+            max_results: 1,
+            ..Default::default()
+        };
+        let (total, results) = search_by_keyword_counted(&db, "МОСКВА", &page).unwrap();
+        assert_eq!((total, results.len()), (2, 1));
+    }
+
+    #[test]
+    fn test_case_folding_beyond_lowercase() {
+        assert_eq!(fold_case("Straße"), "strasse");
+        assert_eq!(fold_case("ΚΟΣ"), "κοσ");
+        assert_eq!(fold_case("README"), "readme");
+
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("fold.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/maps/Straße.pdf".into(), "Straße.pdf".into()),
+            Index::new("/maps/STRASSE_alt.pdf".into(), "STRASSE_alt.pdf".into()),
+            Index::new("/maps/strand.pdf".into(), "strand.pdf".into()),
+            Index::new("/ΚΌΣΜΟΣ/κοσμος.txt".into(), "κοσμος.txt".into()),
+            Index::new("/ΚΌΣΜΟΣ/ΚΟΣΜΟΣ.txt".into(), "ΚΟΣΜΟΣ.txt".into()),
+        ])
+        .unwrap();
+        let names = |keyword: &str, config: &SearchConfig| -> Vec<String> {
+            let mut names: Vec<String> = search_by_keyword(&db, keyword, config)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.name)
+                .collect();
+            names.sort();
+            names
+        };
+        let name_only = SearchConfig {
+            search_in_path: false,
+            ..Default::default()
+        };
+
+        let both = vec!["STRASSE_alt.pdf", "Straße.pdf"];
+        assert_eq!(names("straße", &name_only), both);
+        assert_eq!(names("STRASSE", &name_only), both);
+        assert_eq!(names("strasse", &name_only), both);
+        assert_eq!(names("ΚΟΣ", &name_only), vec!["ΚΟΣΜΟΣ.txt", "κοσμος.txt"]);
+
+        // ASCII keywords with `ss` are still matched and counted in SQL
+        assert!(!folds_outside_sql("class", &name_only));
+        let page = SearchConfig {
+            max_results: 1,
+            ..name_only.clone()
+        };
+        let (total, results) = search_by_keyword_counted(&db, "strasse", &page).unwrap();
+        assert_eq!((total, results.len()), (2, 1));
+        assert_eq!(count_matches(&db, "SS", &name_only).unwrap(), 2);
+
+        let stem = SearchConfig {
+            match_stem: true,
+            ..name_only.clone()
+        };
+        assert_eq!(names("STRASSE", &stem), vec!["Straße.pdf"]);
+        let sensitive = SearchConfig {
+            case_sensitive: true,
+            ..name_only.clone()
+        };
+        assert_eq!(names("Straße", &sensitive), vec!["Straße.pdf"]);
+        assert!(names("strasse", &sensitive).is_empty());
+
+        let excluded = SearchConfig {
+            exclude_filters: vec!["STRASSE".to_string()],
+            ..name_only
+        };
+        assert_eq!(names("pdf", &excluded), vec!["strand.pdf"]);
+    }
+
+    #[test]
+    fn test_combined_keywords_and_or() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("combine.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/docs/2023_invoice.pdf".into(), "2023_invoice.pdf".into()),
+            Index::new("/docs/2023_invoice.txt".into(), "2023_invoice.txt".into()),
+            Index::new("/docs/2024_invoice.pdf".into(), "2024_invoice.pdf".into()),
+            Index::new("/docs/2023_notes.pdf".into(), "2023_notes.pdf".into()),
+        ])
+        .unwrap();
+        let keywords = vec!["invoice 2023".to_string(), "pdf".to_string()];
+        let paths = |config: &SearchConfig| -> Vec<(String, Vec<String>)> {
+            search_multiple_keywords(&db, &keywords, config)
+                .unwrap()
+                .into_iter()
+                .map(|(keyword, results)| (keyword, results.into_iter().map(|r| r.name).collect()))
+                .collect()
+        };
+
+        // Substring AND runs in SQL; regex AND merges per-keyword results
+        let and = SearchConfig {
+            combine: KeywordCombine::And,
+            ..Default::default()
+        };
+        let expected = vec![(
+            "invoice & 2023 & pdf".to_string(),
+            vec!["2023_invoice.pdf".to_string()],
+        )];
+        assert_eq!(paths(&and), expected);
+        let regex_and = SearchConfig {
+            search_mode: SearchMode::Regex,
+            ..and.clone()
+        };
+        assert_eq!(paths(&regex_and), expected);
+
+        // OR lists files matched by several keywords once, in sort order
+        let or = SearchConfig {
+            combine: KeywordCombine::Or,
+            sort_by: SortBy::Name,
+            ..Default::default()
+        };
+        let (label, names) = paths(&or).remove(0);
+        assert_eq!(label, "invoice 2023 | pdf");
+        assert_eq!(
+            names,
+            vec!["2023_invoice.pdf", "2023_notes.pdf", "2024_invoice.pdf"]
         );
 
-        // Test that spaces are NOT delimiters when not specified
-        assert_eq!(
-            parse_search_keywords_with_delimiters("my photo|video music", &['|']),
-            vec!["my photo", "video music"]
-        );
+        // Pages and totals cover the combined group
+        let or_words = vec!["invoice".to_string(), "notes".to_string()];
+        let page = SearchConfig {
+            max_results: 2,
+            offset: 1,
+            ..or.clone()
+        };
+        let (total, results) = search_combined_counted(&db, &or_words, &page).unwrap();
+        assert_eq!(total, 4);
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["2023_invoice.txt", "2023_notes.pdf"]);
+        let (total, _) = search_combined_counted(&db, &keywords, &and).unwrap();
+        assert_eq!(total, 1);
+    }
+
+    #[test]
+    fn test_counted_search_totals_every_match() {
+        let (_temp_dir, db) = create_test_db_with_data();
+        db.enable_fts().unwrap();
+
+        let base = SearchConfig {
+            max_results: 1,
+            offset: 1,
+            ..Default::default()
+        };
+        let cases = [
+            ("summer", base.clone()),
+            (
+                "summer",
+                SearchConfig {
+                    use_fts: true,
+                    ..base.clone()
+                },
+            ),
+            (
+                "*.jpg",
+                SearchConfig {
+                    search_mode: SearchMode::Glob,
+                    ..base.clone()
+                },
+            ),
+            (
+                "^summer",
+                SearchConfig {
+                    search_mode: SearchMode::Regex,
+                    search_in_path: false,
+                    ..base.clone()
+                },
+            ),
+            (
+                "summer",
+                SearchConfig {
+                    exclude_filters: vec!["hits".to_string()],
+                    ..base.clone()
+                },
+            ),
+        ];
+        for (keyword, config) in cases {
+            let unlimited = SearchConfig {
+                max_results: 2000,
+                offset: 0,
+                ..config.clone()
+            };
+            let expected = search_by_keyword(&db, keyword, &unlimited).unwrap().len();
+            assert!(expected > 1, "{keyword}");
+
+            let (total, page) = search_by_keyword_counted(&db, keyword, &config).unwrap();
+            assert_eq!(total, expected, "{keyword} {config:?}");
+            assert_eq!(page.len(), 1);
+
+            // Short, exactly ending and empty pages give the same total
+            for (offset, max_results) in [(0, 2000), (expected - 1, 5), (expected, 5), (99, 5)] {
+                let config = SearchConfig {
+                    offset,
+                    max_results,
+                    ..config.clone()
+                };
+                let (total, _) = search_by_keyword_counted(&db, keyword, &config).unwrap();
+                assert_eq!(total, expected, "{keyword} {config:?}");
+            }
+        }
+
+        assert_eq!(search_by_keyword_counted(&db, " ", &base).unwrap().0, 0);
+    }
+
+    #[test]
+    fn test_counts_in_sql() {
+        let keywords = |list: &[&str]| list.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+        let config = SearchConfig::default();
+        assert!(counts_in_sql(&keywords(&["summer", "class"]), &config));
+        assert!(!counts_in_sql(&keywords(&["summer", "café"]), &config));
+
+        for mode in [SearchMode::Regex, SearchMode::Fuzzy] {
+            let config = SearchConfig {
+                search_mode: mode,
+                ..Default::default()
+            };
+            assert!(!counts_in_sql(&keywords(&["summer"]), &config));
+        }
+        let glob = SearchConfig {
+            search_mode: SearchMode::Glob,
+            ..Default::default()
+        };
+        assert!(counts_in_sql(&keywords(&["summer"]), &glob));
+        assert!(!counts_in_sql(&keywords(&["*.jpg"]), &glob));
+
+        let or = SearchConfig {
+            combine: KeywordCombine::Or,
+            ..Default::default()
+        };
+        assert!(!counts_in_sql(&keywords(&["summer", "beach"]), &or));
+        let and = SearchConfig {
+            combine: KeywordCombine::And,
+            ..Default::default()
+        };
+        assert!(counts_in_sql(&keywords(&["summer", "beach"]), &and));
+    }
+
+    #[test]
+    fn test_offset_pages_without_duplicates_or_gaps() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("pages.reminex.db")).unwrap();
+        let idxs: Vec<Index> = (0..23)
+            .map(|i| {
+                let name = format!("page_{:02}.txt", (i * 7) % 23);
+                Index::new(format!("/p/{}", name), name)
+            })
+            .collect();
+        db.add_idxs(&idxs).unwrap();
+        let mut expected: Vec<String> = idxs.iter().map(|idx| idx.path.clone()).collect();
+        expected.sort();
+
+        for (mode, keyword) in [
+            (SearchMode::Substring, "page"),
+            (SearchMode::Glob, "page_*.txt"),
+            (SearchMode::Regex, "^page_"),
+        ] {
+            let mut seen = Vec::new();
+            for offset in (0..30).step_by(5) {
+                let config = SearchConfig {
+                    search_mode: mode,
+                    max_results: 5,
+                    offset,
+                    ..Default::default()
+                };
+                let page = search_by_keyword(&db, keyword, &config).unwrap();
+                assert!(page.len() <= 5);
+                seen.extend(page.into_iter().map(|r| r.path));
+            }
+            assert_eq!(seen, expected, "{mode:?}");
+        }
+
+        // Stem matches are paged too
+        db.add_idxs(&[
+            Index::new("/s/a/IMG.jpg".into(), "IMG.jpg".into()),
+            Index::new("/s/b/IMG.png".into(), "IMG.png".into()),
+        ])
+        .unwrap();
+        let config = SearchConfig {
+            match_stem: true,
+            max_results: 1,
+            offset: 1,
+            ..Default::default()
+        };
+        let page = search_by_keyword(&db, "img", &config).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].path, "/s/b/IMG.png");
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("receipt", "Receipt_2023.pdf"), 100);
+        assert_eq!(fuzzy_score("reciept", "receipt.pdf"), 85);
+        assert_eq!(fuzzy_score("reciept", "tax-receipt-2023.pdf"), 85);
+        assert_eq!(fuzzy_score("fiel", "file.txt"), 75);
+        assert_eq!(fuzzy_score("sumer", "summer.jpg"), 83);
+        assert!(fuzzy_score("reciept", "holiday.jpg") < DEFAULT_FUZZY_THRESHOLD);
+        assert_eq!(fuzzy_score("", "anything"), 0);
 
-        // Test with space as custom delimiter
-        assert_eq!(
-            parse_search_keywords_with_delimiters("photo video music", &[' ']),
-            vec!["photo", "video", "music"]
-        );
+        assert_eq!(edit_distance("reciept", "receipt", true), 1);
+        assert_eq!(edit_distance("kitten", "sitting", true), 3);
+        assert_eq!(edit_distance("ab", "", true), 2);
+        assert_eq!(score_at_least("reciept", "receipt.pdf", 90), None);
+    }
 
-        // Test with empty delimiters (should treat whole input as one keyword)
-        assert_eq!(
-            parse_search_keywords_with_delimiters("photo;video", &[]),
-            vec!["photo;video"]
-        );
+    #[test]
+    fn test_fuzzy_search_ranks_by_score() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("fuzzy.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/d/receipt.pdf".into(), "receipt.pdf".into()),
+            Index::new("/d/reciept.pdf".into(), "reciept.pdf".into()),
+            Index::new("/d/recipe.txt".into(), "recipe.txt".into()),
+            Index::new("/d/holiday.jpg".into(), "holiday.jpg".into()),
+        ])
+        .unwrap();
+        let config = SearchConfig {
+            search_mode: SearchMode::Fuzzy,
+            sort_by: SortBy::Score,
+            ..Default::default()
+        };
+        let ranked = |config: &SearchConfig| -> Vec<(String, Option<i64>)> {
+            search_by_keyword(&db, "reciept", config)
+                .unwrap()
+                .into_iter()
+                .map(|r| (r.name, r.score))
+                .collect()
+        };
 
-        // Test with whitespace trimming
         assert_eq!(
-            parse_search_keywords_with_delimiters("  photo  |  video  ", &['|']),
-            vec!["photo", "video"]
+            ranked(&config),
+            vec![
+                ("reciept.pdf".to_string(), Some(100)),
+                ("receipt.pdf".to_string(), Some(85)),
+                ("recipe.txt".to_string(), Some(71)),
+            ]
         );
 
-        // Test with empty input
-        assert_eq!(
-            parse_search_keywords_with_delimiters("", &['|']),
-            Vec::<String>::new()
-        );
+        // A higher threshold and max_results keep only the best
+        let strict = SearchConfig {
+            fuzzy_threshold: 90,
+            ..config.clone()
+        };
+        assert_eq!(ranked(&strict).len(), 1);
+        let one = SearchConfig {
+            max_results: 1,
+            sort_by: SortBy::Path,
+            ..config
+        };
+        assert_eq!(ranked(&one), vec![("reciept.pdf".to_string(), Some(100))]);
     }
 
     #[test]
-    fn test_search_by_keyword() {
-        let (_temp, db) = create_test_db_with_data();
-        let config = SearchConfig::default();
-
-        let results = search_by_keyword(&db, "summer", &config).unwrap();
-        assert_eq!(results.len(), 3); // summer.jpg, summer_vacation.mp4, summer_hits.mp3
+    fn test_fuzzy_search_keeps_best_page_of_many_matches() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("many.reminex.db")).unwrap();
+        let words = ["report", "reprot", "repost", "rapport", "resort", "reports"];
+        let idxs: Vec<Index> = (0..3000)
+            .map(|i| {
+                let name = format!("{}_{:04}.txt", words[i % words.len()], i);
+                Index::new(format!("/r/{}", name), name)
+            })
+            .collect();
+        db.add_idxs(&idxs).unwrap();
 
-        let results = search_by_keyword(&db, "winter", &config).unwrap();
-        assert_eq!(results.len(), 1);
+        // The same page as scoring every name and sorting them all
+        let mut expected: Vec<(i64, String)> = idxs
+            .iter()
+            .map(|idx| (fuzzy_score("repotr", &idx.name), idx.path.clone()))
+            .filter(|(score, _)| *score >= DEFAULT_FUZZY_THRESHOLD)
+            .collect();
+        expected.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        let expected: Vec<(i64, String)> = expected.into_iter().skip(500).take(7).collect();
+        assert_eq!(expected.len(), 7);
 
-        let results = search_by_keyword(&db, "nonexistent", &config).unwrap();
-        assert_eq!(results.len(), 0);
+        let config = SearchConfig {
+            search_mode: SearchMode::Fuzzy,
+            sort_by: SortBy::Score,
+            offset: 500,
+            max_results: 7,
+            ..Default::default()
+        };
+        let found: Vec<(i64, String)> = search_by_keyword(&db, "repotr", &config)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.score.unwrap(), r.path))
+            .collect();
+        assert_eq!(found, expected);
     }
 
     #[test]
-    fn test_search_multiple_keywords() {
-        let (_temp, db) = create_test_db_with_data();
-        let config = SearchConfig::default();
-        let keywords = vec!["summer".to_string(), "winter".to_string()];
+    fn test_subsequence_pattern() {
+        assert_eq!(subsequence_pattern("smr"), "%s%m%r%");
+        assert_eq!(subsequence_pattern("a b"), "%a%b%");
+    }
 
-        let results = search_multiple_keywords(&db, &keywords, &config).unwrap();
-        assert_eq!(results.len(), 2);
-        assert_eq!(results[0].0, "summer");
-        assert_eq!(results[0].1.len(), 3);
-        assert_eq!(results[1].0, "winter");
-        assert_eq!(results[1].1.len(), 1);
+    #[test]
+    fn test_levenshtein() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("abc", ""), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("照片", "照片"), 0);
+        assert_eq!(levenshtein("summer", "sumer"), 1);
+        // A swap is two edits without transpositions
+        assert_eq!(levenshtein("reciept", "receipt"), 2);
     }
 
     #[test]
-    fn test_search_from_input() {
-        let (_temp, db) = create_test_db_with_data();
-        let config = SearchConfig::default();
+    fn test_suggest_keywords() {
+        let (_temp_dir, db) = create_test_db_with_data();
 
-        let results = search_from_input(&db, "summer; winter", &config).unwrap();
-        assert_eq!(results.len(), 2);
+        let suggestions = suggest_keywords(&db, "sumer", 3).unwrap();
+        assert_eq!(suggestions.first().map(String::as_str), Some("summer"));
 
-        let results = search_from_input(&db, "", &config).unwrap();
-        assert_eq!(results.len(), 0);
+        assert!(suggest_keywords(&db, "zzzzzzzz", 3).unwrap().is_empty());
+        assert!(suggest_keywords(&db, "sumer", 0).unwrap().is_empty());
     }
 
     #[test]
-    fn test_search_config() {
-        let (_temp, db) = create_test_db_with_data();
+    fn test_search_filters_by_owner_and_mode() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("perms.reminex.db")).unwrap();
+        let mut mine = Index::new("/home/me/report.txt".into(), "report.txt".into());
+        mine.owner = Some(1000);
+        mine.mode = Some(0o100600);
+        let mut theirs = Index::new("/home/you/report.txt".into(), "report.txt".into());
+        theirs.owner = Some(1001);
+        theirs.mode = Some(0o100644);
+        db.add_idxs(&[mine, theirs]).unwrap();
 
-        // Test max_results limit
         let config = SearchConfig {
-            max_results: 1,
+            owner: Some(1000),
             ..Default::default()
         };
-        let results = search_by_keyword(&db, "summer", &config).unwrap();
+        let results = search_by_keyword(&db, "report", &config).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "/home/me/report.txt");
+
+        let config = SearchConfig {
+            mode: Some(0o644),
+            ..Default::default()
+        };
+        let results = search_by_keyword(&db, "report", &config).unwrap();
         assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "/home/you/report.txt");
     }
 
     #[test]
-    fn test_build_tree() {
-        // Use platform-independent path construction
-        use std::path::MAIN_SEPARATOR;
-        let sep = MAIN_SEPARATOR.to_string();
+    fn test_search_filters_by_modified_and_indexed_time() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("times.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::with_metadata("/old/report.txt".into(), "report.txt".into(), 100.0, 1),
+            Index::with_metadata("/new/report.txt".into(), "report.txt".into(), 200.0, 1),
+        ])
+        .unwrap();
+        db.batch_operation(|conn| {
+            conn.execute(
+                "UPDATE files SET indexed_at = 1000 WHERE path = '/new/report.txt'",
+                [],
+            )?;
+            conn.execute(
+                "UPDATE files SET indexed_at = 2000 WHERE path = '/old/report.txt'",
+                [],
+            )?;
+            Ok(())
+        })
+        .unwrap();
 
-        let base = if cfg!(windows) {
-            "Z:".to_string()
-        } else {
-            "".to_string()
+        // The two filters look at different columns
+        let config = SearchConfig {
+            modified_after: Some(150.0),
+            ..Default::default()
         };
+        let results = search_by_keyword(&db, "report", &config).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "/new/report.txt");
 
-        let results = vec![
-            SearchResult {
-                path: format!("{}{sep}photos{sep}2023{sep}summer.jpg", base),
-                name: "summer.jpg".to_string(),
-            },
-            SearchResult {
-                path: format!("{}{sep}photos{sep}2023{sep}winter.jpg", base),
-                name: "winter.jpg".to_string(),
-            },
-            SearchResult {
-                path: format!("{}{sep}documents{sep}report.pdf", base),
-                name: "report.pdf".to_string(),
-            },
-        ];
+        let config = SearchConfig {
+            indexed_after: Some(1500.0),
+            ..Default::default()
+        };
+        let results = search_by_keyword(&db, "report", &config).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].path, "/old/report.txt");
 
-        let tree = build_tree(&results, "搜索结果");
+        // Values are bound, so ones that do not print as SQL numbers work
+        for (search_mode, keyword) in [
+            (SearchMode::Substring, "report"),
+            (SearchMode::Glob, "report*"),
+            (SearchMode::Regex, "^report"),
+        ] {
+            let config = SearchConfig {
+                modified_after: Some(f64::NEG_INFINITY),
+                indexed_after: Some(1500.0),
+                search_mode,
+                ..Default::default()
+            };
+            let results = search_by_keyword(&db, keyword, &config).unwrap();
+            assert_eq!(results.len(), 1, "{:?}", search_mode);
+            assert_eq!(results[0].path, "/old/report.txt");
+        }
+        let config = SearchConfig {
+            combine: KeywordCombine::And,
+            modified_after: Some(150.0),
+            ..Default::default()
+        };
+        let keywords = ["new".to_string(), "report".to_string()];
+        let (total, results) = search_combined_counted(&db, &keywords, &config).unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(results[0].path, "/new/report.txt");
+    }
 
-        assert!(tree.name.contains("搜索结果"));
+    #[test]
+    fn test_search_filters_by_size() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("sizes.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::with_metadata("/v/small.mp4".into(), "small.mp4".into(), 0.0, 10),
+            Index::with_metadata("/v/medium.mp4".into(), "medium.mp4".into(), 0.0, 500),
+            Index::with_metadata("/v/large.mp4".into(), "large.mp4".into(), 0.0, 9000),
+            Index::new("/v/unknown.mp4".into(), "unknown.mp4".into()),
+        ])
+        .unwrap();
+        let sizes = |config: &SearchConfig| -> Vec<Option<i64>> {
+            search_by_keyword(&db, "mp4", config)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.size)
+                .collect()
+        };
 
-        // The tree structure depends on the platform and common prefix detection
-        // Just verify we have a valid tree structure
-        assert!(!tree.children.is_empty(), "Tree should have children");
+        // Rows without a size are only returned without a size filter
+        assert_eq!(sizes(&SearchConfig::default()).len(), 4);
+        let at_least = SearchConfig {
+            min_size: Some(500),
+            ..Default::default()
+        };
+        assert_eq!(sizes(&at_least), vec![Some(9000), Some(500)]);
+        let between = SearchConfig {
+            max_size: Some(500),
+            ..at_least.clone()
+        };
+        assert_eq!(sizes(&between), vec![Some(500)]);
+        let at_most = SearchConfig {
+            max_size: Some(100),
+            ..Default::default()
+        };
+        assert_eq!(sizes(&at_most), vec![Some(10)]);
+    }
 
-        // Find photos folder (might be nested under platform-specific root)
-        fn find_node_recursive<'a>(node: &'a TreeNode, name: &str) -> Option<&'a TreeNode> {
-            if node.name == name {
-                return Some(node);
-            }
-            for child in &node.children {
-                if let Some(found) = find_node_recursive(child, name) {
-                    return Some(found);
-                }
-            }
-            None
-        }
+    #[test]
+    fn test_search_sorts_results() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("sort.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::with_metadata("/a/zeta.log".into(), "zeta.log".into(), 300.0, 20),
+            Index::with_metadata("/b/alpha.log".into(), "alpha.log".into(), 100.0, 900),
+            Index::with_metadata("/c/mid.log".into(), "mid.log".into(), 200.0, 20),
+            Index::new("/d/bare.log".into(), "bare.log".into()),
+        ])
+        .unwrap();
+        let paths = |sort_by: SortBy, descending: bool, max_results: usize| -> Vec<String> {
+            let config = SearchConfig {
+                sort_by,
+                descending,
+                max_results,
+                ..Default::default()
+            };
+            search_by_keyword(&db, "log", &config)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.path)
+                .collect()
+        };
 
-        let photos = find_node_recursive(&tree, "photos").expect("Should find photos folder");
-        assert_eq!(photos.children.len(), 1); // 2023 folder
+        assert_eq!(
+            paths(SortBy::Path, true, 10),
+            vec!["/d/bare.log", "/c/mid.log", "/b/alpha.log", "/a/zeta.log"]
+        );
+        assert_eq!(
+            paths(SortBy::Name, false, 10),
+            vec!["/b/alpha.log", "/d/bare.log", "/c/mid.log", "/a/zeta.log"]
+        );
+        // Equal sizes fall back to path order, missing sizes come last
+        assert_eq!(
+            paths(SortBy::Size, false, 10),
+            vec!["/a/zeta.log", "/c/mid.log", "/b/alpha.log", "/d/bare.log"]
+        );
+        assert_eq!(
+            paths(SortBy::Size, true, 10),
+            vec!["/b/alpha.log", "/a/zeta.log", "/c/mid.log", "/d/bare.log"]
+        );
+        // The limit keeps the newest files rather than the first paths
+        assert_eq!(
+            paths(SortBy::Mtime, true, 2),
+            vec!["/a/zeta.log", "/c/mid.log"]
+        );
 
-        let year_2023 = &photos.children[0];
-        assert_eq!(year_2023.name, "2023");
-        assert_eq!(year_2023.children.len(), 2); // summer.jpg and winter.jpg
+        // Merged results are sorted the same way as SQL orders them
+        let mut results = search_by_keyword(&db, "log", &SearchConfig::default()).unwrap();
+        SortBy::Size.sort(&mut results, true);
+        let sorted: Vec<_> = results.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(sorted, paths(SortBy::Size, true, 10));
     }
 
     #[test]
-    fn test_tree_node_is_leaf() {
-        let mut node = TreeNode::new("file.txt".to_string(), PathBuf::from("Z:\\file.txt"));
-        assert!(node.is_leaf());
+    fn test_search_filters_by_extension() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("ext.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("/b/backup.tar.gz".into(), "backup.tar.gz".into()),
+            Index::new("/b/backup.GZ".into(), "backup.GZ".into()),
+            Index::new("/b/backup".into(), "backup".into()),
+            Index::new("/b/.gz".into(), ".gz".into()),
+            Index::new("/b/backup.jpg".into(), "backup.jpg".into()),
+            Index::new("/b/backup.jpeg".into(), "backup.jpeg".into()),
+            Index::new("/b/it's.o'k".into(), "it's.o'k".into()),
+        ])
+        .unwrap();
+        let paths = |extensions: &[&str], case_sensitive: bool| -> Vec<String> {
+            let config = SearchConfig {
+                extensions: extensions.iter().map(|e| e.to_string()).collect(),
+                case_sensitive,
+                ..Default::default()
+            };
+            search_by_keyword(&db, "b", &config)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.path)
+                .collect()
+        };
 
-        node.children.push(TreeNode::new(
-            "child".to_string(),
-            PathBuf::from("Z:\\child"),
-        ));
-        assert!(!node.is_leaf());
+        assert_eq!(
+            paths(&["gz"], false),
+            vec!["/b/backup.GZ", "/b/backup.tar.gz"]
+        );
+        // Leading dots and case are ignored, even for case-sensitive searches
+        assert_eq!(
+            paths(&[".GZ"], true),
+            vec!["/b/backup.GZ", "/b/backup.tar.gz"]
+        );
+        assert_eq!(
+            paths(&["jpg", "jpeg"], false),
+            vec!["/b/backup.jpeg", "/b/backup.jpg"]
+        );
+        assert_eq!(paths(&["tar.gz"], false), vec!["/b/backup.tar.gz"]);
+        assert_eq!(paths(&[".", ""], false).len(), 7);
+        // Quotes in the extension are escaped
+        let config = SearchConfig {
+            extensions: vec!["o'k".to_string()],
+            ..Default::default()
+        };
+        let results = search_by_keyword(&db, "it", &config).unwrap();
+        assert_eq!(results.len(), 1);
     }
 
     #[test]
-    fn test_format_tree_node() {
-        let mut root = TreeNode::new("root".to_string(), PathBuf::from("Z:\\"));
-        root.children.push(TreeNode::new(
-            "file1.txt".to_string(),
-            PathBuf::from("Z:\\file1.txt"),
-        ));
-        root.children.push(TreeNode::new(
-            "file2.txt".to_string(),
-            PathBuf::from("Z:\\file2.txt"),
-        ));
+    fn test_extension_filter_without_ext_column() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("old.reminex.db");
+        rusqlite::Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE files (path TEXT PRIMARY KEY, name TEXT NOT NULL, mtime REAL, size INTEGER);
+                 INSERT INTO files (path, name) VALUES
+                     ('/l/app.log', 'app.log'), ('/l/app.log.txt', 'app.log.txt');",
+            )
+            .unwrap();
+        let config = SearchConfig {
+            extensions: vec!["log".to_string()],
+            ..Default::default()
+        };
+        let search = |db: &Database| -> Vec<String> {
+            search_by_keyword(db, "app", &config)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.path)
+                .collect()
+        };
 
-        let output = format_tree_node(&root.children[0], "", false);
-        assert!(output.contains("├─ file1.txt"));
+        // Not upgraded yet: the suffix match is used
+        assert_eq!(search(&Database::new(&db_path)), vec!["/l/app.log"]);
+        // Upgraded: the backfilled column is used
+        assert_eq!(
+            search(&Database::init(&db_path).unwrap()),
+            vec!["/l/app.log"]
+        );
+    }
 
-        let output = format_tree_node(&root.children[1], "", true);
-        assert!(output.contains("└─ file2.txt"));
+    #[test]
+    fn test_owner_and_mode_filters_without_columns() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("old.reminex.db");
+        rusqlite::Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE files (path TEXT PRIMARY KEY, name TEXT NOT NULL, mtime REAL, size INTEGER);
+                 INSERT INTO files (path, name) VALUES ('/l/app.log', 'app.log');",
+            )
+            .unwrap();
+        let db = Database::new(&db_path);
+
+        let owner = SearchConfig {
+            owner: Some(1000),
+            ..Default::default()
+        };
+        assert!(search_by_keyword(&db, "app", &owner).unwrap().is_empty());
+        let mode = SearchConfig {
+            mode: Some(0o644),
+            ..Default::default()
+        };
+        assert!(search_by_keyword(&db, "app", &mode).unwrap().is_empty());
+        // The other filters still bind their values in order
+        let mixed = SearchConfig {
+            owner: Some(1000),
+            max_size: Some(10),
+            ..Default::default()
+        };
+        assert!(search_by_keyword(&db, "app", &mixed).unwrap().is_empty());
     }
 
     #[test]
-    fn test_search_empty_keyword() {
-        let (_temp, db) = create_test_db_with_data();
+    fn test_indexed_after_filter_without_column() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("old.reminex.db");
+        rusqlite::Connection::open(&db_path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE files (path TEXT PRIMARY KEY, name TEXT NOT NULL, mtime REAL, size INTEGER);
+                 INSERT INTO files (path, name, mtime) VALUES ('/l/app.log', 'app.log', 100.0);",
+            )
+            .unwrap();
+        let db = Database::new(&db_path);
+
+        let config = SearchConfig {
+            indexed_after: Some(0.0),
+            ..Default::default()
+        };
+        assert!(search_by_keyword(&db, "app", &config).unwrap().is_empty());
+        // The mtime filter before it is unaffected
+        let config = SearchConfig {
+            modified_after: Some(50.0),
+            ..Default::default()
+        };
+        assert_eq!(search_by_keyword(&db, "app", &config).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_search_normalizes_path_separators() {
+        let temp_dir = TempDir::new().unwrap();
+        let db = Database::init(temp_dir.path().join("sep.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::new("D:\\a\\b\\win.txt".into(), "win.txt".into()),
+            Index::new("/mnt/a/b/unix.txt".into(), "unix.txt".into()),
+            Index::new("/mnt/ab/other.txt".into(), "other.txt".into()),
+        ])
+        .unwrap();
+
         let config = SearchConfig::default();
+        for query in ["a/b", "a\\b"] {
+            let results = search_by_keyword(&db, query, &config).unwrap();
+            let paths: Vec<_> = results.iter().map(|r| r.path.as_str()).collect();
+            // Display paths keep their original separators
+            assert_eq!(paths, vec!["/mnt/a/b/unix.txt", "D:\\a\\b\\win.txt"]);
+        }
+    }
 
-        let results = search_by_keyword(&db, "", &config).unwrap();
-        assert_eq!(results.len(), 0);
+    #[test]
+    fn test_merge_by_keyword_is_deterministic() {
+        fn result(path: &str) -> SearchResult {
+            SearchResult {
+                path: path.to_string(),
+                name: path.rsplit('/').next().unwrap().to_string(),
+                ..Default::default()
+            }
+        }
 
-        let results = search_by_keyword(&db, "   ", &config).unwrap();
-        assert_eq!(results.len(), 0);
+        let results = vec![
+            (
+                "b.reminex.db".to_string(),
+                "beta".to_string(),
+                vec![result("/z/beta.txt")],
+            ),
+            (
+                "b.reminex.db".to_string(),
+                "alpha".to_string(),
+                vec![result("/z/alpha.txt")],
+            ),
+            (
+                "a.reminex.db".to_string(),
+                "beta".to_string(),
+                vec![result("/a/beta.txt"), result("/m/abeta.txt")],
+            ),
+        ];
+
+        let merged = merge_by_keyword(results.clone(), SortBy::Path, false);
+        let keywords: Vec<_> = merged.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(keywords, vec!["beta", "alpha"]);
+        let paths: Vec<_> = merged[0].1.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, vec!["/a/beta.txt", "/m/abeta.txt", "/z/beta.txt"]);
+
+        let merged = merge_by_keyword(results, SortBy::Name, false);
+        let paths: Vec<_> = merged[0].1.iter().map(|r| r.path.as_str()).collect();
+        assert_eq!(paths, vec!["/m/abeta.txt", "/a/beta.txt", "/z/beta.txt"]);
     }
 }
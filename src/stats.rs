@@ -0,0 +1,406 @@
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::category::extension_of;
+use crate::db::{Database, IndexInfo};
+
+/// File count and byte total for one extension.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExtensionStats {
+    /// Lowercased extension without the dot (empty for files without one)
+    pub ext: String,
+    /// Number of indexed files, including those without a known size
+    pub count: u64,
+    /// Sum of known file sizes in bytes
+    pub bytes: u64,
+}
+
+/// A file with a known size, for the largest-files listing.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LargeFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// Number of entries in [`DbStats::largest_files`]
+pub const LARGEST_FILES: usize = 10;
+
+/// Aggregate statistics for a database.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DbStats {
+    /// Total number of indexed files
+    pub file_count: u64,
+    /// Number of files without a recorded size
+    pub unknown_size_count: u64,
+    /// Sum of known file sizes in bytes
+    pub total_bytes: u64,
+    /// Per-extension totals, sorted by bytes, then count, descending
+    pub extensions: Vec<ExtensionStats>,
+    /// Largest files with a known size, biggest first
+    pub largest_files: Vec<LargeFile>,
+    /// Earliest recorded modification time (Unix seconds)
+    pub oldest_mtime: Option<f64>,
+    /// Latest recorded modification time (Unix seconds)
+    pub newest_mtime: Option<f64>,
+    /// Root, time and reminex version of the latest scan; empty in
+    /// [`DbStats::combine`] totals
+    #[serde(flatten)]
+    pub index_info: IndexInfo,
+}
+
+impl DbStats {
+    /// Combines the stats of several databases into one total.
+    ///
+    /// Extensions are summed across databases, the largest files are the
+    /// overall [`LARGEST_FILES`] biggest, and the mtime range spans all.
+    pub fn combine<'a>(all: impl IntoIterator<Item = &'a DbStats>) -> DbStats {
+        let mut total = DbStats::default();
+        let mut by_ext: HashMap<&str, ExtensionStats> = HashMap::new();
+
+        for stats in all {
+            total.file_count += stats.file_count;
+            total.unknown_size_count += stats.unknown_size_count;
+            total.total_bytes += stats.total_bytes;
+            for ext in &stats.extensions {
+                let entry = by_ext.entry(&ext.ext).or_insert_with(|| ExtensionStats {
+                    ext: ext.ext.clone(),
+                    count: 0,
+                    bytes: 0,
+                });
+                entry.count += ext.count;
+                entry.bytes += ext.bytes;
+            }
+            total
+                .largest_files
+                .extend(stats.largest_files.iter().cloned());
+            total.oldest_mtime = min_mtime(total.oldest_mtime, stats.oldest_mtime);
+            total.newest_mtime = max_mtime(total.newest_mtime, stats.newest_mtime);
+        }
+
+        total.extensions = by_ext.into_values().collect();
+        sort_extensions(&mut total.extensions);
+        total
+            .largest_files
+            .sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        total.largest_files.truncate(LARGEST_FILES);
+        total
+    }
+
+    /// Share of the known bytes taken up by `ext`, as a percentage.
+    pub fn byte_share(&self, ext: &ExtensionStats) -> f64 {
+        if self.total_bytes == 0 {
+            0.0
+        } else {
+            ext.bytes as f64 * 100.0 / self.total_bytes as f64
+        }
+    }
+}
+
+/// Computes per-extension file counts and byte totals for a database.
+///
+/// Files with a NULL size (indexed without metadata) are counted but
+/// excluded from the byte totals.
+pub fn collect_stats(db: &Database) -> Result<DbStats> {
+    collect(db, None)
+}
+
+/// Like [`collect_stats`], limited to the rows indexed under one scan root.
+///
+/// Rows are selected by `root_id`, so the root must be registered; see
+/// [`Database::root_id`]. Rows of registered roots nested inside it are
+/// left out, as they belong to the deeper root.
+pub fn collect_root_stats(db: &Database, root_id: i64) -> Result<DbStats> {
+    collect(db, Some(root_id))
+}
+
+fn collect(db: &Database, root_id: Option<i64>) -> Result<DbStats> {
+    let index_info = db.index_info()?;
+    let (scope, params) = match root_id {
+        Some(root_id) => ("root_id = ?1", vec![root_id]),
+        None => ("1", Vec::new()),
+    };
+    db.batch_operation_readonly(|conn| {
+        let mut stmt = conn
+            .prepare(&format!("SELECT name, size FROM files WHERE {scope}"))
+            .context("Failed to prepare stats query")?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(&params), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Option<i64>>(1)?))
+            })
+            .context("Failed to query files")?;
+
+        let mut stats = DbStats::default();
+        let mut by_ext: HashMap<String, ExtensionStats> = HashMap::new();
+
+        for row in rows {
+            let (name, size) = row.context("Failed to read file row")?;
+            let ext = extension_of(&name).unwrap_or_default();
+            let entry = by_ext.entry(ext.clone()).or_insert_with(|| ExtensionStats {
+                ext,
+                count: 0,
+                bytes: 0,
+            });
+
+            entry.count += 1;
+            stats.file_count += 1;
+            match size {
+                Some(size) => {
+                    let size = size.max(0) as u64;
+                    entry.bytes += size;
+                    stats.total_bytes += size;
+                }
+                None => stats.unknown_size_count += 1,
+            }
+        }
+
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT path, size FROM files WHERE {scope} AND size IS NOT NULL
+                 ORDER BY size DESC, path LIMIT {LARGEST_FILES}"
+            ))
+            .context("Failed to prepare largest files query")?;
+        stats.largest_files = stmt
+            .query_map(rusqlite::params_from_iter(&params), |row| {
+                Ok(LargeFile {
+                    path: row.get(0)?,
+                    size: row.get::<_, i64>(1)?.max(0) as u64,
+                })
+            })
+            .context("Failed to query largest files")?
+            .collect::<rusqlite::Result<_>>()?;
+
+        (stats.oldest_mtime, stats.newest_mtime) = conn
+            .query_row(
+                &format!("SELECT MIN(mtime), MAX(mtime) FROM files WHERE {scope}"),
+                rusqlite::params_from_iter(&params),
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .context("Failed to query modification time range")?;
+
+        stats.extensions = by_ext.into_values().collect();
+        sort_extensions(&mut stats.extensions);
+        stats.index_info = index_info;
+
+        Ok(stats)
+    })
+}
+
+/// Sorts extensions by bytes, then count, descending, then by name.
+fn sort_extensions(extensions: &mut [ExtensionStats]) {
+    extensions.sort_by(|a, b| {
+        b.bytes
+            .cmp(&a.bytes)
+            .then(b.count.cmp(&a.count))
+            .then_with(|| a.ext.cmp(&b.ext))
+    });
+}
+
+fn min_mtime(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn max_mtime(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 MiB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Parses a human-readable size such as `512`, `10MB` or `1.5 GiB` into bytes.
+///
+/// Units are case-insensitive and binary, matching [`format_bytes`]: `K`,
+/// `KB` and `KiB` all mean 1024 bytes. A bare number is in bytes.
+pub fn parse_bytes(text: &str) -> Result<u64> {
+    const UNITS: [(&str, u32); 5] = [("", 0), ("k", 1), ("m", 2), ("g", 3), ("t", 4)];
+
+    let text = text.trim();
+    let split = text
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let unit = unit.trim().to_ascii_lowercase();
+    let prefix = unit
+        .strip_suffix("ib")
+        .or_else(|| unit.strip_suffix('b'))
+        .unwrap_or(&unit);
+
+    let value: f64 = number
+        .parse()
+        .ok()
+        .filter(|value: &f64| value.is_finite())
+        .with_context(|| format!("Invalid size: {}", text))?;
+    let Some(&(_, power)) = UNITS.iter().find(|(name, _)| *name == prefix) else {
+        bail!("Unknown size unit in: {}", text);
+    };
+    Ok((value * 1024f64.powi(power as i32)).round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Index;
+
+    #[test]
+    fn test_collect_stats_by_extension() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("stats.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::with_metadata("/v/a.mp4".into(), "a.mp4".into(), 1700000000.0, 800),
+            Index::with_metadata("/v/b.MP4".into(), "b.MP4".into(), 1600000000.5, 100),
+            Index::with_metadata("/d/c.txt".into(), "c.txt".into(), 0.0, 100),
+            Index::new("/d/d.txt".into(), "d.txt".into()),
+            Index::new("/d/Makefile".into(), "Makefile".into()),
+        ])
+        .unwrap();
+
+        let stats = collect_stats(&db).unwrap();
+        assert_eq!(stats.file_count, 5);
+        assert_eq!(stats.unknown_size_count, 2);
+        assert_eq!(stats.total_bytes, 1000);
+        assert_eq!(stats.oldest_mtime, Some(0.0));
+        assert_eq!(stats.newest_mtime, Some(1700000000.0));
+
+        let rows: Vec<_> = stats
+            .extensions
+            .iter()
+            .map(|e| (e.ext.as_str(), e.count, e.bytes))
+            .collect();
+        assert_eq!(rows, vec![("mp4", 2, 900), ("txt", 2, 100), ("", 1, 0)]);
+        assert_eq!(stats.byte_share(&stats.extensions[0]), 90.0);
+
+        let largest: Vec<_> = stats
+            .largest_files
+            .iter()
+            .map(|f| (f.path.as_str(), f.size))
+            .collect();
+        assert_eq!(
+            largest,
+            vec![("/v/a.mp4", 800), ("/d/c.txt", 100), ("/v/b.MP4", 100)]
+        );
+    }
+
+    #[test]
+    fn test_collect_root_stats_counts_only_that_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("stats.reminex.db")).unwrap();
+        db.add_idxs(&[
+            Index::with_metadata("/v/a.mp4".into(), "a.mp4".into(), 1700000000.0, 800),
+            Index::with_metadata("/v/b.txt".into(), "b.txt".into(), 1600000000.0, 100),
+            Index::with_metadata("/d/c.txt".into(), "c.txt".into(), 0.0, 50),
+        ])
+        .unwrap();
+        let root = db.register_root("/v").unwrap();
+        db.register_root("/d").unwrap();
+
+        let stats = collect_root_stats(&db, root).unwrap();
+        assert_eq!(stats.file_count, 2);
+        assert_eq!(stats.total_bytes, 900);
+        assert_eq!(stats.oldest_mtime, Some(1600000000.0));
+        assert_eq!(stats.largest_files.len(), 2);
+        assert_eq!(stats.largest_files[0].path, "/v/a.mp4");
+    }
+
+    #[test]
+    fn test_combine_sums_databases() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let first = Database::init(temp_dir.path().join("first.reminex.db")).unwrap();
+        first
+            .add_idxs(&[
+                Index::with_metadata("/a/x.txt".into(), "x.txt".into(), 200.0, 10),
+                Index::new("/a/y.jpg".into(), "y.jpg".into()),
+            ])
+            .unwrap();
+        let second = Database::init(temp_dir.path().join("second.reminex.db")).unwrap();
+        second
+            .add_idxs(&[Index::with_metadata(
+                "/b/z.txt".into(),
+                "z.txt".into(),
+                100.0,
+                30,
+            )])
+            .unwrap();
+        let empty = DbStats::default();
+
+        let stats = [
+            collect_stats(&first).unwrap(),
+            collect_stats(&second).unwrap(),
+        ];
+        let total = DbStats::combine(stats.iter().chain([&empty]));
+        assert_eq!(total.file_count, 3);
+        assert_eq!(total.unknown_size_count, 1);
+        assert_eq!(total.total_bytes, 40);
+        let rows: Vec<_> = total
+            .extensions
+            .iter()
+            .map(|e| (e.ext.as_str(), e.count, e.bytes))
+            .collect();
+        assert_eq!(rows, vec![("txt", 2, 40), ("jpg", 1, 0)]);
+        assert_eq!(total.largest_files[0].path, "/b/z.txt");
+        assert_eq!(
+            (total.oldest_mtime, total.newest_mtime),
+            (Some(100.0), Some(200.0))
+        );
+
+        assert_eq!(DbStats::combine([]), DbStats::default());
+    }
+
+    #[test]
+    fn test_collect_stats_of_empty_database() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db = Database::init(temp_dir.path().join("empty.reminex.db")).unwrap();
+
+        let stats = collect_stats(&db).unwrap();
+        let expected = DbStats {
+            index_info: IndexInfo {
+                reminex_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(stats, expected);
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(0), "0 B");
+        assert_eq!(format_bytes(1023), "1023 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        assert_eq!(parse_bytes("512").unwrap(), 512);
+        assert_eq!(parse_bytes("512b").unwrap(), 512);
+        assert_eq!(parse_bytes("10MB").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_bytes("10m").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_bytes(" 1.5 GiB ").unwrap(), 1536 * 1024 * 1024);
+        assert_eq!(parse_bytes("2k").unwrap(), 2048);
+        assert!(parse_bytes("").is_err());
+        assert!(parse_bytes("MB").is_err());
+        assert!(parse_bytes("10 parsecs").is_err());
+        assert!(parse_bytes("1.2.3k").is_err());
+    }
+}
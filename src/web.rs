@@ -1,41 +1,132 @@
 use axum::{
     Router,
-    extract::{Query, State},
-    http::StatusCode,
-    response::{Html, IntoResponse, Json},
+    extract::{Path as UrlPath, Query, State},
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode, header},
+    response::{Html, IntoResponse, Json, Response},
     routing::{get, post},
 };
 use chrono::Utc;
+use rust_embed::RustEmbed;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use tower_http::services::ServeDir;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::{Mutex, Notify, OwnedSemaphorePermit, Semaphore};
+use tower_http::set_header::SetResponseHeaderLayer;
 
-use crate::db::Database;
+use crate::cache::{CacheConfig, ResultCache, db_fingerprint};
+use crate::db::{Database, IndexInfo};
 use crate::export;
-use crate::history::{SearchHistory, SearchHistoryItem};
+use crate::history::{DEFAULT_MAX_ENTRIES, SearchHistory, SearchHistoryItem};
 use crate::indexer;
 use crate::searcher::{
-    SearchConfig, SearchResult, TreeNode, build_tree, parse_search_keywords,
-    parse_search_keywords_with_delimiters, search_in_selected_database,
+    DatabaseResults, KeywordCombine, SearchConfig, SearchMode, SearchResult, SortBy, TreeNode,
+    build_tree, merge_by_keyword, parse_search_keywords, parse_search_keywords_with_delimiters,
+    search_in_selected_database_counted,
 };
+use crate::stats::{DbStats, collect_stats};
 
 /// Web server state
 #[derive(Clone)]
 pub struct AppState {
     pub db_paths: Vec<PathBuf>,
     pub history: Arc<Mutex<SearchHistory>>,
+    pub cache: Arc<Mutex<ResultCache<SearchOutput>>>,
+    /// Whether to shut down after the first search request
+    pub once: bool,
+    /// Signalled when the server should shut down gracefully
+    pub shutdown: Arc<Notify>,
+    /// One single-permit semaphore per target database of `/api/index`
+    /// with a job running
+    pub index_jobs: IndexJobs,
+}
+
+/// Semaphores of the running `/api/index` jobs, keyed by database path
+type IndexJobs = Arc<std::sync::Mutex<HashMap<PathBuf, Arc<Semaphore>>>>;
+
+impl AppState {
+    /// Claims the right to index `db_path`, or `None` if a job already runs.
+    ///
+    /// The job holds the returned claim until it finishes.
+    fn try_start_index_job(&self, db_path: &Path) -> Option<IndexJob> {
+        // Relative and absolute spellings of a path name the same database
+        let key = std::path::absolute(db_path).unwrap_or_else(|_| db_path.to_path_buf());
+        let semaphore = self
+            .index_jobs
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_insert_with(|| Arc::new(Semaphore::new(1)))
+            .clone();
+        let permit = semaphore.try_acquire_owned().ok()?;
+        Some(IndexJob {
+            jobs: self.index_jobs.clone(),
+            key,
+            permit: Some(permit),
+        })
+    }
+}
+
+/// Claim on indexing one database, see [`AppState::try_start_index_job`]
+///
+/// Dropping it releases the database and, once nothing else refers to its
+/// semaphore, removes the entry from [`AppState::index_jobs`].
+struct IndexJob {
+    jobs: IndexJobs,
+    key: PathBuf,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for IndexJob {
+    fn drop(&mut self) {
+        // Under the lock, no other request can take a new reference
+        let mut jobs = self
+            .jobs
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        drop(self.permit.take());
+        if jobs
+            .get(&self.key)
+            .is_some_and(|semaphore| Arc::strong_count(semaphore) == 1)
+        {
+            jobs.remove(&self.key);
+        }
+    }
+}
+
+/// Options for the web server
+#[derive(Debug, Clone, Default)]
+pub struct ServerOptions {
+    /// Search result cache settings
+    pub cache: CacheConfig,
+    /// Shut down after answering a single `/api/search` request
+    pub once: bool,
+    /// Search history file (defaults to [`SearchHistory::default_path`])
+    pub history_path: Option<PathBuf>,
 }
 
 /// Search request from web client
-#[derive(Debug, Deserialize)]
+///
+/// Accepted as a query string (`GET`) or a JSON body (`POST`). Query strings
+/// are decoded as `application/x-www-form-urlencoded`, so a literal `+` means
+/// a space and `%`, `&`, `+` and `#` in paths must be percent-encoded
+/// (`%25`, `%26`, `%2B`, `%23`). Clients sending arbitrary paths should prefer
+/// the JSON body, which needs no extra encoding.
+#[derive(Debug, Clone, Deserialize)]
 pub struct SearchRequest {
     pub query: String,
     #[serde(default = "default_selected_db")]
     pub selected_db: String,
-    #[serde(default)]
+    /// Results per keyword and page; also accepted as `page_size`
+    #[serde(default, alias = "page_size")]
     pub limit: Option<usize>,
+    /// Matches to skip per keyword, for paging; takes precedence over `page`
+    #[serde(default)]
+    pub offset: Option<usize>,
+    /// 1-based page of `limit` results per keyword
+    #[serde(default)]
+    pub page: Option<usize>,
     #[serde(default)]
     pub name_only: bool,
     #[serde(default)]
@@ -46,28 +137,136 @@ pub struct SearchRequest {
     pub include_filters: Option<String>,
     #[serde(default)]
     pub exclude_filters: Option<String>,
+    /// Comma-separated extensions to keep, e.g. "jpg,png"
+    #[serde(default)]
+    pub extensions: Option<String>,
     #[serde(default)]
     pub delimiters: Option<String>, // JSON string of custom delimiters
+    #[serde(default)]
+    pub stem: bool,
+    /// Keyword matching: "substring" (default), "glob", "regex" or "fuzzy"
+    #[serde(default)]
+    pub mode: SearchMode,
+    /// Keyword combination: "separate" (default, one group per keyword),
+    /// "and" (files matching every keyword) or "or" (files matching any),
+    /// the last two as a single group
+    #[serde(default)]
+    pub combine: KeywordCombine,
+    /// Result order: "path" (default), "name", "size", "mtime" or "score",
+    /// which ranks fuzzy matches best first
+    #[serde(default)]
+    pub sort: SortBy,
+    /// Reverse `sort`, e.g. largest or newest first
+    #[serde(default)]
+    pub desc: bool,
+    /// Keep keywords without hits as `count: 0` entries (on by default)
+    #[serde(default = "default_include_empty")]
+    pub include_empty: bool,
+    /// Nest results by database, then keyword, instead of merging databases
+    #[serde(default)]
+    pub group_by_db: bool,
 }
 
 fn default_selected_db() -> String {
     "all".to_string()
 }
 
+fn default_include_empty() -> bool {
+    true
+}
+
+impl SearchRequest {
+    /// Results per keyword and page
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(2000)
+    }
+
+    /// Matches skipped per keyword, from `offset` or `page`
+    fn offset(&self) -> usize {
+        self.offset
+            .or_else(|| {
+                self.page
+                    .map(|page| page.saturating_sub(1).saturating_mul(self.limit()))
+            })
+            .unwrap_or(0)
+    }
+
+    /// Builds a normalized key identifying this request for result caching.
+    fn cache_key(&self) -> String {
+        let normalized = SearchRequest {
+            query: self.query.trim().to_string(),
+            selected_db: self.selected_db.trim().to_string(),
+            root_path: self.root_path.as_ref().map(|s| s.trim().to_string()),
+            ..self.clone()
+        };
+        format!("{:?}", normalized)
+    }
+}
+
+/// How results in a [`SearchResponse`] are grouped
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Grouping {
+    /// Merged across databases into `results`, one entry per keyword
+    #[default]
+    Keyword,
+    /// Nested in `databases`, one entry per database, then per keyword
+    Database,
+}
+
 /// Search response to web client
 #[derive(Debug, Serialize)]
 pub struct SearchResponse {
     pub success: bool,
+    pub grouping: Grouping,
+    /// Keyword groups merged across databases (`grouping: "keyword"`)
     pub results: Vec<KeywordResults>,
+    /// Per-database keyword groups (`grouping: "database"`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub databases: Vec<DatabaseGroup>,
+    /// Some keyword has matches past this page
+    pub has_more: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
 }
 
+/// Results from one database, grouped by keyword
+#[derive(Debug, Serialize, Clone)]
+pub struct DatabaseGroup {
+    pub database: String,
+    pub label: Option<String>,
+    pub results: Vec<KeywordResults>,
+}
+
+/// Results of one search request, as cached between identical requests
+#[derive(Debug, Clone, Default)]
+pub struct SearchOutput {
+    pub grouping: Grouping,
+    pub results: Vec<KeywordResults>,
+    pub databases: Vec<DatabaseGroup>,
+    pub has_more: bool,
+}
+
+impl SearchOutput {
+    /// Total number of matches across all groups.
+    fn total_count(&self) -> usize {
+        self.results
+            .iter()
+            .chain(self.databases.iter().flat_map(|db| &db.results))
+            .map(|kr| kr.count)
+            .sum()
+    }
+}
+
 /// Results for a single keyword
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct KeywordResults {
     pub keyword: String,
+    /// Number of results on this page
     pub count: usize,
+    /// Number of matches in total, including those past this page
+    #[serde(default)]
+    pub total: usize,
     pub tree: TreeNodeJson,
     pub root_path: String,
 }
@@ -83,6 +282,20 @@ pub struct IndexRequest {
     pub with_metadata: bool,
     #[serde(default)]
     pub incremental: bool,
+    #[serde(default)]
+    pub follow_junctions: bool,
+    #[serde(default)]
+    pub index_archives: bool,
+    #[serde(default)]
+    pub respect_gitignore: bool,
+    /// Globs of files and directories to leave out, see
+    /// [`indexer::IndexConfig::exclude_globs`]
+    #[serde(default)]
+    pub exclude_patterns: Vec<String>,
+    /// Deepest directory level to index, see
+    /// [`indexer::IndexConfig::max_depth`]
+    #[serde(default)]
+    pub max_depth: Option<usize>,
 }
 
 fn default_batch_size() -> usize {
@@ -99,9 +312,22 @@ pub struct IndexResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub skipped_paths: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub counts: Option<IndexCounts>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
 }
 
+/// What an index job wrote, see [`indexer::IndexResult`]
+#[derive(Debug, Serialize, PartialEq)]
+pub struct IndexCounts {
+    pub added: usize,
+    pub updated: usize,
+    /// Unchanged files left alone by an incremental scan
+    pub skipped: usize,
+    /// Directories not entered because of `max_depth`
+    pub depth_limited: usize,
+}
+
 /// JSON-serializable tree node
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TreeNodeJson {
@@ -109,6 +335,12 @@ pub struct TreeNodeJson {
     pub path: String,
     pub is_leaf: bool,
     pub children: Vec<TreeNodeJson>,
+    /// File size in bytes, on leaves indexed with metadata
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<i64>,
+    /// Modification time as unix seconds, on leaves indexed with metadata
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtime: Option<f64>,
 }
 
 impl From<&TreeNode> for TreeNodeJson {
@@ -118,6 +350,8 @@ impl From<&TreeNode> for TreeNodeJson {
             path: node.path.to_string_lossy().to_string(),
             is_leaf: node.is_leaf(),
             children: node.children.iter().map(TreeNodeJson::from).collect(),
+            size: node.size,
+            mtime: node.mtime,
         }
     }
 }
@@ -137,40 +371,57 @@ fn parse_filter_keywords(input: &str) -> Vec<String> {
 /// This function replaces the original root path in database with a new one.
 /// Useful when database is moved between different machines or mount points.
 ///
+/// `items` come from one database, and `indexed_roots` are the scan roots it
+/// recorded: paths under one of them have the longest such root replaced.
+/// Other paths, such as those indexed before roots were recorded, have a
+/// prefix guessed from the path itself replaced:
+///
 /// For Windows: Supports drive letter replacement (e.g., F:\ -> D:\)
 /// For all systems: Supports full path prefix replacement
 fn apply_root_path_replacement(
-    results: Vec<(String, Vec<SearchResult>)>,
+    items: &mut [SearchResult],
     new_root: &str,
-) -> Vec<(String, Vec<SearchResult>)> {
-    // Try to detect the common prefix from the first result
-    let common_prefix = results
-        .iter()
-        .flat_map(|(_, items)| items.first())
-        .map(|item| &item.path)
-        .next()
-        .and_then(|first_path| detect_root_prefix(first_path));
-
-    if common_prefix.is_none() {
-        return results; // No results or couldn't detect prefix
+    indexed_roots: &[String],
+) {
+    for item in items {
+        let indexed_root = indexed_roots
+            .iter()
+            .filter(|root| {
+                item.path
+                    .strip_prefix(root.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '\\']))
+            })
+            .max_by_key(|root| root.len());
+        let old_prefix = match indexed_root {
+            Some(root) => Some(root.clone()),
+            None => detect_root_prefix(&item.path),
+        };
+        if let Some(old_prefix) = old_prefix {
+            item.path = replace_path_prefix(&item.path, &old_prefix, new_root);
+        }
     }
+}
 
-    let old_prefix = common_prefix.unwrap();
-    let new_root = new_root.trim_end_matches(['/', '\\']);
-
-    results
-        .into_iter()
-        .map(|(keyword, items)| {
-            let replaced_items = items
-                .into_iter()
-                .map(|mut item| {
-                    item.path = replace_path_prefix(&item.path, &old_prefix, new_root);
-                    item
-                })
-                .collect();
-            (keyword, replaced_items)
-        })
-        .collect()
+/// Returns the scan roots recorded in a database, for
+/// [`apply_root_path_replacement`].
+fn recorded_roots(db_paths: &[PathBuf], database: &str) -> Vec<String> {
+    let Some(path) = db_paths
+        .iter()
+        .find(|p| p.file_name().and_then(|n| n.to_str()) == Some(database))
+    else {
+        return Vec::new();
+    };
+    let db = Database::new(path);
+    let mut roots = db.root_paths().unwrap_or_default();
+    if let Ok(IndexInfo {
+        indexed_root: Some(root),
+        ..
+    }) = db.index_info()
+        && !roots.contains(&root)
+    {
+        roots.push(root);
+    }
+    roots
 }
 
 /// Detect the root prefix from a file path
@@ -194,29 +445,150 @@ fn detect_root_prefix(path: &str) -> Option<String> {
 }
 
 /// Replace the prefix of a path
+///
+/// The rest of the path is joined with the separator style of the new
+/// prefix (e.g. `/mnt/data` or `D:\`), or of the path itself when the new
+/// prefix has none, such as a bare drive `D:`.
 fn replace_path_prefix(path: &str, old_prefix: &str, new_prefix: &str) -> String {
-    if let Some(remainder) = path.strip_prefix(old_prefix) {
-        // Handle both forward and backward slashes
-        let remainder = remainder.trim_start_matches(['/', '\\']);
-
-        if remainder.is_empty() {
-            new_prefix.to_string()
+    let Some(remainder) = path.strip_prefix(old_prefix) else {
+        return path.to_string();
+    };
+    // Handle both forward and backward slashes
+    let remainder = remainder.trim_start_matches(['/', '\\']);
+    let trimmed = new_prefix.trim_end_matches(['/', '\\']);
+    if remainder.is_empty() {
+        return if trimmed.is_empty() {
+            new_prefix
         } else {
-            format!("{}\\{}", new_prefix, remainder)
+            trimmed
         }
-    } else {
-        path.to_string()
+        .to_string();
     }
+
+    let old_separator = separator_of(path);
+    let separator = separator_of(new_prefix)
+        .or(old_separator)
+        .unwrap_or(std::path::MAIN_SEPARATOR);
+    let remainder = match old_separator {
+        Some(old) if old != separator => remainder.replace(old, &separator.to_string()),
+        _ => remainder.to_string(),
+    };
+    format!("{trimmed}{separator}{remainder}")
+}
+
+/// The first path separator used in a path, if any
+fn separator_of(path: &str) -> Option<char> {
+    path.chars().find(|c| matches!(c, '/' | '\\'))
 }
 
-/// Search handler
+/// Search handler for query-string requests (`GET /api/v1/search`)
 async fn search_handler(
     State(state): State<Arc<AppState>>,
     Query(params): Query<SearchRequest>,
 ) -> impl IntoResponse {
+    respond_to_search(&state, params).await
+}
+
+/// Search handler for JSON body requests (`POST /api/v1/search`)
+async fn search_post_handler(
+    State(state): State<Arc<AppState>>,
+    Json(params): Json<SearchRequest>,
+) -> impl IntoResponse {
+    respond_to_search(&state, params).await
+}
+
+async fn respond_to_search(state: &Arc<AppState>, params: SearchRequest) -> Json<SearchResponse> {
+    let response = search(state, params).await;
+
+    // In single-request mode, graceful shutdown still delivers this response
+    if state.once {
+        state.shutdown.notify_one();
+    }
+
+    response
+}
+
+/// Answers a search request from the cache or the databases
+async fn search(state: &Arc<AppState>, params: SearchRequest) -> Json<SearchResponse> {
+    // Serve identical requests from the cache while the databases are unchanged
+    let cache_key = params.cache_key();
+    let fingerprint = db_fingerprint(&state.db_paths);
+    let cached = state.cache.lock().await.get(&cache_key, &fingerprint);
+
+    let output = match cached {
+        Some(output) => output,
+        None => match run_search(state, &params) {
+            Ok(results) => {
+                state
+                    .cache
+                    .lock()
+                    .await
+                    .insert(cache_key, results.clone(), fingerprint);
+                results
+            }
+            Err(error) => {
+                return Json(SearchResponse {
+                    success: false,
+                    grouping: Grouping::default(),
+                    results: vec![],
+                    databases: vec![],
+                    has_more: false,
+                    error: Some(error),
+                });
+            }
+        },
+    };
+
+    // 自动保存到历史记录（后台执行，不阻塞响应）
+    let history_item = SearchHistoryItem {
+        query: params.query.clone(),
+        selected_db: params.selected_db.clone(),
+        timestamp: Utc::now(),
+        result_count: output.total_count(),
+        name_only: params.name_only,
+        case_sensitive: params.case_sensitive,
+    };
+    let history = state.history.clone();
+    tokio::spawn(async move {
+        let _ = with_history(history, move |history| history.add_entry(history_item)).await;
+    });
+
+    Json(SearchResponse {
+        success: true,
+        grouping: output.grouping,
+        results: output.results,
+        databases: output.databases,
+        has_more: output.has_more,
+        error: None,
+    })
+}
+
+/// Runs a search request against the selected databases and builds result trees
+///
+/// `offset` and `limit` apply per keyword to the merged results. One extra
+/// match is requested to tell whether there are more pages.
+fn run_search(state: &AppState, params: &SearchRequest) -> Result<SearchOutput, String> {
+    // Parse selected databases (support comma-separated list)
+    let selected_dbs: Vec<&str> = params.selected_db.split(',').map(|s| s.trim()).collect();
+    let db_count = if selected_dbs.contains(&"all") {
+        state.db_paths.len()
+    } else {
+        selected_dbs.len()
+    };
+
+    // A single database pages in SQL; merged results are paged after merging,
+    // so each database returns everything up to the end of the page
+    let limit = params.limit();
+    let (sql_offset, page_skip) = if db_count == 1 {
+        (params.offset(), 0)
+    } else {
+        (0, params.offset())
+    };
+
     // Configure search
     let config = SearchConfig {
-        max_results: params.limit.unwrap_or(2000),
+        max_results: page_skip.saturating_add(limit).saturating_add(1),
+        offset: sql_offset,
         search_in_path: !params.name_only,
         case_sensitive: params.case_sensitive,
         include_filters: params
@@ -229,6 +601,17 @@ async fn search_handler(
             .as_ref()
             .map(|s| parse_filter_keywords(s))
             .unwrap_or_default(),
+        extensions: params
+            .extensions
+            .as_ref()
+            .map(|s| parse_filter_keywords(s))
+            .unwrap_or_default(),
+        match_stem: params.stem,
+        search_mode: params.mode,
+        combine: params.combine,
+        sort_by: params.sort,
+        descending: params.desc,
+        ..Default::default()
     };
 
     // Parse keywords with custom delimiters if provided
@@ -254,48 +637,123 @@ async fn search_handler(
         parse_search_keywords(&params.query)
     };
 
-    // Parse selected databases (support comma-separated list)
-    let selected_dbs: Vec<&str> = params.selected_db.split(',').map(|s| s.trim()).collect();
-
-    // Collect all results from all selected databases
+    // Collect all results from all selected databases, with each
+    // (database, keyword) total kept aside
     let mut all_results = Vec::new();
+    let mut totals = Vec::new();
 
     for db in selected_dbs {
-        match search_in_selected_database(&state.db_paths, db, &keywords, &config) {
-            Ok(results) => all_results.extend(results),
+        match search_in_selected_database_counted(&state.db_paths, db, &keywords, &config) {
+            Ok(results) => {
+                for (db_name, keyword, total, items) in results {
+                    totals.push((db_name.clone(), keyword.clone(), total));
+                    all_results.push((db_name, keyword, items));
+                }
+            }
             Err(e) => {
-                return Json(SearchResponse {
-                    success: false,
-                    results: vec![],
-                    error: Some(format!("Search failed in database '{}': {}", db, e)),
-                });
+                return Err(format!("Search failed in database '{}': {}", db, e));
             }
         }
     }
+    let total_of = |database: Option<&str>, keyword: &str| -> usize {
+        totals
+            .iter()
+            .filter(|(db, kw, _)| kw == keyword && database.is_none_or(|d| d == db))
+            .map(|(_, _, total)| total)
+            .sum()
+    };
 
-    let results = all_results;
+    // Roots are replaced per database, before results of several are merged
+    if let Some(ref new_root) = params.root_path {
+        let mut roots: HashMap<String, Vec<String>> = HashMap::new();
+        for (database, _, items) in &mut all_results {
+            let indexed_roots = roots
+                .entry(database.clone())
+                .or_insert_with(|| recorded_roots(&state.db_paths, database));
+            apply_root_path_replacement(items, new_root, indexed_roots);
+        }
+    }
 
-    // Group results by keyword (merge across databases if searching all)
-    let mut keyword_map: std::collections::HashMap<String, Vec<SearchResult>> =
-        std::collections::HashMap::new();
+    if !params.group_by_db {
+        // Group results by keyword (merge across databases if searching all)
+        let mut merged = merge_by_keyword(all_results, params.sort, params.desc);
+        let has_more = page_groups(&mut merged, page_skip, limit);
+        return Ok(SearchOutput {
+            grouping: Grouping::Keyword,
+            results: build_keyword_results(merged, |kw| total_of(None, kw), params),
+            databases: vec![],
+            has_more,
+        });
+    }
 
-    for (_db_name, keyword, items) in results {
-        keyword_map.entry(keyword).or_default().extend(items);
+    // Keep each database's results apart, in the order they were searched
+    let mut by_db: Vec<(String, DatabaseResults)> = Vec::new();
+    for row in all_results {
+        match by_db.iter_mut().find(|(db_name, _)| *db_name == row.0) {
+            Some((_, rows)) => rows.push(row),
+            None => by_db.push((row.0.clone(), vec![row])),
+        }
     }
 
-    // Apply root path replacement if specified
-    let processed_results: Vec<(String, Vec<SearchResult>)> = keyword_map.into_iter().collect();
-    let processed_results = if let Some(ref new_root) = params.root_path {
-        apply_root_path_replacement(processed_results, new_root)
-    } else {
-        processed_results
-    };
+    let mut has_more = false;
+    let databases = by_db
+        .into_iter()
+        .map(|(database, rows)| {
+            let label = state
+                .db_paths
+                .iter()
+                .find(|p| p.file_name().and_then(|n| n.to_str()) == Some(database.as_str()))
+                .and_then(|p| Database::new(p).label().ok().flatten());
+            let mut groups = merge_by_keyword(rows, params.sort, params.desc);
+            has_more |= page_groups(&mut groups, page_skip, limit);
+            let results = build_keyword_results(groups, |kw| total_of(Some(&database), kw), params);
+            DatabaseGroup {
+                database,
+                label,
+                results,
+            }
+        })
+        .collect();
+
+    Ok(SearchOutput {
+        grouping: Grouping::Database,
+        results: vec![],
+        databases,
+        has_more,
+    })
+}
+
+/// Cuts each keyword's sorted items down to one page.
+///
+/// # Returns
+/// True if some keyword had items past the page
+fn page_groups(groups: &mut [(String, Vec<SearchResult>)], skip: usize, limit: usize) -> bool {
+    let mut has_more = false;
+    for (_, items) in groups {
+        items.drain(..skip.min(items.len()));
+        if items.len() > limit {
+            items.truncate(limit);
+            has_more = true;
+        }
+    }
+    has_more
+}
 
+/// Builds a result tree for each keyword group.
+fn build_keyword_results(
+    groups: Vec<(String, Vec<SearchResult>)>,
+    total_of: impl Fn(&str) -> usize,
+    params: &SearchRequest,
+) -> Vec<KeywordResults> {
     // Build trees for each keyword
     let mut keyword_results = Vec::new();
-    for (keyword, items) in processed_results {
+    for (keyword, items) in groups {
         if items.is_empty() {
+            if !params.include_empty {
+                continue;
+            }
             keyword_results.push(KeywordResults {
+                total: total_of(&keyword),
                 keyword,
                 count: 0,
                 tree: TreeNodeJson {
@@ -303,6 +761,8 @@ async fn search_handler(
                     path: ".".to_string(),
                     is_leaf: true,
                     children: vec![],
+                    size: None,
+                    mtime: None,
                 },
                 root_path: String::new(),
             });
@@ -314,6 +774,7 @@ async fn search_handler(
         let tree_json = TreeNodeJson::from(&tree);
 
         keyword_results.push(KeywordResults {
+            total: total_of(&keyword),
             keyword,
             count: items.len(),
             tree: tree_json,
@@ -321,52 +782,59 @@ async fn search_handler(
         });
     }
 
-    // 自动保存到历史记录（异步执行，不阻塞响应）
-    let total_count: usize = keyword_results.iter().map(|kr| kr.count).sum();
-    if total_count > 0 {
-        let history_item = SearchHistoryItem {
-            query: params.query.clone(),
-            selected_db: params.selected_db.clone(),
-            timestamp: Utc::now(),
-            result_count: total_count,
-            name_only: params.name_only,
-            case_sensitive: params.case_sensitive,
-        };
-
-        let history = state.history.clone();
-        tokio::spawn(async move {
-            let history = history.lock().await;
-            let _ = history.add_entry(history_item);
-        });
-    }
-
-    Json(SearchResponse {
-        success: true,
-        results: keyword_results,
-        error: None,
-    })
+    keyword_results
 }
 
 /// Index handler - process indexing request
 async fn index_handler(
+    State(state): State<Arc<AppState>>,
     Json(req): Json<IndexRequest>,
 ) -> Result<Json<IndexResponse>, (StatusCode, Json<IndexResponse>)> {
+    // Concurrent scans into one database would interleave their writes
+    let Some(job) = state.try_start_index_job(Path::new(&req.db_path)) else {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(IndexResponse {
+                success: false,
+                message: String::new(),
+                duration_secs: None,
+                skipped_paths: None,
+                counts: None,
+                error: Some(format!(
+                    "An index job for '{}' is already running",
+                    req.db_path
+                )),
+            }),
+        ));
+    };
+
+    // Never index the server's own live databases or the one being written
+    let mut exclude_paths = state.db_paths.clone();
+    exclude_paths.push(PathBuf::from(&req.db_path));
+
     // Spawn blocking task for indexing (I/O intensive)
     let result = tokio::task::spawn_blocking(move || {
-        // Open database
-        let db = Database::new(&req.db_path);
+        // Released when the scan ends, even if the client has gone away
+        let _job = job;
+
+        // Open database, creating it or upgrading its schema as needed
+        let db = Database::init(&req.db_path).map_err(|e| format!("{:#}", e))?;
 
         // Perform indexing based on mode
-        let index_result = if req.incremental {
-            indexer::scan_idxs_with_metadata(&req.root_path, &db, req.batch_size)
-                .map_err(|e| format!("Indexing failed: {}", e))?
-        } else if req.with_metadata {
-            indexer::scan_idxs_with_metadata(&req.root_path, &db, req.batch_size)
-                .map_err(|e| format!("Indexing failed: {}", e))?
-        } else {
-            indexer::scan_idxs(&req.root_path, &db, req.batch_size)
-                .map_err(|e| format!("Indexing failed: {}", e))?
+        let config = indexer::IndexConfig {
+            with_metadata: req.with_metadata,
+            incremental: req.incremental,
+            follow_junctions: req.follow_junctions,
+            index_archives: req.index_archives,
+            respect_gitignore: req.respect_gitignore,
+            exclude_globs: req.exclude_patterns.clone(),
+            max_depth: req.max_depth,
+            exclude_paths,
+            ..Default::default()
         };
+        let index_result =
+            indexer::scan_idxs_with_config(&req.root_path, &db, req.batch_size, &config)
+                .map_err(|e| format!("Indexing failed: {}", e))?;
 
         Ok::<_, String>(index_result)
     })
@@ -379,11 +847,15 @@ async fn index_handler(
                 message: String::new(),
                 duration_secs: None,
                 skipped_paths: None,
+                counts: None,
                 error: Some(format!("Task join error: {}", e)),
             }),
         )
     })?;
 
+    // Indexed data may have changed, drop all cached result sets
+    state.cache.lock().await.clear();
+
     match result {
         Ok(index_result) => {
             let message = if index_result.skipped_paths.is_empty() {
@@ -404,6 +876,12 @@ async fn index_handler(
                 } else {
                     Some(index_result.skipped_paths)
                 },
+                counts: Some(IndexCounts {
+                    added: index_result.added,
+                    updated: index_result.updated,
+                    skipped: index_result.skipped,
+                    depth_limited: index_result.depth_limited,
+                }),
                 error: None,
             }))
         }
@@ -414,20 +892,125 @@ async fn index_handler(
                 message: String::new(),
                 duration_secs: None,
                 skipped_paths: None,
+                counts: None,
                 error: Some(e),
             }),
         )),
     }
 }
 
+const INDEX_HTML: &str = include_str!("../static/index.html");
+const INDEXER_HTML: &str = include_str!("../static/indexer.html");
+
+/// Files of the `static/` directory, compiled into the binary so the server
+/// works from any working directory
+#[derive(RustEmbed)]
+#[folder = "static/"]
+struct StaticAssets;
+
+/// Computes a strong ETag from the content hash of an embedded page
+fn content_etag(content: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+/// Serve an embedded HTML page with ETag validation
+///
+/// Browsers revalidate on every load (`no-cache`) and get a `304 Not Modified`
+/// when the page is unchanged.
+fn embedded_page(content: &'static str, etag: &str, headers: &HeaderMap) -> Response {
+    let not_modified = etag_matches(headers, etag);
+
+    let cache_headers = [
+        (header::ETAG, etag.to_string()),
+        (header::CACHE_CONTROL, "no-cache".to_string()),
+    ];
+
+    if not_modified {
+        (StatusCode::NOT_MODIFIED, cache_headers).into_response()
+    } else {
+        (cache_headers, Html(content)).into_response()
+    }
+}
+
+/// Returns true if the request's `If-None-Match` lists `etag`.
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| {
+            v.split(',')
+                .any(|tag| tag.trim() == etag || tag.trim() == "*")
+        })
+}
+
+/// Serve a file embedded from the `static/` directory
+///
+/// The ETag is derived from the file's SHA-256, so browsers get a
+/// `304 Not Modified` until the binary ships a different file.
+async fn static_handler(UrlPath(path): UrlPath<String>, headers: HeaderMap) -> Response {
+    let Some(file) = StaticAssets::get(&path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let hash = file.metadata.sha256_hash();
+    let etag = format!(
+        "\"{}\"",
+        hash[..8]
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>()
+    );
+    if etag_matches(&headers, &etag) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+
+    (
+        [
+            (header::CONTENT_TYPE, file.metadata.mimetype().to_string()),
+            (header::ETAG, etag),
+        ],
+        file.data,
+    )
+        .into_response()
+}
+
 /// Root handler - serve the main HTML page
-async fn root_handler() -> Html<&'static str> {
-    Html(include_str!("../static/index.html"))
+async fn root_handler(headers: HeaderMap) -> Response {
+    static ETAG: OnceLock<String> = OnceLock::new();
+    embedded_page(
+        INDEX_HTML,
+        ETAG.get_or_init(|| content_etag(INDEX_HTML)),
+        &headers,
+    )
 }
 
 /// Indexer page handler - serve the indexer HTML page
-async fn indexer_handler() -> Html<&'static str> {
-    Html(include_str!("../static/indexer.html"))
+async fn indexer_handler(headers: HeaderMap) -> Response {
+    static ETAG: OnceLock<String> = OnceLock::new();
+    embedded_page(
+        INDEXER_HTML,
+        ETAG.get_or_init(|| content_etag(INDEXER_HTML)),
+        &headers,
+    )
+}
+
+/// Version of the HTTP API schema, bumped on breaking changes
+pub const API_VERSION: u32 = 1;
+
+/// Version information for clients
+#[derive(Debug, Serialize)]
+pub struct VersionResponse {
+    pub version: &'static str,
+    pub api_version: u32,
+}
+
+/// Report the crate and API versions so clients can detect capabilities
+async fn version_handler() -> Json<VersionResponse> {
+    Json(VersionResponse {
+        version: env!("CARGO_PKG_VERSION"),
+        api_version: API_VERSION,
+    })
 }
 
 /// Health check endpoint
@@ -445,6 +1028,11 @@ pub struct DatabaseListResponse {
 pub struct DatabaseInfo {
     pub name: String,
     pub path: String,
+    /// Friendly label set with `reminex label`, shown instead of the name
+    pub label: Option<String>,
+    /// Root, time and reminex version of the latest scan, when recorded
+    #[serde(flatten)]
+    pub index_info: IndexInfo,
 }
 
 /// List available databases
@@ -459,16 +1047,129 @@ async fn list_databases_handler(State(state): State<Arc<AppState>>) -> impl Into
                 .unwrap_or("unknown")
                 .to_string(),
             path: path.to_string_lossy().to_string(),
+            label: Database::new(path).label().ok().flatten(),
+            index_info: Database::new(path).index_info().unwrap_or_default(),
         })
         .collect();
 
     Json(DatabaseListResponse { databases })
 }
 
-/// Get search history
-async fn get_history_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let history = state.history.lock().await;
-    match history.get_all() {
+/// Statistics for one database
+#[derive(Debug, Serialize)]
+struct DatabaseStats {
+    name: String,
+    stats: DbStats,
+}
+
+/// Query parameters of the stats endpoint
+#[derive(Debug, Deserialize)]
+struct StatsQuery {
+    /// Database file name, or "all" for every database plus a total
+    #[serde(default = "default_selected_db")]
+    selected_db: String,
+}
+
+/// Per-extension file counts and byte totals for one or all databases
+///
+/// With `selected_db=all` the response also carries a `total` combining
+/// every database.
+async fn stats_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<StatsQuery>,
+) -> impl IntoResponse {
+    let selected_db = query.selected_db.trim().to_string();
+    let db_paths: Vec<PathBuf> = if selected_db == "all" {
+        state.db_paths.clone()
+    } else {
+        match state
+            .db_paths
+            .iter()
+            .find(|path| path.file_name().and_then(|n| n.to_str()) == Some(&selected_db))
+        {
+            Some(path) => vec![path.clone()],
+            None => {
+                return Json(serde_json::json!({
+                    "success": false,
+                    "error": format!("数据库不存在: {}", selected_db)
+                }));
+            }
+        }
+    };
+
+    let result = tokio::task::spawn_blocking(move || {
+        db_paths
+            .iter()
+            .map(|path| {
+                let name = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .unwrap_or("unknown")
+                    .to_string();
+                collect_stats(&Database::new(path)).map(|stats| DatabaseStats { name, stats })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()
+    })
+    .await;
+
+    match result {
+        Ok(Ok(databases)) if selected_db == "all" => {
+            let total = DbStats::combine(databases.iter().map(|db| &db.stats));
+            Json(serde_json::json!({
+                "success": true,
+                "databases": databases,
+                "total": total
+            }))
+        }
+        Ok(Ok(databases)) => Json(serde_json::json!({
+            "success": true,
+            "databases": databases
+        })),
+        Ok(Err(e)) => Json(serde_json::json!({
+            "success": false,
+            "error": format!("Failed to collect stats: {:#}", e)
+        })),
+        Err(e) => Json(serde_json::json!({
+            "success": false,
+            "error": format!("Stats task failed: {}", e)
+        })),
+    }
+}
+
+/// Runs a history file operation on the blocking thread pool.
+///
+/// The lock is held for the whole operation, so concurrent writers never
+/// lose each other's entries.
+async fn with_history<T: Send + 'static>(
+    history: Arc<Mutex<SearchHistory>>,
+    op: impl FnOnce(&SearchHistory) -> anyhow::Result<T> + Send + 'static,
+) -> anyhow::Result<T> {
+    tokio::task::spawn_blocking(move || op(&history.blocking_lock())).await?
+}
+
+/// Query parameters for listing search history
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    /// Number of most recent entries to return
+    #[serde(default = "default_history_limit")]
+    limit: usize,
+}
+
+fn default_history_limit() -> usize {
+    50
+}
+
+/// Get the most recent search history entries (`?limit=`, default 50)
+async fn get_history_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<HistoryQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit;
+    match with_history(state.history.clone(), move |history| {
+        history.get_recent(limit)
+    })
+    .await
+    {
         Ok(items) => Json(serde_json::json!({
             "success": true,
             "history": items
@@ -505,8 +1206,11 @@ async fn add_history_handler(
         case_sensitive: req.case_sensitive,
     };
 
-    let history = state.history.lock().await;
-    match history.add_entry(item) {
+    match with_history(state.history.clone(), move |history| {
+        history.add_entry(item)
+    })
+    .await
+    {
         Ok(_) => Json(serde_json::json!({
             "success": true
         })),
@@ -517,10 +1221,9 @@ async fn add_history_handler(
     }
 }
 
-/// Clear search history
+/// Clear search history (`DELETE /history`, or `POST /history/clear`)
 async fn clear_history_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let history = state.history.lock().await;
-    match history.clear() {
+    match with_history(state.history.clone(), |history| history.clear()).await {
         Ok(_) => Json(serde_json::json!({
             "success": true
         })),
@@ -531,6 +1234,41 @@ async fn clear_history_handler(State(state): State<Arc<AppState>>) -> impl IntoR
     }
 }
 
+/// Body of `POST /export`
+///
+/// Results the page already holds are converted and returned as TOML
+/// text inside JSON. A search request without `results` is run here and
+/// its results are returned as a downloadable file.
+///
+/// The shape is chosen by whether `results` is present, so a malformed
+/// results body is rejected instead of being run as a search.
+#[derive(Debug, Deserialize)]
+#[serde(try_from = "serde_json::Value")]
+enum ExportBody {
+    Results(ExportRequest),
+    Search(Box<SearchRequest>),
+}
+
+impl TryFrom<serde_json::Value> for ExportBody {
+    type Error = serde_json::Error;
+
+    fn try_from(body: serde_json::Value) -> Result<Self, Self::Error> {
+        if body.get("results").is_some() {
+            serde_json::from_value(body).map(ExportBody::Results)
+        } else {
+            serde_json::from_value(body).map(ExportBody::Search)
+        }
+    }
+}
+
+/// Query parameters of `POST /export`
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    /// File format when exporting a search: toml (default), csv, json or ndjson
+    #[serde(default)]
+    format: export::ExportFormat,
+}
+
 /// Export search results
 #[derive(Debug, Deserialize)]
 struct ExportRequest {
@@ -546,9 +1284,87 @@ struct ExportRequest {
     #[serde(default)]
     exclude_filters: Vec<String>,
     results: Vec<KeywordResults>,
+    #[serde(default = "default_include_empty")]
+    include_empty: bool,
+}
+
+async fn export_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ExportQuery>,
+    Json(body): Json<ExportBody>,
+) -> Response {
+    match body {
+        ExportBody::Results(req) => export_results_handler(req).await.into_response(),
+        ExportBody::Search(params) => export_search(&state, &params, query.format),
+    }
+}
+
+/// Runs a search and returns its results as an attachment in `format`
+fn export_search(
+    state: &AppState,
+    params: &SearchRequest,
+    format: export::ExportFormat,
+) -> Response {
+    let failure = |status: StatusCode, error: String| {
+        (
+            status,
+            Json(serde_json::json!({ "success": false, "error": error })),
+        )
+            .into_response()
+    };
+
+    let output = match run_search(state, params) {
+        Ok(output) => output,
+        Err(error) => return failure(StatusCode::BAD_REQUEST, error),
+    };
+    let filters = |filters: &Option<String>| {
+        filters
+            .as_deref()
+            .map(parse_filter_keywords)
+            .unwrap_or_default()
+    };
+    let exported = export::convert_from_web_results(export::ConvertParams {
+        query: params.query.clone(),
+        selected_db: params.selected_db.clone(),
+        name_only: params.name_only,
+        case_sensitive: params.case_sensitive,
+        limit: params.limit,
+        include_filters: filters(&params.include_filters),
+        exclude_filters: filters(&params.exclude_filters),
+        keyword_results: output.results,
+        include_empty: params.include_empty,
+    });
+
+    let content = match exported.render(format) {
+        Ok(content) => content,
+        Err(e) => {
+            return failure(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to export: {}", e),
+            );
+        }
+    };
+    let content_type = match format {
+        export::ExportFormat::Toml => "application/toml; charset=utf-8",
+        export::ExportFormat::Csv => "text/csv; charset=utf-8",
+        export::ExportFormat::Json => "application/json",
+        export::ExportFormat::Ndjson => "application/x-ndjson",
+    };
+    let disposition = format!(
+        "attachment; filename=\"reminex-results.{}\"",
+        format.extension()
+    );
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        content,
+    )
+        .into_response()
 }
 
-async fn export_results_handler(Json(req): Json<ExportRequest>) -> impl IntoResponse {
+async fn export_results_handler(req: ExportRequest) -> impl IntoResponse {
     let exported = export::convert_from_web_results(export::ConvertParams {
         query: req.query,
         selected_db: req.selected_db,
@@ -558,6 +1374,7 @@ async fn export_results_handler(Json(req): Json<ExportRequest>) -> impl IntoResp
         include_filters: req.include_filters,
         exclude_filters: req.exclude_filters,
         keyword_results: req.results,
+        include_empty: req.include_empty,
     });
 
     match exported.to_toml() {
@@ -574,24 +1391,80 @@ async fn export_results_handler(Json(req): Json<ExportRequest>) -> impl IntoResp
 
 /// Create and configure the web application router
 pub fn create_app(db_paths: Vec<PathBuf>) -> Router {
-    let history = SearchHistory::new(SearchHistory::default_path(), 100);
-    let state = Arc::new(AppState {
+    create_app_with_options(db_paths, ServerOptions::default())
+}
+
+/// Create the web application router with custom server options
+pub fn create_app_with_options(db_paths: Vec<PathBuf>, options: ServerOptions) -> Router {
+    build_router(new_state(db_paths, options))
+}
+
+fn new_state(db_paths: Vec<PathBuf>, options: ServerOptions) -> Arc<AppState> {
+    let history_path = options
+        .history_path
+        .unwrap_or_else(SearchHistory::default_path);
+    let history = SearchHistory::new(history_path, DEFAULT_MAX_ENTRIES);
+    Arc::new(AppState {
         db_paths,
         history: Arc::new(Mutex::new(history)),
-    });
+        cache: Arc::new(Mutex::new(ResultCache::new(options.cache))),
+        once: options.once,
+        shutdown: Arc::new(Notify::new()),
+        index_jobs: Arc::default(),
+    })
+}
+
+/// Routes of the versioned API, relative to their mount point
+fn api_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/search", get(search_handler).post(search_post_handler))
+        .route("/index", post(index_handler))
+        .route("/databases", get(list_databases_handler))
+        .route("/stats", get(stats_handler))
+        .route(
+            "/history",
+            get(get_history_handler)
+                .post(add_history_handler)
+                .delete(clear_history_handler),
+        )
+        .route("/history/clear", post(clear_history_handler))
+        .route("/export", post(export_handler))
+}
+
+fn build_router(state: Arc<AppState>) -> Router {
+    // Unprefixed routes are deprecated aliases of /api/v1, kept for one release
+    let legacy_api = api_routes().layer(SetResponseHeaderLayer::overriding(
+        HeaderName::from_static("deprecation"),
+        HeaderValue::from_static("true"),
+    ));
+
+    // API responses are never cached by browsers or proxies
+    let api = Router::new()
+        .route("/api/version", get(version_handler))
+        .nest(
+            "/api/v1",
+            api_routes().route("/version", get(version_handler)),
+        )
+        .nest("/api", legacy_api)
+        .layer(SetResponseHeaderLayer::overriding(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("no-store"),
+        ));
+
+    // Static files are revalidated via ETag on each load
+    let static_files = Router::new()
+        .route("/static/*path", get(static_handler))
+        .layer(SetResponseHeaderLayer::if_not_present(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("no-cache"),
+        ));
 
     Router::new()
         .route("/", get(root_handler))
         .route("/indexer", get(indexer_handler))
-        .route("/api/search", get(search_handler))
-        .route("/api/index", post(index_handler))
-        .route("/api/databases", get(list_databases_handler))
-        .route("/api/history", get(get_history_handler))
-        .route("/api/history", post(add_history_handler))
-        .route("/api/history/clear", post(clear_history_handler))
-        .route("/api/export", post(export_results_handler))
         .route("/health", get(health_handler))
-        .nest_service("/static", ServeDir::new("static"))
+        .merge(api)
+        .merge(static_files)
         .with_state(state)
 }
 
@@ -604,11 +1477,24 @@ pub async fn run_server_with_retry(
     db_paths: Vec<PathBuf>,
     start_port: u16,
     auto_retry: bool,
+) -> anyhow::Result<()> {
+    run_server_with_options(db_paths, start_port, auto_retry, ServerOptions::default()).await
+}
+
+/// Start the web server with custom server options
+pub async fn run_server_with_options(
+    db_paths: Vec<PathBuf>,
+    start_port: u16,
+    auto_retry: bool,
+    options: ServerOptions,
 ) -> anyhow::Result<()> {
     // Initialize tracing
     tracing_subscriber::fmt::init();
 
-    let app = create_app(db_paths);
+    let once = options.once;
+    let state = new_state(db_paths, options);
+    let shutdown = state.shutdown.clone();
+    let app = build_router(state);
 
     let max_retries = if auto_retry { 32 } else { 1 };
     let mut last_error = None;
@@ -623,9 +1509,15 @@ pub async fn run_server_with_retry(
                     println!("⚠️  端口 {} 已被占用，自动切换到端口 {}", start_port, port);
                 }
                 println!("🌐 Web 服务器运行在 http://localhost:{}", port);
-                println!("📂 按 Ctrl+C 停止");
+                if once {
+                    println!("📂 处理一次搜索请求后自动退出");
+                } else {
+                    println!("📂 按 Ctrl+C 停止");
+                }
 
-                axum::serve(listener, app).await?;
+                axum::serve(listener, app)
+                    .with_graceful_shutdown(async move { shutdown.notified().await })
+                    .await?;
                 return Ok(());
             }
             Err(e) => {
@@ -647,3 +1539,1028 @@ pub async fn run_server_with_retry(
         Err(last_error.unwrap().into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Index;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    fn test_app() -> Router {
+        create_app(Vec::new())
+    }
+
+    /// An app recording search history in `dir` instead of the user's config
+    fn app_with_history(db_paths: Vec<PathBuf>, dir: &Path) -> Router {
+        let options = ServerOptions {
+            history_path: Some(dir.join("history.json")),
+            ..Default::default()
+        };
+        create_app_with_options(db_paths, options)
+    }
+
+    #[tokio::test]
+    async fn test_embedded_page_etag_and_not_modified() {
+        let response = test_app()
+            .oneshot(Request::get("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+        let etag = response.headers()[header::ETAG].clone();
+
+        let response = test_app()
+            .oneshot(
+                Request::get("/")
+                    .header(header::IF_NONE_MATCH, etag.clone())
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag);
+
+        let response = test_app()
+            .oneshot(
+                Request::get("/")
+                    .header(header::IF_NONE_MATCH, "\"stale\"")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_static_files_are_embedded() {
+        let response = test_app()
+            .oneshot(
+                Request::get("/static/indexer.html")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/html");
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+        let etag = response.headers()[header::ETAG].clone();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, INDEXER_HTML.as_bytes());
+
+        let response = test_app()
+            .oneshot(
+                Request::get("/static/indexer.html")
+                    .header(header::IF_NONE_MATCH, etag)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+        for missing in ["/static/missing.js", "/static/../Cargo.toml"] {
+            let response = test_app()
+                .oneshot(Request::get(missing).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_once_mode_signals_shutdown_after_search() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let options = ServerOptions {
+            once: true,
+            history_path: Some(temp_dir.path().join("history.json")),
+            ..Default::default()
+        };
+        let state = new_state(Vec::new(), options);
+        let shutdown = state.shutdown.clone();
+
+        let response = build_router(state)
+            .oneshot(
+                Request::get("/api/v1/search?query=a")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        tokio::time::timeout(std::time::Duration::from_secs(1), shutdown.notified())
+            .await
+            .expect("shutdown was not signalled");
+    }
+
+    #[tokio::test]
+    async fn test_versioned_api_and_legacy_aliases() {
+        let response = test_app()
+            .oneshot(Request::get("/api/version").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("deprecation").is_none());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(json["api_version"], API_VERSION);
+
+        let response = test_app()
+            .oneshot(Request::get("/api/databases").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["deprecation"], "true");
+    }
+
+    fn result(path: &str) -> SearchResult {
+        SearchResult {
+            path: path.to_string(),
+            name: path.rsplit('/').next().unwrap().to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_search_all_databases_is_stable() {
+        use crate::db::Index;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut db_paths = Vec::new();
+        for (db_name, files) in [
+            ("one.reminex.db", ["/z/alpha1.txt", "/a/beta1.txt"]),
+            ("two.reminex.db", ["/m/alpha2.txt", "/b/beta2.txt"]),
+        ] {
+            let path = temp_dir.path().join(db_name);
+            let db = Database::init(&path).unwrap();
+            let idxs: Vec<_> = files
+                .iter()
+                .map(|f| Index::new(f.to_string(), f.rsplit('/').next().unwrap().to_string()))
+                .collect();
+            db.add_idxs(&idxs).unwrap();
+            db_paths.push(path);
+        }
+
+        let state = new_state(db_paths, ServerOptions::default());
+        let params: SearchRequest =
+            serde_json::from_value(serde_json::json!({ "query": "alpha;beta;gamma" })).unwrap();
+
+        let first = run_search(&state, &params).unwrap().results;
+        let keywords: Vec<_> = first.iter().map(|r| r.keyword.as_str()).collect();
+        assert_eq!(keywords, vec!["alpha", "beta", "gamma"]);
+
+        for _ in 0..10 {
+            let again = run_search(&state, &params).unwrap().results;
+            assert_eq!(
+                serde_json::to_string(&again).unwrap(),
+                serde_json::to_string(&first).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_group_by_db() {
+        use crate::db::Index;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut db_paths = Vec::new();
+        for (db_name, files) in [
+            ("one.reminex.db", vec!["/a/alpha1.txt", "/a/beta1.txt"]),
+            ("two.reminex.db", vec!["/b/alpha2.txt"]),
+        ] {
+            let path = temp_dir.path().join(db_name);
+            let db = Database::init(&path).unwrap();
+            let idxs: Vec<_> = files
+                .iter()
+                .map(|f| Index::new(f.to_string(), f.rsplit('/').next().unwrap().to_string()))
+                .collect();
+            db.add_idxs(&idxs).unwrap();
+            db_paths.push(path);
+        }
+        Database::new(&db_paths[1]).set_label("Backup").unwrap();
+        let state = new_state(db_paths, ServerOptions::default());
+
+        // Merged by keyword unless asked otherwise
+        let params: SearchRequest =
+            serde_json::from_value(serde_json::json!({ "query": "alpha;beta" })).unwrap();
+        let merged = run_search(&state, &params).unwrap();
+        assert_eq!(merged.grouping, Grouping::Keyword);
+        assert_eq!(merged.results[0].count, 2);
+        assert!(merged.databases.is_empty());
+
+        let params: SearchRequest = serde_json::from_value(
+            serde_json::json!({ "query": "alpha;beta", "group_by_db": true }),
+        )
+        .unwrap();
+        let grouped = run_search(&state, &params).unwrap();
+        assert_eq!(grouped.grouping, Grouping::Database);
+        assert!(grouped.results.is_empty());
+        assert_eq!(grouped.total_count(), merged.total_count());
+
+        let summary: Vec<_> = grouped
+            .databases
+            .iter()
+            .map(|db| {
+                let counts: Vec<_> = db
+                    .results
+                    .iter()
+                    .map(|kr| (kr.keyword.as_str(), kr.count))
+                    .collect();
+                (db.database.as_str(), db.label.as_deref(), counts)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("one.reminex.db", None, vec![("alpha", 1), ("beta", 1)]),
+                (
+                    "two.reminex.db",
+                    Some("Backup"),
+                    vec![("alpha", 1), ("beta", 0)]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_include_empty() {
+        use crate::db::Index;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("empty.reminex.db");
+        let db = Database::init(&db_path).unwrap();
+        db.add_idx(&Index::new("/a/alpha.txt".into(), "alpha.txt".into()))
+            .unwrap();
+        let state = new_state(vec![db_path], ServerOptions::default());
+
+        let summary = |results: Vec<KeywordResults>| -> Vec<(String, usize)> {
+            results.into_iter().map(|r| (r.keyword, r.count)).collect()
+        };
+
+        // Machine output keeps every keyword by default
+        let params: SearchRequest =
+            serde_json::from_value(serde_json::json!({ "query": "alpha;gamma" })).unwrap();
+        assert_eq!(
+            summary(run_search(&state, &params).unwrap().results),
+            vec![("alpha".to_string(), 1), ("gamma".to_string(), 0)]
+        );
+
+        let params: SearchRequest = serde_json::from_value(
+            serde_json::json!({ "query": "alpha;gamma", "include_empty": false }),
+        )
+        .unwrap();
+        assert_eq!(
+            summary(run_search(&state, &params).unwrap().results),
+            vec![("alpha".to_string(), 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_index_excludes_managed_databases() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join("notes.txt"), b"notes").unwrap();
+        let managed = root.join("managed.reminex.db");
+        Database::init(&managed).unwrap();
+        let target = root.join("target.reminex.db");
+        let target_db = Database::init(&target).unwrap();
+
+        let body = serde_json::json!({
+            "root_path": root.to_string_lossy(),
+            "db_path": target.to_string_lossy(),
+        });
+        let response = create_app(vec![managed])
+            .oneshot(
+                Request::post("/api/v1/index")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let paths: Vec<String> = target_db
+            .batch_operation(|conn| {
+                let mut stmt = conn.prepare("SELECT path FROM files")?;
+                let paths = stmt
+                    .query_map([], |row| row.get(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(paths)
+            })
+            .unwrap();
+        assert_eq!(paths.len(), 1, "unexpected paths: {:?}", paths);
+        assert!(paths[0].ends_with("notes.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_index_rejects_concurrent_job_for_same_db() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().join("files");
+        std::fs::create_dir(&root).unwrap();
+        std::fs::write(root.join("a.txt"), b"a").unwrap();
+        let target = temp_dir.path().join("target.reminex.db");
+
+        let state = new_state(vec![], ServerOptions::default());
+        let request = |db_path: &Path| {
+            let body = serde_json::json!({
+                "root_path": root.to_string_lossy(),
+                "db_path": db_path.to_string_lossy(),
+            });
+            Request::post("/api/v1/index")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        // A job for the target database is already running
+        let running = state.try_start_index_job(&target).unwrap();
+        let response = build_router(state.clone())
+            .oneshot(request(&target))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        // Other databases are unaffected
+        let response = build_router(state.clone())
+            .oneshot(request(&temp_dir.path().join("other.reminex.db")))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        drop(running);
+        let response = build_router(state.clone())
+            .oneshot(request(&target))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // Finished jobs leave no entries behind
+        assert!(state.index_jobs.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_query_string_decoding() {
+        let uri: axum::http::Uri =
+            "/api/v1/search?query=c%2B%2B+notes&root_path=D%3A%5Cx%20%26%20y%2B%25"
+                .parse()
+                .unwrap();
+        let Query(params) = Query::<SearchRequest>::try_from_uri(&uri).unwrap();
+        // An unencoded '+' is a space, an encoded one is kept
+        assert_eq!(params.query, "c++ notes");
+        assert_eq!(params.root_path.as_deref(), Some("D:\\x & y+%"));
+    }
+
+    #[tokio::test]
+    async fn test_search_accepts_json_body() {
+        use crate::db::Index;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("special.reminex.db");
+        let db = Database::init(&db_path).unwrap();
+        db.add_idx(&Index::new(
+            "/docs/c++ & 100%.txt".to_string(),
+            "c++ & 100%.txt".to_string(),
+        ))
+        .unwrap();
+
+        let options = ServerOptions {
+            history_path: Some(temp_dir.path().join("history.json")),
+            ..Default::default()
+        };
+        let body = serde_json::json!({ "query": "c++ & 100%" });
+        let response = create_app_with_options(vec![db_path], options)
+            .oneshot(
+                Request::post("/api/v1/search")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["success"], true);
+        assert_eq!(json["results"][0]["keyword"], "c++ & 100%");
+        assert_eq!(json["results"][0]["count"], 1);
+    }
+
+    #[test]
+    fn test_replace_path_prefix_separators() {
+        // Windows paths onto a Windows root
+        assert_eq!(
+            replace_path_prefix("F:\\photos\\a.jpg", "F:", "D:\\"),
+            "D:\\photos\\a.jpg"
+        );
+        assert_eq!(
+            replace_path_prefix("F:\\photos\\a.jpg", "F:\\photos", "D:"),
+            "D:\\a.jpg"
+        );
+        // Unix paths onto a Unix root
+        assert_eq!(
+            replace_path_prefix("/data/photos/a.jpg", "/data", "/mnt/data"),
+            "/mnt/data/photos/a.jpg"
+        );
+        assert_eq!(
+            replace_path_prefix("/data/photos/a.jpg", "/data/photos", "/mnt/data/"),
+            "/mnt/data/a.jpg"
+        );
+        assert_eq!(
+            replace_path_prefix("/data", "/data", "/mnt/data/"),
+            "/mnt/data"
+        );
+        assert_eq!(replace_path_prefix("/data/a.jpg", "/data", "/"), "/a.jpg");
+        // Across styles the rest of the path follows the new root
+        assert_eq!(
+            replace_path_prefix("F:\\photos\\2020\\a.jpg", "F:", "/mnt/f"),
+            "/mnt/f/photos/2020/a.jpg"
+        );
+        assert_eq!(
+            replace_path_prefix("/data/photos/a.jpg", "/data", "D:\\backup"),
+            "D:\\backup\\photos\\a.jpg"
+        );
+        // Paths outside the old prefix are left alone
+        assert_eq!(
+            replace_path_prefix("/other/a.jpg", "/data", "/mnt/data"),
+            "/other/a.jpg"
+        );
+    }
+
+    #[test]
+    fn test_root_path_replacement_across_drives() {
+        let items = vec![
+            result("F:\\photos\\a.jpg"),
+            result("F:\\photos\\deep\\b.jpg"),
+            result("G:\\music\\c.mp3"),
+        ];
+        let paths = |roots: &[&str]| -> Vec<String> {
+            let roots: Vec<String> = roots.iter().map(|root| root.to_string()).collect();
+            let mut items = items.clone();
+            apply_root_path_replacement(&mut items, "D:\\", &roots);
+            items.into_iter().map(|item| item.path).collect()
+        };
+
+        // Without recorded roots each path's own drive is swapped
+        assert_eq!(
+            paths(&[]),
+            vec![
+                "D:\\photos\\a.jpg",
+                "D:\\photos\\deep\\b.jpg",
+                "D:\\music\\c.mp3"
+            ]
+        );
+        // The longest recorded root wins; paths outside all fall back
+        assert_eq!(
+            paths(&["F:\\photos", "F:\\photos\\deep", "F:\\pho"]),
+            vec!["D:\\a.jpg", "D:\\b.jpg", "D:\\music\\c.mp3"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_root_path_replaced_per_database_when_merged() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut db_paths = Vec::new();
+        for (name, root, file) in [
+            ("f.reminex.db", "F:\\photos", "F:\\photos\\2023\\a.jpg"),
+            (
+                "g.reminex.db",
+                "G:\\backup\\photos",
+                "G:\\backup\\photos\\b.jpg",
+            ),
+        ] {
+            let path = temp_dir.path().join(name);
+            let db = Database::init(&path).unwrap();
+            db.add_idxs(&[Index::new(
+                file.into(),
+                file.rsplit('\\').next().unwrap().into(),
+            )])
+            .unwrap();
+            db.record_scan(root).unwrap();
+            db_paths.push(path);
+        }
+
+        let response = app_with_history(db_paths, temp_dir.path())
+            .oneshot(
+                Request::get("/api/v1/search?query=jpg&root_path=/mnt/photos")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let mut paths = Vec::new();
+        collect_leaf_paths(&json["results"][0]["tree"], &mut paths);
+        paths.sort();
+        assert_eq!(paths, vec!["/mnt/photos/2023/a.jpg", "/mnt/photos/b.jpg"]);
+    }
+
+    /// Paths of the leaves of a serialized result tree, in order
+    fn collect_leaf_paths(node: &serde_json::Value, paths: &mut Vec<String>) {
+        if node["is_leaf"] == true {
+            paths.push(node["path"].as_str().unwrap().to_string());
+        }
+        for child in node["children"].as_array().unwrap() {
+            collect_leaf_paths(child, paths);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_database_list_includes_labels() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let labeled = temp_dir.path().join("a1b2.reminex.db");
+        Database::init(&labeled)
+            .unwrap()
+            .set_label("Photos Archive")
+            .unwrap();
+        let plain = temp_dir.path().join("plain.reminex.db");
+        Database::init(&plain).unwrap();
+
+        let response = create_app(vec![labeled, plain])
+            .oneshot(
+                Request::get("/api/v1/databases")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["databases"][0]["name"], "a1b2.reminex.db");
+        assert_eq!(json["databases"][0]["label"], "Photos Archive");
+        assert!(json["databases"][1]["label"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_search_regex_mode() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("code.reminex.db");
+        Database::init(&db_path)
+            .unwrap()
+            .add_idxs(&[
+                Index::new("/src/main.rs".into(), "main.rs".into()),
+                Index::new("/src/main.rs.bak".into(), "main.rs.bak".into()),
+            ])
+            .unwrap();
+
+        let search = |query: &'static str| {
+            let app = app_with_history(vec![db_path.clone()], temp_dir.path());
+            async move {
+                let response = app
+                    .oneshot(Request::get(query).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+
+        let json = search("/api/v1/search?query=%5C.rs%24&mode=regex").await;
+        assert_eq!(json["success"], true);
+        assert_eq!(json["results"][0]["count"], 1);
+
+        let json = search("/api/v1/search?query=%5C.rs%24").await;
+        assert_eq!(json["results"][0]["count"], 0);
+
+        let json = search("/api/v1/search?query=main(&mode=regex").await;
+        assert_eq!(json["success"], false);
+        assert!(
+            json["error"]
+                .as_str()
+                .unwrap()
+                .contains("Invalid regular expression")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_combines_keywords() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("docs.reminex.db");
+        Database::init(&db_path)
+            .unwrap()
+            .add_idxs(&[
+                Index::new("/d/2023_invoice.pdf".into(), "2023_invoice.pdf".into()),
+                Index::new("/d/2024_invoice.pdf".into(), "2024_invoice.pdf".into()),
+                Index::new("/d/2023_notes.txt".into(), "2023_notes.txt".into()),
+            ])
+            .unwrap();
+        let app = app_with_history(vec![db_path], temp_dir.path());
+
+        for (uri, keyword, total) in [
+            (
+                "/api/v1/search?query=invoice;2023&combine=and",
+                "invoice & 2023",
+                1,
+            ),
+            (
+                "/api/v1/search?query=invoice;2023&combine=or",
+                "invoice | 2023",
+                3,
+            ),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let results = json["results"].as_array().unwrap();
+            assert_eq!(results.len(), 1, "{uri}");
+            assert_eq!(results[0]["keyword"], keyword);
+            assert_eq!(results[0]["total"], total);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_export_round_trip_keeps_file_metadata() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("audit.reminex.db");
+        Database::init(&db_path)
+            .unwrap()
+            .add_idxs(&[
+                Index::with_metadata(
+                    "/logs/app.log".into(),
+                    "app.log".into(),
+                    1_704_067_200.0,
+                    2048,
+                ),
+                Index::new("/logs/old.log".into(), "old.log".into()),
+            ])
+            .unwrap();
+
+        let app = app_with_history(vec![db_path], temp_dir.path());
+        let response = app
+            .clone()
+            .oneshot(
+                Request::get("/api/v1/search?query=log")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        // The page posts the search response back unchanged to export it
+        let export_request = serde_json::json!({
+            "query": "log",
+            "selected_db": "all",
+            "limit": 2000,
+            "results": json["results"],
+        });
+        let response = app
+            .oneshot(
+                Request::post("/api/v1/export")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(export_request.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let exported =
+            export::ExportedSearchResults::from_toml(json["toml"].as_str().unwrap()).unwrap();
+
+        let files = &exported.results[0].files;
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "/logs/app.log");
+        assert_eq!(files[0].size, Some(2048));
+        assert_eq!(files[0].modified.as_deref(), Some("2024-01-01T00:00:00Z"));
+        assert_eq!((files[1].size, files[1].modified.as_deref()), (None, None));
+    }
+
+    /// Names of the leaves of a serialized result tree, in order
+    fn collect_leaf_names(node: &serde_json::Value, names: &mut Vec<String>) {
+        if node["is_leaf"] == true {
+            names.push(node["name"].as_str().unwrap().to_string());
+        }
+        for child in node["children"].as_array().unwrap() {
+            collect_leaf_names(child, names);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_search_pages_across_databases() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut db_paths = Vec::new();
+        for (name, files) in [
+            ("a.reminex.db", ["/m/1.md", "/m/3.md", "/m/5.md"]),
+            ("b.reminex.db", ["/m/2.md", "/m/4.md", "/m/6.md"]),
+        ] {
+            let path = temp_dir.path().join(name);
+            let idxs: Vec<Index> = files
+                .iter()
+                .map(|file| Index::new(file.to_string(), file[3..].to_string()))
+                .collect();
+            Database::init(&path).unwrap().add_idxs(&idxs).unwrap();
+            db_paths.push(path);
+        }
+        let app = app_with_history(db_paths, temp_dir.path());
+        let page = |uri: String| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+                let mut names = Vec::new();
+                collect_leaf_names(&json["results"][0]["tree"], &mut names);
+                (names, json["has_more"].as_bool().unwrap())
+            }
+        };
+
+        // Merged across databases, pages follow the path order
+        assert_eq!(
+            page("/api/v1/search?query=md&limit=4".into()).await,
+            (
+                vec!["1.md", "2.md", "3.md", "4.md"]
+                    .into_iter()
+                    .map(String::from)
+                    .collect(),
+                true
+            )
+        );
+        assert_eq!(
+            page("/api/v1/search?query=md&limit=4&page=2".into()).await,
+            (vec!["5.md".to_string(), "6.md".to_string()], false)
+        );
+        assert_eq!(
+            page("/api/v1/search?query=md&page_size=4&page=2".into()).await,
+            (vec!["5.md".to_string(), "6.md".to_string()], false)
+        );
+        // One database pages in SQL
+        assert_eq!(
+            page("/api/v1/search?query=md&limit=1&offset=1&selected_db=b.reminex.db".into()).await,
+            (vec!["4.md".to_string()], true)
+        );
+        assert_eq!(
+            page("/api/v1/search?query=md&limit=2&offset=1&selected_db=b.reminex.db".into()).await,
+            (vec!["4.md".to_string(), "6.md".to_string()], false)
+        );
+
+        // Totals count every match, not just the page
+        for (uri, total) in [
+            ("/api/v1/search?query=md&limit=1", 6),
+            (
+                "/api/v1/search?query=md&limit=1&selected_db=a.reminex.db",
+                3,
+            ),
+            ("/api/v1/search?query=md&limit=1&group_by_db=true", 3),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            let group = if uri.contains("group_by_db") {
+                &json["databases"][0]["results"][0]
+            } else {
+                &json["results"][0]
+            };
+            assert_eq!(
+                (group["count"].as_u64(), group["total"].as_u64()),
+                (Some(1), Some(total)),
+                "{uri}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stats_for_one_or_all_databases() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let mut db_paths = Vec::new();
+        for (name, size) in [("a.reminex.db", 10), ("b.reminex.db", 5)] {
+            let path = temp_dir.path().join(name);
+            Database::init(&path)
+                .unwrap()
+                .add_idxs(&[Index::with_metadata(
+                    format!("/{name}/f.txt"),
+                    "f.txt".into(),
+                    0.0,
+                    size,
+                )])
+                .unwrap();
+            db_paths.push(path);
+        }
+        let app = create_app(db_paths);
+        let stats = |uri: &'static str| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+                    .await
+                    .unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+
+        let json = stats("/api/v1/stats?selected_db=all").await;
+        assert_eq!(json["databases"].as_array().unwrap().len(), 2);
+        assert_eq!(json["total"]["file_count"], 2);
+        assert_eq!(json["total"]["total_bytes"], 15);
+
+        let json = stats("/api/v1/stats?selected_db=b.reminex.db").await;
+        assert_eq!(json["databases"][0]["name"], "b.reminex.db");
+        assert_eq!(json["databases"][0]["stats"]["total_bytes"], 5);
+        assert!(json.get("total").is_none());
+
+        let json = stats("/api/v1/stats?selected_db=missing.reminex.db").await;
+        assert_eq!(json["success"], false);
+    }
+
+    #[tokio::test]
+    async fn test_history_records_searches_and_clears() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let app = app_with_history(Vec::new(), temp_dir.path());
+        let send = |request: Request<Body>| {
+            let app = app.clone();
+            async move {
+                let response = app.oneshot(request).await.unwrap();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+        let history = |uri: &'static str| send(Request::get(uri).body(Body::empty()).unwrap());
+
+        for query in ["first", "second", "third"] {
+            send(
+                Request::get(format!("/api/v1/search?query={}", query))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await;
+        }
+        // Searches are recorded in the background, even without results
+        let mut json = history("/api/v1/history").await;
+        for _ in 0..100 {
+            if json["history"].as_array().unwrap().len() == 3 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            json = history("/api/v1/history").await;
+        }
+        assert_eq!(json["success"], true);
+        let queries: Vec<_> = json["history"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|item| item["query"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(queries.len(), 3);
+        assert!(queries.contains(&"first".to_string()));
+        assert_eq!(json["history"][0]["result_count"], 0);
+
+        let json = history("/api/v1/history?limit=2").await;
+        assert_eq!(json["history"].as_array().unwrap().len(), 2);
+
+        let json = send(
+            Request::delete("/api/v1/history")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(json["success"], true);
+        let json = history("/api/v1/history").await;
+        assert!(json["history"].as_array().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_export_runs_search_and_returns_attachment() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let db_path = temp_dir.path().join("docs.reminex.db");
+        Database::init(&db_path)
+            .unwrap()
+            .add_idxs(&[
+                Index::with_metadata("/docs/a, b.txt".into(), "a, b.txt".into(), 0.0, 7),
+                Index::new("/docs/other.md".into(), "other.md".into()),
+            ])
+            .unwrap();
+        let app = app_with_history(vec![db_path], temp_dir.path());
+
+        let export = |uri: &'static str, body: serde_json::Value| {
+            let app = app.clone();
+            async move {
+                let response = app
+                    .oneshot(
+                        Request::post(uri)
+                            .header(header::CONTENT_TYPE, "application/json")
+                            .body(Body::from(body.to_string()))
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                let status = response.status();
+                let headers = response.headers().clone();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                (status, headers, String::from_utf8(body.to_vec()).unwrap())
+            }
+        };
+
+        let (_, headers, body) = export(
+            "/api/v1/export?format=csv",
+            serde_json::json!({ "query": "txt" }),
+        )
+        .await;
+        assert_eq!(headers[header::CONTENT_TYPE], "text/csv; charset=utf-8");
+        assert_eq!(
+            headers[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"reminex-results.csv\""
+        );
+        assert_eq!(
+            body,
+            "\u{feff}keyword,path,size,modified\ntxt,\"/docs/a, b.txt\",7,1970-01-01T00:00:00Z\n"
+        );
+
+        // TOML is the default and carries the search parameters
+        let (_, headers, body) = export(
+            "/api/v1/export",
+            serde_json::json!({ "query": "md", "name_only": true }),
+        )
+        .await;
+        assert!(
+            headers[header::CONTENT_DISPOSITION]
+                .to_str()
+                .unwrap()
+                .ends_with(".toml\"")
+        );
+        let exported = export::ExportedSearchResults::from_toml(&body).unwrap();
+        assert!(exported.search_params.name_only);
+        assert_eq!(exported.results[0].files[0].path, "/docs/other.md");
+
+        // Results sent by the page are still answered with TOML text
+        let (_, _, body) = export(
+            "/api/v1/export",
+            serde_json::json!({ "query": "md", "selected_db": "all", "limit": 10, "results": [] }),
+        )
+        .await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["success"], true);
+        assert!(json["toml"].as_str().unwrap().contains("query = \"md\""));
+
+        // A results body missing fields is an error, not a search
+        let (status, headers, _) = export(
+            "/api/v1/export?format=csv",
+            serde_json::json!({ "query": "txt", "results": [] }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(headers.get(header::CONTENT_DISPOSITION).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_api_responses_are_not_stored() {
+        let response = test_app()
+            .oneshot(
+                Request::get("/api/v1/databases")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-store");
+    }
+}